@@ -228,6 +228,70 @@ fn dtl_scalar_vs_branch_rate_full_tables(c: &mut Criterion) {
     group.finish();
 }
 
+/// Copy selection with copies spread over many species branches.
+///
+/// Transfers are disabled so recipient choice does not mask the cost of
+/// picking the affected copy; the branch-rate variant exercises the weighted
+/// selection path.
+fn dtl_pergene_copy_selection_species_scaling(c: &mut Criterion) {
+    let (d, t, l) = (0.4, 0.0, 0.3);
+    let mut group = c.benchmark_group("dtl_pergene_copy_selection_species_scaling");
+    group.sample_size(10);
+    group.warm_up_time(Duration::from_millis(200));
+    group.measurement_time(Duration::from_secs(2));
+    for &n in &[250usize, 1000, 4000] {
+        let species_tree = make_species_tree(n, 99);
+        let root = species_tree.root;
+        let avg_events = pilot_dtl_events(&species_tree, d, t, l);
+        let branch_rates = expanded_branch_rates(&species_tree, root, d, t, l);
+        group.throughput(Throughput::Elements(avg_events));
+        group.bench_with_input(BenchmarkId::new("scalar", n), &n, |b, _| {
+            let mut rng = StdRng::seed_from_u64(123);
+            b.iter(|| {
+                simulate_dtl(&species_tree, root, d, t, l, None, None, false, &mut rng).unwrap()
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("branch_rates", n), &n, |b, _| {
+            let mut rng = StdRng::seed_from_u64(123);
+            b.iter(|| {
+                simulate_dtl_batch_with_branch_rates(
+                    &species_tree,
+                    branch_rates.clone(),
+                    None,
+                    None,
+                    1,
+                    false,
+                    &mut rng,
+                )
+                .unwrap()
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Copy selection in families growing to thousands of copies on a small tree.
+fn dtl_pergene_copy_selection_family_size(c: &mut Criterion) {
+    let species_tree = make_species_tree(20, 99);
+    let root = species_tree.root;
+    let (t, l) = (0.0, 0.2);
+    let mut group = c.benchmark_group("dtl_pergene_copy_selection_family_size");
+    group.sample_size(10);
+    group.warm_up_time(Duration::from_millis(200));
+    group.measurement_time(Duration::from_secs(2));
+    for &d in &[1.0, 2.0, 3.0] {
+        let avg_events = pilot_dtl_events(&species_tree, d, t, l);
+        group.throughput(Throughput::Elements(avg_events));
+        group.bench_with_input(BenchmarkId::new("lambda_d", d), &d, |b, &d| {
+            let mut rng = StdRng::seed_from_u64(123);
+            b.iter(|| {
+                simulate_dtl(&species_tree, root, d, t, l, None, None, false, &mut rng).unwrap()
+            });
+        });
+    }
+    group.finish();
+}
+
 criterion_group!(
     benches,
     dtl_pergene_scaling,
@@ -235,6 +299,8 @@ criterion_group!(
     dtl_perspecies_scaling,
     dtl_perspecies_rate_sweep,
     dtl_batch_pergene,
-    dtl_scalar_vs_branch_rate_full_tables
+    dtl_scalar_vs_branch_rate_full_tables,
+    dtl_pergene_copy_selection_species_scaling,
+    dtl_pergene_copy_selection_family_size
 );
 criterion_main!(benches);
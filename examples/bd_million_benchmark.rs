@@ -1,6 +1,7 @@
 use rand::rngs::StdRng;
 use rand::SeedableRng;
 use rustree::bd::simulate_bd_tree_bwd;
+use rustree::FlatNode;
 use std::time::Instant;
 
 fn fmt_num(n: usize) -> String {
@@ -32,6 +33,8 @@ fn main() {
 
         let mut total_time = 0.0;
         let mut total_nodes = 0usize;
+        let mut flat_bytes = 0usize;
+        let mut compact_bytes = 0usize;
 
         for i in 0..runs_per_size {
             let mut rng = StdRng::seed_from_u64(42 + i as u64);
@@ -42,6 +45,9 @@ fn main() {
 
             total_time += elapsed;
             total_nodes += tree.nodes.len();
+            flat_bytes += tree.nodes.capacity() * std::mem::size_of::<FlatNode>()
+                + tree.nodes.iter().map(|n| n.name.capacity()).sum::<usize>();
+            compact_bytes += tree.to_compact_tree().unwrap().heap_bytes();
         }
 
         let avg_time = total_time / runs_per_size as f64;
@@ -49,10 +55,12 @@ fn main() {
         let trees_per_min = 60.0 / avg_time;
 
         println!(
-            "{:.3}s avg, {:>12} nodes, {:.1} trees/min",
+            "{:.3}s avg, {:>12} nodes, {:.1} trees/min, {:.1} MB flat / {:.1} MB compact",
             avg_time,
             fmt_num(avg_nodes),
-            trees_per_min
+            trees_per_min,
+            flat_bytes as f64 / runs_per_size as f64 / 1e6,
+            compact_bytes as f64 / runs_per_size as f64 / 1e6
         );
     }
 
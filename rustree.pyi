@@ -958,6 +958,43 @@ class PySpeciesTree:
         """
         ...
 
+    def pairwise_distances_condensed(
+        self,
+        distance_type: str,
+        leaves_only: bool = True,
+    ) -> Tuple[List[str], npt.NDArray[np.float64]]:
+        """Compute the condensed upper-triangular distance matrix in parallel.
+
+        Args:
+            distance_type: ``"topological"`` or ``"metric"``.
+            leaves_only: If True, only compute distances between leaf nodes.
+
+        Returns:
+            ``(names, distances)`` where ``distances`` is in
+            ``scipy.spatial.distance.pdist`` order over the species tree nodes
+            ``names``.
+
+        Raises:
+            ValueError: If distance_type is invalid.
+        """
+        ...
+
+    def save_pairwise_distances_npy(
+        self,
+        filepath: str,
+        distance_type: str,
+        leaves_only: bool = True,
+    ) -> None:
+        """Stream the condensed distance matrix to a NumPy ``.npy`` file.
+
+        Rows are written block by block, so the full matrix is never held
+        in memory. Load with ``numpy.load(filepath)``.
+
+        Raises:
+            ValueError: If distance_type is invalid or writing fails.
+        """
+        ...
+
     def compute_ghost_lengths(
         self,
         sampled_leaf_names: List[str],
@@ -1246,7 +1283,7 @@ class PyGeneTree:
         Columns: ``node_id``, ``name``, ``parent``, ``left_child``,
         ``left_child_name``, ``right_child``, ``right_child_name``,
         ``length``, ``depth``, ``species_node``, ``species_node_left``,
-        ``species_node_right``, ``event``.
+        ``species_node_right``, ``event``, ``wgd``.
 
         Args:
             filepath: Optional path to save as CSV file.
@@ -1317,6 +1354,43 @@ class PyGeneTree:
         """
         ...
 
+    def pairwise_distances_condensed(
+        self,
+        distance_type: str,
+        leaves_only: bool = True,
+    ) -> Tuple[List[str], npt.NDArray[np.float64]]:
+        """Compute the condensed upper-triangular distance matrix in parallel.
+
+        Args:
+            distance_type: ``"topological"`` or ``"metric"``.
+            leaves_only: If True, only compute distances between leaf nodes.
+
+        Returns:
+            ``(names, distances)`` where ``distances`` is in
+            ``scipy.spatial.distance.pdist`` order over the gene tree nodes
+            ``names``.
+
+        Raises:
+            ValueError: If distance_type is invalid.
+        """
+        ...
+
+    def save_pairwise_distances_npy(
+        self,
+        filepath: str,
+        distance_type: str,
+        leaves_only: bool = True,
+    ) -> None:
+        """Stream the condensed distance matrix to a NumPy ``.npy`` file.
+
+        Rows are written block by block, so the full matrix is never held
+        in memory. Load with ``numpy.load(filepath)``.
+
+        Raises:
+            ValueError: If distance_type is invalid or writing fails.
+        """
+        ...
+
     def compute_induced_transfers(
         self,
         sampled_leaf_names: List[str],
@@ -1482,6 +1556,29 @@ class PyDtlSimIter:
         """
         ...
 
+    def summarize(
+        self,
+        output_dir: Optional[str] = None,
+    ) -> Dict[str, pd.DataFrame]:
+        """Consume the remaining simulations into forest-level summary statistics.
+
+        Trees are discarded as they are generated, so memory does not grow
+        with the number of families.
+
+        Args:
+            output_dir: Optional directory to also write
+                ``branch_events.csv``, ``transfers.csv`` and
+                ``copy_numbers.csv``.
+
+        Returns:
+            A dict of DataFrames keyed ``branch_events``, ``transfers`` and
+            ``copy_numbers``.
+
+        Raises:
+            ValueError: If a simulation or writing fails.
+        """
+        ...
+
 class GeneForest:
     """A collection of gene trees associated with a single species tree.
 
@@ -1529,6 +1626,27 @@ class GeneForest:
         """
         ...
 
+    def summarize(
+        self,
+        output_dir: Optional[str] = None,
+    ) -> Dict[str, pd.DataFrame]:
+        """Forest-level summary statistics.
+
+        Args:
+            output_dir: Optional directory to also write
+                ``branch_events.csv``, ``transfers.csv`` and
+                ``copy_numbers.csv``.
+
+        Returns:
+            A dict of DataFrames keyed ``branch_events`` (event totals per
+            species branch), ``transfers`` (donor/recipient counts) and
+            ``copy_numbers`` (copy-number histograms per extant species).
+
+        Raises:
+            ValueError: If a simulation or writing fails.
+        """
+        ...
+
     def prune_to_species_tree(self, target: PySpeciesTree) -> GeneForest:
         """Prune the forest to match a target species tree.
 
@@ -13,8 +13,10 @@ pub enum RustreeError {
     Index(String),
     /// Tree structure errors (missing root, invalid topology).
     Tree(String),
-    /// DTL simulation errors (no valid recipients, exceeded retries).
+    /// DTL simulation errors (no valid recipients, invalid state).
     Simulation(String),
+    /// A conditioned simulation accepted no family within its attempt budget.
+    ConditioningExhausted { attempts: usize, message: String },
     /// File I/O errors.
     Io(std::io::Error),
     /// XML parsing errors (from quick_xml).
@@ -51,6 +53,9 @@ impl fmt::Display for RustreeError {
             RustreeError::Index(msg) => write!(f, "Index error: {msg}"),
             RustreeError::Tree(msg) => write!(f, "Tree error: {msg}"),
             RustreeError::Simulation(msg) => write!(f, "Simulation error: {msg}"),
+            RustreeError::ConditioningExhausted { attempts, message } => {
+                write!(f, "Simulation error: no family accepted after {attempts} attempts. {message}")
+            }
             RustreeError::Io(err) => write!(f, "IO error: {err}"),
             RustreeError::Xml(msg) => write!(f, "XML error: {msg}"),
             RustreeError::ExternalTool(msg) => write!(f, "External tool error: {msg}"),
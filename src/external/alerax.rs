@@ -272,10 +272,13 @@ fn inspect_gene_tree_for_alerax(
     Ok(None)
 }
 
+/// Gene families kept for ALERax plus the families skipped as too small.
+pub type PartitionedAleRaxFamilies = (Vec<(String, String)>, Vec<SkippedAleRaxFamily>);
+
 pub fn partition_gene_trees_for_alerax(
     species_tree: &FlatTree,
     gene_trees: &[(String, String)],
-) -> Result<(Vec<(String, String)>, Vec<SkippedAleRaxFamily>), String> {
+) -> Result<PartitionedAleRaxFamilies, String> {
     if species_tree.nodes.is_empty() {
         return Err("Species tree is empty".to_string());
     }
@@ -1015,6 +1015,7 @@ mod tests {
                 species_id: 0,
                 child1: 1,
                 child2: 2,
+                wgd: false,
             },
             DTLEvent::Loss {
                 time: 0.4,
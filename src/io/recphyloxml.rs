@@ -77,6 +77,8 @@ struct GeneNode {
     branch_length: f64,
     species_location: String,
     event_type: Event,
+    /// Duplication flagged `wgd="true"` (whole-genome duplication).
+    wgd: bool,
     children: Vec<GeneNode>,
 }
 
@@ -87,6 +89,7 @@ impl GeneNode {
             branch_length: 0.0,
             species_location: String::new(),
             event_type: Event::Leaf,
+            wgd: false,
             children: Vec::new(),
         }
     }
@@ -96,16 +99,27 @@ impl GeneNode {
 ///
 /// Returns a tuple of (species_tree, gene_tree, node_mapping, event_mapping).
 pub fn parse_recphyloxml(xml_content: &str) -> Result<RecPhyloComponents, ParseError> {
+    parse_recphyloxml_with_wgd(xml_content).map(|(components, _)| components)
+}
+
+/// Like [`parse_recphyloxml`], also returning the gene nodes of duplications
+/// flagged as whole-genome duplications (`wgd="true"`).
+pub(crate) fn parse_recphyloxml_with_wgd(
+    xml_content: &str,
+) -> Result<(RecPhyloComponents, Vec<usize>), ParseError> {
     // Parse the species tree
     let species_root = parse_species_tree(xml_content)?;
     let (species_tree, species_name_map) = species_node_to_flat_tree(&species_root)?;
 
     // Parse the gene tree
     let gene_root = parse_gene_tree(xml_content)?;
-    let (gene_tree, node_mapping, event_mapping) =
+    let ((gene_tree, node_mapping, event_mapping), wgd_nodes) =
         gene_node_to_flat_tree(&gene_root, &species_name_map)?;
 
-    Ok((species_tree, gene_tree, node_mapping, event_mapping))
+    Ok((
+        (species_tree, gene_tree, node_mapping, event_mapping),
+        wgd_nodes,
+    ))
 }
 
 /// Parse a RecPhyloXML file and return the components for creating a RecTree.
@@ -114,6 +128,14 @@ pub fn parse_recphyloxml_file(filepath: &str) -> Result<RecPhyloComponents, Pars
     parse_recphyloxml(&xml_content)
 }
 
+/// File-based version of [`parse_recphyloxml_with_wgd`].
+pub(crate) fn parse_recphyloxml_file_with_wgd(
+    filepath: &str,
+) -> Result<(RecPhyloComponents, Vec<usize>), ParseError> {
+    let xml_content = fs::read_to_string(filepath)?;
+    parse_recphyloxml_with_wgd(&xml_content)
+}
+
 /// Parse the species tree section from RecPhyloXML.
 fn parse_species_tree(xml_content: &str) -> Result<SpeciesNode, ParseError> {
     let mut reader = Reader::from_str(xml_content);
@@ -263,6 +285,7 @@ fn parse_gene_tree(xml_content: &str) -> Result<GeneNode, ParseError> {
                             if let Some(species_loc) = get_attribute(e, b"speciesLocation") {
                                 node.species_location = species_loc;
                             }
+                            node.wgd = get_attribute(e, b"wgd").is_some_and(|v| v == "true");
                         }
                     }
                     "branchingOut" if in_events_rec => {
@@ -453,14 +476,16 @@ fn species_node_to_flat_tree(
     ))
 }
 
-/// Convert a GeneNode tree to a FlatTree with mapping vectors.
+/// Convert a GeneNode tree to a FlatTree with mapping vectors, plus the
+/// indices of whole-genome duplication nodes.
 fn gene_node_to_flat_tree(
     root: &GeneNode,
     species_name_map: &HashMap<String, usize>,
-) -> Result<GeneTreeComponents, ParseError> {
+) -> Result<(GeneTreeComponents, Vec<usize>), ParseError> {
     let mut nodes = Vec::new();
     let mut node_mapping = Vec::new();
     let mut event_mapping = Vec::new();
+    let mut wgd_nodes = Vec::new();
 
     fn traverse(
         node: &GeneNode,
@@ -468,6 +493,7 @@ fn gene_node_to_flat_tree(
         nodes: &mut Vec<FlatNode>,
         node_mapping: &mut Vec<Option<usize>>,
         event_mapping: &mut Vec<Event>,
+        wgd_nodes: &mut Vec<usize>,
         species_name_map: &HashMap<String, usize>,
     ) -> Result<usize, ParseError> {
         let index = nodes.len();
@@ -497,6 +523,9 @@ fn gene_node_to_flat_tree(
         // Add mappings
         node_mapping.push(Some(species_idx));
         event_mapping.push(node.event_type.clone());
+        if node.wgd && node.event_type == Event::Duplication {
+            wgd_nodes.push(index);
+        }
 
         // Reject non-binary nodes
         if node.children.len() > 2 {
@@ -515,6 +544,7 @@ fn gene_node_to_flat_tree(
                 nodes,
                 node_mapping,
                 event_mapping,
+                wgd_nodes,
                 species_name_map,
             )?;
             nodes[index].left_child = Some(left_idx);
@@ -526,6 +556,7 @@ fn gene_node_to_flat_tree(
                     nodes,
                     node_mapping,
                     event_mapping,
+                    wgd_nodes,
                     species_name_map,
                 )?;
                 nodes[index].right_child = Some(right_idx);
@@ -541,16 +572,20 @@ fn gene_node_to_flat_tree(
         &mut nodes,
         &mut node_mapping,
         &mut event_mapping,
+        &mut wgd_nodes,
         species_name_map,
     )?;
 
     Ok((
-        FlatTree {
-            nodes,
-            root: root_idx,
-        },
-        node_mapping,
-        event_mapping,
+        (
+            FlatTree {
+                nodes,
+                root: root_idx,
+            },
+            node_mapping,
+            event_mapping,
+        ),
+        wgd_nodes,
     ))
 }
 
@@ -583,15 +618,21 @@ pub fn parse_gene_tree_only(
     xml_content: &str,
     species_tree: &FlatTree,
 ) -> Result<GeneTreeComponents, ParseError> {
+    parse_gene_tree_only_with_wgd(xml_content, species_tree).map(|(components, _)| components)
+}
+
+/// Like [`parse_gene_tree_only`], also returning the gene nodes of
+/// duplications flagged as whole-genome duplications (`wgd="true"`).
+pub(crate) fn parse_gene_tree_only_with_wgd(
+    xml_content: &str,
+    species_tree: &FlatTree,
+) -> Result<(GeneTreeComponents, Vec<usize>), ParseError> {
     // Build name map from provided species tree
     let species_name_map = build_species_name_map(species_tree);
 
     // Parse only gene tree section
     let gene_root = parse_gene_tree(xml_content)?;
-    let (gene_tree, node_mapping, event_mapping) =
-        gene_node_to_flat_tree(&gene_root, &species_name_map)?;
-
-    Ok((gene_tree, node_mapping, event_mapping))
+    gene_node_to_flat_tree(&gene_root, &species_name_map)
 }
 
 /// Parse gene-tree-only RecPhyloXML file (no `<spTree>` section).
@@ -605,6 +646,15 @@ pub fn parse_gene_tree_only_file(
     parse_gene_tree_only(&xml_content, species_tree)
 }
 
+/// File-based version of [`parse_gene_tree_only_with_wgd`].
+pub(crate) fn parse_gene_tree_only_file_with_wgd(
+    xml_filepath: &str,
+    species_tree: &FlatTree,
+) -> Result<(GeneTreeComponents, Vec<usize>), ParseError> {
+    let xml_content = fs::read_to_string(xml_filepath)?;
+    parse_gene_tree_only_with_wgd(&xml_content, species_tree)
+}
+
 /// Extract per-node annotations from a gene-tree-only XML.
 ///
 /// Walks the parsed XML gene tree and returns a map from node name to
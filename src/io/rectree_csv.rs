@@ -26,12 +26,13 @@ pub struct RecTreeColumns {
     pub species_node_left: Vec<String>,
     pub species_node_right: Vec<String>,
     pub event: Vec<String>,
+    pub wgd: Vec<bool>,
 }
 
 impl RecTreeColumns {
     /// CSV header line.
     pub fn csv_header() -> &'static str {
-        "node_id,name,parent,left_child,left_child_name,right_child,right_child_name,length,depth,species_node,species_node_left,species_node_right,event"
+        "node_id,name,parent,left_child,left_child_name,right_child,right_child_name,length,depth,species_node,species_node_left,species_node_right,event,wgd"
     }
 
     /// Convert to a full CSV string (header + rows).
@@ -43,7 +44,7 @@ impl RecTreeColumns {
         csv.push('\n');
         for i in 0..n {
             csv.push_str(&format!(
-                "{},{},{},{},{},{},{},{:.6},{},{},{},{},{}\n",
+                "{},{},{},{},{},{},{},{:.6},{},{},{},{},{},{}\n",
                 self.node_id[i],
                 self.name[i],
                 self.parent[i],
@@ -57,6 +58,7 @@ impl RecTreeColumns {
                 self.species_node_left[i],
                 self.species_node_right[i],
                 self.event[i],
+                self.wgd[i],
             ));
         }
         csv
@@ -70,7 +72,7 @@ impl RecTreeColumns {
         for i in 0..self.node_id.len() {
             writeln!(
                 writer,
-                "{},{},{},{},{},{},{},{:.6},{},{},{},{},{}",
+                "{},{},{},{},{},{},{},{:.6},{},{},{},{},{},{}",
                 self.node_id[i],
                 self.name[i],
                 self.parent[i],
@@ -84,6 +86,7 @@ impl RecTreeColumns {
                 self.species_node_left[i],
                 self.species_node_right[i],
                 self.event[i],
+                self.wgd[i],
             )?;
         }
         writer.flush()
@@ -140,6 +143,7 @@ impl RecTreeColumns {
                 .map(|&i| self.species_node_right[i].clone())
                 .collect(),
             event: indices.iter().map(|&i| self.event[i].clone()).collect(),
+            wgd: indices.iter().map(|&i| self.wgd[i]).collect(),
         }
     }
 }
@@ -164,6 +168,7 @@ impl RecTree {
             species_node_left: Vec::with_capacity(n),
             species_node_right: Vec::with_capacity(n),
             event: Vec::with_capacity(n),
+            wgd: Vec::with_capacity(n),
         };
 
         for (i, node) in nodes.iter().enumerate() {
@@ -213,6 +218,7 @@ impl RecTree {
                 Event::Loss => "Loss".to_string(),
                 Event::Leaf => "Leaf".to_string(),
             });
+            cols.wgd.push(self.is_wgd(i));
         }
 
         cols
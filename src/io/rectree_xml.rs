@@ -191,13 +191,16 @@ impl RecTree {
             }
             Event::Duplication => {
                 xml.push_str(&indent_str);
+                xml.push_str("\t\t<duplication");
                 if let Some(name) = species_name {
-                    xml.push_str("\t\t<duplication speciesLocation=\"");
+                    xml.push_str(" speciesLocation=\"");
                     xml.push_str(name);
-                    xml.push_str("\"/>\n");
-                } else {
-                    xml.push_str("\t\t<duplication/>\n");
+                    xml.push('"');
+                }
+                if self.is_wgd(node_idx) {
+                    xml.push_str(" wgd=\"true\"");
                 }
+                xml.push_str("/>\n");
             }
             Event::Transfer => {
                 if node.left_child.is_some() && node.right_child.is_some() {
@@ -274,32 +277,26 @@ impl RecTree {
 
     /// Parse a RecPhyloXML string and create a RecTree.
     pub fn from_xml(xml_content: &str) -> Result<Self, RustreeError> {
-        use super::recphyloxml::parse_recphyloxml;
+        use super::recphyloxml::parse_recphyloxml_with_wgd;
 
-        let (species_tree, gene_tree, node_mapping, event_mapping) =
-            parse_recphyloxml(xml_content)?;
+        let ((species_tree, gene_tree, node_mapping, event_mapping), wgd_nodes) =
+            parse_recphyloxml_with_wgd(xml_content)?;
 
-        Ok(RecTree::new_owned(
-            species_tree,
-            gene_tree,
-            node_mapping,
-            event_mapping,
-        ))
+        let mut rec_tree = RecTree::new_owned(species_tree, gene_tree, node_mapping, event_mapping);
+        rec_tree.set_wgd_nodes(wgd_nodes);
+        Ok(rec_tree)
     }
 
     /// Parse a RecPhyloXML file and create a RecTree.
     pub fn from_xml_file(filepath: &str) -> Result<Self, RustreeError> {
-        use super::recphyloxml::parse_recphyloxml_file;
+        use super::recphyloxml::parse_recphyloxml_file_with_wgd;
 
-        let (species_tree, gene_tree, node_mapping, event_mapping) =
-            parse_recphyloxml_file(filepath)?;
+        let ((species_tree, gene_tree, node_mapping, event_mapping), wgd_nodes) =
+            parse_recphyloxml_file_with_wgd(filepath)?;
 
-        Ok(RecTree::new_owned(
-            species_tree,
-            gene_tree,
-            node_mapping,
-            event_mapping,
-        ))
+        let mut rec_tree = RecTree::new_owned(species_tree, gene_tree, node_mapping, event_mapping);
+        rec_tree.set_wgd_nodes(wgd_nodes);
+        Ok(rec_tree)
     }
 
     /// Parse gene-tree-only RecPhyloXML with a separate species tree.
@@ -310,17 +307,14 @@ impl RecTree {
         xml_content: &str,
         species_tree: FlatTree,
     ) -> Result<Self, RustreeError> {
-        use super::recphyloxml::parse_gene_tree_only;
+        use super::recphyloxml::parse_gene_tree_only_with_wgd;
 
-        let (gene_tree, node_mapping, event_mapping) =
-            parse_gene_tree_only(xml_content, &species_tree)?;
+        let ((gene_tree, node_mapping, event_mapping), wgd_nodes) =
+            parse_gene_tree_only_with_wgd(xml_content, &species_tree)?;
 
-        Ok(RecTree::new_owned(
-            species_tree,
-            gene_tree,
-            node_mapping,
-            event_mapping,
-        ))
+        let mut rec_tree = RecTree::new_owned(species_tree, gene_tree, node_mapping, event_mapping);
+        rec_tree.set_wgd_nodes(wgd_nodes);
+        Ok(rec_tree)
     }
 
     /// Parse gene-tree-only RecPhyloXML file with a separate species tree.
@@ -328,17 +322,14 @@ impl RecTree {
         xml_filepath: &str,
         species_tree: FlatTree,
     ) -> Result<Self, RustreeError> {
-        use super::recphyloxml::parse_gene_tree_only_file;
+        use super::recphyloxml::parse_gene_tree_only_file_with_wgd;
 
-        let (gene_tree, node_mapping, event_mapping) =
-            parse_gene_tree_only_file(xml_filepath, &species_tree)?;
+        let ((gene_tree, node_mapping, event_mapping), wgd_nodes) =
+            parse_gene_tree_only_file_with_wgd(xml_filepath, &species_tree)?;
 
-        Ok(RecTree::new_owned(
-            species_tree,
-            gene_tree,
-            node_mapping,
-            event_mapping,
-        ))
+        let mut rec_tree = RecTree::new_owned(species_tree, gene_tree, node_mapping, event_mapping);
+        rec_tree.set_wgd_nodes(wgd_nodes);
+        Ok(rec_tree)
     }
 
     /// Parse reconciled tree from separate files: Newick species tree + gene tree XML.
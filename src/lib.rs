@@ -19,12 +19,17 @@ pub mod node; // Tree data structures and traversal // I/O utilities (CSV export
 // Simulation modules
 pub mod simulation;
 pub use simulation::bd;
+pub use simulation::clock;
 pub use simulation::dtl;
+pub use simulation::genome;
+pub use simulation::sequence;
 
 // Tree operations
 pub mod comparison;
 pub mod debug;
 pub mod metric_functions;
+pub mod pairwise;
+pub mod perturbation;
 pub mod robinson_foulds;
 pub mod sampling;
 pub mod surgery;
@@ -32,6 +37,7 @@ pub mod topology;
 
 // Analysis
 pub mod induced_transfers;
+pub mod reconciliation;
 
 // Shared bindings utilities
 pub mod bindings_common;
@@ -51,5 +57,5 @@ pub mod r;
 // For other items, use qualified paths (e.g., `rustree::sampling::compute_lca`).
 pub use newick::parse_newick;
 pub use node::{parse_recphyloxml, parse_recphyloxml_file, Event, GeneForest, RecTree};
-pub use node::{FlatNode, FlatTree, Node, TraversalOrder};
+pub use node::{CompactTree, FlatNode, FlatTree, Node, TraversalOrder, TreeView};
 pub use topology::{UnlabeledShape, UnrootedShapeKey, UnrootedTopologyKey};
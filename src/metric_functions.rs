@@ -2,7 +2,7 @@
 // The functions in this repo act on the branch lengths
 // and depths of nodes in phylogenetic trees.
 
-use crate::node::{FlatTree, Node, TraversalOrder};
+use crate::node::{FlatTree, Node, TraversalOrder, TreeView};
 use std::str::FromStr;
 
 /// Type of distance to compute between nodes.
@@ -311,33 +311,15 @@ impl FlatTree {
     }
     /// Assigns depths to each node in the tree.
     /// Each node's depth includes its own stem length plus all ancestral stem lengths.
+    ///
+    /// Does nothing for an empty tree.
     pub fn assign_depths(&mut self) {
-        let root_index = self.root;
-        // Root depth includes its own stem length
-        let root_length = self.nodes[root_index].length;
-        self.nodes[root_index].depth = Some(root_length);
-
-        let mut stack = vec![root_index];
-
-        while let Some(node_index) = stack.pop() {
-            let current_depth = self.nodes[node_index].depth.expect(
-                "depths must be assigned before calling this function - call assign_depths() first",
-            );
-
-            // Process left child
-            if let Some(left_index) = self.nodes[node_index].left_child {
-                let left_length = self.nodes[left_index].length;
-                let left_depth = current_depth + left_length;
-                self.nodes[left_index].depth = Some(left_depth);
-                stack.push(left_index);
-            }
-
-            // Process right child
-            if let Some(right_index) = self.nodes[node_index].right_child {
-                let right_length = self.nodes[right_index].length;
-                let right_depth = current_depth + right_length;
-                self.nodes[right_index].depth = Some(right_depth);
-                stack.push(right_index);
+        let Ok(depths) = self.depths_from_lengths() else {
+            return;
+        };
+        for (node, depth) in self.nodes.iter_mut().zip(depths) {
+            if depth.is_some() {
+                node.depth = depth;
             }
         }
     }
@@ -411,7 +393,7 @@ impl FlatTree {
     /// # Returns
     ///
     /// The index `i` in `depths` such that `depths[i]` is closest to `value`.
-    fn find_closest_index(&self, depths: &[f64], value: f64) -> usize {
+    pub(crate) fn find_closest_index(&self, depths: &[f64], value: f64) -> usize {
         match depths.binary_search_by(|probe| probe.total_cmp(&value)) {
             Ok(idx) => idx,
             Err(idx) => {
@@ -520,41 +502,7 @@ impl FlatTree {
     /// # Returns
     /// The index of the lowest common ancestor.
     pub fn find_lca(&self, node_a: usize, node_b: usize) -> Result<usize, String> {
-        let n = self.nodes.len();
-        if node_a >= n {
-            return Err(format!(
-                "node_a index {} is out of bounds (tree has {} nodes)",
-                node_a, n
-            ));
-        }
-        if node_b >= n {
-            return Err(format!(
-                "node_b index {} is out of bounds (tree has {} nodes)",
-                node_b, n
-            ));
-        }
-
-        // Collect ancestors of node_a (including itself)
-        let mut ancestors_a = std::collections::HashSet::new();
-        let mut current = Some(node_a);
-        while let Some(idx) = current {
-            ancestors_a.insert(idx);
-            current = self.nodes[idx].parent;
-        }
-
-        // Walk up from node_b until we find a common ancestor
-        let mut current = Some(node_b);
-        while let Some(idx) = current {
-            if ancestors_a.contains(&idx) {
-                return Ok(idx);
-            }
-            current = self.nodes[idx].parent;
-        }
-
-        Err(format!(
-            "No common ancestor found for nodes {} and {}",
-            node_a, node_b
-        ))
+        self.lca(node_a, node_b).map_err(|e| e.to_string())
     }
 
     /// Precomputes a matrix of LCA depths for all node pairs.
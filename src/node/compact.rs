@@ -0,0 +1,331 @@
+//! Compact struct-of-arrays storage for very large flat trees.
+//!
+//! [`FlatNode`] keeps an owned `String` and `usize` links per node, which costs
+//! over 100 bytes per node plus a separate allocation for the name. [`CompactTree`]
+//! stores each field in its own column with `u32` links, and packs all names
+//! into one arena, bringing this down to about 33 bytes per node plus the name text.
+//!
+//! Both layouts implement [`TreeView`], so traversal, depth and LCA queries
+//! written against it run on either. [`RecTree`](super::RecTree),
+//! [`GeneForest`](super::GeneForest) and the other tree algorithms still take a
+//! `FlatTree`; convert back before using them.
+
+use super::{FlatNode, FlatTree, TreeView};
+use crate::bd::BDEvent;
+use crate::error::RustreeError;
+
+/// Sentinel for an absent parent or child link.
+const NO_NODE: u32 = u32::MAX;
+
+/// A flat tree stored column-wise, with `u32` node indices and arena-backed names.
+///
+/// Node indices are the same as in the [`FlatTree`] it was built from, so
+/// index-based results carry over between the two representations. Accessors
+/// take a node index and mirror the fields of [`FlatNode`]; the shared
+/// algorithms come from [`TreeView`].
+///
+/// Missing depths are stored as NaN, so a node depth of `Some(NaN)` converts
+/// back to `None`.
+#[derive(Clone, Debug)]
+pub struct CompactTree {
+    /// All node names, concatenated.
+    name_arena: String,
+    /// End offset of each node's name in `name_arena`.
+    name_ends: Vec<u32>,
+    left_child: Vec<u32>,
+    right_child: Vec<u32>,
+    parent: Vec<u32>,
+    depth: Vec<f64>,
+    length: Vec<f64>,
+    bd_event: Vec<Option<BDEvent>>,
+    root: u32,
+}
+
+#[inline]
+fn pack(index: Option<usize>) -> u32 {
+    index.map_or(NO_NODE, |i| i as u32)
+}
+
+#[inline]
+fn unpack(index: u32) -> Option<usize> {
+    (index != NO_NODE).then_some(index as usize)
+}
+
+impl CompactTree {
+    /// Returns the number of nodes in the tree.
+    pub fn len(&self) -> usize {
+        self.name_ends.len()
+    }
+
+    /// Returns true if the tree has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.name_ends.is_empty()
+    }
+
+    /// Index of the root node.
+    pub fn root(&self) -> usize {
+        self.root as usize
+    }
+
+    /// Name of node `index`.
+    pub fn name(&self, index: usize) -> &str {
+        let start = if index == 0 {
+            0
+        } else {
+            self.name_ends[index - 1] as usize
+        };
+        &self.name_arena[start..self.name_ends[index] as usize]
+    }
+
+    pub fn left_child(&self, index: usize) -> Option<usize> {
+        unpack(self.left_child[index])
+    }
+
+    pub fn right_child(&self, index: usize) -> Option<usize> {
+        unpack(self.right_child[index])
+    }
+
+    pub fn parent(&self, index: usize) -> Option<usize> {
+        unpack(self.parent[index])
+    }
+
+    /// Distance from the root, or `None` if depths have not been assigned.
+    pub fn depth(&self, index: usize) -> Option<f64> {
+        let depth = self.depth[index];
+        (!depth.is_nan()).then_some(depth)
+    }
+
+    pub fn length(&self, index: usize) -> f64 {
+        self.length[index]
+    }
+
+    pub fn bd_event(&self, index: usize) -> Option<BDEvent> {
+        self.bd_event[index]
+    }
+
+    /// Returns true if node `index` has no children.
+    pub fn is_leaf(&self, index: usize) -> bool {
+        self.left_child[index] == NO_NODE && self.right_child[index] == NO_NODE
+    }
+
+    /// Materializes node `index` as a [`FlatNode`].
+    pub fn node(&self, index: usize) -> FlatNode {
+        FlatNode {
+            name: self.name(index).to_string(),
+            left_child: self.left_child(index),
+            right_child: self.right_child(index),
+            parent: self.parent(index),
+            depth: self.depth(index),
+            length: self.length(index),
+            bd_event: self.bd_event(index),
+        }
+    }
+
+    /// Assigns depths from branch lengths, as [`FlatTree::assign_depths`] does.
+    ///
+    /// Returns an error if the tree is empty.
+    pub fn assign_depths(&mut self) -> Result<(), RustreeError> {
+        let depths = self.depths_from_lengths()?;
+        for (stored, depth) in self.depth.iter_mut().zip(depths) {
+            if let Some(depth) = depth {
+                *stored = depth;
+            }
+        }
+        Ok(())
+    }
+
+    /// Approximate heap memory held by the tree, in bytes.
+    pub fn heap_bytes(&self) -> usize {
+        self.name_arena.capacity()
+            + 4 * (self.name_ends.capacity()
+                + self.left_child.capacity()
+                + self.right_child.capacity()
+                + self.parent.capacity())
+            + 8 * (self.depth.capacity() + self.length.capacity())
+            + self.bd_event.capacity() * std::mem::size_of::<Option<BDEvent>>()
+    }
+
+    /// Converts back to a [`FlatTree`] with the same node indices.
+    #[must_use]
+    pub fn to_flat_tree(&self) -> FlatTree {
+        FlatTree {
+            nodes: (0..self.len()).map(|i| self.node(i)).collect(),
+            root: self.root(),
+        }
+    }
+}
+
+impl TreeView for CompactTree {
+    fn len(&self) -> usize {
+        CompactTree::len(self)
+    }
+
+    fn root(&self) -> usize {
+        CompactTree::root(self)
+    }
+
+    fn name(&self, index: usize) -> &str {
+        CompactTree::name(self, index)
+    }
+
+    fn left_child(&self, index: usize) -> Option<usize> {
+        CompactTree::left_child(self, index)
+    }
+
+    fn right_child(&self, index: usize) -> Option<usize> {
+        CompactTree::right_child(self, index)
+    }
+
+    fn parent(&self, index: usize) -> Option<usize> {
+        CompactTree::parent(self, index)
+    }
+
+    fn depth(&self, index: usize) -> Option<f64> {
+        CompactTree::depth(self, index)
+    }
+
+    fn length(&self, index: usize) -> f64 {
+        CompactTree::length(self, index)
+    }
+
+    fn is_leaf(&self, index: usize) -> bool {
+        CompactTree::is_leaf(self, index)
+    }
+}
+
+impl FlatTree {
+    /// Converts to a [`CompactTree`] with the same node indices.
+    ///
+    /// Returns an error if the tree has `u32::MAX` or more nodes, or if the
+    /// names total 4 GiB or more.
+    pub fn to_compact_tree(&self) -> Result<CompactTree, RustreeError> {
+        let n = self.nodes.len();
+        if n >= NO_NODE as usize {
+            return Err(RustreeError::Tree(format!(
+                "CompactTree supports fewer than {NO_NODE} nodes, got {n}"
+            )));
+        }
+        let name_bytes: usize = self.nodes.iter().map(|node| node.name.len()).sum();
+        if name_bytes > u32::MAX as usize {
+            return Err(RustreeError::Tree(format!(
+                "CompactTree supports at most {} bytes of node names, got {name_bytes}",
+                u32::MAX
+            )));
+        }
+
+        let mut name_arena = String::with_capacity(name_bytes);
+        let mut name_ends = Vec::with_capacity(n);
+        for node in &self.nodes {
+            name_arena.push_str(&node.name);
+            name_ends.push(name_arena.len() as u32);
+        }
+
+        Ok(CompactTree {
+            name_arena,
+            name_ends,
+            left_child: self
+                .nodes
+                .iter()
+                .map(|node| pack(node.left_child))
+                .collect(),
+            right_child: self
+                .nodes
+                .iter()
+                .map(|node| pack(node.right_child))
+                .collect(),
+            parent: self.nodes.iter().map(|node| pack(node.parent)).collect(),
+            depth: self
+                .nodes
+                .iter()
+                .map(|node| node.depth.unwrap_or(f64::NAN))
+                .collect(),
+            length: self.nodes.iter().map(|node| node.length).collect(),
+            bd_event: self.nodes.iter().map(|node| node.bd_event).collect(),
+            root: self.root as u32,
+        })
+    }
+}
+
+impl From<&CompactTree> for FlatTree {
+    fn from(tree: &CompactTree) -> Self {
+        tree.to_flat_tree()
+    }
+}
+
+impl TryFrom<&FlatTree> for CompactTree {
+    type Error = RustreeError;
+
+    fn try_from(tree: &FlatTree) -> Result<Self, Self::Error> {
+        tree.to_compact_tree()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newick::parse_newick;
+
+    fn sample_tree() -> FlatTree {
+        let mut nodes = parse_newick("((A:1,B:2)AB:0.5,(C:1,D:0.25)CD:1)root:0;").unwrap();
+        nodes.pop().unwrap().to_flat_tree()
+    }
+
+    fn assert_same_nodes(a: &FlatTree, b: &FlatTree) {
+        assert_eq!(a.root, b.root);
+        assert_eq!(a.nodes.len(), b.nodes.len());
+        for (x, y) in a.nodes.iter().zip(&b.nodes) {
+            assert_eq!(x.name, y.name);
+            assert_eq!(x.left_child, y.left_child);
+            assert_eq!(x.right_child, y.right_child);
+            assert_eq!(x.parent, y.parent);
+            assert_eq!(x.depth, y.depth);
+            assert_eq!(x.length, y.length);
+            assert_eq!(x.bd_event, y.bd_event);
+        }
+    }
+
+    #[test]
+    fn round_trip_preserves_every_field() {
+        let mut tree = sample_tree();
+        tree.nodes[1].bd_event = Some(BDEvent::Speciation);
+        tree.nodes[2].bd_event = Some(BDEvent::Leaf);
+        let compact = tree.to_compact_tree().unwrap();
+        assert_same_nodes(&compact.to_flat_tree(), &tree);
+
+        tree.assign_depths();
+        let compact = CompactTree::try_from(&tree).unwrap();
+        assert_same_nodes(&FlatTree::from(&compact), &tree);
+    }
+
+    #[test]
+    fn accessors_match_flat_tree() {
+        let mut tree = sample_tree();
+        let mut compact = tree.to_compact_tree().unwrap();
+        tree.assign_depths();
+        compact.assign_depths().unwrap();
+
+        assert_eq!(compact.len(), tree.len());
+        assert_eq!(compact.root(), tree.root);
+        for (i, node) in tree.nodes.iter().enumerate() {
+            assert_eq!(compact.name(i), node.name);
+            assert_eq!(compact.parent(i), node.parent);
+            assert_eq!(compact.depth(i), node.depth);
+        }
+        assert_eq!(compact.find_node_index("CD"), tree.find_node_index("CD"));
+        assert_eq!(compact.postorder().unwrap(), tree.postorder_indices());
+        assert_eq!(
+            TreeView::postorder(&tree).unwrap(),
+            tree.postorder_indices()
+        );
+        let (a, d) = (tree.find_node_index("A").unwrap(), 6);
+        assert_eq!(compact.lca(a, d).unwrap(), tree.find_lca(a, d).unwrap());
+        assert_eq!(compact.lca(a, a).unwrap(), a);
+        assert_eq!(compact.level(a).unwrap(), 2);
+        let leaves: Vec<&str> = compact
+            .leaf_indices()
+            .into_iter()
+            .map(|i| compact.name(i))
+            .collect();
+        assert_eq!(leaves, ["A", "B", "C", "D"]);
+    }
+}
@@ -121,16 +121,19 @@ impl GeneForest {
         }
 
         let shared_species = Arc::new(sampled_species_tree);
-        let species_leaf_name_set: HashSet<&str> = names.iter().map(|s| s.as_str()).collect();
+        // Resolve the names once per species node, so gene nodes are matched by index.
+        let name_set: HashSet<&str> = names.iter().map(|s| s.as_str()).collect();
+        let kept_species: Vec<bool> = self
+            .species_tree
+            .nodes
+            .iter()
+            .map(|node| name_set.contains(node.name.as_str()))
+            .collect();
 
         let mut sampled_trees = Vec::with_capacity(self.gene_trees.len());
         for rt in &self.gene_trees {
-            let sampled = sample_single_gene_tree(
-                rt,
-                &species_leaf_name_set,
-                &species_old_to_new,
-                &shared_species,
-            )?;
+            let sampled =
+                sample_single_gene_tree(rt, &kept_species, &species_old_to_new, &shared_species)?;
             sampled_trees.push(sampled);
         }
 
@@ -168,6 +171,8 @@ impl GeneForest {
     pub fn sample_extant(&self) -> Result<Self, String> {
         let shared_species = Arc::clone(&self.species_tree);
         let mut sampled_trees = Vec::with_capacity(self.gene_trees.len());
+        // Identity species mapping (species tree is unchanged)
+        let identity: Vec<Option<usize>> = (0..self.species_tree.nodes.len()).map(Some).collect();
 
         for rt in &self.gene_trees {
             // Find extant leaf indices
@@ -192,10 +197,6 @@ impl GeneForest {
                 extract_induced_subtree(&rt.gene_tree, &extant_indices)
                     .ok_or_else(|| "Failed to extract extant gene subtree".to_string())?;
 
-            // Identity species mapping (species tree is unchanged)
-            let identity: Vec<Option<usize>> =
-                (0..self.species_tree.nodes.len()).map(Some).collect();
-
             let (new_node_mapping, new_event_mapping) = remap_gene_tree_indices(
                 &sampled_gene_tree,
                 &gene_old_to_new,
@@ -226,18 +227,18 @@ impl GeneForest {
 
 /// Sample a single gene tree to match a pruned species tree.
 ///
-/// Given a `RecTree` and the set of species leaf names being kept,
+/// Given a `RecTree` and which species nodes are kept (by index),
 /// this function:
 /// 1. Identifies which gene leaves map to the kept species
 /// 2. Extracts the induced gene subtree for those leaves
 /// 3. Remaps node_mapping and event_mapping to the new indices
 fn sample_single_gene_tree(
     rt: &RecTree,
-    species_leaf_names: &HashSet<&str>,
+    kept_species: &[bool],
     species_old_to_new: &[Option<usize>],
     shared_species: &Arc<FlatTree>,
 ) -> Result<RecTree, String> {
-    let gene_leaves_to_keep = find_gene_leaves_for_species(rt, species_leaf_names);
+    let gene_leaves_to_keep = find_gene_leaves_for_species(rt, kept_species);
 
     if gene_leaves_to_keep.is_empty() {
         if rt.node_mapping.iter().all(|m| m.is_none()) {
@@ -269,11 +270,8 @@ fn sample_single_gene_tree(
     ))
 }
 
-/// Find gene tree leaf indices whose species mapping is in the kept set.
-fn find_gene_leaves_for_species(
-    rt: &RecTree,
-    species_leaf_names: &HashSet<&str>,
-) -> HashSet<usize> {
+/// Find gene tree leaf indices whose species mapping is kept.
+fn find_gene_leaves_for_species(rt: &RecTree, kept_species: &[bool]) -> HashSet<usize> {
     rt.gene_tree
         .nodes
         .iter()
@@ -282,10 +280,7 @@ fn find_gene_leaves_for_species(
             node.left_child.is_none()
                 && node.right_child.is_none()
                 && rt.node_mapping[*idx]
-                    .map(|sp_idx| {
-                        species_leaf_names.contains(rt.species_tree.nodes[sp_idx].name.as_str())
-                    })
-                    .unwrap_or(false)
+                    .is_some_and(|sp_idx| kept_species.get(sp_idx).copied().unwrap_or(false))
         })
         .map(|(idx, _)| idx)
         .collect()
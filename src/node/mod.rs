@@ -3,21 +3,26 @@
 //! This module provides two tree representations:
 //! - `Node`: Recursive tree with Box-based children (heap-allocated)
 //! - `FlatTree`/`FlatNode`: Vector-based tree with index references
+//! - `CompactTree`: Column-wise storage of a `FlatTree` for very large trees
 //!
 //! Both representations support traversal (pre-order, in-order, post-order)
-//! and can be converted between each other.
+//! and can be converted between each other. `FlatTree` and `CompactTree` share
+//! the index-based `TreeView` trait.
 
+mod compact;
 mod conversion;
 pub mod gene_forest;
 mod iter;
 pub mod rectree;
 mod traits;
 
+pub use compact::CompactTree;
+
 // Re-export iterator types
 pub use iter::{advance_flat_tree, FlatTreeIndexIter, FlatTreeIter, FlatTreeState, NodeIter};
 
 // Re-export traits
-pub use traits::HasName;
+pub use traits::{HasName, TreeView};
 
 // Re-export conversion functions
 pub use conversion::{map_by_topology, rename_gene_tree};
@@ -25,6 +30,7 @@ pub use conversion::{map_by_topology, rename_gene_tree};
 // Re-export reconciliation types
 pub use crate::io::rectree_csv::RecTreeColumns;
 pub use gene_forest::{remap_gene_tree_indices, GeneForest};
+pub(crate) use rectree::SubtreeIntervals;
 pub use rectree::{Event, RecTree};
 
 // Re-export XML parsing functions (now in io module)
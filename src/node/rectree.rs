@@ -1,7 +1,7 @@
 //! Reconciled tree structure for DTL (Duplication-Transfer-Loss) model.
 
 use super::{FlatNode, FlatTree};
-use crate::dtl::DTLEvent;
+use crate::dtl::{DTLEvent, FamilyRates};
 use crate::error::RustreeError;
 use std::sync::Arc;
 
@@ -20,6 +20,38 @@ pub enum Event {
     Leaf,
 }
 
+/// Postorder position and subtree size of every node of a tree, so that
+/// subtree membership is a range check instead of a walk up the tree.
+#[derive(Clone, Debug)]
+pub(crate) struct SubtreeIntervals {
+    position: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl SubtreeIntervals {
+    pub(crate) fn new(tree: &FlatTree) -> Self {
+        let n = tree.nodes.len();
+        let mut position = vec![0; n];
+        let mut size = vec![1; n];
+        if n > 0 {
+            for (pos, idx) in tree.postorder_indices().into_iter().enumerate() {
+                position[idx] = pos;
+                let node = &tree.nodes[idx];
+                for child in [node.left_child, node.right_child].into_iter().flatten() {
+                    size[idx] += size[child];
+                }
+            }
+        }
+        Self { position, size }
+    }
+
+    /// Whether `node` lies in the subtree rooted at `ancestor` (itself included).
+    pub(crate) fn contains(&self, ancestor: usize, node: usize) -> bool {
+        self.position[node] <= self.position[ancestor]
+            && self.position[node] + self.size[ancestor] > self.position[ancestor]
+    }
+}
+
 /// Reconciled tree structure for DTL model.
 ///
 /// Represents a gene tree reconciled with a species tree, storing the mapping
@@ -42,6 +74,12 @@ pub struct RecTree {
     /// Detailed DTL events from simulation (if available).
     /// `None` for trees parsed from files or after sampling operations.
     pub dtl_events: Option<Vec<DTLEvent>>,
+    /// Realized per-family D/T/L rates when the family was simulated with
+    /// rate heterogeneity. `None` otherwise.
+    pub family_rates: Option<FamilyRates>,
+    /// Sorted indices of gene nodes whose duplication belongs to a
+    /// whole-genome duplication. Empty when there are none or unknown.
+    pub wgd_nodes: Vec<usize>,
 }
 
 fn validate_mappings(
@@ -110,6 +148,8 @@ impl RecTree {
             node_mapping,
             event_mapping,
             dtl_events: None,
+            family_rates: None,
+            wgd_nodes: Vec::new(),
         })
     }
 
@@ -151,6 +191,8 @@ impl RecTree {
             node_mapping,
             event_mapping,
             dtl_events: Some(dtl_events),
+            family_rates: None,
+            wgd_nodes: Vec::new(),
         })
     }
 
@@ -188,6 +230,53 @@ impl RecTree {
         )
     }
 
+    /// Whether gene node `gene_node_idx` is a whole-genome duplication.
+    pub fn is_wgd(&self, gene_node_idx: usize) -> bool {
+        self.wgd_nodes.binary_search(&gene_node_idx).is_ok()
+    }
+
+    /// Record the whole-genome duplication nodes, in any order.
+    pub(crate) fn set_wgd_nodes(&mut self, mut wgd_nodes: Vec<usize>) {
+        wgd_nodes.sort_unstable();
+        wgd_nodes.dedup();
+        self.wgd_nodes = wgd_nodes;
+    }
+
+    /// Whether gene node `gene` is a transfer event.
+    ///
+    /// Transfer children carry the Transfer label as well; only nodes with
+    /// two children are transfer events.
+    pub fn is_transfer_event(&self, gene: usize) -> bool {
+        let node = &self.gene_tree.nodes[gene];
+        self.event_mapping[gene] == Event::Transfer
+            && node.left_child.is_some()
+            && node.right_child.is_some()
+    }
+
+    /// Child of transfer event `gene` received by the recipient: the child
+    /// mapped outside the donor's species subtree.
+    ///
+    /// `None` if `gene` is not a transfer event or no child maps outside the
+    /// donor. `species_subtrees` must be built from `self.species_tree`.
+    pub(crate) fn transfer_recipient_child(
+        &self,
+        gene: usize,
+        species_subtrees: &SubtreeIntervals,
+    ) -> Option<usize> {
+        if !self.is_transfer_event(gene) {
+            return None;
+        }
+        let donor = self.node_mapping[gene]?;
+        let node = &self.gene_tree.nodes[gene];
+        [node.right_child, node.left_child]
+            .into_iter()
+            .flatten()
+            .find(|&child| {
+                self.node_mapping[child]
+                    .is_some_and(|species| !species_subtrees.contains(donor, species))
+            })
+    }
+
     /// Gets the species tree node index for a given gene tree node.
     /// Returns `None` inside `Ok` if the mapping is unknown (e.g., after pruning).
     ///
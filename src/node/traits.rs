@@ -1,6 +1,7 @@
 //! Traits and operator implementations for tree types.
 
 use super::{FlatNode, FlatTree, Node};
+use crate::error::RustreeError;
 use std::ops::{Index, IndexMut};
 
 /// Trait for types that have a name field.
@@ -32,6 +33,190 @@ impl HasName for &FlatNode {
     }
 }
 
+/// Read-only, index-based access to a binary tree's nodes.
+///
+/// Implemented by [`FlatTree`] and [`CompactTree`](super::CompactTree), so the
+/// provided traversal, depth and LCA algorithms run on either layout. Node
+/// indices must be in bounds; the provided methods check the root and the
+/// nodes they are given.
+pub trait TreeView {
+    /// Number of nodes.
+    fn len(&self) -> usize;
+
+    /// Index of the root node.
+    fn root(&self) -> usize;
+
+    fn name(&self, index: usize) -> &str;
+
+    fn left_child(&self, index: usize) -> Option<usize>;
+
+    fn right_child(&self, index: usize) -> Option<usize>;
+
+    fn parent(&self, index: usize) -> Option<usize>;
+
+    /// Stored distance from the root, or `None` if depths are not assigned.
+    fn depth(&self, index: usize) -> Option<f64>;
+
+    /// Length of the branch above node `index`.
+    fn length(&self, index: usize) -> f64;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if node `index` has no children.
+    fn is_leaf(&self, index: usize) -> bool {
+        self.left_child(index).is_none() && self.right_child(index).is_none()
+    }
+
+    /// Find a node by name and return its index.
+    fn find_node_index(&self, name: &str) -> Option<usize> {
+        (0..self.len()).find(|&i| self.name(i) == name)
+    }
+
+    /// Indices of all structural leaves (nodes with no children).
+    fn leaf_indices(&self) -> Vec<usize> {
+        (0..self.len()).filter(|&i| self.is_leaf(i)).collect()
+    }
+
+    /// Node indices in post-order (children before parents, left before right).
+    ///
+    /// Returns an error if the root is out of bounds, e.g. for an empty tree.
+    fn postorder(&self) -> Result<Vec<usize>, RustreeError> {
+        let root = checked_node(self, self.root())?;
+        let mut order = Vec::with_capacity(self.len());
+        let mut stack = vec![(root, false)];
+        while let Some((node, expanded)) = stack.pop() {
+            if expanded {
+                order.push(node);
+                continue;
+            }
+            stack.push((node, true));
+            if let Some(right) = self.right_child(node) {
+                stack.push((right, false));
+            }
+            if let Some(left) = self.left_child(node) {
+                stack.push((left, false));
+            }
+        }
+        Ok(order)
+    }
+
+    /// Distance of every node from the root, from branch lengths alone.
+    ///
+    /// The root's depth is its own branch length, as in
+    /// [`FlatTree::assign_depths`]; nodes not reachable from the root get
+    /// `None`. Returns an error if the root is out of bounds.
+    fn depths_from_lengths(&self) -> Result<Vec<Option<f64>>, RustreeError> {
+        let root = checked_node(self, self.root())?;
+        let mut depths = vec![None; self.len()];
+        depths[root] = Some(self.length(root));
+        let mut stack = vec![(root, self.length(root))];
+        while let Some((node, depth)) = stack.pop() {
+            for child in [self.left_child(node), self.right_child(node)]
+                .into_iter()
+                .flatten()
+            {
+                let child_depth = depth + self.length(child);
+                depths[child] = Some(child_depth);
+                stack.push((child, child_depth));
+            }
+        }
+        Ok(depths)
+    }
+
+    /// Number of edges between node `index` and the root.
+    ///
+    /// Returns an error if `index` is out of bounds or the parent links
+    /// contain a cycle.
+    fn level(&self, index: usize) -> Result<usize, RustreeError> {
+        let mut node = checked_node(self, index)?;
+        let mut level = 0;
+        while let Some(parent) = self.parent(node) {
+            level += 1;
+            if level >= self.len() {
+                return Err(RustreeError::Tree(format!(
+                    "parent links above node {index} contain a cycle"
+                )));
+            }
+            node = parent;
+        }
+        Ok(level)
+    }
+
+    /// Lowest common ancestor of two nodes, following parent links.
+    ///
+    /// Takes O(h) time and no extra memory. For many queries on the same tree
+    /// use [`LcaTable`](crate::metric_functions::LcaTable).
+    fn lca(&self, a: usize, b: usize) -> Result<usize, RustreeError> {
+        let (mut a, mut b) = (a, b);
+        let (mut level_a, mut level_b) = (self.level(a)?, self.level(b)?);
+        while level_a > level_b {
+            a = self.parent(a).unwrap_or(a);
+            level_a -= 1;
+        }
+        while level_b > level_a {
+            b = self.parent(b).unwrap_or(b);
+            level_b -= 1;
+        }
+        while a != b {
+            match (self.parent(a), self.parent(b)) {
+                (Some(pa), Some(pb)) => (a, b) = (pa, pb),
+                _ => {
+                    return Err(RustreeError::Tree(format!(
+                        "nodes {a} and {b} have no common ancestor"
+                    )))
+                }
+            }
+        }
+        Ok(a)
+    }
+}
+
+fn checked_node<T: TreeView + ?Sized>(tree: &T, index: usize) -> Result<usize, RustreeError> {
+    if index >= tree.len() {
+        return Err(RustreeError::Index(format!(
+            "node index {index} is out of bounds (tree has {} nodes)",
+            tree.len()
+        )));
+    }
+    Ok(index)
+}
+
+impl TreeView for FlatTree {
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn root(&self) -> usize {
+        self.root
+    }
+
+    fn name(&self, index: usize) -> &str {
+        &self.nodes[index].name
+    }
+
+    fn left_child(&self, index: usize) -> Option<usize> {
+        self.nodes[index].left_child
+    }
+
+    fn right_child(&self, index: usize) -> Option<usize> {
+        self.nodes[index].right_child
+    }
+
+    fn parent(&self, index: usize) -> Option<usize> {
+        self.nodes[index].parent
+    }
+
+    fn depth(&self, index: usize) -> Option<f64> {
+        self.nodes[index].depth
+    }
+
+    fn length(&self, index: usize) -> f64 {
+        self.nodes[index].length
+    }
+}
+
 impl Index<usize> for FlatTree {
     type Output = FlatNode;
 
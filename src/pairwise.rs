@@ -0,0 +1,324 @@
+//! Parallel, memory-aware pairwise distance computation.
+//!
+//! [`FlatTree::pairwise_distances`] walks both paths to the LCA for every pair
+//! and returns owned entries, so large trees are limited by time and memory.
+//! Here each distance is `r(a) + r(b) - 2 r(lca(a, b))`, with `r` the distance
+//! from the root and the LCA found in O(1) through [`LcaTable`]. Rows of the
+//! upper triangle are computed in parallel with rayon, and can be collected into
+//! a condensed matrix or streamed block by block to CSV or NumPy `.npy` files.
+
+use crate::error::RustreeError;
+use crate::metric_functions::{DistanceType, LcaTable, PairwiseDistance};
+use crate::node::FlatTree;
+use rayon::prelude::*;
+use std::io::Write;
+
+/// Number of distances computed per block when streaming rows.
+const STREAM_BLOCK_VALUES: usize = 1 << 22;
+
+/// O(1) distance queries between any two nodes of a tree.
+pub struct DistanceOracle {
+    lca: LcaTable,
+    /// Edges (topological) or summed branch lengths (metric) from the root.
+    from_root: Vec<f64>,
+}
+
+impl DistanceOracle {
+    /// Precomputes LCA and root-distance tables for `tree`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tree has no nodes (via [`LcaTable::new`]).
+    pub fn new(tree: &FlatTree, distance_type: DistanceType) -> Self {
+        let mut from_root = vec![0.0; tree.nodes.len()];
+        let mut stack = vec![tree.root];
+        while let Some(idx) = stack.pop() {
+            let node = &tree.nodes[idx];
+            for child in [node.left_child, node.right_child].into_iter().flatten() {
+                let step = match distance_type {
+                    DistanceType::Topological => 1.0,
+                    DistanceType::Metric => tree.nodes[child].length,
+                };
+                from_root[child] = from_root[idx] + step;
+                stack.push(child);
+            }
+        }
+        DistanceOracle {
+            lca: LcaTable::new(tree),
+            from_root,
+        }
+    }
+
+    /// Distance between nodes `a` and `b`.
+    #[inline]
+    pub fn distance(&self, a: usize, b: usize) -> f64 {
+        if a == b {
+            return 0.0;
+        }
+        let lca = self.lca.lca(a, b);
+        self.from_root[a] + self.from_root[b] - 2.0 * self.from_root[lca]
+    }
+
+    /// Distances from `nodes[row]` to every later entry of `nodes`.
+    fn upper_row(&self, nodes: &[usize], row: usize, out: &mut [f64]) {
+        let a = nodes[row];
+        for (cell, &b) in out.iter_mut().zip(&nodes[row + 1..]) {
+            *cell = self.distance(a, b);
+        }
+    }
+}
+
+/// Upper triangle of a pairwise distance matrix, stored row by row.
+///
+/// Uses the same layout as SciPy's `pdist`: the distance between `nodes[i]`
+/// and `nodes[j]` (`i < j`) is at `n*i - i*(i+1)/2 + (j - i - 1)`.
+#[derive(Clone, Debug)]
+pub struct CondensedDistances {
+    /// Node indices, in matrix order.
+    pub nodes: Vec<usize>,
+    /// Upper-triangular distances, of length `n*(n-1)/2`.
+    pub values: Vec<f64>,
+}
+
+impl CondensedDistances {
+    /// Distance between the `i`-th and `j`-th nodes of [`nodes`](Self::nodes).
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+        if i == j {
+            return 0.0;
+        }
+        let (i, j) = (i.min(j), i.max(j));
+        self.values[condensed_offset(self.nodes.len(), i) + (j - i - 1)]
+    }
+}
+
+/// Offset of the first entry of row `i` in a condensed matrix of `n` points.
+#[inline]
+fn condensed_offset(n: usize, i: usize) -> usize {
+    n * i - i * (i + 1) / 2
+}
+
+impl FlatTree {
+    fn distance_nodes(&self, leaves_only: bool) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|&i| {
+                !leaves_only
+                    || (self.nodes[i].left_child.is_none() && self.nodes[i].right_child.is_none())
+            })
+            .collect()
+    }
+
+    /// Computes the condensed upper-triangular distance matrix in parallel.
+    ///
+    /// Holds `n*(n-1)/2` values; use
+    /// [`for_each_pairwise_distance_row`](Self::for_each_pairwise_distance_row)
+    /// or the file writers when that does not fit in memory.
+    pub fn pairwise_distances_condensed(
+        &self,
+        distance_type: DistanceType,
+        leaves_only: bool,
+    ) -> CondensedDistances {
+        let nodes = self.distance_nodes(leaves_only);
+        let n = nodes.len();
+        let mut values = vec![0.0; n * n.saturating_sub(1) / 2];
+        if n > 1 {
+            let oracle = DistanceOracle::new(self, distance_type);
+            let mut rows = Vec::with_capacity(n - 1);
+            let mut rest = values.as_mut_slice();
+            for row in 0..n - 1 {
+                let (head, tail) = rest.split_at_mut(n - row - 1);
+                rows.push(head);
+                rest = tail;
+            }
+            rows.into_par_iter()
+                .enumerate()
+                .for_each(|(row, out)| oracle.upper_row(&nodes, row, out));
+        }
+        CondensedDistances { nodes, values }
+    }
+
+    /// Streams the rows of the upper-triangular distance matrix in order.
+    ///
+    /// `sink(row, nodes, distances)` receives the distances from `nodes[row]`
+    /// to `nodes[row + 1..]`. Rows are computed in parallel a block at a time,
+    /// so memory stays bounded regardless of tree size. The first error
+    /// returned by `sink` stops the stream.
+    pub fn for_each_pairwise_distance_row<E, F>(
+        &self,
+        distance_type: DistanceType,
+        leaves_only: bool,
+        mut sink: F,
+    ) -> Result<(), E>
+    where
+        F: FnMut(usize, &[usize], &[f64]) -> Result<(), E>,
+    {
+        let nodes = self.distance_nodes(leaves_only);
+        let n = nodes.len();
+        if n < 2 {
+            return Ok(());
+        }
+        let oracle = DistanceOracle::new(self, distance_type);
+
+        let mut start = 0;
+        while start < n - 1 {
+            // Grow the block until it holds about STREAM_BLOCK_VALUES distances.
+            let mut end = start;
+            let mut block_values = 0;
+            while end < n - 1 && (end == start || block_values < STREAM_BLOCK_VALUES) {
+                block_values += n - end - 1;
+                end += 1;
+            }
+            let block: Vec<Vec<f64>> = (start..end)
+                .into_par_iter()
+                .map(|row| {
+                    let mut out = vec![0.0; n - row - 1];
+                    oracle.upper_row(&nodes, row, &mut out);
+                    out
+                })
+                .collect();
+            for (offset, distances) in block.iter().enumerate() {
+                sink(start + offset, &nodes, distances)?;
+            }
+            start = end;
+        }
+        Ok(())
+    }
+
+    /// Streams pairwise distances to `writer` as CSV, without holding the matrix.
+    ///
+    /// Rows match [`PairwiseDistance::to_csv_row`] under
+    /// [`PairwiseDistance::csv_header`], in the order of
+    /// [`pairwise_distances`](Self::pairwise_distances).
+    pub fn write_pairwise_distances_csv<W: Write>(
+        &self,
+        writer: &mut W,
+        distance_type: DistanceType,
+        leaves_only: bool,
+    ) -> Result<(), RustreeError> {
+        writeln!(writer, "{}", PairwiseDistance::csv_header())?;
+        self.for_each_pairwise_distance_row(
+            distance_type,
+            leaves_only,
+            |row, nodes, distances| -> Result<(), RustreeError> {
+                let name1 = &self.nodes[nodes[row]].name;
+                for (&j, &distance) in nodes[row + 1..].iter().zip(distances) {
+                    writeln!(writer, "{},{},{}", name1, self.nodes[j].name, distance)?;
+                }
+                Ok(())
+            },
+        )?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Streams the condensed distance matrix to `writer` as a 1-D NumPy
+    /// `.npy` array of little-endian `f64`, without holding the matrix.
+    ///
+    /// The layout matches [`pairwise_distances_condensed`](Self::pairwise_distances_condensed),
+    /// so `scipy.spatial.distance.squareform(np.load(path))` recovers the full matrix.
+    pub fn write_pairwise_distances_npy<W: Write>(
+        &self,
+        writer: &mut W,
+        distance_type: DistanceType,
+        leaves_only: bool,
+    ) -> Result<(), RustreeError> {
+        let n = self.distance_nodes(leaves_only).len();
+        write_npy_f64_header(writer, n * n.saturating_sub(1) / 2)?;
+        self.for_each_pairwise_distance_row(
+            distance_type,
+            leaves_only,
+            |_, _, distances| -> Result<(), RustreeError> {
+                let mut bytes = Vec::with_capacity(8 * distances.len());
+                for distance in distances {
+                    bytes.extend_from_slice(&distance.to_le_bytes());
+                }
+                writer.write_all(&bytes)?;
+                Ok(())
+            },
+        )?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes an NPY v1.0 header for a 1-D little-endian `f64` array of `len` values.
+fn write_npy_f64_header<W: Write>(writer: &mut W, len: usize) -> std::io::Result<()> {
+    const MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+    let mut header = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': ({len},), }}");
+    // Magic, two length bytes and the header (ending in '\n') align to 64 bytes.
+    let unpadded = MAGIC.len() + 2 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newick::parse_newick;
+
+    fn make_tree() -> FlatTree {
+        let mut nodes =
+            parse_newick("(((A:1,B:2)AB:0.5,C:3)ABC:1,((D:0.25,E:1)DE:2,F:0.75)DEF:1.5)root:0;")
+                .unwrap();
+        nodes.pop().unwrap().to_flat_tree()
+    }
+
+    #[test]
+    fn condensed_matches_pairwise_distances() {
+        let tree = make_tree();
+        for distance_type in [DistanceType::Metric, DistanceType::Topological] {
+            for leaves_only in [true, false] {
+                let expected = tree.pairwise_distances(distance_type, leaves_only).unwrap();
+                let condensed = tree.pairwise_distances_condensed(distance_type, leaves_only);
+                assert_eq!(condensed.values.len(), expected.len());
+                for (got, want) in condensed.values.iter().zip(&expected) {
+                    assert!((got - want.distance).abs() < 1e-12);
+                }
+                let n = condensed.nodes.len();
+                let last = condensed.get(n - 1, n - 2);
+                assert_eq!(last, *condensed.values.last().unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn streamed_csv_matches_pairwise_rows() {
+        let tree = make_tree();
+        let mut csv = Vec::new();
+        tree.write_pairwise_distances_csv(&mut csv, DistanceType::Topological, true)
+            .unwrap();
+
+        let mut expected = format!("{}\n", PairwiseDistance::csv_header());
+        for d in tree
+            .pairwise_distances(DistanceType::Topological, true)
+            .unwrap()
+        {
+            expected.push_str(&d.to_csv_row());
+            expected.push('\n');
+        }
+        assert_eq!(String::from_utf8(csv).unwrap(), expected);
+    }
+
+    #[test]
+    fn npy_output_has_aligned_header_and_condensed_payload() {
+        let tree = make_tree();
+        let mut npy = Vec::new();
+        tree.write_pairwise_distances_npy(&mut npy, DistanceType::Metric, true)
+            .unwrap();
+
+        let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        let header = std::str::from_utf8(&npy[10..10 + header_len]).unwrap();
+        assert!(header.contains("'shape': (15,)"));
+        assert!(header.ends_with('\n'));
+
+        let payload: Vec<f64> = npy[10 + header_len..]
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        let condensed = tree.pairwise_distances_condensed(DistanceType::Metric, true);
+        assert_eq!(payload, condensed.values);
+    }
+}
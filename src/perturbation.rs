@@ -0,0 +1,377 @@
+//! Random topology perturbation of trees.
+//!
+//! Applies random NNI and/or SPR rearrangements (see [`crate::surgery`]) to a
+//! true tree to mimic gene tree inference error, either a fixed number of
+//! times or until a target rooted Robinson-Foulds distance is reached.
+//! Rearrangements can be biased toward short branches, where inference is
+//! least reliable.
+
+use crate::error::RustreeError;
+use crate::node::FlatTree;
+use crate::robinson_foulds::robinson_foulds;
+use crate::simulation::utils::sample_weighted_index;
+use crate::surgery::{nni_topology, spr_topology};
+use rand::Rng;
+use std::collections::{BTreeSet, HashSet};
+
+/// Which rearrangements to draw.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RearrangementKind {
+    Nni,
+    Spr,
+    /// Each move is an SPR with probability `spr_fraction`, otherwise an NNI.
+    Mixed {
+        spr_fraction: f64,
+    },
+}
+
+/// When to stop perturbing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PerturbationTarget {
+    /// Apply exactly this many rearrangements.
+    Moves(usize),
+    /// Rearrange until the rooted RF distance to the original tree is at least this.
+    RobinsonFoulds(usize),
+}
+
+/// Topology perturbation settings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreePerturbation {
+    pub kind: RearrangementKind,
+    pub target: PerturbationTarget,
+    /// If set, branches are picked with weight `exp(-length / scale)`, so
+    /// short branches are rearranged more often. Uniform otherwise.
+    pub short_branch_scale: Option<f64>,
+    /// Upper bound on rearrangements when targeting an RF distance.
+    pub max_moves: usize,
+}
+
+impl TreePerturbation {
+    /// Apply `k` random rearrangements.
+    pub fn moves(kind: RearrangementKind, k: usize) -> Result<Self, RustreeError> {
+        Self::with_target(kind, PerturbationTarget::Moves(k))
+    }
+
+    /// Rearrange until the rooted RF distance reaches `distance`.
+    pub fn robinson_foulds(kind: RearrangementKind, distance: usize) -> Result<Self, RustreeError> {
+        Self::with_target(kind, PerturbationTarget::RobinsonFoulds(distance))
+    }
+
+    fn with_target(
+        kind: RearrangementKind,
+        target: PerturbationTarget,
+    ) -> Result<Self, RustreeError> {
+        let max_moves = match target {
+            PerturbationTarget::Moves(k) => k,
+            PerturbationTarget::RobinsonFoulds(d) => d.saturating_mul(100).max(100),
+        };
+        let perturbation = Self {
+            kind,
+            target,
+            short_branch_scale: None,
+            max_moves,
+        };
+        perturbation.validate()?;
+        Ok(perturbation)
+    }
+
+    /// Bias rearrangements toward branches shorter than about `scale`.
+    pub fn with_short_branch_bias(mut self, scale: f64) -> Result<Self, RustreeError> {
+        self.short_branch_scale = Some(scale);
+        self.validate()?;
+        Ok(self)
+    }
+
+    pub fn with_max_moves(mut self, max_moves: usize) -> Self {
+        self.max_moves = max_moves;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), RustreeError> {
+        if let RearrangementKind::Mixed { spr_fraction } = self.kind {
+            if !(0.0..=1.0).contains(&spr_fraction) {
+                return Err(RustreeError::Validation(format!(
+                    "spr_fraction must be in [0, 1], got {spr_fraction}"
+                )));
+            }
+        }
+        if let Some(scale) = self.short_branch_scale {
+            if !(scale.is_finite() && scale > 0.0) {
+                return Err(RustreeError::Validation(format!(
+                    "short_branch_scale must be positive and finite, got {scale}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn branch_weight(&self, length: f64) -> f64 {
+        self.short_branch_scale.map_or(1.0, |scale| {
+            (-length.max(0.0) / scale).exp().max(f64::MIN_POSITIVE)
+        })
+    }
+}
+
+/// A perturbed tree and a summary of what changed.
+#[derive(Clone, Debug)]
+pub struct PerturbedTree {
+    /// The rearranged tree. Node indices and names match the input tree.
+    pub tree: FlatTree,
+    /// Number of rearrangements applied.
+    pub moves: usize,
+    /// Rooted RF distance to the input tree.
+    pub robinson_foulds: usize,
+    /// Internal nodes of `tree` whose clade does not exist in the input tree.
+    pub modified_clades: Vec<usize>,
+}
+
+impl PerturbedTree {
+    /// Leaf names of each modified clade, in the order of `modified_clades`.
+    pub fn modified_clade_leaves(&self) -> Vec<Vec<String>> {
+        let leaves = clade_leaves(&self.tree);
+        self.modified_clades
+            .iter()
+            .map(|&idx| leaves[idx].iter().cloned().collect())
+            .collect()
+    }
+}
+
+/// Randomly rearrange the topology of `tree`.
+///
+/// The tree must be binary with unique leaf names. Branch lengths follow the
+/// moved subtrees: an SPR splits the recipient branch in half for the
+/// regrafted parent and merges the pruned parent's branch into the promoted
+/// sibling. Depths are recomputed if the input had them.
+pub fn perturb_tree<R: Rng>(
+    tree: &FlatTree,
+    perturbation: &TreePerturbation,
+    rng: &mut R,
+) -> Result<PerturbedTree, RustreeError> {
+    perturbation.validate()?;
+    let mut noisy = tree.clone();
+    let mut moves = 0;
+    let mut distance = 0;
+
+    loop {
+        let done = match perturbation.target {
+            PerturbationTarget::Moves(k) => moves >= k,
+            PerturbationTarget::RobinsonFoulds(d) => distance >= d,
+        };
+        if done {
+            break;
+        }
+        if moves >= perturbation.max_moves {
+            return Err(RustreeError::Simulation(format!(
+                "reached {} rearrangements without the target RF distance (current distance {distance})",
+                perturbation.max_moves
+            )));
+        }
+
+        let spr = match perturbation.kind {
+            RearrangementKind::Nni => false,
+            RearrangementKind::Spr => true,
+            RearrangementKind::Mixed { spr_fraction } => rng.gen::<f64>() < spr_fraction,
+        };
+        if spr {
+            random_spr(&mut noisy, perturbation, rng)?;
+        } else {
+            random_nni(&mut noisy, perturbation, rng)?;
+        }
+        moves += 1;
+        if matches!(perturbation.target, PerturbationTarget::RobinsonFoulds(_)) {
+            distance = robinson_foulds(tree, &noisy)?;
+        }
+    }
+
+    if tree.nodes.iter().all(|node| node.depth.is_some()) {
+        noisy.assign_depths();
+    } else {
+        for node in &mut noisy.nodes {
+            node.depth = None;
+        }
+    }
+
+    let original: HashSet<BTreeSet<String>> = internal_clades(tree)
+        .into_iter()
+        .map(|(_, clade)| clade)
+        .collect();
+    let modified_clades = internal_clades(&noisy)
+        .into_iter()
+        .filter(|(_, clade)| !original.contains(clade))
+        .map(|(idx, _)| idx)
+        .collect();
+
+    Ok(PerturbedTree {
+        robinson_foulds: robinson_foulds(tree, &noisy)?,
+        tree: noisy,
+        moves,
+        modified_clades,
+    })
+}
+
+fn random_nni<R: Rng>(
+    tree: &mut FlatTree,
+    perturbation: &TreePerturbation,
+    rng: &mut R,
+) -> Result<(), RustreeError> {
+    let weights: Vec<f64> = tree
+        .nodes
+        .iter()
+        .map(|node| {
+            let internal = node.left_child.is_some() && node.right_child.is_some();
+            if internal && node.parent.is_some() {
+                perturbation.branch_weight(node.length)
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    let node = sample_weighted_index(&weights, rng).ok_or_else(|| {
+        RustreeError::Validation("tree has no internal branch to apply an NNI to".to_string())
+    })?;
+    let swap_left = rng.gen::<bool>();
+    nni_topology(tree, node, swap_left).map_err(RustreeError::Simulation)
+}
+
+fn random_spr<R: Rng>(
+    tree: &mut FlatTree,
+    perturbation: &TreePerturbation,
+    rng: &mut R,
+) -> Result<(), RustreeError> {
+    let n = tree.nodes.len();
+    // Each subtree is a contiguous run of postorder positions, so subtree
+    // membership is a range check instead of a walk up the tree.
+    let mut position = vec![0; n];
+    let mut size = vec![1; n];
+    for (pos, idx) in tree.postorder_indices().into_iter().enumerate() {
+        position[idx] = pos;
+        let node = &tree.nodes[idx];
+        for child in [node.left_child, node.right_child].into_iter().flatten() {
+            size[idx] += size[child];
+        }
+    }
+    let in_subtree = |root: usize, r: usize| {
+        position[r] <= position[root] && position[r] + size[root] > position[root]
+    };
+
+    // A pruned node needs at least one non-trivial regraft point: the nodes
+    // outside its subtree other than its parent and sibling.
+    let weights: Vec<f64> = (0..n)
+        .map(|idx| {
+            let node = &tree.nodes[idx];
+            let Some(parent) = node.parent else {
+                return 0.0;
+            };
+            let p_node = &tree.nodes[parent];
+            let has_sibling = p_node.left_child.is_some() && p_node.right_child.is_some();
+            if n > size[idx] + 1 + usize::from(has_sibling) {
+                perturbation.branch_weight(node.length)
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    let moving = sample_weighted_index(&weights, rng).ok_or_else(|| {
+        RustreeError::Validation("tree has no subtree to apply an SPR to".to_string())
+    })?;
+    let parent = tree.nodes[moving]
+        .parent
+        .ok_or_else(|| RustreeError::Simulation(format!("SPR node {moving} has no parent")))?;
+    let (left, right) = (
+        tree.nodes[parent].left_child,
+        tree.nodes[parent].right_child,
+    );
+    let candidates: Vec<usize> = (0..n)
+        .filter(|&r| r != parent && Some(r) != left && Some(r) != right && !in_subtree(moving, r))
+        .collect();
+    let recipient = candidates[rng.gen_range(0..candidates.len())];
+    let sibling = if left == Some(moving) { right } else { left };
+
+    spr_topology(tree, moving, recipient).map_err(RustreeError::Simulation)?;
+
+    // Keep path lengths sensible: the promoted sibling absorbs the pruned
+    // parent branch, and the regrafted parent splits the recipient branch.
+    if let Some(sibling) = sibling {
+        tree.nodes[sibling].length += tree.nodes[parent].length;
+    }
+    let half = tree.nodes[recipient].length / 2.0;
+    tree.nodes[parent].length = half;
+    tree.nodes[recipient].length = half;
+    Ok(())
+}
+
+/// Leaf-name set below every node, indexed by node.
+fn clade_leaves(tree: &FlatTree) -> Vec<BTreeSet<String>> {
+    let mut leaves = vec![BTreeSet::new(); tree.nodes.len()];
+    let mut stack = vec![(tree.root, false)];
+    while let Some((idx, expanded)) = stack.pop() {
+        let node = &tree.nodes[idx];
+        if expanded {
+            let mut clade = BTreeSet::new();
+            for child in [node.left_child, node.right_child].into_iter().flatten() {
+                clade.extend(leaves[child].iter().cloned());
+            }
+            leaves[idx] = clade;
+        } else if node.left_child.is_none() && node.right_child.is_none() {
+            leaves[idx].insert(node.name.clone());
+        } else {
+            stack.push((idx, true));
+            stack.extend(node.left_child.map(|c| (c, false)));
+            stack.extend(node.right_child.map(|c| (c, false)));
+        }
+    }
+    leaves
+}
+
+/// Non-root internal nodes with their clades (the clades counted by rooted RF).
+fn internal_clades(tree: &FlatTree) -> Vec<(usize, BTreeSet<String>)> {
+    clade_leaves(tree)
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| {
+            let node = &tree.nodes[*idx];
+            node.parent.is_some() && (node.left_child.is_some() || node.right_child.is_some())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newick::parse_newick;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn tree(newick: &str) -> FlatTree {
+        let mut nodes = parse_newick(newick).unwrap();
+        nodes.pop().unwrap().to_flat_tree()
+    }
+
+    #[test]
+    fn single_nni_changes_one_clade() {
+        let original = tree("((A:1,B:1)AB:1,C:2)root:0;");
+        let perturbation = TreePerturbation::moves(RearrangementKind::Nni, 1).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        let noisy = perturb_tree(&original, &perturbation, &mut rng).unwrap();
+        assert_eq!(noisy.moves, 1);
+        assert_eq!(noisy.robinson_foulds, 2);
+        assert_eq!(noisy.modified_clades.len(), 1);
+        let clade = &noisy.modified_clade_leaves()[0];
+        assert!(clade.contains(&"C".to_string()));
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        assert!(
+            TreePerturbation::moves(RearrangementKind::Mixed { spr_fraction: 1.5 }, 1).is_err()
+        );
+        assert!(TreePerturbation::moves(RearrangementKind::Nni, 1)
+            .unwrap()
+            .with_short_branch_bias(0.0)
+            .is_err());
+        let two_leaves = tree("(A:1,B:1)root:0;");
+        let perturbation = TreePerturbation::moves(RearrangementKind::Nni, 1).unwrap();
+        let mut rng = StdRng::seed_from_u64(2);
+        assert!(perturb_tree(&two_leaves, &perturbation, &mut rng).is_err());
+    }
+}
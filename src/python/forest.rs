@@ -109,6 +109,29 @@ impl PyGeneForest {
         Ok(PyGeneForest { forest: sampled })
     }
 
+    /// Forest-level summary statistics as a dict of pandas DataFrames.
+    ///
+    /// Keys are `branch_events` (event totals per species branch),
+    /// `transfers` (donor/recipient counts) and `copy_numbers` (copy-number
+    /// histograms per extant species).
+    ///
+    /// # Arguments
+    /// * `output_dir` - Optional directory to also write the three CSV files
+    #[pyo3(signature = (output_dir=None))]
+    fn summarize(&self, py: Python, output_dir: Option<&str>) -> PyResult<PyObject> {
+        let summary = crate::dtl::ForestSummary::from_rec_trees(
+            Arc::clone(&self.forest.species_tree),
+            &self.forest.gene_trees,
+        )
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        if let Some(dir) = output_dir {
+            summary
+                .save_csv(dir)
+                .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        }
+        super::sim_iter::summary_to_dataframes(py, &summary)
+    }
+
     /// Prune the forest to match a target species tree.
     ///
     /// The target species tree's leaves must be a subset of this forest's
@@ -35,6 +35,7 @@ pub(crate) fn columns_to_dataframe(py: Python, cols: &RecTreeColumns) -> PyResul
     dict.set_item("species_node_left", &cols.species_node_left)?;
     dict.set_item("species_node_right", &cols.species_node_right)?;
     dict.set_item("event", &cols.event)?;
+    dict.set_item("wgd", &cols.wgd)?;
     let df = pandas.call_method1("DataFrame", (dict,))?;
     Ok(df.into())
 }
@@ -708,7 +709,7 @@ impl PyGeneTree {
     ///
     /// Returns a DataFrame with columns: node_id, name, parent, left_child,
     /// left_child_name, right_child, right_child_name, length, depth,
-    /// species_node, species_node_left, species_node_right, event
+    /// species_node, species_node_left, species_node_right, event, wgd
     ///
     /// # Arguments
     /// * `filepath` - Optional path to save the CSV file
@@ -798,7 +799,7 @@ impl PyGeneTree {
         distance_type: &str,
         leaves_only: bool,
     ) -> PyResult<()> {
-        use crate::metric_functions::{DistanceType, PairwiseDistance};
+        use crate::metric_functions::DistanceType;
 
         let dist_type = match distance_type.to_lowercase().as_str() {
             "topological" | "topo" => DistanceType::Topological,
@@ -811,27 +812,64 @@ impl PyGeneTree {
             }
         };
 
-        let distances = self
-            .rec_tree
+        let file = File::create(filepath)
+            .map_err(|e| PyValueError::new_err(format!("Failed to write CSV file: {}", e)))?;
+        self.rec_tree
             .gene_tree
-            .pairwise_distances(dist_type, leaves_only)
-            .map_err(|e| {
-                PyValueError::new_err(format!("Failed to compute pairwise distances: {}", e))
-            })?;
-
-        let mut csv = String::from(PairwiseDistance::csv_header());
-        csv.push('\n');
-        for d in &distances {
-            csv.push_str(&d.to_csv_row());
-            csv.push('\n');
-        }
-
-        fs::write(filepath, csv)
+            .write_pairwise_distances_csv(&mut BufWriter::new(file), dist_type, leaves_only)
             .map_err(|e| PyValueError::new_err(format!("Failed to write CSV file: {}", e)))?;
 
         Ok(())
     }
 
+    /// Compute the condensed upper-triangular distance matrix in parallel.
+    ///
+    /// Returns `(names, distances)` where `distances` is a 1-D NumPy array in
+    /// `scipy.spatial.distance.pdist` order over `names`.
+    #[pyo3(signature = (distance_type, leaves_only=true))]
+    fn pairwise_distances_condensed(
+        &self,
+        py: Python,
+        distance_type: &str,
+        leaves_only: bool,
+    ) -> PyResult<(Vec<String>, PyObject)> {
+        use numpy::PyArray1;
+
+        let dist_type = parse_distance_type(distance_type)?;
+        let tree = &self.rec_tree.gene_tree;
+        let condensed =
+            py.allow_threads(|| tree.pairwise_distances_condensed(dist_type, leaves_only));
+        let names = condensed
+            .nodes
+            .iter()
+            .map(|&i| tree.nodes[i].name.clone())
+            .collect();
+        let values = PyArray1::from_vec(py, condensed.values);
+        Ok((names, values.into_any().unbind()))
+    }
+
+    /// Stream the condensed distance matrix to a NumPy `.npy` file.
+    ///
+    /// Rows are computed in parallel and written block by block, so the full
+    /// matrix is never held in memory. Load with `numpy.load(filepath)`.
+    #[pyo3(signature = (filepath, distance_type, leaves_only=true))]
+    fn save_pairwise_distances_npy(
+        &self,
+        py: Python,
+        filepath: &str,
+        distance_type: &str,
+        leaves_only: bool,
+    ) -> PyResult<()> {
+        let dist_type = parse_distance_type(distance_type)?;
+        let file = File::create(filepath)
+            .map_err(|e| PyValueError::new_err(format!("Failed to write NPY file: {}", e)))?;
+        let tree = &self.rec_tree.gene_tree;
+        py.allow_threads(|| {
+            tree.write_pairwise_distances_npy(&mut BufWriter::new(file), dist_type, leaves_only)
+        })
+        .map_err(|e| PyValueError::new_err(format!("Failed to write NPY file: {}", e)))
+    }
+
     /// Compute induced transfers by projecting transfers onto a sampled species tree.
     #[pyo3(signature = (sampled_leaf_names, mode="projection", remove_undetectable=false))]
     fn compute_induced_transfers(
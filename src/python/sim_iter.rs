@@ -6,14 +6,11 @@ use rand::rngs::StdRng;
 use std::fs;
 use std::sync::Arc;
 
-use crate::bd::TreeEvent;
-use crate::dtl::{count_extant_genes, DTLConfig};
+use crate::dtl::{ConditioningStats, ForestSummary};
 use crate::error::RustreeError;
 use crate::node::{FlatTree, RecTree};
-use crate::simulation::dtl::gillespie::{simulate_dtl_gillespie, DTLMode};
-use crate::simulation::dtl::stream::{
-    simulate_dtl_gillespie_prepared, PreparedDtlRuntime, MAX_EXTANT_ATTEMPTS,
-};
+use crate::simulation::dtl::gillespie::DTLMode;
+use crate::simulation::dtl::stream::FamilySimulator;
 
 use super::forest::PyGeneForest;
 use super::gene_tree::PyGeneTree;
@@ -27,6 +24,92 @@ pub(crate) fn digit_width(n: usize) -> usize {
     }
 }
 
+/// Convert a [`ForestSummary`] into a dict of pandas DataFrames with keys
+/// `branch_events`, `transfers` and `copy_numbers` (same columns as the CSVs).
+pub(crate) fn summary_to_dataframes(py: Python, summary: &ForestSummary) -> PyResult<PyObject> {
+    let pandas = super::import_pymodule(py, "pandas")?;
+    let species = summary.species_tree();
+    let name = |i: usize| species.nodes[i].name.clone();
+
+    let counts = summary.branch_counts();
+    let branches = pyo3::types::PyDict::new(py);
+    branches.set_item("species_node", (0..counts.len()).collect::<Vec<_>>())?;
+    branches.set_item(
+        "species_name",
+        (0..counts.len()).map(name).collect::<Vec<_>>(),
+    )?;
+    branches.set_item(
+        "speciations",
+        counts.iter().map(|c| c.speciations).collect::<Vec<_>>(),
+    )?;
+    branches.set_item(
+        "duplications",
+        counts.iter().map(|c| c.duplications).collect::<Vec<_>>(),
+    )?;
+    branches.set_item(
+        "transfers_out",
+        counts.iter().map(|c| c.transfers_out).collect::<Vec<_>>(),
+    )?;
+    branches.set_item(
+        "transfers_in",
+        counts.iter().map(|c| c.transfers_in).collect::<Vec<_>>(),
+    )?;
+    branches.set_item(
+        "losses",
+        counts.iter().map(|c| c.losses).collect::<Vec<_>>(),
+    )?;
+    branches.set_item(
+        "leaves",
+        counts.iter().map(|c| c.leaves).collect::<Vec<_>>(),
+    )?;
+
+    let pairs = summary.transfer_counts();
+    let transfers = pyo3::types::PyDict::new(py);
+    transfers.set_item("donor", pairs.keys().map(|&(d, _)| d).collect::<Vec<_>>())?;
+    transfers.set_item(
+        "donor_name",
+        pairs.keys().map(|&(d, _)| name(d)).collect::<Vec<_>>(),
+    )?;
+    transfers.set_item(
+        "recipient",
+        pairs.keys().map(|&(_, r)| r).collect::<Vec<_>>(),
+    )?;
+    transfers.set_item(
+        "recipient_name",
+        pairs.keys().map(|&(_, r)| name(r)).collect::<Vec<_>>(),
+    )?;
+    transfers.set_item("count", pairs.values().copied().collect::<Vec<_>>())?;
+
+    let (mut sp_col, mut name_col, mut copies_col, mut families_col) =
+        (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for &sp in summary.extant_species() {
+        let histogram = summary.copy_number_histogram(sp).unwrap_or(&[]);
+        for (c, &families) in histogram.iter().enumerate() {
+            sp_col.push(sp);
+            name_col.push(name(sp));
+            copies_col.push(c);
+            families_col.push(families);
+        }
+    }
+    let copy_numbers = pyo3::types::PyDict::new(py);
+    copy_numbers.set_item("species_node", sp_col)?;
+    copy_numbers.set_item("species_name", name_col)?;
+    copy_numbers.set_item("copies", copies_col)?;
+    copy_numbers.set_item("families", families_col)?;
+
+    let result = pyo3::types::PyDict::new(py);
+    result.set_item(
+        "branch_events",
+        pandas.call_method1("DataFrame", (branches,))?,
+    )?;
+    result.set_item("transfers", pandas.call_method1("DataFrame", (transfers,))?)?;
+    result.set_item(
+        "copy_numbers",
+        pandas.call_method1("DataFrame", (copy_numbers,))?,
+    )?;
+    Ok(result.into())
+}
+
 /// Lazy iterator for DTL gene tree simulation.
 ///
 /// Generates one gene tree per `next()` call using the Gillespie algorithm.
@@ -60,92 +143,40 @@ pub(crate) fn digit_width(n: usize) -> usize {
 /// ```
 #[pyclass]
 pub struct PyDtlSimIter {
-    // Pre-computed state (owned)
-    pub(crate) species_arc: Arc<FlatTree>,
-    pub(crate) species_events: Vec<TreeEvent>,
-    pub(crate) depths: Vec<f64>,
-    pub(crate) contemporaneity: Vec<Vec<usize>>,
-    pub(crate) lca_depths: Option<Vec<Vec<f64>>>,
-    pub(crate) runtime: PreparedDtlRuntime,
-    // Simulation parameters
-    pub(crate) origin_species: usize,
-    pub(crate) config: DTLConfig,
+    // Pre-computed state and parameters, shared with the Rust iterator
+    pub(crate) simulator: FamilySimulator,
     pub(crate) n_simulations: usize,
-    pub(crate) require_extant: bool,
-    pub(crate) mode: DTLMode,
     // Mutable state
     pub(crate) rng: StdRng,
     pub(crate) completed: usize,
+    pub(crate) stats: ConditioningStats,
 }
 
 impl PyDtlSimIter {
-    /// Run one Gillespie simulation and return the result.
+    /// Run one conditioned Gillespie simulation and return the result.
     ///
-    /// Retries if `require_extant` is true and the tree has no extant genes.
+    /// Retries (through [`FamilySimulator::simulate_family`]) if `require_extant`
+    /// is true and the tree has no extant genes.
     /// Returns `None` when all requested simulations have been completed.
     fn next_simulation(&mut self) -> Option<Result<RecTree, RustreeError>> {
         if self.completed >= self.n_simulations {
             return None;
         }
-
-        let mut attempts = 0;
-        loop {
-            let lca_ref = self.lca_depths.as_deref();
-            let result = if self.config.uses_branch_rates() {
-                simulate_dtl_gillespie_prepared(
-                    self.mode,
-                    &self.species_arc,
-                    &self.species_events,
-                    &self.depths,
-                    &self.contemporaneity,
-                    lca_ref,
-                    self.origin_species,
-                    &self.config,
-                    &self.runtime,
-                    &mut self.rng,
-                )
-            } else {
-                simulate_dtl_gillespie(
-                    self.mode,
-                    &self.species_arc,
-                    &self.species_events,
-                    &self.depths,
-                    &self.contemporaneity,
-                    lca_ref,
-                    self.origin_species,
-                    &self.config,
-                    &mut self.rng,
-                )
-            };
-
-            match result {
-                Ok((mut rec_tree, events)) => {
-                    if !self.require_extant || count_extant_genes(&rec_tree) > 0 {
-                        self.completed += 1;
-                        rec_tree.dtl_events = Some(events);
-                        return Some(Ok(rec_tree));
-                    }
-                    // Retry: tree had no extant genes
-                    attempts += 1;
-                    if attempts >= MAX_EXTANT_ATTEMPTS {
-                        let rate_hint = if self.config.uses_branch_rates() {
-                            "The branch-specific DTL rates may make extant genes extremely unlikely.".to_string()
-                        } else {
-                            format!(
-                                "The DTL rates (d={}, t={}, l={}) may make extant genes extremely unlikely.",
-                                self.config.lambda_d, self.config.lambda_t, self.config.lambda_l
-                            )
-                        };
-                        return Some(Err(RustreeError::Simulation(format!(
-                            "Failed to generate a gene tree with extant genes after {} attempts. \
-                             {}",
-                            MAX_EXTANT_ATTEMPTS, rate_hint
-                        ))));
-                    }
-                }
-                Err(e) => return Some(Err(e)),
-            }
+        let result = self
+            .simulator
+            .simulate_family(&mut self.rng, &mut self.stats)
+            .map(|(mut rec_tree, events)| {
+                rec_tree.dtl_events = Some(events);
+                rec_tree
+            });
+        if result.is_ok() {
+            self.completed += 1;
         }
+        Some(result)
+    }
+
+    fn species_arc(&self) -> Arc<FlatTree> {
+        Arc::clone(self.simulator.species_tree())
     }
 }
 
@@ -161,7 +192,7 @@ impl PyDtlSimIter {
     /// Each call runs one Gillespie simulation. Returns a PyGeneTree or
     /// raises StopIteration when all requested simulations are done.
     fn __next__(mut slf: PyRefMut<'_, Self>) -> PyResult<Option<PyGeneTree>> {
-        let species_arc = Arc::clone(&slf.species_arc);
+        let species_arc = slf.species_arc();
         match slf.next_simulation() {
             None => Ok(None), // StopIteration
             Some(Ok(mut rec_tree)) => {
@@ -198,7 +229,7 @@ impl PyDtlSimIter {
     /// # Raises
     /// ValueError if no simulations remain or the simulation fails.
     fn single(&mut self) -> PyResult<PyGeneTree> {
-        let species_arc = Arc::clone(&self.species_arc);
+        let species_arc = self.species_arc();
         match self.next_simulation() {
             None => Err(PyValueError::new_err("No simulations remaining")),
             Some(Ok(mut rec_tree)) => {
@@ -218,7 +249,7 @@ impl PyDtlSimIter {
     fn collect_all(&mut self) -> PyResult<PyGeneForest> {
         let mut trees = Vec::with_capacity(self.n_simulations.saturating_sub(self.completed));
         loop {
-            let species_arc = Arc::clone(&self.species_arc);
+            let species_arc = self.species_arc();
             match self.next_simulation() {
                 None => break,
                 Some(Ok(mut rec_tree)) => {
@@ -229,10 +260,7 @@ impl PyDtlSimIter {
             }
         }
         Ok(PyGeneForest {
-            forest: crate::node::gene_forest::GeneForest::from_rec_trees(
-                Arc::clone(&self.species_arc),
-                trees,
-            ),
+            forest: crate::node::gene_forest::GeneForest::from_rec_trees(self.species_arc(), trees),
         })
     }
 
@@ -249,7 +277,7 @@ impl PyDtlSimIter {
         let width = digit_width(self.n_simulations);
         let mut idx = self.completed;
         loop {
-            let species_arc = Arc::clone(&self.species_arc);
+            let species_arc = self.species_arc();
             match self.next_simulation() {
                 None => break,
                 Some(Ok(mut rec_tree)) => {
@@ -283,7 +311,7 @@ impl PyDtlSimIter {
         let width = digit_width(self.n_simulations);
         let mut idx = self.completed;
         loop {
-            let species_arc = Arc::clone(&self.species_arc);
+            let species_arc = self.species_arc();
             match self.next_simulation() {
                 None => break,
                 Some(Ok(mut rec_tree)) => {
@@ -304,8 +332,45 @@ impl PyDtlSimIter {
         Ok(())
     }
 
+    /// Consume the remaining simulations into forest-level summary statistics.
+    ///
+    /// Trees are discarded as they are generated, so memory does not grow
+    /// with the number of families.
+    ///
+    /// # Arguments
+    /// * `output_dir` - Optional directory to also write `branch_events.csv`,
+    ///   `transfers.csv` and `copy_numbers.csv`
+    ///
+    /// # Returns
+    /// A dict of pandas DataFrames with keys `branch_events`, `transfers` and
+    /// `copy_numbers`.
+    #[pyo3(signature = (output_dir=None))]
+    fn summarize(&mut self, py: Python, output_dir: Option<&str>) -> PyResult<PyObject> {
+        let mut summary = ForestSummary::new(self.species_arc());
+        loop {
+            let species_arc = self.species_arc();
+            match self.next_simulation() {
+                None => break,
+                Some(Ok(mut rec_tree)) => {
+                    rec_tree.species_tree = species_arc;
+                    summary
+                        .add(&rec_tree)
+                        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+                }
+                Some(Err(e)) => return Err(PyValueError::new_err(e.to_string())),
+            }
+        }
+        if let Some(dir) = output_dir {
+            summary
+                .save_csv(dir)
+                .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        }
+        summary_to_dataframes(py, &summary)
+    }
+
     fn __repr__(&self) -> String {
-        let mode_str = match self.mode {
+        let config = self.simulator.config();
+        let mode_str = match self.simulator.mode() {
             DTLMode::PerGene => "per_gene",
             DTLMode::PerSpecies => "per_species",
         };
@@ -314,9 +379,9 @@ impl PyDtlSimIter {
             mode_str,
             self.completed,
             self.n_simulations,
-            self.config.lambda_d,
-            self.config.lambda_t,
-            self.config.lambda_l
+            config.lambda_d,
+            config.lambda_t,
+            config.lambda_l
         )
     }
 
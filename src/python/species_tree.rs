@@ -10,7 +10,7 @@ use std::sync::Arc;
 
 use crate::dtl::{
     prepare_simulation, simulate_dtl, simulate_dtl_batch, simulate_dtl_per_species,
-    simulate_dtl_per_species_batch, BranchDTLRates, DTLConfig,
+    simulate_dtl_per_species_batch, BranchDTLRates, ConditioningStats, DTLConfig,
 };
 use crate::node::{FlatTree, RecTree};
 use crate::sampling::{
@@ -18,6 +18,7 @@ use crate::sampling::{
     mark_nodes_postorder, NodeMark,
 };
 use crate::simulation::dtl::gillespie::DTLMode;
+use crate::simulation::dtl::stream::FamilySimulator;
 
 use super::forest::PyGeneForest;
 use super::gene_tree::PyGeneTree;
@@ -985,19 +986,22 @@ impl PySpeciesTree {
         let rng = init_rng(seed);
 
         Ok(PyDtlSimIter {
-            species_arc: prepared.species_tree,
-            species_events: prepared.species_events,
-            depths: prepared.depths,
-            contemporaneity: prepared.contemporaneity,
-            lca_depths: prepared.lca_depths,
-            runtime: prepared.runtime,
-            origin_species,
-            config,
+            simulator: FamilySimulator::new(
+                DTLMode::PerGene,
+                prepared.species_tree,
+                prepared.species_events,
+                prepared.depths,
+                prepared.contemporaneity,
+                prepared.lca_depths,
+                prepared.branch_totals,
+                origin_species,
+                config,
+                require_extant,
+            ),
             n_simulations: n,
-            require_extant,
-            mode: DTLMode::PerGene,
             rng,
             completed: 0,
+            stats: ConditioningStats::default(),
         })
     }
 
@@ -1027,19 +1031,22 @@ impl PySpeciesTree {
         let rng = init_rng(seed);
 
         Ok(PyDtlSimIter {
-            species_arc: prepared.species_tree,
-            species_events: prepared.species_events,
-            depths: prepared.depths,
-            contemporaneity: prepared.contemporaneity,
-            lca_depths: prepared.lca_depths,
-            runtime: prepared.runtime,
-            origin_species,
-            config,
+            simulator: FamilySimulator::new(
+                DTLMode::PerGene,
+                prepared.species_tree,
+                prepared.species_events,
+                prepared.depths,
+                prepared.contemporaneity,
+                prepared.lca_depths,
+                prepared.branch_totals,
+                origin_species,
+                config,
+                require_extant,
+            ),
             n_simulations: n,
-            require_extant,
-            mode: DTLMode::PerGene,
             rng,
             completed: 0,
+            stats: ConditioningStats::default(),
         })
     }
 
@@ -1073,19 +1080,22 @@ impl PySpeciesTree {
         let rng = init_rng(seed);
 
         Ok(PyDtlSimIter {
-            species_arc: prepared.species_tree,
-            species_events: prepared.species_events,
-            depths: prepared.depths,
-            contemporaneity: prepared.contemporaneity,
-            lca_depths: prepared.lca_depths,
-            runtime: prepared.runtime,
-            origin_species,
-            config,
+            simulator: FamilySimulator::new(
+                DTLMode::PerSpecies,
+                prepared.species_tree,
+                prepared.species_events,
+                prepared.depths,
+                prepared.contemporaneity,
+                prepared.lca_depths,
+                prepared.branch_totals,
+                origin_species,
+                config,
+                require_extant,
+            ),
             n_simulations: n,
-            require_extant,
-            mode: DTLMode::PerSpecies,
             rng,
             completed: 0,
+            stats: ConditioningStats::default(),
         })
     }
 
@@ -1115,19 +1125,22 @@ impl PySpeciesTree {
         let rng = init_rng(seed);
 
         Ok(PyDtlSimIter {
-            species_arc: prepared.species_tree,
-            species_events: prepared.species_events,
-            depths: prepared.depths,
-            contemporaneity: prepared.contemporaneity,
-            lca_depths: prepared.lca_depths,
-            runtime: prepared.runtime,
-            origin_species,
-            config,
+            simulator: FamilySimulator::new(
+                DTLMode::PerSpecies,
+                prepared.species_tree,
+                prepared.species_events,
+                prepared.depths,
+                prepared.contemporaneity,
+                prepared.lca_depths,
+                prepared.branch_totals,
+                origin_species,
+                config,
+                require_extant,
+            ),
             n_simulations: n,
-            require_extant,
-            mode: DTLMode::PerSpecies,
             rng,
             completed: 0,
+            stats: ConditioningStats::default(),
         })
     }
 
@@ -1171,7 +1184,7 @@ impl PySpeciesTree {
         distance_type: &str,
         leaves_only: bool,
     ) -> PyResult<()> {
-        use crate::metric_functions::{DistanceType, PairwiseDistance};
+        use crate::metric_functions::DistanceType;
 
         let dist_type = match distance_type.to_lowercase().as_str() {
             "topological" | "topo" => DistanceType::Topological,
@@ -1184,26 +1197,63 @@ impl PySpeciesTree {
             }
         };
 
-        let distances = self
-            .tree
-            .pairwise_distances(dist_type, leaves_only)
-            .map_err(|e| {
-                PyValueError::new_err(format!("Failed to compute pairwise distances: {}", e))
-            })?;
-
-        let mut csv = String::from(PairwiseDistance::csv_header());
-        csv.push('\n');
-        for d in &distances {
-            csv.push_str(&d.to_csv_row());
-            csv.push('\n');
-        }
-
-        fs::write(filepath, csv)
+        let file = File::create(filepath)
+            .map_err(|e| PyValueError::new_err(format!("Failed to write CSV file: {}", e)))?;
+        self.tree
+            .write_pairwise_distances_csv(&mut BufWriter::new(file), dist_type, leaves_only)
             .map_err(|e| PyValueError::new_err(format!("Failed to write CSV file: {}", e)))?;
 
         Ok(())
     }
 
+    /// Compute the condensed upper-triangular distance matrix in parallel.
+    ///
+    /// Returns `(names, distances)` where `distances` is a 1-D NumPy array in
+    /// `scipy.spatial.distance.pdist` order over `names`.
+    #[pyo3(signature = (distance_type, leaves_only=true))]
+    fn pairwise_distances_condensed(
+        &self,
+        py: Python,
+        distance_type: &str,
+        leaves_only: bool,
+    ) -> PyResult<(Vec<String>, PyObject)> {
+        use numpy::PyArray1;
+
+        let dist_type = parse_distance_type(distance_type)?;
+        let tree = &self.tree;
+        let condensed =
+            py.allow_threads(|| tree.pairwise_distances_condensed(dist_type, leaves_only));
+        let names = condensed
+            .nodes
+            .iter()
+            .map(|&i| tree.nodes[i].name.clone())
+            .collect();
+        let values = PyArray1::from_vec(py, condensed.values);
+        Ok((names, values.into_any().unbind()))
+    }
+
+    /// Stream the condensed distance matrix to a NumPy `.npy` file.
+    ///
+    /// Rows are computed in parallel and written block by block, so the full
+    /// matrix is never held in memory. Load with `numpy.load(filepath)`.
+    #[pyo3(signature = (filepath, distance_type, leaves_only=true))]
+    fn save_pairwise_distances_npy(
+        &self,
+        py: Python,
+        filepath: &str,
+        distance_type: &str,
+        leaves_only: bool,
+    ) -> PyResult<()> {
+        let dist_type = parse_distance_type(distance_type)?;
+        let file = File::create(filepath)
+            .map_err(|e| PyValueError::new_err(format!("Failed to write NPY file: {}", e)))?;
+        let tree = &self.tree;
+        py.allow_threads(|| {
+            tree.write_pairwise_distances_npy(&mut BufWriter::new(file), dist_type, leaves_only)
+        })
+        .map_err(|e| PyValueError::new_err(format!("Failed to write NPY file: {}", e)))
+    }
+
     /// Compute ghost branch lengths for a sampled species tree.
     fn compute_ghost_lengths(
         &self,
@@ -59,6 +59,90 @@ fn save_pairwise_distances_csv_r(tree_list: List, filepath: &str, distance_type:
     Ok(())
 }
 
+/// Compute the condensed upper-triangular distance matrix in parallel.
+///
+/// @param tree_list A tree list from simulate_species_tree_r or parse_newick_r
+/// @param distance_type Type of distance: "topological" or "metric"
+/// @param leaves_only If TRUE, only compute distances between leaf nodes (default TRUE)
+/// @return A list with `names` (nodes in matrix order) and `distances` (in `stats::dist` order)
+/// @export
+#[extendr]
+fn pairwise_distances_condensed_r(tree_list: List, distance_type: &str, leaves_only: bool) -> Result<List> {
+    let tree = rlist_to_flattree(&tree_list)?;
+
+    let dist_type = crate::bindings_common::parse_distance_type(distance_type)?;
+
+    let condensed = tree.pairwise_distances_condensed(dist_type, leaves_only);
+    let names: Vec<String> = condensed
+        .nodes
+        .iter()
+        .map(|&i| tree.nodes[i].name.clone())
+        .collect();
+
+    Ok(list!(
+        names = names,
+        distances = condensed.values
+    ))
+}
+
+/// Stream the condensed distance matrix to a NumPy `.npy` file.
+///
+/// @param tree_list A tree list from simulate_species_tree_r or parse_newick_r
+/// @param filepath Path to save the NPY file
+/// @param distance_type Type of distance: "topological" or "metric"
+/// @param leaves_only If TRUE, only compute distances between leaf nodes (default TRUE)
+/// @export
+#[extendr]
+fn save_pairwise_distances_npy_r(tree_list: List, filepath: &str, distance_type: &str, leaves_only: bool) -> Result<()> {
+    let tree = rlist_to_flattree(&tree_list)?;
+
+    let dist_type = crate::bindings_common::parse_distance_type(distance_type)?;
+
+    let file = fs::File::create(filepath)
+        .map_err(|e| format!("Failed to write NPY file: {}", e))?;
+    tree.write_pairwise_distances_npy(&mut std::io::BufWriter::new(file), dist_type, leaves_only)
+        .map_err(|e| format!("Failed to write NPY file: {}", e))?;
+
+    Ok(())
+}
+
+/// Forest-level summary statistics of a list of simulated gene trees.
+///
+/// @param gene_tree_lists A list of gene tree lists sharing one species tree (e.g. from simulate_dtl_batch_r)
+/// @param output_dir Optional directory to also write branch_events.csv, transfers.csv and copy_numbers.csv (NULL = none)
+/// @return A list of three column lists: branch_events, transfers and copy_numbers
+/// @export
+#[extendr]
+fn summarize_forest_r(gene_tree_lists: List, output_dir: Robj) -> Result<List> {
+    use crate::dtl::ForestSummary;
+    use std::sync::Arc;
+
+    let rec_trees: Vec<RecTree> = gene_tree_lists
+        .values()
+        .enumerate()
+        .map(|(i, robj)| {
+            let gene_tree_list: List = robj.try_into().map_err(|_| {
+                Error::Other(format!("Element {} is not a rustree gene tree list", i + 1))
+            })?;
+            let (gene_tree, species_tree, node_mapping, event_mapping) =
+                rlist_to_genetree(&gene_tree_list)?;
+            Ok(RecTree::new_owned(species_tree, gene_tree, node_mapping, event_mapping))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let species_tree = rec_trees
+        .first()
+        .map(|rec_tree| Arc::clone(&rec_tree.species_tree))
+        .ok_or("gene_tree_lists is empty")?;
+
+    let summary = ForestSummary::from_rec_trees(species_tree, &rec_trees)
+        .map_err(|e| Error::Other(e.to_string()))?;
+    if !output_dir.is_null() && !output_dir.is_na() {
+        let dir = output_dir.as_str().ok_or("output_dir must be a string")?;
+        summary.save_csv(dir).map_err(|e| Error::Other(e.to_string()))?;
+    }
+    Ok(forest_summary_to_rlist(&summary))
+}
+
 /// Parse a RecPhyloXML file into a gene tree with reconciliation information.
 ///
 /// @param filepath Path to the RecPhyloXML file (e.g., from ALERax)
@@ -5,7 +5,7 @@ use extendr_api::prelude::*;
 use std::str::FromStr;
 
 use crate::bd::{BDEvent, TreeEvent};
-use crate::dtl::DTLEvent;
+use crate::dtl::{DTLEvent, ForestSummary};
 use crate::node::{Event, FlatNode, FlatTree};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -775,6 +775,7 @@ pub(crate) fn dtl_events_to_rlist(events: &[DTLEvent], species_tree: &FlatTree)
     let mut species_names: Vec<String> = Vec::with_capacity(n);
     let mut from_species: Vec<Rstr> = Vec::with_capacity(n);
     let mut to_species: Vec<Rstr> = Vec::with_capacity(n);
+    let mut wgd_flags: Vec<bool> = Vec::with_capacity(n);
 
     let sp_name = |idx: usize| -> String {
         if idx < species_tree.nodes.len() {
@@ -785,6 +786,7 @@ pub(crate) fn dtl_events_to_rlist(events: &[DTLEvent], species_tree: &FlatTree)
     };
 
     for event in events {
+        wgd_flags.push(matches!(event, DTLEvent::Duplication { wgd: true, .. }));
         match event {
             DTLEvent::Speciation {
                 time,
@@ -860,7 +862,8 @@ pub(crate) fn dtl_events_to_rlist(events: &[DTLEvent], species_tree: &FlatTree)
         gene_id = gene_ids,
         species = species_names,
         from_species = from_species,
-        to_species = to_species
+        to_species = to_species,
+        wgd = wgd_flags
     )
 }
 
@@ -908,6 +911,18 @@ pub(crate) fn rlist_to_dtl_events(
         .map(|s| s.to_string())
         .collect();
 
+    // wgd is absent from lists written before whole-genome duplications existed
+    let wgd_flags: Vec<bool> = match events_list.dollar("wgd") {
+        Ok(wgd) if wgd.is_null() => Vec::new(),
+        Ok(wgd) => wgd
+            .as_logical_vector()
+            .ok_or("Failed to get wgd column")?
+            .iter()
+            .map(|v| v.is_true())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
     // Build name→index map
     let name_to_idx: std::collections::HashMap<&str, usize> = species_tree
         .nodes
@@ -942,6 +957,7 @@ pub(crate) fn rlist_to_dtl_events(
                 species_id: sp_idx,
                 child1: 0,
                 child2: 0,
+                wgd: wgd_flags.get(i).copied().unwrap_or(false),
             },
             "Transfer" => {
                 let from_idx = resolve(&from_species_strs[i])?;
@@ -973,3 +989,58 @@ pub(crate) fn rlist_to_dtl_events(
 
     Ok(dtl_events)
 }
+
+/// Serialize a ForestSummary to an R list of three column lists, with the
+/// same columns as its CSV tables.
+pub(crate) fn forest_summary_to_rlist(summary: &ForestSummary) -> List {
+    let species = summary.species_tree();
+    let name = |i: usize| species.nodes[i].name.clone();
+
+    let counts = summary.branch_counts();
+    let count = |f: fn(&crate::dtl::BranchEventCounts) -> usize| -> Vec<i32> {
+        counts.iter().map(|c| f(c) as i32).collect()
+    };
+    let branch_events = list!(
+        species_node = (0..counts.len() as i32).collect::<Vec<i32>>(),
+        species_name = (0..counts.len()).map(name).collect::<Vec<String>>(),
+        speciations = count(|c| c.speciations),
+        duplications = count(|c| c.duplications),
+        transfers_out = count(|c| c.transfers_out),
+        transfers_in = count(|c| c.transfers_in),
+        losses = count(|c| c.losses),
+        leaves = count(|c| c.leaves)
+    );
+
+    let pairs = summary.transfer_counts();
+    let transfers = list!(
+        donor = pairs.keys().map(|&(d, _)| d as i32).collect::<Vec<i32>>(),
+        donor_name = pairs.keys().map(|&(d, _)| name(d)).collect::<Vec<String>>(),
+        recipient = pairs.keys().map(|&(_, r)| r as i32).collect::<Vec<i32>>(),
+        recipient_name = pairs.keys().map(|&(_, r)| name(r)).collect::<Vec<String>>(),
+        count = pairs.values().map(|&c| c as i32).collect::<Vec<i32>>()
+    );
+
+    let (mut sp_col, mut name_col, mut copies_col, mut families_col) =
+        (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for &sp in summary.extant_species() {
+        let histogram = summary.copy_number_histogram(sp).unwrap_or(&[]);
+        for (c, &families) in histogram.iter().enumerate() {
+            sp_col.push(sp as i32);
+            name_col.push(name(sp));
+            copies_col.push(c as i32);
+            families_col.push(families as i32);
+        }
+    }
+    let copy_numbers = list!(
+        species_node = sp_col,
+        species_name = name_col,
+        copies = copies_col,
+        families = families_col
+    );
+
+    list!(
+        branch_events = branch_events,
+        transfers = transfers,
+        copy_numbers = copy_numbers
+    )
+}
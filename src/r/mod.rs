@@ -111,6 +111,9 @@ extendr_module! {
     fn extract_induced_subtree_by_names_r;
     fn pairwise_distances_r;
     fn save_pairwise_distances_csv_r;
+    fn pairwise_distances_condensed_r;
+    fn save_pairwise_distances_npy_r;
+    fn summarize_forest_r;
     fn parse_recphyloxml_r;
     fn induced_transfers_r;
     fn simulate_dtl_stream_xml_r;
@@ -0,0 +1,111 @@
+//! Duplication-loss reconciliation by LCA mapping.
+//!
+//! Each gene node maps to the lowest common ancestor of the species of its
+//! leaves, and is a duplication when it maps to the same species as one of
+//! its children. Losses are then placed on every species branch that a gene
+//! lineage skips between a node and its child. For transfer-free families
+//! this minimises both the number of duplications and of losses.
+
+use super::{map_leaves_by_name, validate_leaf_species, RecTreeBuilder};
+use crate::error::RustreeError;
+use crate::metric_functions::LcaTable;
+use crate::node::{Event, FlatTree, RecTree};
+use std::sync::Arc;
+
+/// Reconciles `gene_tree` with `species_tree` by LCA mapping, with gene leaves
+/// matched to species by name as in [`map_leaves_by_name`].
+pub fn reconcile_lca_by_name(
+    species_tree: Arc<FlatTree>,
+    gene_tree: &FlatTree,
+) -> Result<RecTree, RustreeError> {
+    let leaf_species = map_leaves_by_name(gene_tree, &species_tree)?;
+    reconcile_lca(species_tree, gene_tree, &leaf_species)
+}
+
+/// Reconciles `gene_tree` with `species_tree` by LCA mapping.
+///
+/// `leaf_species[g]` is the species of gene leaf `g`. The returned tree keeps
+/// every node of `gene_tree`, labelled [`Event::Speciation`],
+/// [`Event::Duplication`] or [`Event::Leaf`], and inserts an unnamed
+/// speciation node with an [`Event::Loss`] leaf for every inferred loss.
+///
+/// # Errors
+/// Returns an error if either tree is not binary or a leaf has no valid
+/// species.
+pub fn reconcile_lca(
+    species_tree: Arc<FlatTree>,
+    gene_tree: &FlatTree,
+    leaf_species: &[Option<usize>],
+) -> Result<RecTree, RustreeError> {
+    validate_leaf_species(gene_tree, &species_tree, leaf_species)?;
+    let lca = LcaTable::new(&species_tree);
+
+    let mut mapping = vec![0; gene_tree.nodes.len()];
+    let mut events = vec![Event::Leaf; gene_tree.nodes.len()];
+    for u in gene_tree.postorder_indices() {
+        let node = &gene_tree.nodes[u];
+        match (node.left_child, node.right_child) {
+            (Some(v), Some(w)) => {
+                mapping[u] = lca.lca(mapping[v], mapping[w]);
+                events[u] = if mapping[u] == mapping[v] || mapping[u] == mapping[w] {
+                    Event::Duplication
+                } else {
+                    Event::Speciation
+                };
+            }
+            _ => mapping[u] = leaf_species[u].expect("validated leaf mapping"),
+        }
+    }
+
+    let species_nodes = &species_tree.nodes;
+    // Child of species `x` on the way down to its descendant `target`.
+    let step_towards = |x: usize, target: usize| -> (usize, bool) {
+        let left = species_nodes[x]
+            .left_child
+            .expect("validated binary species tree");
+        if lca.lca(left, target) == left {
+            (left, true)
+        } else {
+            (
+                species_nodes[x]
+                    .right_child
+                    .expect("validated binary species tree"),
+                false,
+            )
+        }
+    };
+
+    let mut builder = RecTreeBuilder::new(gene_tree);
+    let mut stack = vec![(gene_tree.root, None)];
+    while let Some((u, slot)) = stack.pop() {
+        let node = builder.push(Some(u), slot, mapping[u], events[u].clone());
+        let gene = &gene_tree.nodes[u];
+        let (Some(v), Some(w)) = (gene.left_child, gene.right_child) else {
+            continue;
+        };
+        for (child, is_left) in [(w, false), (v, true)] {
+            let target = mapping[child];
+            // A speciation already descends one species branch.
+            let mut species = mapping[u];
+            if events[u] == Event::Speciation {
+                species = step_towards(species, target).0;
+            }
+            let mut slot = Some((node, is_left));
+            while species != target {
+                let (next, next_is_left) = step_towards(species, target);
+                let sibling = if next_is_left {
+                    species_nodes[species].right_child
+                } else {
+                    species_nodes[species].left_child
+                }
+                .expect("validated binary species tree");
+                let loss_node = builder.push(None, slot, species, Event::Speciation);
+                builder.push(None, Some((loss_node, !next_is_left)), sibling, Event::Loss);
+                slot = Some((loss_node, next_is_left));
+                species = next;
+            }
+            stack.push((child, slot));
+        }
+    }
+    builder.finish(species_tree)
+}
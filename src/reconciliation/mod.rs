@@ -0,0 +1,235 @@
+//! Native gene tree / species tree reconciliation.
+//!
+//! These methods reconcile a rooted binary gene tree with a species tree
+//! without calling out to external tools (see [`crate::external::alerax`]),
+//! and return the result as a [`RecTree`]:
+//!
+//! - [`lca`]: duplication-loss reconciliation by LCA mapping, for
+//!   transfer-free families.
+//! - [`parsimony`]: maximum-parsimony DTL reconciliation with D/T/L costs.
+//! - [`rates`]: duplication, transfer and loss rate estimates from the event
+//!   counts of already reconciled gene trees.
+//! - [`undated`]: likelihood and posterior sampling under the undated DTL
+//!   model of ALE, with rate fitting over a [`GeneForest`].
+
+pub mod lca;
+pub mod parsimony;
+pub mod rates;
+pub mod undated;
+
+pub use lca::{reconcile_lca, reconcile_lca_by_name};
+pub use parsimony::{reconcile_parsimony, DtlCosts, ParsimonyOptions, ParsimonyReconciliation};
+pub use rates::{
+    estimate_dtl_rates, DtlRateEstimate, DtlRateEstimates, Exposure, RateCoverage, RateEstimate,
+};
+pub use undated::{
+    fit_undated_rates, UndatedDtlModel, UndatedFit, UndatedRates, UndatedReconciliation,
+};
+
+use crate::error::RustreeError;
+use crate::io::recphyloxml::build_species_name_map;
+use crate::node::{Event, FlatNode, FlatTree, GeneForest, RecTree};
+use std::sync::Arc;
+
+/// Maps each gene tree leaf to a species tree node by name.
+///
+/// A leaf whose name is a species name maps to that species. Otherwise the
+/// suffix after the last `_` is dropped, so simulated gene names of the form
+/// `{species}_{index}` map to their species. Internal gene nodes map to `None`.
+///
+/// # Errors
+/// Returns an error if a leaf matches no species.
+pub fn map_leaves_by_name(
+    gene_tree: &FlatTree,
+    species_tree: &FlatTree,
+) -> Result<Vec<Option<usize>>, RustreeError> {
+    let species_by_name = build_species_name_map(species_tree);
+    gene_tree
+        .nodes
+        .iter()
+        .map(|node| {
+            if node.left_child.is_some() || node.right_child.is_some() {
+                return Ok(None);
+            }
+            let species = species_by_name.get(&node.name).or_else(|| {
+                node.name
+                    .rsplit_once('_')
+                    .and_then(|(prefix, _)| species_by_name.get(prefix))
+            });
+            species.copied().map(Some).ok_or_else(|| {
+                RustreeError::Validation(format!(
+                    "gene leaf '{}' does not match any species name",
+                    node.name
+                ))
+            })
+        })
+        .collect()
+}
+
+/// A gene tree with the species of each of its leaves (`None` for internal nodes).
+pub type GeneFamily = (FlatTree, Vec<Option<usize>>);
+
+/// Where a new node attaches: its parent and whether it is the left child.
+pub(crate) type Slot = Option<(usize, bool)>;
+
+/// Extant gene trees of `forest` with the species of their leaves, ready for
+/// reconciliation. Families without extant genes are dropped.
+pub fn extant_families(forest: &GeneForest) -> Result<Vec<GeneFamily>, RustreeError> {
+    let extant = forest.sample_extant().map_err(RustreeError::Tree)?;
+    Ok(extant
+        .gene_trees
+        .into_iter()
+        .map(|rec| {
+            let leaf_species = rec
+                .gene_tree
+                .nodes
+                .iter()
+                .zip(&rec.node_mapping)
+                .map(|(node, &species)| {
+                    (node.left_child.is_none() && node.right_child.is_none())
+                        .then_some(species)
+                        .flatten()
+                })
+                .collect();
+            (rec.gene_tree, leaf_species)
+        })
+        .collect())
+}
+
+/// Checks that `species_tree` is non-empty and binary.
+pub(crate) fn validate_species_tree(species_tree: &FlatTree) -> Result<(), RustreeError> {
+    if species_tree.nodes.is_empty() {
+        return Err(RustreeError::Tree("species tree has no nodes".to_string()));
+    }
+    if let Some(node) = species_tree
+        .nodes
+        .iter()
+        .find(|node| node.left_child.is_some() != node.right_child.is_some())
+    {
+        return Err(RustreeError::Tree(format!(
+            "species node '{}' has a single child; reconciliation needs a binary species tree",
+            node.name
+        )));
+    }
+    Ok(())
+}
+
+/// Whether `a` and `b` have the same node names and links at every index, so
+/// species indices mean the same node in both.
+pub(crate) fn same_species_tree(a: &FlatTree, b: &FlatTree) -> bool {
+    a.root == b.root
+        && a.nodes.len() == b.nodes.len()
+        && a.nodes.iter().zip(&b.nodes).all(|(x, y)| {
+            x.name == y.name
+                && x.parent == y.parent
+                && x.left_child == y.left_child
+                && x.right_child == y.right_child
+        })
+}
+
+/// Checks that both trees are binary and that every gene leaf has a valid species.
+pub(crate) fn validate_leaf_species(
+    gene_tree: &FlatTree,
+    species_tree: &FlatTree,
+    leaf_species: &[Option<usize>],
+) -> Result<(), RustreeError> {
+    if leaf_species.len() != gene_tree.nodes.len() {
+        return Err(RustreeError::Validation(format!(
+            "leaf_species length {} must match gene_tree node count {}",
+            leaf_species.len(),
+            gene_tree.nodes.len()
+        )));
+    }
+    validate_species_tree(species_tree)?;
+    for idx in gene_tree.postorder_indices() {
+        let node = &gene_tree.nodes[idx];
+        match (node.left_child, node.right_child) {
+            (None, None) => match leaf_species[idx] {
+                Some(sp) if sp < species_tree.nodes.len() => {}
+                Some(sp) => {
+                    return Err(RustreeError::Index(format!(
+                    "leaf_species[{idx}] = {sp} is out of bounds for species tree with {} nodes",
+                    species_tree.nodes.len()
+                )))
+                }
+                None => {
+                    return Err(RustreeError::Validation(format!(
+                        "gene leaf '{}' has no species mapping",
+                        node.name
+                    )))
+                }
+            },
+            (Some(_), Some(_)) => {}
+            _ => {
+                return Err(RustreeError::Tree(format!(
+                    "gene node '{}' has a single child; reconciliation needs a binary gene tree",
+                    node.name
+                )))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Builds a reconciled gene tree node by node, root first.
+///
+/// Nodes copied from the input gene tree keep their name and branch length;
+/// inserted speciation-loss and loss nodes have no name and zero length.
+pub(crate) struct RecTreeBuilder<'a> {
+    gene_tree: &'a FlatTree,
+    nodes: Vec<FlatNode>,
+    node_mapping: Vec<Option<usize>>,
+    event_mapping: Vec<Event>,
+}
+
+impl<'a> RecTreeBuilder<'a> {
+    pub fn new(gene_tree: &'a FlatTree) -> Self {
+        let capacity = 2 * gene_tree.nodes.len();
+        Self {
+            gene_tree,
+            nodes: Vec::with_capacity(capacity),
+            node_mapping: Vec::with_capacity(capacity),
+            event_mapping: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Adds a node copied from gene node `gene`, or an inserted node if `None`,
+    /// as the left (`true`) or right child of `slot`'s parent.
+    pub fn push(&mut self, gene: Option<usize>, slot: Slot, species: usize, event: Event) -> usize {
+        let idx = self.nodes.len();
+        let (name, length) = gene.map_or((String::new(), 0.0), |g| {
+            let node = &self.gene_tree.nodes[g];
+            (node.name.clone(), node.length)
+        });
+        self.nodes.push(FlatNode {
+            name,
+            left_child: None,
+            right_child: None,
+            parent: slot.map(|(parent, _)| parent),
+            depth: None,
+            length,
+            bd_event: None,
+        });
+        self.node_mapping.push(Some(species));
+        self.event_mapping.push(event);
+        match slot {
+            Some((parent, true)) => self.nodes[parent].left_child = Some(idx),
+            Some((parent, false)) => self.nodes[parent].right_child = Some(idx),
+            None => {}
+        }
+        idx
+    }
+
+    pub fn finish(self, species_tree: Arc<FlatTree>) -> Result<RecTree, RustreeError> {
+        let gene_tree = FlatTree {
+            nodes: self.nodes,
+            root: 0,
+        };
+        RecTree::try_new(
+            species_tree,
+            gene_tree,
+            self.node_mapping,
+            self.event_mapping,
+        )
+    }
+}
@@ -0,0 +1,697 @@
+//! Maximum-parsimony DTL reconciliation.
+//!
+//! Dynamic programming over gene nodes × species locations finds the minimum
+//! total cost of duplications, transfers and losses needed to embed a rooted
+//! binary gene tree in a species tree, together with the number of co-optimal
+//! scenarios, which allows uniform sampling and enumeration of optimal
+//! reconciliations.
+//!
+//! Locations are species branches in the undated model, where a transfer can
+//! reach any branch that is neither an ancestor nor a descendant of the donor.
+//! In the time-consistent model the species tree is cut into time slices at
+//! its node depths, locations are (branch, slice) pairs, and transfers only
+//! connect branches alive in the same slice. Gene lineages only move forward
+//! in time, so every returned scenario is time-consistent. This costs
+//! O(|G| · P) for P (branch, slice) pairs, against O(|G| · |S|) undated.
+
+use super::{validate_leaf_species, RecTreeBuilder, Slot};
+use crate::error::RustreeError;
+use crate::node::{Event, FlatTree, RecTree};
+use rand::Rng;
+use std::sync::Arc;
+
+/// Event costs for DTL parsimony. Speciations are free.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DtlCosts {
+    pub duplication: f64,
+    pub transfer: f64,
+    pub loss: f64,
+}
+
+impl Default for DtlCosts {
+    /// The usual D = 2, T = 3, L = 1.
+    fn default() -> Self {
+        Self {
+            duplication: 2.0,
+            transfer: 3.0,
+            loss: 1.0,
+        }
+    }
+}
+
+/// Options for [`reconcile_parsimony`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ParsimonyOptions {
+    pub costs: DtlCosts,
+    /// Restrict transfers to contemporaneous branches of a dated species tree
+    /// and keep every scenario time-consistent. Requires species depths.
+    pub time_consistent: bool,
+}
+
+/// A DP state: a gene node at a location (species branch, or branch and slice).
+#[derive(Clone, Copy, Debug)]
+enum State {
+    /// The gene node's event happens at the location.
+    At(usize, usize),
+    /// The gene lineage enters the location, possibly moving down with losses.
+    Within(usize, usize),
+    /// Undated only: best entry into the species subtree below the location.
+    Below(usize, usize),
+    /// The gene lineage is transferred away from the location.
+    Away(usize, usize),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Action {
+    Leaf,
+    Speciation,
+    Duplication,
+    Transfer,
+    /// Speciation followed by loss in `lost`; the lineage continues in the
+    /// left (`kept_left`) or right species child.
+    SpeciationLoss {
+        lost: usize,
+        kept_left: bool,
+    },
+    /// Moves to another state without creating a node.
+    Pass,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Choice {
+    action: Action,
+    extra: f64,
+    next: [Option<State>; 2],
+}
+
+/// Minimum cost and number of co-optimal scenarios for each state.
+#[derive(Clone, Debug, Default)]
+struct Table {
+    cost: Vec<f64>,
+    count: Vec<f64>,
+}
+
+impl Table {
+    fn new(len: usize) -> Self {
+        Self {
+            cost: vec![f64::INFINITY; len],
+            count: vec![0.0; len],
+        }
+    }
+
+    #[inline]
+    fn get(&self, i: usize) -> (f64, f64) {
+        (self.cost[i], self.count[i])
+    }
+
+    #[inline]
+    fn set(&mut self, i: usize, (cost, count): (f64, f64)) {
+        self.cost[i] = cost;
+        self.count[i] = count;
+    }
+}
+
+/// Time slices of a dated species tree. Slice `j` ends at the `j`-th distinct
+/// node depth; a branch is alive from the slice after its parent's depth up
+/// to the slice ending at its own depth. The root is alone in slice 0.
+#[derive(Clone, Debug)]
+struct Slices {
+    last: Vec<usize>,
+    /// Location of each branch in its first slice; later slices follow contiguously.
+    base: Vec<usize>,
+    /// Locations of the branches alive in each slice.
+    locs: Vec<Vec<usize>>,
+    loc_slice: Vec<usize>,
+    loc_branch: Vec<usize>,
+}
+
+impl Slices {
+    fn new(species_tree: &FlatTree) -> Result<Self, RustreeError> {
+        let nodes = &species_tree.nodes;
+        let depths = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                node.depth.ok_or_else(|| {
+                    RustreeError::missing_depth("reconcile_parsimony", i, node.name.clone())
+                })
+            })
+            .collect::<Result<Vec<f64>, _>>()?;
+        let mut times = depths.clone();
+        times.sort_by(f64::total_cmp);
+        times.dedup();
+        let rank = |i: usize| times.partition_point(|&t| t < depths[i]);
+
+        let mut first = vec![0; nodes.len()];
+        let mut last = vec![0; nodes.len()];
+        for (i, node) in nodes.iter().enumerate() {
+            last[i] = rank(i);
+            first[i] = node.parent.map_or(0, |p| rank(p) + 1);
+            if last[i] < first[i] {
+                return Err(RustreeError::Validation(format!(
+                    "time-consistent reconciliation needs species nodes strictly deeper than \
+                     their parent and the root, but node '{}' is not",
+                    node.name
+                )));
+            }
+        }
+
+        let mut base = vec![0; nodes.len()];
+        let mut locs = vec![Vec::new(); times.len()];
+        let mut loc_slice = Vec::new();
+        let mut loc_branch = Vec::new();
+        for i in 0..nodes.len() {
+            base[i] = loc_slice.len();
+            for (j, slice_locs) in locs.iter_mut().enumerate().take(last[i] + 1).skip(first[i]) {
+                slice_locs.push(loc_slice.len());
+                loc_slice.push(j);
+                loc_branch.push(i);
+            }
+        }
+        Ok(Self {
+            last,
+            base,
+            locs,
+            loc_slice,
+            loc_branch,
+        })
+    }
+}
+
+/// Best and runner-up entry costs over the branches of one slice, used to
+/// answer "best recipient other than the donor" in O(1).
+#[derive(Clone, Copy, Debug)]
+struct SliceBest {
+    cost: f64,
+    count: f64,
+    /// Number of branches achieving `cost`.
+    ties: usize,
+    second_cost: f64,
+    second_count: f64,
+}
+
+impl SliceBest {
+    const EMPTY: Self = Self {
+        cost: f64::INFINITY,
+        count: 0.0,
+        ties: 0,
+        second_cost: f64::INFINITY,
+        second_count: 0.0,
+    };
+}
+
+/// Filled DP tables for one gene tree, from [`reconcile_parsimony`].
+///
+/// Scenarios are counted by their events and locations; in the
+/// time-consistent model the slice of each duplication and transfer is part of
+/// the scenario, so placements that differ only in time count separately.
+/// Counts are kept as `f64` and are exact below 2^53.
+#[derive(Clone, Debug)]
+pub struct ParsimonyReconciliation {
+    species_tree: Arc<FlatTree>,
+    gene_tree: FlatTree,
+    leaf_species: Vec<Option<usize>>,
+    costs: DtlCosts,
+    /// `None` in the undated model.
+    slices: Option<Slices>,
+    n_locs: usize,
+    at: Table,
+    within: Table,
+    /// Undated only.
+    below: Table,
+    /// Undated only.
+    away: Table,
+    /// Time-consistent only, indexed by `gene * n_slices + slice`.
+    slice_best: Vec<SliceBest>,
+    cost: f64,
+    count: f64,
+}
+
+/// Reconciles `gene_tree` with `species_tree` by maximum parsimony.
+///
+/// `leaf_species[g]` is the species of gene leaf `g` (see
+/// [`map_leaves_by_name`](super::map_leaves_by_name)). The gene tree root may
+/// be placed anywhere in the species tree at no cost.
+///
+/// # Errors
+/// Returns an error if either tree is not binary, a leaf has no valid
+/// species, a cost is negative or not finite, or, with
+/// [`time_consistent`](ParsimonyOptions::time_consistent), the species tree
+/// lacks depths or has zero-length branches.
+pub fn reconcile_parsimony(
+    species_tree: Arc<FlatTree>,
+    gene_tree: &FlatTree,
+    leaf_species: &[Option<usize>],
+    options: ParsimonyOptions,
+) -> Result<ParsimonyReconciliation, RustreeError> {
+    validate_leaf_species(gene_tree, &species_tree, leaf_species)?;
+    let DtlCosts {
+        duplication,
+        transfer,
+        loss,
+    } = options.costs;
+    if [duplication, transfer, loss]
+        .iter()
+        .any(|c| !c.is_finite() || *c < 0.0)
+    {
+        return Err(RustreeError::Validation(format!(
+            "DTL costs must be finite and non-negative, got {:?}",
+            options.costs
+        )));
+    }
+    let slices = options
+        .time_consistent
+        .then(|| Slices::new(&species_tree))
+        .transpose()?;
+
+    let n_genes = gene_tree.nodes.len();
+    let n_locs = slices
+        .as_ref()
+        .map_or(species_tree.nodes.len(), |sl| sl.loc_slice.len());
+    let undated_len = if slices.is_some() {
+        0
+    } else {
+        n_genes * n_locs
+    };
+    let n_slices = slices.as_ref().map_or(0, |sl| sl.locs.len());
+    let mut rec = ParsimonyReconciliation {
+        species_tree,
+        gene_tree: gene_tree.clone(),
+        leaf_species: leaf_species.to_vec(),
+        costs: options.costs,
+        slices,
+        n_locs,
+        at: Table::new(n_genes * n_locs),
+        within: Table::new(n_genes * n_locs),
+        below: Table::new(undated_len),
+        away: Table::new(undated_len),
+        slice_best: vec![SliceBest::EMPTY; n_genes * n_slices],
+        cost: f64::INFINITY,
+        count: 0.0,
+    };
+    rec.fill();
+    Ok(rec)
+}
+
+/// Whether two costs are equal up to rounding.
+#[inline]
+fn near(a: f64, b: f64) -> bool {
+    a == b
+        || (a.is_finite() && b.is_finite() && (a - b).abs() <= 1e-9 * (1.0 + a.abs().max(b.abs())))
+}
+
+/// Folds a candidate into the running (minimum cost, co-optimal count).
+#[inline]
+fn merge(best: &mut (f64, f64), (cost, count): (f64, f64)) {
+    if count <= 0.0 || !cost.is_finite() {
+        return;
+    }
+    if near(cost, best.0) {
+        best.1 += count;
+    } else if cost < best.0 {
+        *best = (cost, count);
+    }
+}
+
+impl ParsimonyReconciliation {
+    /// Minimum total DTL cost.
+    pub fn cost(&self) -> f64 {
+        self.cost
+    }
+
+    /// Number of co-optimal reconciliations.
+    pub fn count_optimal(&self) -> f64 {
+        self.count
+    }
+
+    /// One optimal reconciliation, chosen deterministically.
+    pub fn best(&self) -> Result<RecTree, RustreeError> {
+        self.trace(|_| 0)
+    }
+
+    /// Samples an optimal reconciliation uniformly among all co-optimal ones.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Result<RecTree, RustreeError> {
+        self.trace(|counts| {
+            let mut target = rng.gen::<f64>() * counts.iter().sum::<f64>();
+            for (i, &count) in counts.iter().enumerate() {
+                if target < count {
+                    return i;
+                }
+                target -= count;
+            }
+            counts.len() - 1
+        })
+    }
+
+    /// Lists up to `limit` distinct optimal reconciliations.
+    pub fn enumerate(&self, limit: usize) -> Result<Vec<RecTree>, RustreeError> {
+        let mut out = Vec::new();
+        // Index of the option taken at each decision of the traceback, in order;
+        // advanced like an odometer so that every combination is visited once.
+        let mut script: Vec<usize> = Vec::new();
+        while out.len() < limit {
+            let mut arity = Vec::new();
+            out.push(self.trace(|counts| {
+                let pick = script.get(arity.len()).copied().unwrap_or(0);
+                arity.push(counts.len());
+                pick
+            })?);
+
+            script.resize(arity.len(), 0);
+            loop {
+                match script.len() {
+                    0 => return Ok(out),
+                    k if script[k - 1] + 1 < arity[k - 1] => {
+                        script[k - 1] += 1;
+                        break;
+                    }
+                    _ => {
+                        script.pop();
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    #[inline]
+    fn index(&self, gene: usize, loc: usize) -> usize {
+        gene * self.n_locs + loc
+    }
+
+    #[inline]
+    fn loc_species(&self, loc: usize) -> usize {
+        self.slices.as_ref().map_or(loc, |sl| sl.loc_branch[loc])
+    }
+
+    /// Whether the species node itself (not just its branch) is at `loc`.
+    #[inline]
+    fn at_node(&self, loc: usize, species: usize) -> bool {
+        self.slices
+            .as_ref()
+            .is_none_or(|sl| sl.loc_slice[loc] == sl.last[species])
+    }
+
+    /// Location of species child `child` right after the node at `loc`.
+    #[inline]
+    fn child_loc(&self, loc: usize, species: usize, child: usize) -> Option<usize> {
+        match &self.slices {
+            Some(sl) => (sl.loc_slice[loc] == sl.last[species]).then(|| sl.base[child]),
+            None => Some(child),
+        }
+    }
+
+    /// Same branch, next slice.
+    #[inline]
+    fn later_loc(&self, loc: usize, species: usize) -> Option<usize> {
+        self.slices
+            .as_ref()
+            .and_then(|sl| (sl.loc_slice[loc] < sl.last[species]).then_some(loc + 1))
+    }
+
+    fn value(&self, state: State) -> (f64, f64) {
+        match state {
+            State::At(g, loc) => self.at.get(self.index(g, loc)),
+            State::Within(g, loc) => self.within.get(self.index(g, loc)),
+            State::Below(g, loc) => self.below.get(self.index(g, loc)),
+            State::Away(g, loc) => match &self.slices {
+                None => self.away.get(self.index(g, loc)),
+                Some(sl) => {
+                    let best = self.slice_best[g * sl.locs.len() + sl.loc_slice[loc]];
+                    let (own_cost, own_count) = self.within.get(self.index(g, loc));
+                    if best.ties == 0 {
+                        (f64::INFINITY, 0.0)
+                    } else if own_count > 0.0 && near(own_cost, best.cost) {
+                        if best.ties > 1 {
+                            (best.cost, best.count - own_count)
+                        } else {
+                            (best.second_cost, best.second_count)
+                        }
+                    } else {
+                        (best.cost, best.count)
+                    }
+                }
+            },
+        }
+    }
+
+    fn choice_value(&self, choice: &Choice) -> (f64, f64) {
+        let mut cost = choice.extra;
+        let mut count = 1.0;
+        for state in choice.next.iter().flatten() {
+            let (c, n) = self.value(*state);
+            cost += c;
+            count *= n;
+        }
+        (cost, count)
+    }
+
+    fn for_each_choice<F: FnMut(Choice)>(&self, state: State, mut f: F) {
+        let pass = |next: State| Choice {
+            action: Action::Pass,
+            extra: 0.0,
+            next: [Some(next), None],
+        };
+        match state {
+            State::At(g, loc) => {
+                let s = self.loc_species(loc);
+                let sp = &self.species_tree.nodes[s];
+                let node = &self.gene_tree.nodes[g];
+                let (Some(g1), Some(g2)) = (node.left_child, node.right_child) else {
+                    if self.leaf_species[g] == Some(s) && self.at_node(loc, s) {
+                        f(Choice {
+                            action: Action::Leaf,
+                            extra: 0.0,
+                            next: [None, None],
+                        });
+                    }
+                    return;
+                };
+                if let (Some(s1), Some(s2)) = (sp.left_child, sp.right_child) {
+                    if let (Some(l1), Some(l2)) =
+                        (self.child_loc(loc, s, s1), self.child_loc(loc, s, s2))
+                    {
+                        for (a, b) in [(l1, l2), (l2, l1)] {
+                            f(Choice {
+                                action: Action::Speciation,
+                                extra: 0.0,
+                                next: [Some(State::Within(g1, a)), Some(State::Within(g2, b))],
+                            });
+                        }
+                    }
+                }
+                f(Choice {
+                    action: Action::Duplication,
+                    extra: self.costs.duplication,
+                    next: [Some(State::Within(g1, loc)), Some(State::Within(g2, loc))],
+                });
+                for next in [
+                    [State::Within(g1, loc), State::Away(g2, loc)],
+                    [State::Away(g1, loc), State::Within(g2, loc)],
+                ] {
+                    f(Choice {
+                        action: Action::Transfer,
+                        extra: self.costs.transfer,
+                        next: next.map(Some),
+                    });
+                }
+            }
+            State::Within(g, loc) => {
+                f(pass(State::At(g, loc)));
+                let s = self.loc_species(loc);
+                if let Some(later) = self.later_loc(loc, s) {
+                    f(pass(State::Within(g, later)));
+                }
+                let sp = &self.species_tree.nodes[s];
+                if let (Some(s1), Some(s2)) = (sp.left_child, sp.right_child) {
+                    for (kept, lost, kept_left) in [(s1, s2, true), (s2, s1, false)] {
+                        if let Some(next) = self.child_loc(loc, s, kept) {
+                            f(Choice {
+                                action: Action::SpeciationLoss { lost, kept_left },
+                                extra: self.costs.loss,
+                                next: [Some(State::Within(g, next)), None],
+                            });
+                        }
+                    }
+                }
+            }
+            State::Below(g, s) => {
+                f(pass(State::Within(g, s)));
+                let sp = &self.species_tree.nodes[s];
+                for child in [sp.left_child, sp.right_child].into_iter().flatten() {
+                    f(pass(State::Below(g, child)));
+                }
+            }
+            State::Away(g, loc) => match &self.slices {
+                None => {
+                    if let Some(parent) = self.species_tree.nodes[loc].parent {
+                        f(pass(State::Away(g, parent)));
+                        let p = &self.species_tree.nodes[parent];
+                        let sibling = if p.left_child == Some(loc) {
+                            p.right_child
+                        } else {
+                            p.left_child
+                        };
+                        if let Some(sibling) = sibling {
+                            f(pass(State::Below(g, sibling)));
+                        }
+                    }
+                }
+                Some(sl) => {
+                    for &recipient in &sl.locs[sl.loc_slice[loc]] {
+                        if recipient != loc {
+                            f(pass(State::Within(g, recipient)));
+                        }
+                    }
+                }
+            },
+        }
+    }
+
+    fn solve(&self, state: State) -> (f64, f64) {
+        let mut best = (f64::INFINITY, 0.0);
+        self.for_each_choice(state, |choice| merge(&mut best, self.choice_value(&choice)));
+        best
+    }
+
+    fn fill(&mut self) {
+        let species_post = self.species_tree.postorder_indices();
+        let slice_locs = self.slices.as_ref().map(|sl| sl.locs.clone());
+        for g in self.gene_tree.postorder_indices() {
+            match &slice_locs {
+                None => {
+                    for &s in &species_post {
+                        self.solve_into(State::At(g, s));
+                        self.solve_into(State::Within(g, s));
+                        self.solve_into(State::Below(g, s));
+                    }
+                    for &s in species_post.iter().rev() {
+                        self.solve_into(State::Away(g, s));
+                    }
+                }
+                Some(slice_locs) => {
+                    // Lineages only move to later slices, so fill from the present back.
+                    for (slice, locs) in slice_locs.iter().enumerate().rev() {
+                        for &loc in locs {
+                            self.solve_into(State::At(g, loc));
+                            self.solve_into(State::Within(g, loc));
+                        }
+                        self.slice_best[g * slice_locs.len() + slice] = self.best_in_slice(g, locs);
+                    }
+                }
+            }
+        }
+
+        let root = self.gene_tree.root;
+        let mut best = (f64::INFINITY, 0.0);
+        for loc in 0..self.n_locs {
+            merge(&mut best, self.at.get(self.index(root, loc)));
+        }
+        (self.cost, self.count) = best;
+    }
+
+    fn solve_into(&mut self, state: State) {
+        let value = self.solve(state);
+        match state {
+            State::At(g, loc) => self.at.set(self.index(g, loc), value),
+            State::Within(g, loc) => self.within.set(self.index(g, loc), value),
+            State::Below(g, loc) => self.below.set(self.index(g, loc), value),
+            State::Away(g, loc) => self.away.set(self.index(g, loc), value),
+        }
+    }
+
+    fn best_in_slice(&self, g: usize, locs: &[usize]) -> SliceBest {
+        let entries: Vec<(f64, f64)> = locs
+            .iter()
+            .map(|&loc| self.within.get(self.index(g, loc)))
+            .filter(|&(cost, count)| count > 0.0 && cost.is_finite())
+            .collect();
+        let cost = entries.iter().map(|e| e.0).fold(f64::INFINITY, f64::min);
+        let (tied, rest): (Vec<_>, Vec<_>) = entries.iter().partition(|e| near(e.0, cost));
+        let mut second = (f64::INFINITY, 0.0);
+        for &e in rest {
+            merge(&mut second, e);
+        }
+        SliceBest {
+            cost,
+            count: tied.iter().map(|e| e.1).sum(),
+            ties: tied.len(),
+            second_cost: second.0,
+            second_count: second.1,
+        }
+    }
+
+    /// Builds one optimal reconciliation; `choose` picks among the co-optimal
+    /// options of each decision, given their scenario counts.
+    fn trace<F: FnMut(&[f64]) -> usize>(&self, mut choose: F) -> Result<RecTree, RustreeError> {
+        if self.count <= 0.0 {
+            return Err(RustreeError::Validation(
+                "gene tree has no reconciliation with the species tree".to_string(),
+            ));
+        }
+        let root = self.gene_tree.root;
+        let mut options: Vec<(State, f64)> = (0..self.n_locs)
+            .map(|loc| (State::At(root, loc), self.at.get(self.index(root, loc))))
+            .filter(|(_, (cost, count))| *count > 0.0 && near(*cost, self.cost))
+            .map(|(state, (_, count))| (state, count))
+            .collect();
+        let start = pick(&mut options, &mut choose);
+
+        let mut builder = RecTreeBuilder::new(&self.gene_tree);
+        let mut stack: Vec<(State, Slot)> = vec![(start, None)];
+        while let Some((state, slot)) = stack.pop() {
+            let target = self.value(state).0;
+            let mut options: Vec<(Choice, f64)> = Vec::new();
+            self.for_each_choice(state, |choice| {
+                let (cost, count) = self.choice_value(&choice);
+                if count > 0.0 && near(cost, target) {
+                    options.push((choice, count));
+                }
+            });
+            if options.is_empty() {
+                return Err(RustreeError::Tree(format!(
+                    "no optimal option for parsimony state {state:?}"
+                )));
+            }
+            let choice = pick(&mut options, &mut choose);
+
+            let (gene, loc) = match state {
+                State::At(g, loc) | State::Within(g, loc) | State::Away(g, loc) => (g, loc),
+                State::Below(g, s) => (g, s),
+            };
+            let species = self.loc_species(loc);
+            let event = match choice.action {
+                Action::Pass => {
+                    stack.push((choice.next[0].expect("pass has a next state"), slot));
+                    continue;
+                }
+                Action::SpeciationLoss { lost, kept_left } => {
+                    let node = builder.push(None, slot, species, Event::Speciation);
+                    builder.push(None, Some((node, !kept_left)), lost, Event::Loss);
+                    let next = choice.next[0].expect("speciation-loss has a next state");
+                    stack.push((next, Some((node, kept_left))));
+                    continue;
+                }
+                Action::Leaf => Event::Leaf,
+                Action::Speciation => Event::Speciation,
+                Action::Duplication => Event::Duplication,
+                Action::Transfer => Event::Transfer,
+            };
+            let node = builder.push(Some(gene), slot, species, event);
+            if let [Some(left), Some(right)] = choice.next {
+                stack.push((right, Some((node, false))));
+                stack.push((left, Some((node, true))));
+            }
+        }
+        builder.finish(Arc::clone(&self.species_tree))
+    }
+}
+
+/// Takes the option chosen by `choose`, skipping the call when there is only one.
+fn pick<T: Copy, F: FnMut(&[f64]) -> usize>(options: &mut [(T, f64)], choose: &mut F) -> T {
+    if options.len() == 1 {
+        return options[0].0;
+    }
+    let counts: Vec<f64> = options.iter().map(|o| o.1).collect();
+    options[choose(&counts).min(options.len() - 1)].0
+}
@@ -0,0 +1,540 @@
+//! Duplication, transfer and loss rate estimates from reconciled gene trees.
+//!
+//! Given gene trees whose reconciliation is known — simulated truth, or
+//! scenarios sampled by ALERax or [`super::undated`] — each rate is estimated
+//! as an event count divided by the gene lineage exposure in which the events
+//! could have happened, both over the whole species tree and per species
+//! branch. Intervals are the Garwood Poisson intervals for the count, using
+//! the Wilson–Hilferty approximation of the chi-square quantiles.
+
+use super::undated::UndatedRates;
+use crate::error::RustreeError;
+use crate::node::{Event, FlatTree, GeneForest, RecTree, SubtreeIntervals};
+use crate::simulation::dtl::utils::is_extant_species;
+use crate::simulation::dtl::DTLConfig;
+use rayon::prelude::*;
+
+/// How gene lineage exposure is measured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exposure {
+    /// Lineage time from the depths of the gene nodes, which must be on the
+    /// species tree time scale (simulated gene trees). Rates are per unit time
+    /// and comparable with [`DTLConfig`].
+    GeneDepths,
+    /// Lineage time for undated reconciliations. Speciations and leaves sit at
+    /// the end of their species branch, and chains of duplications, transfers
+    /// and losses are spaced evenly between the previous event and the end of
+    /// the branch.
+    EvenlySpaced,
+    /// Number of lineage steps that end in a speciation or a leaf. Rates are
+    /// then event counts relative to speciations, as in the undated model of
+    /// ALE and ALERax (see [`UndatedRates`]).
+    SpeciationSteps,
+}
+
+/// Estimate of one event rate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateEstimate {
+    pub events: usize,
+    pub exposure: f64,
+    /// `events / exposure`; zero without events, infinite without exposure.
+    pub rate: f64,
+    pub lower: f64,
+    /// Infinite when there is no exposure.
+    pub upper: f64,
+}
+
+impl RateEstimate {
+    fn new(events: usize, exposure: f64, z: f64) -> Self {
+        let k = events as f64;
+        if exposure <= 0.0 {
+            let rate = if events == 0 { 0.0 } else { f64::INFINITY };
+            return Self {
+                events,
+                exposure,
+                rate,
+                lower: rate,
+                upper: f64::INFINITY,
+            };
+        }
+        let lower = if events == 0 {
+            0.0
+        } else {
+            chi_square_quantile(-z, 2.0 * k) / 2.0
+        };
+        Self {
+            events,
+            exposure,
+            rate: k / exposure,
+            lower: lower / exposure,
+            upper: chi_square_quantile(z, 2.0 * k + 2.0) / 2.0 / exposure,
+        }
+    }
+
+    /// Whether `rate` lies in the confidence interval.
+    pub fn contains(&self, rate: f64) -> bool {
+        self.lower <= rate && rate <= self.upper
+    }
+}
+
+/// Duplication, transfer and loss estimates over the same exposure.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DtlRateEstimate {
+    pub duplication: RateEstimate,
+    /// Transfers counted on the donor branch.
+    pub transfer: RateEstimate,
+    pub loss: RateEstimate,
+}
+
+impl DtlRateEstimate {
+    fn new(counts: [usize; 3], exposure: f64, z: f64) -> Self {
+        Self {
+            duplication: RateEstimate::new(counts[0], exposure, z),
+            transfer: RateEstimate::new(counts[1], exposure, z),
+            loss: RateEstimate::new(counts[2], exposure, z),
+        }
+    }
+
+    pub fn exposure(&self) -> f64 {
+        self.duplication.exposure
+    }
+
+    /// Whether all three intervals contain the given rates.
+    pub fn contains(&self, lambda_d: f64, lambda_t: f64, lambda_l: f64) -> bool {
+        self.duplication.contains(lambda_d)
+            && self.transfer.contains(lambda_t)
+            && self.loss.contains(lambda_l)
+    }
+
+    /// Point estimates as undated model rates, for comparison with ALERax
+    /// when estimated with [`Exposure::SpeciationSteps`].
+    pub fn point_estimates(&self) -> UndatedRates {
+        UndatedRates {
+            duplication: self.duplication.rate,
+            transfer: self.transfer.rate,
+            loss: self.loss.rate,
+        }
+    }
+}
+
+/// Global and per species branch rate estimates of a forest.
+#[derive(Clone, Debug)]
+pub struct DtlRateEstimates {
+    pub exposure: Exposure,
+    pub confidence: f64,
+    pub global: DtlRateEstimate,
+    /// Estimates per species node index; the root index is the root stem.
+    pub branches: Vec<DtlRateEstimate>,
+}
+
+/// Simulation rates checked against estimated intervals.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateCoverage {
+    /// Configured (D, T, L) rates averaged over branches, weighted by exposure.
+    pub expected: (f64, f64, f64),
+    /// Whether the global intervals contain the expected rates.
+    pub global: (bool, bool, bool),
+    /// Fraction of exposed branches whose interval contains the configured rate.
+    pub branches: (f64, f64, f64),
+}
+
+impl DtlRateEstimates {
+    /// Compares the estimates with the rates of a simulation configuration,
+    /// either constant or per branch ([`DTLConfig::branch_rates`]).
+    ///
+    /// # Errors
+    /// Returns an error for [`Exposure::SpeciationSteps`] estimates, which are
+    /// not per unit time, or if the configuration has branch rates for a
+    /// different species tree.
+    pub fn coverage(&self, config: &DTLConfig) -> Result<RateCoverage, RustreeError> {
+        if self.exposure == Exposure::SpeciationSteps {
+            return Err(RustreeError::Validation(
+                "speciation-step rates cannot be compared with per-time simulation rates"
+                    .to_string(),
+            ));
+        }
+        config.validate_for_tree(self.branches.len())?;
+
+        let mut weighted = [0.0; 3];
+        let mut covered = [0usize; 3];
+        let mut exposed = 0usize;
+        for (species, estimate) in self.branches.iter().enumerate() {
+            let exposure = estimate.exposure();
+            if exposure <= 0.0 {
+                continue;
+            }
+            exposed += 1;
+            let (d, t, l) = config.branch_event_rates(species);
+            let pairs = [
+                (d, &estimate.duplication),
+                (t, &estimate.transfer),
+                (l, &estimate.loss),
+            ];
+            for (i, (rate, branch)) in pairs.into_iter().enumerate() {
+                weighted[i] += rate * exposure;
+                covered[i] += usize::from(branch.contains(rate));
+            }
+        }
+
+        let total = self.global.exposure();
+        let expected = weighted.map(|w| if total > 0.0 { w / total } else { 0.0 });
+        let fraction = covered.map(|c| {
+            if exposed == 0 {
+                1.0
+            } else {
+                c as f64 / exposed as f64
+            }
+        });
+        Ok(RateCoverage {
+            expected: (expected[0], expected[1], expected[2]),
+            global: (
+                self.global.duplication.contains(expected[0]),
+                self.global.transfer.contains(expected[1]),
+                self.global.loss.contains(expected[2]),
+            ),
+            branches: (fraction[0], fraction[1], fraction[2]),
+        })
+    }
+}
+
+/// Per-branch event counts (D, T, L) and exposure of one or more families.
+#[derive(Clone, Debug)]
+struct Tally {
+    counts: Vec<[usize; 3]>,
+    exposure: Vec<f64>,
+}
+
+impl Tally {
+    fn new(n: usize) -> Self {
+        Self {
+            counts: vec![[0; 3]; n],
+            exposure: vec![0.0; n],
+        }
+    }
+
+    fn merge(mut self, other: Tally) -> Tally {
+        for (mine, theirs) in self.counts.iter_mut().zip(&other.counts) {
+            for (m, t) in mine.iter_mut().zip(theirs) {
+                *m += t;
+            }
+        }
+        for (mine, theirs) in self.exposure.iter_mut().zip(&other.exposure) {
+            *mine += theirs;
+        }
+        self
+    }
+}
+
+/// Estimates duplication, transfer and loss rates from the reconciled gene
+/// trees of `forest`, with intervals at the given `confidence` level.
+///
+/// Duplications and losses are counted on the species branch they map to and
+/// transfers on the donor branch, the recipient being the child mapped outside
+/// the donor's subtree. A loss dated at the end of an extinct species branch
+/// is the extinction of that species, not a gene loss, and ends its lineage
+/// like a leaf. Gene nodes without a species mapping are ignored.
+///
+/// # Errors
+/// Returns an error if `confidence` is not in (0, 1), a gene maps outside the
+/// species tree, or a required species or gene depth is missing.
+pub fn estimate_dtl_rates(
+    forest: &GeneForest,
+    exposure: Exposure,
+    confidence: f64,
+) -> Result<DtlRateEstimates, RustreeError> {
+    if !(confidence > 0.0 && confidence < 1.0) {
+        return Err(RustreeError::Validation(format!(
+            "confidence must be in (0, 1), got {confidence}"
+        )));
+    }
+    let species = forest.species_tree.as_ref();
+    let n = species.nodes.len();
+    let bounds = if exposure == Exposure::SpeciationSteps {
+        Vec::new()
+    } else {
+        branch_bounds(species)?
+    };
+    let species_subtrees = SubtreeIntervals::new(species);
+
+    let tally = forest
+        .gene_trees
+        .par_iter()
+        .map(|rec| tally_family(rec, species, &species_subtrees, &bounds, exposure))
+        .try_reduce(|| Tally::new(n), |a, b| Ok(a.merge(b)))?;
+
+    let z = normal_quantile(0.5 + confidence / 2.0);
+    let mut totals = [0usize; 3];
+    for counts in &tally.counts {
+        for (total, count) in totals.iter_mut().zip(counts) {
+            *total += count;
+        }
+    }
+    Ok(DtlRateEstimates {
+        exposure,
+        confidence,
+        global: DtlRateEstimate::new(totals, tally.exposure.iter().sum(), z),
+        branches: tally
+            .counts
+            .iter()
+            .zip(&tally.exposure)
+            .map(|(&counts, &exposure)| DtlRateEstimate::new(counts, exposure, z))
+            .collect(),
+    })
+}
+
+/// `(start, end)` time of every species branch.
+fn branch_bounds(species: &FlatTree) -> Result<Vec<(f64, f64)>, RustreeError> {
+    species
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(idx, node)| {
+            let depth = node.depth.ok_or_else(|| {
+                RustreeError::missing_depth("estimate_dtl_rates", idx, &node.name)
+            })?;
+            Ok((depth - node.length, depth))
+        })
+        .collect()
+}
+
+fn tally_family(
+    rec: &RecTree,
+    species: &FlatTree,
+    species_subtrees: &SubtreeIntervals,
+    bounds: &[(f64, f64)],
+    exposure: Exposure,
+) -> Result<Tally, RustreeError> {
+    let n = species.nodes.len();
+    let genes = &rec.gene_tree.nodes;
+    if let Some(&sp) = rec.node_mapping.iter().flatten().find(|&&sp| sp >= n) {
+        return Err(RustreeError::Index(format!(
+            "gene node maps to species {sp}, species tree has {n} nodes"
+        )));
+    }
+
+    // Gene lineages ended by the extinction of their species.
+    let extinct: Vec<bool> = genes
+        .iter()
+        .zip(&rec.node_mapping)
+        .zip(&rec.event_mapping)
+        .map(|((node, &sp), event)| {
+            let Some(sp) = sp.filter(|_| *event == Event::Loss) else {
+                return false;
+            };
+            let species_node = &species.nodes[sp];
+            species_node.left_child.is_none()
+                && !is_extant_species(species_node)
+                && node
+                    .depth
+                    .zip(species_node.depth)
+                    .is_some_and(|(gene, end)| (gene - end).abs() <= 1e-9 * end.abs().max(1.0))
+        })
+        .collect();
+
+    let mut tally = Tally::new(n);
+    // Whether each gene node was received by transfer from its parent.
+    let mut received = vec![false; genes.len()];
+    for (gene, &is_extinct) in extinct.iter().enumerate() {
+        let Some(sp) = rec.node_mapping[gene] else {
+            continue;
+        };
+        match rec.event_mapping[gene] {
+            Event::Duplication => tally.counts[sp][0] += 1,
+            Event::Loss if !is_extinct => tally.counts[sp][2] += 1,
+            Event::Transfer => {
+                if !rec.is_transfer_event(gene) {
+                    continue;
+                }
+                tally.counts[sp][1] += 1;
+                if let Some(child) = rec.transfer_recipient_child(gene, species_subtrees) {
+                    received[child] = true;
+                }
+            }
+            Event::Speciation | Event::Leaf | Event::Loss => {
+                if exposure == Exposure::SpeciationSteps {
+                    tally.exposure[sp] += 1.0;
+                }
+            }
+        }
+    }
+    if exposure == Exposure::SpeciationSteps {
+        return Ok(tally);
+    }
+
+    let times = match exposure {
+        Exposure::GeneDepths => genes
+            .iter()
+            .enumerate()
+            .map(|(idx, node)| {
+                node.depth.ok_or_else(|| {
+                    RustreeError::missing_depth("estimate_dtl_rates", idx, &node.name)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => evenly_spaced_times(rec, bounds, &received, &extinct),
+    };
+
+    for (gene, node) in genes.iter().enumerate() {
+        let Some(mut sp) = rec.node_mapping[gene] else {
+            continue;
+        };
+        let end = times[gene];
+        let start = match node.parent {
+            Some(parent) if rec.node_mapping[parent].is_some() => times[parent],
+            Some(_) => continue,
+            None => bounds[sp].0,
+        };
+        // Walk up from the child's branch until the lineage start is reached;
+        // a received lineage only lives on the recipient branch.
+        loop {
+            let (branch_start, branch_end) = bounds[sp];
+            let overlap = end.min(branch_end) - start.max(branch_start);
+            if overlap > 0.0 {
+                tally.exposure[sp] += overlap;
+            }
+            match species.nodes[sp].parent {
+                Some(parent) if !received[gene] && branch_start > start => sp = parent,
+                _ => break,
+            }
+        }
+    }
+    Ok(tally)
+}
+
+/// Event times for an undated reconciliation (see [`Exposure::EvenlySpaced`]).
+fn evenly_spaced_times(
+    rec: &RecTree,
+    bounds: &[(f64, f64)],
+    received: &[bool],
+    extinct: &[bool],
+) -> Vec<f64> {
+    let genes = &rec.gene_tree.nodes;
+    let on_branch = |gene: usize| {
+        !matches!(rec.event_mapping[gene], Event::Speciation | Event::Leaf)
+            && !extinct[gene]
+            && rec.node_mapping[gene].is_some()
+    };
+    let postorder = rec.gene_tree.postorder_indices();
+
+    // Number of further events below each event on the same branch.
+    let mut height = vec![0usize; genes.len()];
+    for &gene in &postorder {
+        if !on_branch(gene) {
+            continue;
+        }
+        let node = &genes[gene];
+        height[gene] = [node.left_child, node.right_child]
+            .into_iter()
+            .flatten()
+            .filter(|&child| on_branch(child) && rec.node_mapping[child] == rec.node_mapping[gene])
+            .map(|child| height[child] + 1)
+            .max()
+            .unwrap_or(0);
+    }
+
+    let mut times = vec![0.0; genes.len()];
+    for &gene in postorder.iter().rev() {
+        let Some(sp) = rec.node_mapping[gene] else {
+            continue;
+        };
+        let (start, end) = bounds[sp];
+        if !on_branch(gene) {
+            times[gene] = end;
+            continue;
+        }
+        let previous = genes[gene].parent.and_then(|parent| {
+            let same_branch = on_branch(parent) && rec.node_mapping[parent] == Some(sp);
+            let within = received[gene] && start < times[parent] && times[parent] < end;
+            (same_branch || within).then_some(times[parent])
+        });
+        let from = previous.unwrap_or(start);
+        times[gene] = from + (end - from) / (height[gene] + 2) as f64;
+    }
+    times
+}
+
+/// Quantile of the chi-square distribution with `dof` degrees of freedom at
+/// the probability whose standard normal quantile is `z` (Wilson–Hilferty).
+fn chi_square_quantile(z: f64, dof: f64) -> f64 {
+    let c = 2.0 / (9.0 * dof);
+    (dof * (1.0 - c + z * c.sqrt()).powi(3)).max(0.0)
+}
+
+/// Standard normal quantile (Acklam's rational approximation, relative error
+/// below 1.2e-9).
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e1,
+        2.209460984245205e2,
+        -2.759285104469687e2,
+        1.38357751867269e2,
+        -3.066479806614716e1,
+        2.506628277459239,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e1,
+        1.615858368580409e2,
+        -1.556989798598866e2,
+        6.680131188771972e1,
+        -1.328068155288572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-3,
+        -3.223964580411365e-1,
+        -2.400758277161838,
+        -2.549732539343734,
+        4.374664141464968,
+        2.938163982698783,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-3,
+        3.224671290700398e-1,
+        2.445134137142996,
+        3.754408661907416,
+    ];
+    const P_LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - P_LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantiles_match_tables() {
+        assert!(normal_quantile(0.5).abs() < 1e-12);
+        assert!((normal_quantile(0.975) - 1.959964).abs() < 1e-6);
+        assert!((normal_quantile(0.005) + 2.575829).abs() < 1e-6);
+        // chi-square(10) at 0.975 is 20.483
+        assert!((chi_square_quantile(1.959964, 10.0) - 20.483).abs() < 0.05);
+    }
+
+    #[test]
+    fn poisson_interval_brackets_the_count() {
+        let z = normal_quantile(0.975);
+        let none = RateEstimate::new(0, 2.0, z);
+        assert_eq!((none.rate, none.lower), (0.0, 0.0));
+        // Exact 95% upper bound for zero events is 3.689.
+        assert!((none.upper * 2.0 - 3.689).abs() < 0.05);
+
+        let ten = RateEstimate::new(10, 1.0, z);
+        // Exact 95% interval for ten events is (4.795, 18.390).
+        assert!((ten.lower - 4.795).abs() < 0.05);
+        assert!((ten.upper - 18.390).abs() < 0.05);
+        assert!(ten.contains(10.0) && !ten.contains(20.0));
+    }
+}
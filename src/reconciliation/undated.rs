@@ -0,0 +1,721 @@
+//! Undated DTL likelihood and reconciliation sampling (the undated ALE model).
+//!
+//! Every species branch draws events with relative rates 1 (speciation), δ,
+//! τ and λ, so a lineage on branch `e` speciates, duplicates, transfers or is
+//! lost with probabilities `1/Z`, `δ/Z`, `τ/Z` and `λ/Z`, `Z = 1 + δ + τ + λ`.
+//! Transfers go to a uniformly chosen branch that is neither an ancestor nor
+//! a descendant of the donor; branches with no such recipient (the root) have
+//! no transfer term. The extinction probabilities `E(e)` and the probabilities
+//! `P(u, e)` of observing the gene subtree below `u` from a lineage at the top
+//! of branch `e` follow the recursions of Szöllősi et al. (2013, Syst. Biol.),
+//! solved by fixed-point iteration. Values are kept in linear space, which
+//! suits the small and medium families this model is meant for.
+
+use super::{
+    same_species_tree, validate_leaf_species, validate_species_tree, GeneFamily, RecTreeBuilder,
+    Slot,
+};
+use crate::error::RustreeError;
+use crate::node::{Event, FlatTree, GeneForest, RecTree};
+use rand::Rng;
+use rayon::prelude::*;
+use std::sync::Arc;
+
+const MAX_ITERATIONS: usize = 10_000;
+const TOLERANCE: f64 = 1e-12;
+
+/// Duplication, transfer and loss rates relative to the speciation rate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UndatedRates {
+    pub duplication: f64,
+    pub transfer: f64,
+    pub loss: f64,
+}
+
+impl UndatedRates {
+    fn validate(&self) -> Result<(), RustreeError> {
+        if [self.duplication, self.transfer, self.loss]
+            .iter()
+            .any(|r| !r.is_finite() || *r < 0.0)
+        {
+            return Err(RustreeError::Validation(format!(
+                "undated DTL rates must be finite and non-negative, got {self:?}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Species tree quantities of the undated model for one set of rates.
+#[derive(Clone, Debug)]
+pub struct UndatedDtlModel {
+    species_tree: Arc<FlatTree>,
+    rates: UndatedRates,
+    postorder: Vec<usize>,
+    /// Preorder rank and last preorder rank of the subtree of each branch.
+    enter: Vec<usize>,
+    exit: Vec<usize>,
+    /// Number of transfer recipients of each branch.
+    recipients: Vec<usize>,
+    p_speciation: Vec<f64>,
+    p_duplication: Vec<f64>,
+    p_transfer: Vec<f64>,
+    extinction: Vec<f64>,
+    /// Mean extinction probability over the recipients of each branch.
+    mean_extinction: Vec<f64>,
+}
+
+impl UndatedDtlModel {
+    /// Builds the model and solves for the extinction probabilities.
+    ///
+    /// # Errors
+    /// Returns an error if a rate is negative or not finite, if the species
+    /// tree is empty or has a node with a single child, or if the extinction
+    /// probabilities do not converge.
+    pub fn new(species_tree: Arc<FlatTree>, rates: UndatedRates) -> Result<Self, RustreeError> {
+        rates.validate()?;
+        validate_species_tree(&species_tree)?;
+        let n = species_tree.nodes.len();
+
+        let postorder = species_tree.postorder_indices();
+        let mut enter = vec![0; n];
+        let mut exit = vec![0; n];
+        let mut size = vec![1; n];
+        for &e in &postorder {
+            let node = &species_tree.nodes[e];
+            for child in [node.left_child, node.right_child].into_iter().flatten() {
+                size[e] += size[child];
+            }
+        }
+        let mut ancestors = vec![0; n];
+        let mut rank = 0;
+        let mut stack = vec![species_tree.root];
+        while let Some(e) = stack.pop() {
+            enter[e] = rank;
+            exit[e] = rank + size[e] - 1;
+            rank += 1;
+            let node = &species_tree.nodes[e];
+            for child in [node.right_child, node.left_child].into_iter().flatten() {
+                ancestors[child] = ancestors[e] + 1;
+                stack.push(child);
+            }
+        }
+        let recipients: Vec<usize> = (0..n).map(|e| n - size[e] - ancestors[e]).collect();
+
+        let (mut p_speciation, mut p_duplication, mut p_transfer, mut p_loss) =
+            (vec![0.0; n], vec![0.0; n], vec![0.0; n], vec![0.0; n]);
+        for e in 0..n {
+            let transfer = if recipients[e] > 0 {
+                rates.transfer
+            } else {
+                0.0
+            };
+            let z = 1.0 + rates.duplication + transfer + rates.loss;
+            p_speciation[e] = 1.0 / z;
+            p_duplication[e] = rates.duplication / z;
+            p_transfer[e] = transfer / z;
+            p_loss[e] = rates.loss / z;
+        }
+
+        let mut model = Self {
+            species_tree,
+            rates,
+            postorder,
+            enter,
+            exit,
+            recipients,
+            p_speciation,
+            p_duplication,
+            p_transfer,
+            extinction: vec![0.0; n],
+            mean_extinction: vec![0.0; n],
+        };
+
+        // Increasing iteration from zero converges to the minimal fixed point.
+        let mut extinction = vec![0.0; n];
+        let mut converged = false;
+        for _ in 0..MAX_ITERATIONS {
+            let mean = model.recipient_means(&extinction);
+            let mut delta: f64 = 0.0;
+            for &e in &model.postorder {
+                let node = &model.species_tree.nodes[e];
+                let below = match (node.left_child, node.right_child) {
+                    (Some(f), Some(g)) => extinction[f] * extinction[g],
+                    _ => 0.0,
+                };
+                let value = p_loss[e]
+                    + model.p_speciation[e] * below
+                    + model.p_duplication[e] * extinction[e] * extinction[e]
+                    + model.p_transfer[e] * extinction[e] * mean[e];
+                delta = delta.max((value - extinction[e]).abs());
+                extinction[e] = value;
+            }
+            if delta < TOLERANCE {
+                converged = true;
+                break;
+            }
+        }
+        if !converged {
+            return Err(not_converged("extinction probabilities", rates));
+        }
+        model.mean_extinction = model.recipient_means(&extinction);
+        model.extinction = extinction;
+        Ok(model)
+    }
+
+    pub fn rates(&self) -> UndatedRates {
+        self.rates
+    }
+
+    pub fn species_tree(&self) -> &Arc<FlatTree> {
+        &self.species_tree
+    }
+
+    /// Probability that a gene lineage starting on branch `e` leaves no
+    /// observed descendant.
+    pub fn extinction_probability(&self, e: usize) -> f64 {
+        self.extinction[e]
+    }
+
+    /// Whether `b` is a transfer recipient of `a`: neither an ancestor nor a
+    /// descendant of it, nor `a` itself.
+    #[inline]
+    fn is_recipient(&self, a: usize, b: usize) -> bool {
+        let a_above_b = self.enter[a] <= self.enter[b] && self.enter[b] <= self.exit[a];
+        let b_above_a = self.enter[b] <= self.enter[a] && self.enter[a] <= self.exit[b];
+        !a_above_b && !b_above_a
+    }
+
+    /// Mean of `values` over the transfer recipients of every branch, in O(n).
+    fn recipient_means(&self, values: &[f64]) -> Vec<f64> {
+        let nodes = &self.species_tree.nodes;
+        let total: f64 = values.iter().sum();
+        let mut subtree = values.to_vec();
+        for &e in &self.postorder {
+            for child in [nodes[e].left_child, nodes[e].right_child]
+                .into_iter()
+                .flatten()
+            {
+                subtree[e] += subtree[child];
+            }
+        }
+        let mut above = vec![0.0; nodes.len()];
+        for &e in self.postorder.iter().rev() {
+            if let Some(parent) = nodes[e].parent {
+                above[e] = above[parent] + values[parent];
+            }
+        }
+        (0..nodes.len())
+            .map(|e| match self.recipients[e] {
+                0 => 0.0,
+                k => (total - subtree[e] - above[e]).max(0.0) / k as f64,
+            })
+            .collect()
+    }
+
+    /// Solves the gene tree recursions for one family.
+    ///
+    /// `leaf_species[g]` is the species of gene leaf `g` (see
+    /// [`map_leaves_by_name`](super::map_leaves_by_name)).
+    ///
+    /// # Errors
+    /// Returns an error if either tree is not binary, a leaf has no valid
+    /// species, or the rates are so high that the recursions diverge or do not
+    /// converge.
+    pub fn reconcile(
+        &self,
+        gene_tree: &FlatTree,
+        leaf_species: &[Option<usize>],
+    ) -> Result<UndatedReconciliation, RustreeError> {
+        validate_leaf_species(gene_tree, &self.species_tree, leaf_species)?;
+        let n = self.species_tree.nodes.len();
+        let m = gene_tree.nodes.len();
+        let mut rec = UndatedReconciliation {
+            model: self.clone(),
+            gene_tree: gene_tree.clone(),
+            leaf_species: leaf_species.to_vec(),
+            prob: vec![0.0; m * n],
+            mean_prob: vec![0.0; m * n],
+            log_likelihood: f64::NEG_INFINITY,
+        };
+        rec.fill()?;
+        Ok(rec)
+    }
+
+    /// Log-probability of `gene_tree`, with the origination branch uniform
+    /// over the species tree and conditioned on at least one observed gene.
+    pub fn log_likelihood(
+        &self,
+        gene_tree: &FlatTree,
+        leaf_species: &[Option<usize>],
+    ) -> Result<f64, RustreeError> {
+        Ok(self.reconcile(gene_tree, leaf_species)?.log_likelihood())
+    }
+
+    /// Summed log-likelihood of the extant gene trees of `forest`, computed in
+    /// parallel. Families without extant genes are skipped.
+    ///
+    /// # Errors
+    /// Returns an error if `forest` uses a species tree with different names or
+    /// topology, or if any
+    /// family fails as in [`log_likelihood`](Self::log_likelihood).
+    pub fn forest_log_likelihood(&self, forest: &GeneForest) -> Result<f64, RustreeError> {
+        if !Arc::ptr_eq(&forest.species_tree, &self.species_tree)
+            && !same_species_tree(&forest.species_tree, &self.species_tree)
+        {
+            return Err(RustreeError::Validation(
+                "forest and model use different species trees".to_string(),
+            ));
+        }
+        self.families_log_likelihood(&super::extant_families(forest)?)
+    }
+
+    fn families_log_likelihood(&self, families: &[GeneFamily]) -> Result<f64, RustreeError> {
+        families
+            .par_iter()
+            .map(|(gene_tree, leaf_species)| self.log_likelihood(gene_tree, leaf_species))
+            .sum()
+    }
+}
+
+/// Solved recursions of the undated model for one gene tree.
+#[derive(Clone, Debug)]
+pub struct UndatedReconciliation {
+    model: UndatedDtlModel,
+    gene_tree: FlatTree,
+    leaf_species: Vec<Option<usize>>,
+    /// `P(u, e)` at `u * n_species + e`.
+    prob: Vec<f64>,
+    /// Mean of `P(u, ·)` over the transfer recipients of `e`.
+    mean_prob: Vec<f64>,
+    log_likelihood: f64,
+}
+
+/// One term of the recursion for `P(u, e)`.
+#[derive(Clone, Copy, Debug)]
+enum Term {
+    Leaf,
+    /// Children of `u` go to the left and right species children, or swapped.
+    Speciation {
+        swap: bool,
+    },
+    /// Speciation where the lineage survives only in the left or right child.
+    SpeciationLoss {
+        kept_left: bool,
+    },
+    Duplication,
+    DuplicationLoss,
+    /// One child of `u` stays on the branch and the other is transferred.
+    Transfer {
+        stay_left: bool,
+    },
+    /// `u` stays and the transferred copy is lost.
+    TransferLossStay,
+    /// `u` is transferred and the donor copy is lost.
+    TransferLossGo,
+}
+
+impl UndatedReconciliation {
+    /// Log-probability of the gene tree, as in [`UndatedDtlModel::log_likelihood`].
+    pub fn log_likelihood(&self) -> f64 {
+        self.log_likelihood
+    }
+
+    /// Probability of the subtree below gene node `u` from a lineage at the
+    /// top of species branch `e`.
+    pub fn subtree_probability(&self, u: usize, e: usize) -> f64 {
+        self.prob[self.index(u, e)]
+    }
+
+    /// Samples a reconciliation from the posterior of the undated model.
+    ///
+    /// Nodes of the input gene tree keep their name and branch length.
+    /// Speciation-loss, duplication-loss and transfer-loss events add unnamed
+    /// nodes with a [`Event::Loss`] leaf; inserted transfer nodes have the
+    /// donor lineage on the left and the recipient lineage on the right.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Result<RecTree, RustreeError> {
+        let n = self.n_species();
+        let root = self.gene_tree.root;
+        let start = pick_weighted(rng, (0..n).map(|e| (e, self.prob[self.index(root, e)])))
+            .ok_or_else(|| {
+                RustreeError::Validation("gene tree has probability zero".to_string())
+            })?;
+
+        let model = &self.model;
+        let nodes = &model.species_tree.nodes;
+        let mut builder = RecTreeBuilder::new(&self.gene_tree);
+        let mut stack: Vec<(usize, usize, Slot)> = vec![(root, start, None)];
+        while let Some((u, e, slot)) = stack.pop() {
+            let mut terms = Vec::with_capacity(10);
+            self.for_each_term(u, e, |term, weight| terms.push((term, weight)));
+            let term = pick_weighted(rng, terms).ok_or_else(|| {
+                RustreeError::Tree(format!("gene node {u} has probability zero on branch {e}"))
+            })?;
+            let (v, w) = (
+                self.gene_tree.nodes[u].left_child,
+                self.gene_tree.nodes[u].right_child,
+            );
+            let (f, g) = (nodes[e].left_child, nodes[e].right_child);
+            match term {
+                Term::Leaf => {
+                    builder.push(Some(u), slot, e, Event::Leaf);
+                }
+                Term::Speciation { swap } => {
+                    let node = builder.push(Some(u), slot, e, Event::Speciation);
+                    let (f, g) = (f.expect("internal species"), g.expect("internal species"));
+                    let (to_v, to_w) = if swap { (g, f) } else { (f, g) };
+                    stack.push((w.expect("internal gene"), to_w, Some((node, false))));
+                    stack.push((v.expect("internal gene"), to_v, Some((node, true))));
+                }
+                Term::SpeciationLoss { kept_left } => {
+                    let (f, g) = (f.expect("internal species"), g.expect("internal species"));
+                    let (kept, lost) = if kept_left { (f, g) } else { (g, f) };
+                    let node = builder.push(None, slot, e, Event::Speciation);
+                    builder.push(None, Some((node, !kept_left)), lost, Event::Loss);
+                    stack.push((u, kept, Some((node, kept_left))));
+                }
+                Term::Duplication => {
+                    let node = builder.push(Some(u), slot, e, Event::Duplication);
+                    stack.push((w.expect("internal gene"), e, Some((node, false))));
+                    stack.push((v.expect("internal gene"), e, Some((node, true))));
+                }
+                Term::DuplicationLoss => {
+                    let node = builder.push(None, slot, e, Event::Duplication);
+                    builder.push(None, Some((node, false)), e, Event::Loss);
+                    stack.push((u, e, Some((node, true))));
+                }
+                Term::Transfer { stay_left } => {
+                    let (v, w) = (v.expect("internal gene"), w.expect("internal gene"));
+                    let node = builder.push(Some(u), slot, e, Event::Transfer);
+                    let go = if stay_left { w } else { v };
+                    let h = self.sample_recipient(rng, e, |h| self.prob[self.index(go, h)]);
+                    let (to_v, to_w) = if stay_left { (e, h) } else { (h, e) };
+                    stack.push((w, to_w, Some((node, false))));
+                    stack.push((v, to_v, Some((node, true))));
+                }
+                Term::TransferLossStay => {
+                    let h = self.sample_recipient(rng, e, |h| model.extinction[h]);
+                    let node = builder.push(None, slot, e, Event::Transfer);
+                    builder.push(None, Some((node, false)), h, Event::Loss);
+                    stack.push((u, e, Some((node, true))));
+                }
+                Term::TransferLossGo => {
+                    let h = self.sample_recipient(rng, e, |h| self.prob[self.index(u, h)]);
+                    let node = builder.push(None, slot, e, Event::Transfer);
+                    builder.push(None, Some((node, true)), e, Event::Loss);
+                    stack.push((u, h, Some((node, false))));
+                }
+            }
+        }
+        builder.finish(Arc::clone(&model.species_tree))
+    }
+
+    #[inline]
+    fn n_species(&self) -> usize {
+        self.model.species_tree.nodes.len()
+    }
+
+    #[inline]
+    fn index(&self, u: usize, e: usize) -> usize {
+        u * self.n_species() + e
+    }
+
+    fn sample_recipient<R: Rng, F: Fn(usize) -> f64>(
+        &self,
+        rng: &mut R,
+        e: usize,
+        weight: F,
+    ) -> usize {
+        let candidates = (0..self.n_species())
+            .filter(|&h| self.model.is_recipient(e, h))
+            .map(|h| (h, weight(h)));
+        pick_weighted(rng, candidates)
+            .expect("transfer terms have a recipient with positive weight")
+    }
+
+    /// Calls `f` with every term of the recursion for `P(u, e)` and its value.
+    fn for_each_term<F: FnMut(Term, f64)>(&self, u: usize, e: usize, mut f: F) {
+        let model = &self.model;
+        let species = &model.species_tree.nodes[e];
+        let gene = &self.gene_tree.nodes[u];
+        let (p_s, p_d, p_t) = (
+            model.p_speciation[e],
+            model.p_duplication[e],
+            model.p_transfer[e],
+        );
+        let p = |x: usize, y: usize| self.prob[self.index(x, y)];
+        let p_bar = |x: usize| self.mean_prob[self.index(x, e)];
+        let ext = &model.extinction;
+
+        if self.leaf_species[u] == Some(e) {
+            f(Term::Leaf, p_s);
+        }
+        if let (Some(sf), Some(sg)) = (species.left_child, species.right_child) {
+            if let (Some(v), Some(w)) = (gene.left_child, gene.right_child) {
+                f(Term::Speciation { swap: false }, p_s * p(v, sf) * p(w, sg));
+                f(Term::Speciation { swap: true }, p_s * p(v, sg) * p(w, sf));
+            }
+            f(
+                Term::SpeciationLoss { kept_left: true },
+                p_s * p(u, sf) * ext[sg],
+            );
+            f(
+                Term::SpeciationLoss { kept_left: false },
+                p_s * p(u, sg) * ext[sf],
+            );
+        }
+        if let (Some(v), Some(w)) = (gene.left_child, gene.right_child) {
+            f(Term::Duplication, p_d * p(v, e) * p(w, e));
+            f(Term::Transfer { stay_left: true }, p_t * p(v, e) * p_bar(w));
+            f(
+                Term::Transfer { stay_left: false },
+                p_t * p_bar(v) * p(w, e),
+            );
+        }
+        f(Term::DuplicationLoss, 2.0 * p_d * p(u, e) * ext[e]);
+        f(
+            Term::TransferLossStay,
+            p_t * p(u, e) * model.mean_extinction[e],
+        );
+        f(Term::TransferLossGo, p_t * p_bar(u) * ext[e]);
+    }
+
+    fn fill(&mut self) -> Result<(), RustreeError> {
+        let n = self.n_species();
+        for u in self.gene_tree.postorder_indices() {
+            let row = u * n;
+            let mut converged = false;
+            for _ in 0..MAX_ITERATIONS {
+                let mean = self.model.recipient_means(&self.prob[row..row + n]);
+                self.mean_prob[row..row + n].copy_from_slice(&mean);
+                let mut stable = true;
+                for k in 0..n {
+                    let e = self.model.postorder[k];
+                    // DL and TL-stay terms contain P(u, e) itself: solve for it.
+                    let mut rest = 0.0;
+                    self.for_each_term(u, e, |term, value| {
+                        if !matches!(term, Term::DuplicationLoss | Term::TransferLossStay) {
+                            rest += value;
+                        }
+                    });
+                    let model = &self.model;
+                    let denominator = 1.0
+                        - 2.0 * model.p_duplication[e] * model.extinction[e]
+                        - model.p_transfer[e] * model.mean_extinction[e];
+                    if denominator <= 0.0 {
+                        return Err(RustreeError::Validation(format!(
+                            "undated DTL recursions diverge for rates {:?}",
+                            model.rates
+                        )));
+                    }
+                    let value = rest / denominator;
+                    let old = self.prob[row + e];
+                    if (value - old).abs() > TOLERANCE * value.abs() {
+                        stable = false;
+                    }
+                    self.prob[row + e] = value;
+                }
+                if stable {
+                    converged = true;
+                    break;
+                }
+            }
+            if !converged {
+                return Err(not_converged("subtree probabilities", self.model.rates));
+            }
+        }
+
+        let root = self.gene_tree.root;
+        let origination: f64 = self.prob[root * n..(root + 1) * n].iter().sum::<f64>() / n as f64;
+        let survival = 1.0 - self.model.extinction.iter().sum::<f64>() / n as f64;
+        self.log_likelihood = origination.ln() - survival.ln();
+        Ok(())
+    }
+}
+
+fn not_converged(what: &str, rates: UndatedRates) -> RustreeError {
+    RustreeError::Validation(format!(
+        "undated DTL {what} did not converge within {MAX_ITERATIONS} iterations for rates {rates:?}"
+    ))
+}
+
+/// Draws an item with probability proportional to its weight, or `None` if
+/// no weight is positive.
+fn pick_weighted<T, R: Rng, I: IntoIterator<Item = (T, f64)>>(rng: &mut R, items: I) -> Option<T> {
+    let items: Vec<(T, f64)> = items.into_iter().filter(|(_, w)| *w > 0.0).collect();
+    let total: f64 = items.iter().map(|(_, w)| w).sum();
+    if !total.is_finite() || total <= 0.0 {
+        return None;
+    }
+    let mut target = rng.gen::<f64>() * total;
+    let last = items.len() - 1;
+    for (i, (item, weight)) in items.into_iter().enumerate() {
+        if target < weight || i == last {
+            return Some(item);
+        }
+        target -= weight;
+    }
+    None
+}
+
+/// Maximum-likelihood rates of the undated model.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UndatedFit {
+    pub rates: UndatedRates,
+    pub log_likelihood: f64,
+    /// Nelder-Mead iterations used.
+    pub iterations: usize,
+}
+
+/// Fits undated D/T/L rates to the extant gene trees of `forest` by
+/// maximising the summed log-likelihood with Nelder-Mead over log-rates.
+///
+/// `start` must have positive rates.
+///
+/// # Errors
+/// Returns an error if `start` has a non-positive rate, the forest has no
+/// family with extant genes, or a family cannot be reconciled.
+pub fn fit_undated_rates(
+    forest: &GeneForest,
+    start: UndatedRates,
+    max_iterations: usize,
+) -> Result<UndatedFit, RustreeError> {
+    start.validate()?;
+    if [start.duplication, start.transfer, start.loss].contains(&0.0) {
+        return Err(RustreeError::Validation(
+            "starting rates must be positive to fit on a log scale".to_string(),
+        ));
+    }
+    let families = super::extant_families(forest)?;
+    if families.is_empty() {
+        return Err(RustreeError::Validation(
+            "forest has no family with extant genes".to_string(),
+        ));
+    }
+    // Validate leaf mappings once, so later failures can only be divergence
+    // or non-convergence.
+    UndatedDtlModel::new(Arc::clone(&forest.species_tree), start)?
+        .families_log_likelihood(&families)?;
+
+    let objective = |x: &[f64; 3]| {
+        let rates = UndatedRates {
+            duplication: x[0].exp(),
+            transfer: x[1].exp(),
+            loss: x[2].exp(),
+        };
+        UndatedDtlModel::new(Arc::clone(&forest.species_tree), rates)
+            .and_then(|model| model.families_log_likelihood(&families))
+            .map_or(
+                f64::INFINITY,
+                |ll| if ll.is_nan() { f64::INFINITY } else { -ll },
+            )
+    };
+    let start = [start.duplication.ln(), start.transfer.ln(), start.loss.ln()];
+    let (best, value, iterations) = nelder_mead(objective, start, 1.0, max_iterations);
+    Ok(UndatedFit {
+        rates: UndatedRates {
+            duplication: best[0].exp(),
+            transfer: best[1].exp(),
+            loss: best[2].exp(),
+        },
+        log_likelihood: -value,
+        iterations,
+    })
+}
+
+/// Minimises `f` from `start` with the Nelder-Mead simplex method.
+fn nelder_mead<F: Fn(&[f64; 3]) -> f64>(
+    f: F,
+    start: [f64; 3],
+    step: f64,
+    max_iterations: usize,
+) -> ([f64; 3], f64, usize) {
+    let mut simplex: Vec<([f64; 3], f64)> = (0..4)
+        .map(|k| {
+            let mut x = start;
+            if k > 0 {
+                x[k - 1] += step;
+            }
+            (x, f(&x))
+        })
+        .collect();
+    let along = |from: &[f64; 3], to: &[f64; 3], t: f64| -> [f64; 3] {
+        std::array::from_fn(|i| from[i] + t * (to[i] - from[i]))
+    };
+
+    let mut iterations = 0;
+    while iterations < max_iterations {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        if (simplex[3].1 - simplex[0].1).abs() <= 1e-8 * (1.0 + simplex[0].1.abs()) {
+            break;
+        }
+        iterations += 1;
+        let centroid: [f64; 3] =
+            std::array::from_fn(|i| simplex[..3].iter().map(|(x, _)| x[i]).sum::<f64>() / 3.0);
+        let worst = simplex[3];
+
+        let reflected = along(&worst.0, &centroid, 2.0);
+        let reflected_value = f(&reflected);
+        if reflected_value < simplex[0].1 {
+            let expanded = along(&worst.0, &centroid, 3.0);
+            let expanded_value = f(&expanded);
+            simplex[3] = if expanded_value < reflected_value {
+                (expanded, expanded_value)
+            } else {
+                (reflected, reflected_value)
+            };
+        } else if reflected_value < simplex[2].1 {
+            simplex[3] = (reflected, reflected_value);
+        } else {
+            let contracted = along(&worst.0, &centroid, 0.5);
+            let contracted_value = f(&contracted);
+            if contracted_value < worst.1 {
+                simplex[3] = (contracted, contracted_value);
+            } else {
+                let best = simplex[0].0;
+                for vertex in simplex.iter_mut().skip(1) {
+                    let x = along(&best, &vertex.0, 0.5);
+                    *vertex = (x, f(&x));
+                }
+            }
+        }
+    }
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    (simplex[0].0, simplex[0].1, iterations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newick::parse_newick;
+
+    #[test]
+    fn terms_sum_to_subtree_probabilities() {
+        let species = parse_newick("((A:1,B:1)AB:1,(C:1,D:1)CD:1)R:0;")
+            .unwrap()
+            .pop()
+            .unwrap()
+            .to_flat_tree();
+        let gene = parse_newick("(((A_1:1,C_1:1):1,B_1:1):1,(D_1:1,D_2:1):1):0;")
+            .unwrap()
+            .pop()
+            .unwrap()
+            .to_flat_tree();
+        let leaves = super::super::map_leaves_by_name(&gene, &species).unwrap();
+        let rates = UndatedRates {
+            duplication: 0.3,
+            transfer: 0.4,
+            loss: 0.5,
+        };
+        let model = UndatedDtlModel::new(Arc::new(species), rates).unwrap();
+        let rec = model.reconcile(&gene, &leaves).unwrap();
+        for u in 0..gene.nodes.len() {
+            for e in 0..rec.n_species() {
+                let mut total = 0.0;
+                rec.for_each_term(u, e, |_, value| total += value);
+                let p = rec.subtree_probability(u, e);
+                assert!((total - p).abs() <= 1e-10 * p.max(1e-300), "u={u} e={e}");
+            }
+        }
+    }
+}
@@ -0,0 +1,301 @@
+// Molecular clock models
+//
+// Simulated trees have branch lengths in time units, whereas inferred trees
+// are measured in expected substitutions per site. A clock model assigns a
+// substitution rate to every branch and rescales lengths accordingly. Gene
+// trees inherit the rates of the species lineages they traverse, scaled by a
+// per-family multiplier.
+
+use crate::dtl::RateMultiplier;
+use crate::error::RustreeError;
+use crate::node::{FlatTree, RecTree};
+use crate::simulation::utils::{sample_gamma, sample_lognormal};
+use rand::Rng;
+
+/// Distribution of substitution rates over the branches of a tree.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClockModel {
+    /// Every branch evolves at `rate`.
+    Strict { rate: f64 },
+    /// Independent lognormal rate per branch with mean `mean_rate` and
+    /// log-scale standard deviation `sigma`.
+    UncorrelatedLognormal { mean_rate: f64, sigma: f64 },
+    /// Independent gamma rate per branch with mean `mean_rate` and variance
+    /// `mean_rate² / shape`.
+    UncorrelatedGamma { mean_rate: f64, shape: f64 },
+    /// Autocorrelated lognormal rates (Kishino-Thorne): the log rate at each
+    /// node is drawn around its parent's with variance `sigma² × length`, and
+    /// a branch uses the mean of its end-point rates.
+    AutocorrelatedLognormal { root_rate: f64, sigma: f64 },
+}
+
+impl ClockModel {
+    pub fn validate(&self) -> Result<(), RustreeError> {
+        let (name, rate, spread, spread_name) = match self {
+            ClockModel::Strict { rate } => ("strict", *rate, 0.0, "sigma"),
+            ClockModel::UncorrelatedLognormal { mean_rate, sigma } => {
+                ("uncorrelated lognormal", *mean_rate, *sigma, "sigma")
+            }
+            ClockModel::UncorrelatedGamma { mean_rate, shape } => {
+                if !(shape.is_finite() && *shape > 0.0) {
+                    return Err(RustreeError::Validation(format!(
+                        "uncorrelated gamma clock shape must be positive and finite, got {shape}"
+                    )));
+                }
+                ("uncorrelated gamma", *mean_rate, 0.0, "shape")
+            }
+            ClockModel::AutocorrelatedLognormal { root_rate, sigma } => {
+                ("autocorrelated lognormal", *root_rate, *sigma, "sigma")
+            }
+        };
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(RustreeError::Validation(format!(
+                "{name} clock rate must be positive and finite, got {rate}"
+            )));
+        }
+        if !(spread.is_finite() && spread >= 0.0) {
+            return Err(RustreeError::Validation(format!(
+                "{name} clock {spread_name} must be non-negative and finite, got {spread}"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Draw one rate per branch (indexed by node; the root entry is the root stem).
+    pub fn sample_branch_rates<R: Rng>(
+        &self,
+        tree: &FlatTree,
+        rng: &mut R,
+    ) -> Result<Vec<f64>, RustreeError> {
+        self.validate()?;
+        let n = tree.nodes.len();
+        let rates = match self {
+            ClockModel::Strict { rate } => vec![*rate; n],
+            ClockModel::UncorrelatedLognormal { mean_rate, sigma } => (0..n)
+                .map(|_| mean_rate * sample_lognormal(-0.5 * sigma * sigma, *sigma, rng))
+                .collect(),
+            ClockModel::UncorrelatedGamma { mean_rate, shape } => (0..n)
+                .map(|_| sample_gamma(*shape, mean_rate / shape, rng))
+                .collect(),
+            ClockModel::AutocorrelatedLognormal { root_rate, sigma } => {
+                // Preorder walk so every parent rate is known before its children.
+                let mut node_rates = vec![*root_rate; n];
+                let mut branch_rates = vec![*root_rate; n];
+                let mut stack = vec![tree.root];
+                while let Some(idx) = stack.pop() {
+                    let node = &tree.nodes[idx];
+                    if let Some(parent) = node.parent {
+                        let s = sigma * node.length.max(0.0).sqrt();
+                        node_rates[idx] =
+                            node_rates[parent] * sample_lognormal(-0.5 * s * s, s, rng);
+                        branch_rates[idx] = 0.5 * (node_rates[parent] + node_rates[idx]);
+                    }
+                    stack.extend(node.right_child);
+                    stack.extend(node.left_child);
+                }
+                branch_rates
+            }
+        };
+        Ok(rates)
+    }
+}
+
+/// A tree in substitution units together with the time tree and branch rates
+/// it was derived from.
+#[derive(Clone, Debug)]
+pub struct ClockTree {
+    /// Original tree with lengths in time units.
+    pub time_tree: FlatTree,
+    /// Substitution rate of each branch, indexed by node.
+    pub rates: Vec<f64>,
+    /// Tree with lengths in expected substitutions per site.
+    pub tree: FlatTree,
+}
+
+impl ClockTree {
+    fn from_rates(time_tree: &FlatTree, rates: Vec<f64>) -> Self {
+        let mut tree = time_tree.clone();
+        for (node, rate) in tree.nodes.iter_mut().zip(&rates) {
+            node.length *= rate;
+        }
+        if time_tree.nodes.iter().all(|node| node.depth.is_some()) {
+            tree.assign_depths();
+        } else {
+            for node in &mut tree.nodes {
+                node.depth = None;
+            }
+        }
+        Self {
+            time_tree: time_tree.clone(),
+            rates,
+            tree,
+        }
+    }
+}
+
+/// Gene-tree clock for one family: the family multiplier and the rescaled tree.
+#[derive(Clone, Debug)]
+pub struct FamilyClock {
+    pub multiplier: f64,
+    pub clock: ClockTree,
+}
+
+impl FamilyClock {
+    /// Copy of `rec_tree` whose gene tree is measured in substitutions.
+    pub fn apply_to(&self, rec_tree: &RecTree) -> Result<RecTree, RustreeError> {
+        if rec_tree.gene_tree.nodes.len() != self.clock.tree.nodes.len() {
+            return Err(RustreeError::Validation(format!(
+                "gene tree has {} nodes but the family clock covers {}",
+                rec_tree.gene_tree.nodes.len(),
+                self.clock.tree.nodes.len()
+            )));
+        }
+        let mut rescaled = rec_tree.clone();
+        rescaled.gene_tree = self.clock.tree.clone();
+        Ok(rescaled)
+    }
+}
+
+/// Rescale a tree's branch lengths under a clock model.
+pub fn apply_clock<R: Rng>(
+    tree: &FlatTree,
+    model: &ClockModel,
+    rng: &mut R,
+) -> Result<ClockTree, RustreeError> {
+    let rates = model.sample_branch_rates(tree, rng)?;
+    Ok(ClockTree::from_rates(tree, rates))
+}
+
+/// Rescale a gene tree using the rates of the species branches it runs along.
+///
+/// Each gene branch gets the time-averaged rate of the species lineage it
+/// occupies (walking up from the child's species until the parent's time),
+/// times `family_multiplier`. `species_rates` is indexed by species node,
+/// typically [`ClockTree::rates`] from [`apply_clock`] on the species tree.
+pub fn apply_gene_clock(
+    rec_tree: &RecTree,
+    species_rates: &[f64],
+    family_multiplier: f64,
+) -> Result<FamilyClock, RustreeError> {
+    const OPERATION: &str = "apply_gene_clock";
+    let species_tree = &rec_tree.species_tree;
+    if species_rates.len() != species_tree.nodes.len() {
+        return Err(RustreeError::Validation(format!(
+            "species_rates has {} entries but the species tree has {} nodes",
+            species_rates.len(),
+            species_tree.nodes.len()
+        )));
+    }
+    if !(family_multiplier.is_finite() && family_multiplier >= 0.0) {
+        return Err(RustreeError::Validation(format!(
+            "family multiplier must be non-negative and finite, got {family_multiplier}"
+        )));
+    }
+
+    let species_depth = |idx: usize| {
+        let node = &species_tree.nodes[idx];
+        node.depth
+            .ok_or_else(|| RustreeError::missing_depth(OPERATION, idx, &node.name))
+    };
+
+    let gene_tree = &rec_tree.gene_tree;
+    let mut rates = Vec::with_capacity(gene_tree.nodes.len());
+    for (idx, node) in gene_tree.nodes.iter().enumerate() {
+        let end = node
+            .depth
+            .ok_or_else(|| RustreeError::missing_depth(OPERATION, idx, &node.name))?;
+        let start = end - node.length;
+        let Some(mut species) = rec_tree.node_mapping[idx] else {
+            return Err(RustreeError::Validation(format!(
+                "gene node {idx} ({}) has no species mapping",
+                node.name
+            )));
+        };
+        if node.length <= 0.0 {
+            rates.push(family_multiplier * species_rates[species]);
+            continue;
+        }
+
+        let mut substitutions = 0.0;
+        let mut time = end;
+        loop {
+            let species_node = &species_tree.nodes[species];
+            let branch_start = species_depth(species)? - species_node.length;
+            match species_node.parent {
+                Some(parent) if branch_start > start => {
+                    substitutions += species_rates[species] * (time - branch_start).max(0.0);
+                    time = branch_start;
+                    species = parent;
+                }
+                _ => {
+                    substitutions += species_rates[species] * (time - start).max(0.0);
+                    break;
+                }
+            }
+        }
+        rates.push(family_multiplier * substitutions / node.length);
+    }
+
+    Ok(FamilyClock {
+        multiplier: family_multiplier,
+        clock: ClockTree::from_rates(gene_tree, rates),
+    })
+}
+
+/// Apply species-lineage rates to every family, drawing one gene-specific
+/// multiplier per family from `multiplier`.
+pub fn apply_family_clocks<R: Rng>(
+    rec_trees: &[RecTree],
+    species_rates: &[f64],
+    multiplier: &RateMultiplier,
+    rng: &mut R,
+) -> Result<Vec<FamilyClock>, RustreeError> {
+    multiplier.validate("family clock")?;
+    rec_trees
+        .iter()
+        .map(|rec_tree| apply_gene_clock(rec_tree, species_rates, multiplier.sample(rng)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newick::parse_newick;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn tree(newick: &str) -> FlatTree {
+        let mut nodes = parse_newick(newick).unwrap();
+        let mut tree = nodes.pop().unwrap().to_flat_tree();
+        tree.assign_depths();
+        tree
+    }
+
+    #[test]
+    fn strict_clock_scales_every_branch() {
+        let time_tree = tree("((A:1,B:2)AB:1,C:3)root:0.5;");
+        let mut rng = StdRng::seed_from_u64(1);
+        let clock = apply_clock(&time_tree, &ClockModel::Strict { rate: 0.1 }, &mut rng).unwrap();
+        for (scaled, original) in clock.tree.nodes.iter().zip(&time_tree.nodes) {
+            assert!((scaled.length - 0.1 * original.length).abs() < 1e-12);
+        }
+        assert_eq!(clock.time_tree.nodes[0].length, time_tree.nodes[0].length);
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        assert!(ClockModel::Strict { rate: 0.0 }.validate().is_err());
+        assert!(ClockModel::UncorrelatedGamma {
+            mean_rate: 1.0,
+            shape: -1.0
+        }
+        .validate()
+        .is_err());
+        assert!(ClockModel::AutocorrelatedLognormal {
+            root_rate: 1.0,
+            sigma: f64::NAN
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,760 @@
+// Approximate Bayesian computation of DTL parameters
+//
+// Observed and simulated forests are compared through summary statistics of
+// their extant genes only (copy numbers, phyletic patterns and gene tree
+// shapes), so no reconciliation of the observed families is needed. Rejection
+// ABC keeps the prior draws closest to the observation; SMC-ABC (Beaumont et
+// al. 2009) moves a weighted particle population through decreasing
+// tolerances. Simulations of one round run in parallel, each family batch with
+// its own RNG from `family_rng`, so results do not depend on the number of
+// threads.
+
+use crate::error::RustreeError;
+use crate::node::{Event, FlatTree, GeneForest, RecTree};
+use crate::simulation::utils::sample_standard_normal;
+use rand::Rng;
+use rayon::prelude::*;
+
+use super::parallel::family_rng;
+use super::per_gene::simulate_dtl_batch;
+use super::utils::is_extant_species;
+
+/// Number of summary statistics in [`ForestStatistics`].
+pub const N_FOREST_STATISTICS: usize = 9;
+
+/// Summary statistics of the extant genes of a forest.
+///
+/// Families without extant genes are unobservable and ignored. Copy-number
+/// statistics are over (family, extant species) cells, phyletic statistics
+/// over families, and shape statistics over the extant gene trees, ignoring
+/// their reconciliation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ForestStatistics {
+    /// Families with at least one extant gene.
+    pub families: usize,
+    /// Statistics in the order of [`ForestStatistics::NAMES`].
+    pub values: [f64; N_FOREST_STATISTICS],
+}
+
+impl ForestStatistics {
+    pub const NAMES: [&'static str; N_FOREST_STATISTICS] = [
+        "mean_copies",
+        "single_copy",
+        "multi_copy",
+        "copy_variance",
+        "core",
+        "singleton",
+        "presence_variance",
+        "colless",
+        "cherries",
+    ];
+
+    /// Statistics of the gene trees of `forest`.
+    ///
+    /// # Errors
+    /// Returns an error if the species tree has no extant species or no family
+    /// has an extant gene.
+    pub fn from_forest(forest: &GeneForest) -> Result<Self, RustreeError> {
+        let stats = Self::from_rec_trees(&forest.species_tree, &forest.gene_trees)?;
+        if stats.families == 0 {
+            return Err(RustreeError::Validation(
+                "no gene family has an extant gene".to_string(),
+            ));
+        }
+        Ok(stats)
+    }
+
+    /// Statistics of `rec_trees`, reconciled with `species_tree`.
+    pub fn from_rec_trees(
+        species_tree: &FlatTree,
+        rec_trees: &[RecTree],
+    ) -> Result<Self, RustreeError> {
+        let mut column = vec![None; species_tree.nodes.len()];
+        let mut n_extant = 0;
+        for (idx, node) in species_tree.nodes.iter().enumerate() {
+            if is_extant_species(node) {
+                column[idx] = Some(n_extant);
+                n_extant += 1;
+            }
+        }
+        if n_extant == 0 {
+            return Err(RustreeError::Validation(
+                "species tree has no extant species".to_string(),
+            ));
+        }
+
+        let mut acc = Accumulator::default();
+        for rec in rec_trees {
+            acc.add(rec, &column, n_extant);
+        }
+        Ok(acc.finish(n_extant))
+    }
+
+    /// Euclidean distance between statistics, each divided by its `scale`.
+    pub fn distance(&self, other: &ForestStatistics, scale: &[f64; N_FOREST_STATISTICS]) -> f64 {
+        self.values
+            .iter()
+            .zip(&other.values)
+            .zip(scale)
+            .map(|((a, b), s)| ((a - b) / s).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+/// Running sums behind [`ForestStatistics`].
+#[derive(Default)]
+struct Accumulator {
+    families: usize,
+    copies: f64,
+    copies_squared: f64,
+    single_copy: usize,
+    multi_copy: usize,
+    core: usize,
+    singleton: usize,
+    presence: f64,
+    presence_squared: f64,
+    colless: f64,
+    colless_trees: usize,
+    cherries: f64,
+    cherry_trees: usize,
+}
+
+impl Accumulator {
+    fn add(&mut self, rec: &RecTree, column: &[Option<usize>], n_extant: usize) {
+        let genes = &rec.gene_tree.nodes;
+        let mut copies = vec![0usize; n_extant];
+        // Extant leaves below each gene node.
+        let mut below = vec![0usize; genes.len()];
+        let mut colless = 0usize;
+        let mut cherries = 0usize;
+        for u in rec.gene_tree.postorder_indices() {
+            let node = &genes[u];
+            if let (Some(l), Some(r)) = (node.left_child, node.right_child) {
+                let (nl, nr) = (below[l], below[r]);
+                below[u] = nl + nr;
+                if nl > 0 && nr > 0 {
+                    colless += nl.abs_diff(nr);
+                    cherries += usize::from(nl == 1 && nr == 1);
+                }
+            } else if let Some(child) = node.left_child.or(node.right_child) {
+                below[u] = below[child];
+            } else if rec.event_mapping[u] == Event::Leaf {
+                if let Some(col) = rec.node_mapping[u].and_then(|sp| column[sp]) {
+                    copies[col] += 1;
+                    below[u] = 1;
+                }
+            }
+        }
+        let leaves = below[rec.gene_tree.root];
+        if leaves == 0 {
+            return;
+        }
+
+        self.families += 1;
+        let mut present = 0;
+        for &c in &copies {
+            self.copies += c as f64;
+            self.copies_squared += (c * c) as f64;
+            self.single_copy += usize::from(c == 1);
+            self.multi_copy += usize::from(c > 1);
+            present += usize::from(c > 0);
+        }
+        self.core += usize::from(present == n_extant);
+        self.singleton += usize::from(present == 1);
+        let presence = present as f64 / n_extant as f64;
+        self.presence += presence;
+        self.presence_squared += presence * presence;
+        if leaves >= 3 {
+            self.colless += colless as f64 / ((leaves - 1) * (leaves - 2) / 2) as f64;
+            self.colless_trees += 1;
+        }
+        if leaves >= 2 {
+            self.cherries += cherries as f64 / leaves as f64;
+            self.cherry_trees += 1;
+        }
+    }
+
+    fn finish(self, n_extant: usize) -> ForestStatistics {
+        let mean = |sum: f64, n: usize| if n == 0 { 0.0 } else { sum / n as f64 };
+        let cells = self.families * n_extant;
+        let mean_copies = mean(self.copies, cells);
+        let mean_presence = mean(self.presence, self.families);
+        ForestStatistics {
+            families: self.families,
+            values: [
+                mean_copies,
+                mean(self.single_copy as f64, cells),
+                mean(self.multi_copy as f64, cells),
+                (mean(self.copies_squared, cells) - mean_copies * mean_copies).max(0.0),
+                mean(self.core as f64, self.families),
+                mean(self.singleton as f64, self.families),
+                (mean(self.presence_squared, self.families) - mean_presence * mean_presence)
+                    .max(0.0),
+                mean(self.colless, self.colless_trees),
+                mean(self.cherries, self.cherry_trees),
+            ],
+        }
+    }
+}
+
+/// Prior distribution of one parameter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Prior {
+    /// The parameter is not inferred.
+    Fixed(f64),
+    Uniform {
+        low: f64,
+        high: f64,
+    },
+    /// Uniform on the log scale; `low` must be positive.
+    LogUniform {
+        low: f64,
+        high: f64,
+    },
+}
+
+impl Prior {
+    fn validate(&self, name: &str, non_negative: bool) -> Result<(), RustreeError> {
+        let (low, high) = match *self {
+            Prior::Fixed(value) => (value, value),
+            Prior::Uniform { low, high } => (low, high),
+            Prior::LogUniform { low, high } if low <= 0.0 => {
+                return Err(RustreeError::Validation(format!(
+                    "log-uniform prior on {name} needs a positive lower bound, got {low} (upper {high})"
+                )))
+            }
+            Prior::LogUniform { low, high } => (low, high),
+        };
+        if !low.is_finite() || !high.is_finite() || low > high || (low == high && self.is_free()) {
+            return Err(RustreeError::Validation(format!(
+                "prior on {name} needs finite bounds with low < high, got [{low}, {high}]"
+            )));
+        }
+        if non_negative && low < 0.0 {
+            return Err(RustreeError::Validation(format!(
+                "prior on {name} must not allow negative rates, got lower bound {low}"
+            )));
+        }
+        Ok(())
+    }
+
+    fn is_free(&self) -> bool {
+        !matches!(self, Prior::Fixed(_))
+    }
+
+    /// Support on the sampling scale, on which the prior is uniform.
+    fn bounds(&self) -> (f64, f64) {
+        match *self {
+            Prior::Fixed(value) => (value, value),
+            Prior::Uniform { low, high } => (low, high),
+            Prior::LogUniform { low, high } => (low.ln(), high.ln()),
+        }
+    }
+
+    fn value(&self, scaled: f64) -> f64 {
+        match self {
+            Prior::LogUniform { .. } => scaled.exp(),
+            _ => scaled,
+        }
+    }
+}
+
+/// Priors on the simulation parameters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AbcPriors {
+    pub lambda_d: Prior,
+    pub lambda_t: Prior,
+    pub lambda_l: Prior,
+    /// `None` simulates uniform transfers.
+    pub transfer_alpha: Option<Prior>,
+}
+
+impl AbcPriors {
+    fn validate(&self) -> Result<(), RustreeError> {
+        self.lambda_d.validate("lambda_d", true)?;
+        self.lambda_t.validate("lambda_t", true)?;
+        self.lambda_l.validate("lambda_l", true)?;
+        if let Some(alpha) = &self.transfer_alpha {
+            alpha.validate("transfer_alpha", false)?;
+        }
+        Ok(())
+    }
+
+    fn slots(&self) -> [Prior; 4] {
+        [
+            self.lambda_d,
+            self.lambda_t,
+            self.lambda_l,
+            self.transfer_alpha.unwrap_or(Prior::Fixed(0.0)),
+        ]
+    }
+
+    /// Point of the sampling space; every coordinate is uniform under the prior.
+    fn sample<R: Rng>(&self, rng: &mut R) -> Point {
+        self.slots().map(|prior| {
+            let (low, high) = prior.bounds();
+            if prior.is_free() {
+                rng.gen_range(low..high)
+            } else {
+                low
+            }
+        })
+    }
+
+    fn contains(&self, point: &Point) -> bool {
+        self.slots().iter().zip(point).all(|(prior, &x)| {
+            let (low, high) = prior.bounds();
+            low <= x && x <= high
+        })
+    }
+
+    fn parameters(&self, point: &Point) -> AbcParameters {
+        let [d, t, l, alpha] = self.slots();
+        AbcParameters {
+            lambda_d: d.value(point[0]),
+            lambda_t: t.value(point[1]),
+            lambda_l: l.value(point[2]),
+            transfer_alpha: self.transfer_alpha.map(|_| alpha.value(point[3])),
+        }
+    }
+}
+
+type Point = [f64; 4];
+
+/// One set of simulation parameters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AbcParameters {
+    pub lambda_d: f64,
+    pub lambda_t: f64,
+    pub lambda_l: f64,
+    pub transfer_alpha: Option<f64>,
+}
+
+/// An accepted parameter sample.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AbcSample {
+    pub parameters: AbcParameters,
+    pub distance: f64,
+    /// Normalized importance weight; equal for rejection ABC.
+    pub weight: f64,
+}
+
+/// Accepted samples of an ABC run.
+#[derive(Clone, Debug)]
+pub struct AbcResult {
+    pub samples: Vec<AbcSample>,
+    /// Acceptance tolerance of each round, the last one applying to `samples`.
+    pub tolerances: Vec<f64>,
+    /// Divisor of each statistic in the distance.
+    pub scale: [f64; N_FOREST_STATISTICS],
+    /// Forests simulated in total.
+    pub simulations: usize,
+}
+
+impl AbcResult {
+    /// Weighted posterior mean of the parameters.
+    pub fn posterior_mean(&self) -> AbcParameters {
+        let mut mean = [0.0; 4];
+        for sample in &self.samples {
+            let p = &sample.parameters;
+            let values = [
+                p.lambda_d,
+                p.lambda_t,
+                p.lambda_l,
+                p.transfer_alpha.unwrap_or(0.0),
+            ];
+            for (m, v) in mean.iter_mut().zip(values) {
+                *m += sample.weight * v;
+            }
+        }
+        let has_alpha = self
+            .samples
+            .first()
+            .is_some_and(|s| s.parameters.transfer_alpha.is_some());
+        AbcParameters {
+            lambda_d: mean[0],
+            lambda_t: mean[1],
+            lambda_l: mean[2],
+            transfer_alpha: has_alpha.then_some(mean[3]),
+        }
+    }
+}
+
+/// Settings of [`abc_smc`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SmcAbcOptions {
+    /// Population size.
+    pub particles: usize,
+    /// Rounds after the initial prior population.
+    pub generations: usize,
+    /// Quantile of the previous distances used as the next tolerance.
+    pub quantile: f64,
+    /// Stops early, keeping the last complete population, once this many
+    /// forests have been simulated.
+    pub max_simulations: usize,
+}
+
+impl Default for SmcAbcOptions {
+    fn default() -> Self {
+        Self {
+            particles: 200,
+            generations: 5,
+            quantile: 0.5,
+            max_simulations: 100_000,
+        }
+    }
+}
+
+/// Simulates forests of `observed.families` families on `species_tree` and
+/// compares their statistics with `observed`.
+struct Simulator<'a> {
+    species_tree: &'a FlatTree,
+    observed: &'a ForestStatistics,
+    priors: &'a AbcPriors,
+    simulations: usize,
+}
+
+impl Simulator<'_> {
+    /// Statistics of one forest per point, simulated in parallel.
+    ///
+    /// A point whose families cannot be conditioned on leaving an extant gene
+    /// within the attempt budget yields `None`: it is a rejected particle at
+    /// infinite distance, and still counts as a simulation. Any other error
+    /// aborts the run.
+    fn statistics(
+        &mut self,
+        points: &[Point],
+        seed: u64,
+    ) -> Result<Vec<Option<ForestStatistics>>, RustreeError> {
+        self.simulations += points.len();
+        points
+            .par_iter()
+            .enumerate()
+            .map(|(i, point)| {
+                let p = self.priors.parameters(point);
+                let mut rng = family_rng(seed, i);
+                match simulate_dtl_batch(
+                    self.species_tree,
+                    self.species_tree.root,
+                    p.lambda_d,
+                    p.lambda_t,
+                    p.lambda_l,
+                    p.transfer_alpha,
+                    None,
+                    self.observed.families,
+                    true,
+                    &mut rng,
+                ) {
+                    Ok((trees, _)) => {
+                        ForestStatistics::from_rec_trees(self.species_tree, &trees).map(Some)
+                    }
+                    Err(RustreeError::ConditioningExhausted { .. }) => Ok(None),
+                    Err(e) => Err(e),
+                }
+            })
+            .collect()
+    }
+
+    /// Distance of each simulated forest to the observed one; infinite for
+    /// rejected particles.
+    fn distances(
+        &self,
+        stats: &[Option<ForestStatistics>],
+        scale: &[f64; N_FOREST_STATISTICS],
+    ) -> Vec<f64> {
+        stats
+            .iter()
+            .map(|s| {
+                s.as_ref()
+                    .map_or(f64::INFINITY, |s| self.observed.distance(s, scale))
+            })
+            .collect()
+    }
+}
+
+fn validate_inputs(
+    observed: &ForestStatistics,
+    priors: &AbcPriors,
+    population: usize,
+) -> Result<(), RustreeError> {
+    priors.validate()?;
+    if observed.families == 0 {
+        return Err(RustreeError::Validation(
+            "observed statistics cover no gene family".to_string(),
+        ));
+    }
+    if population == 0 {
+        return Err(RustreeError::Validation(
+            "ABC needs at least one accepted sample".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Per-statistic spread of simulated statistics: the median absolute
+/// deviation, or the standard deviation when that is zero, or 1.
+fn statistic_scale(stats: &[ForestStatistics]) -> [f64; N_FOREST_STATISTICS] {
+    std::array::from_fn(|k| {
+        let mut values: Vec<f64> = stats.iter().map(|s| s.values[k]).collect();
+        let center = median(&mut values);
+        let mut deviations: Vec<f64> = values.iter().map(|v| (v - center).abs()).collect();
+        let mad = median(&mut deviations);
+        if mad > 0.0 {
+            return mad;
+        }
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let sd = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+        if sd > 0.0 {
+            sd
+        } else {
+            1.0
+        }
+    })
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let n = values.len();
+    if n == 0 {
+        0.0
+    } else if n % 2 == 1 {
+        values[n / 2]
+    } else {
+        (values[n / 2 - 1] + values[n / 2]) / 2.0
+    }
+}
+
+/// Rejection ABC: draws `simulations` parameter sets from `priors` and keeps
+/// the `accepted` ones whose simulated statistics are closest to `observed`.
+///
+/// Each draw simulates `observed.families` families conditioned on leaving an
+/// extant gene; draws whose conditioning fails are rejected at infinite
+/// distance. Statistics are scaled by their spread over the other draws.
+///
+/// # Errors
+/// Returns an error for invalid priors, if `accepted` is zero or exceeds
+/// the number of draws that could be conditioned, or if a simulation fails.
+pub fn abc_rejection<R: Rng>(
+    species_tree: &FlatTree,
+    observed: &ForestStatistics,
+    priors: &AbcPriors,
+    simulations: usize,
+    accepted: usize,
+    rng: &mut R,
+) -> Result<AbcResult, RustreeError> {
+    validate_inputs(observed, priors, accepted)?;
+    if accepted > simulations {
+        return Err(RustreeError::Validation(format!(
+            "cannot accept {accepted} of {simulations} simulations"
+        )));
+    }
+
+    let mut simulator = Simulator {
+        species_tree,
+        observed,
+        priors,
+        simulations: 0,
+    };
+    let points: Vec<Point> = (0..simulations).map(|_| priors.sample(rng)).collect();
+    let stats = simulator.statistics(&points, rng.gen())?;
+    let conditioned: Vec<ForestStatistics> = stats.iter().flatten().cloned().collect();
+    if conditioned.len() < accepted {
+        return Err(RustreeError::Simulation(format!(
+            "only {} of {simulations} prior draws left extant genes; cannot accept {accepted}",
+            conditioned.len()
+        )));
+    }
+    let scale = statistic_scale(&conditioned);
+
+    let mut ranked: Vec<(f64, Point)> = simulator
+        .distances(&stats, &scale)
+        .into_iter()
+        .zip(points)
+        .collect();
+    ranked.sort_by(|a, b| a.0.total_cmp(&b.0));
+    ranked.truncate(accepted);
+
+    let weight = 1.0 / accepted as f64;
+    Ok(AbcResult {
+        tolerances: vec![ranked[accepted - 1].0],
+        samples: ranked
+            .iter()
+            .map(|(distance, point)| AbcSample {
+                parameters: priors.parameters(point),
+                distance: *distance,
+                weight,
+            })
+            .collect(),
+        scale,
+        simulations: simulator.simulations,
+    })
+}
+
+/// SMC-ABC with adaptive tolerances (population Monte Carlo).
+///
+/// The first population is drawn from `priors`, redrawing parameters whose
+/// families cannot be conditioned on extant genes, and fixes the statistic scale.
+/// Each later generation sets its tolerance to the `quantile` of the previous
+/// distances, proposes particles by perturbing weighted draws of the previous
+/// population with a Gaussian kernel of twice its variance, on the log scale
+/// for log-uniform priors, and keeps those within tolerance.
+///
+/// # Errors
+/// Returns an error for invalid priors or options, if the budget runs out
+/// before the first population is complete, or if a simulation fails.
+pub fn abc_smc<R: Rng>(
+    species_tree: &FlatTree,
+    observed: &ForestStatistics,
+    priors: &AbcPriors,
+    options: &SmcAbcOptions,
+    rng: &mut R,
+) -> Result<AbcResult, RustreeError> {
+    validate_inputs(observed, priors, options.particles)?;
+    if !(options.quantile > 0.0 && options.quantile < 1.0) {
+        return Err(RustreeError::Validation(format!(
+            "SMC-ABC quantile must be in (0, 1), got {}",
+            options.quantile
+        )));
+    }
+
+    let n = options.particles;
+    let mut simulator = Simulator {
+        species_tree,
+        observed,
+        priors,
+        simulations: 0,
+    };
+    // Prior population; draws that cannot be conditioned are redrawn.
+    let mut points: Vec<Point> = Vec::with_capacity(n);
+    let mut stats: Vec<ForestStatistics> = Vec::with_capacity(n);
+    while points.len() < n {
+        if simulator.simulations >= options.max_simulations {
+            return Err(RustreeError::Simulation(format!(
+                "only {} of {} prior draws left extant genes within {} simulations",
+                points.len(),
+                n,
+                options.max_simulations
+            )));
+        }
+        let budget = options.max_simulations - simulator.simulations;
+        let proposals: Vec<Point> = (0..(n - points.len()).min(budget))
+            .map(|_| priors.sample(rng))
+            .collect();
+        let proposal_stats = simulator.statistics(&proposals, rng.gen())?;
+        for (point, s) in proposals.into_iter().zip(proposal_stats) {
+            if let Some(s) = s {
+                points.push(point);
+                stats.push(s);
+            }
+        }
+    }
+    let scale = statistic_scale(&stats);
+    let mut distances: Vec<f64> = stats.iter().map(|s| observed.distance(s, &scale)).collect();
+    let mut weights = vec![1.0 / n as f64; n];
+    let mut tolerances = vec![distances.iter().copied().fold(0.0, f64::max)];
+    let free: Vec<usize> = (0..4).filter(|&k| priors.slots()[k].is_free()).collect();
+
+    'generations: for _ in 0..options.generations {
+        let tolerance = quantile(&distances, options.quantile);
+        let sigma = kernel_widths(&points, &weights, &free);
+        let cumulative: Vec<f64> = weights
+            .iter()
+            .scan(0.0, |acc, w| {
+                *acc += w;
+                Some(*acc)
+            })
+            .collect();
+
+        let mut next: Vec<(Point, f64)> = Vec::with_capacity(n);
+        while next.len() < n {
+            if simulator.simulations >= options.max_simulations {
+                break 'generations;
+            }
+            let budget = options.max_simulations - simulator.simulations;
+            let proposals: Vec<Point> = (0..(n - next.len()).min(budget))
+                .map(|_| loop {
+                    let u = rng.gen::<f64>() * cumulative[n - 1];
+                    let parent = cumulative.partition_point(|&c| c <= u).min(n - 1);
+                    let mut point = points[parent];
+                    for &k in &free {
+                        point[k] += sigma[k] * sample_standard_normal(rng);
+                    }
+                    if priors.contains(&point) {
+                        break point;
+                    }
+                })
+                .collect();
+            let stats = simulator.statistics(&proposals, rng.gen())?;
+            let proposal_distances = simulator.distances(&stats, &scale);
+            for (point, distance) in proposals.into_iter().zip(proposal_distances) {
+                if distance <= tolerance && next.len() < n {
+                    next.push((point, distance));
+                }
+            }
+        }
+
+        // The prior is uniform on the sampling scale, so the importance weight
+        // is the inverse of the proposal density.
+        let new_weights: Vec<f64> = next
+            .iter()
+            .map(|(point, _)| {
+                let density: f64 = points
+                    .iter()
+                    .zip(&weights)
+                    .map(|(parent, w)| {
+                        w * free
+                            .iter()
+                            .map(|&k| (-0.5 * ((point[k] - parent[k]) / sigma[k]).powi(2)).exp())
+                            .product::<f64>()
+                    })
+                    .sum();
+                1.0 / density.max(f64::MIN_POSITIVE)
+            })
+            .collect();
+        let total: f64 = new_weights.iter().sum();
+        weights = new_weights.iter().map(|w| w / total).collect();
+        (points, distances) = next.into_iter().unzip();
+        tolerances.push(tolerance);
+    }
+
+    Ok(AbcResult {
+        samples: points
+            .iter()
+            .zip(&distances)
+            .zip(&weights)
+            .map(|((point, &distance), &weight)| AbcSample {
+                parameters: priors.parameters(point),
+                distance,
+                weight,
+            })
+            .collect(),
+        tolerances,
+        scale,
+        simulations: simulator.simulations,
+    })
+}
+
+fn quantile(values: &[f64], q: f64) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let idx = ((sorted.len() - 1) as f64 * q).round() as usize;
+    sorted[idx]
+}
+
+/// Gaussian kernel standard deviation per coordinate: twice the weighted
+/// population variance, with a small floor for collapsed coordinates.
+fn kernel_widths(points: &[Point], weights: &[f64], free: &[usize]) -> Point {
+    let mut sigma = [0.0; 4];
+    for &k in free {
+        let mean: f64 = points.iter().zip(weights).map(|(p, w)| w * p[k]).sum();
+        let variance: f64 = points
+            .iter()
+            .zip(weights)
+            .map(|(p, w)| w * (p[k] - mean).powi(2))
+            .sum();
+        sigma[k] = (2.0 * variance).sqrt().max(1e-9 * mean.abs().max(1.0));
+    }
+    sigma
+}
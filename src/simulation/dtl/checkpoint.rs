@@ -0,0 +1,251 @@
+// Checkpoints for long streaming simulations
+//
+// A checkpoint records where a `DtlSimIter` stopped: the number of completed
+// families, the full ChaCha RNG state, the conditioning statistics and the
+// species tree and simulation settings, one per line. Resuming from it
+// continues the same random stream, so the remaining output is identical to
+// that of an uninterrupted run.
+
+use crate::error::RustreeError;
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use std::path::Path;
+
+use super::conditioning::ConditioningStats;
+
+/// RNG accepted by checkpointed iterators.
+///
+/// Seeded with `seed_from_u64`, it produces the same stream as
+/// `rand::rngs::StdRng`, so switching to it does not change results.
+pub type CheckpointRng = ChaCha12Rng;
+
+/// Name of the checkpoint file written next to the simulation outputs.
+pub const CHECKPOINT_FILE: &str = "checkpoint.txt";
+
+const HEADER: &str = "rustree-dtl-checkpoint 2";
+
+/// Prefix of the lines holding the simulation settings.
+const SETTING_PREFIX: &str = "setting.";
+
+/// Saved progress of a streaming DTL simulation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DtlCheckpoint {
+    /// Number of families already produced.
+    pub completed: usize,
+    pub n_simulations: usize,
+    pub rng_seed: [u8; 32],
+    pub rng_stream: u64,
+    pub rng_word_pos: u128,
+    pub stats: ConditioningStats,
+    /// Species tree and settings the checkpoint belongs to, as `(name, value)`.
+    pub settings: Vec<(String, String)>,
+}
+
+impl DtlCheckpoint {
+    pub(crate) fn capture(
+        rng: &CheckpointRng,
+        completed: usize,
+        n_simulations: usize,
+        stats: ConditioningStats,
+        settings: Vec<(String, String)>,
+    ) -> Self {
+        Self {
+            completed,
+            n_simulations,
+            rng_seed: rng.get_seed(),
+            rng_stream: rng.get_stream(),
+            rng_word_pos: rng.get_word_pos(),
+            stats,
+            settings,
+        }
+    }
+
+    /// Rebuilds the RNG exactly as it was when the checkpoint was taken.
+    pub(crate) fn restore_rng(&self) -> CheckpointRng {
+        let mut rng = ChaCha12Rng::from_seed(self.rng_seed);
+        rng.set_stream(self.rng_stream);
+        rng.set_word_pos(self.rng_word_pos);
+        rng
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.completed >= self.n_simulations
+    }
+
+    /// Names of the settings that differ from `settings`, including settings
+    /// present on only one side.
+    pub fn changed_settings(&self, settings: &[(String, String)]) -> Vec<String> {
+        let mut changed: Vec<String> = Vec::new();
+        for (key, value) in &self.settings {
+            if !settings.iter().any(|(k, v)| k == key && v == value) {
+                changed.push(key.clone());
+            }
+        }
+        for (key, _) in settings {
+            if !self.settings.iter().any(|(k, _)| k == key) {
+                changed.push(key.clone());
+            }
+        }
+        changed
+    }
+
+    pub fn to_text(&self) -> String {
+        let seed: String = self.rng_seed.iter().map(|b| format!("{b:02x}")).collect();
+        let mut text = format!(
+            "{HEADER}\ncompleted={}\nn_simulations={}\nrng_seed={seed}\nrng_stream={}\nrng_word_pos={}\nattempts={}\naccepted={}\n",
+            self.completed,
+            self.n_simulations,
+            self.rng_stream,
+            self.rng_word_pos,
+            self.stats.attempts,
+            self.stats.accepted,
+        );
+        for (key, value) in &self.settings {
+            text.push_str(&format!("{SETTING_PREFIX}{key}={value}\n"));
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, RustreeError> {
+        let mut lines = text.lines();
+        if lines.next().map(str::trim) != Some(HEADER) {
+            return Err(RustreeError::Parse(format!(
+                "not a DTL checkpoint (expected header '{HEADER}')"
+            )));
+        }
+        let mut fields = std::collections::HashMap::new();
+        let mut settings = Vec::new();
+        for line in lines.filter(|l| !l.trim().is_empty()) {
+            let (key, value) = line.split_once('=').ok_or_else(|| {
+                RustreeError::Parse(format!("malformed checkpoint line '{line}'"))
+            })?;
+            match key.trim().strip_prefix(SETTING_PREFIX) {
+                Some(name) => settings.push((name.to_string(), value.trim().to_string())),
+                None => {
+                    fields.insert(key.trim(), value.trim());
+                }
+            }
+        }
+        let field = |key: &str| {
+            fields
+                .get(key)
+                .copied()
+                .ok_or_else(|| RustreeError::Parse(format!("checkpoint is missing '{key}'")))
+        };
+        let number = |key: &str| -> Result<u128, RustreeError> {
+            field(key)?
+                .parse()
+                .map_err(|e| RustreeError::Parse(format!("checkpoint field '{key}': {e}")))
+        };
+        let out_of_range =
+            |key: &str| RustreeError::Parse(format!("checkpoint field '{key}' is out of range"));
+        let word = |key: &str| -> Result<u64, RustreeError> {
+            u64::try_from(number(key)?).map_err(|_| out_of_range(key))
+        };
+        let count = |key: &str| -> Result<usize, RustreeError> {
+            usize::try_from(number(key)?).map_err(|_| out_of_range(key))
+        };
+
+        let seed_hex = field("rng_seed")?;
+        // Checking every byte first also keeps the slicing below on char boundaries.
+        if seed_hex.len() != 64 || !seed_hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(RustreeError::Parse(
+                "checkpoint rng_seed must be 64 hex digits".to_string(),
+            ));
+        }
+        let mut rng_seed = [0u8; 32];
+        for (i, byte) in rng_seed.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&seed_hex[2 * i..2 * i + 2], 16)
+                .map_err(|e| RustreeError::Parse(format!("checkpoint rng_seed: {e}")))?;
+        }
+        let checkpoint = Self {
+            completed: count("completed")?,
+            n_simulations: count("n_simulations")?,
+            rng_seed,
+            rng_stream: word("rng_stream")?,
+            rng_word_pos: number("rng_word_pos")?,
+            stats: ConditioningStats {
+                attempts: count("attempts")?,
+                accepted: count("accepted")?,
+            },
+            settings,
+        };
+        if checkpoint.completed > checkpoint.n_simulations {
+            return Err(RustreeError::Parse(format!(
+                "checkpoint has {} completed of {} simulations",
+                checkpoint.completed, checkpoint.n_simulations
+            )));
+        }
+        Ok(checkpoint)
+    }
+
+    /// Writes the checkpoint atomically (temporary file, then rename), so a
+    /// crash never leaves a truncated checkpoint behind.
+    pub fn write(&self, path: &Path) -> Result<(), RustreeError> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, self.to_text())?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<Self, RustreeError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn text_round_trip_restores_the_rng_stream() {
+        let mut rng = ChaCha12Rng::seed_from_u64(3);
+        let _: Vec<f64> = (0..37).map(|_| rng.gen()).collect();
+        let stats = ConditioningStats {
+            attempts: 9,
+            accepted: 4,
+        };
+        let settings = vec![
+            ("species_tree".to_string(), "(A:1,B:1)R;".to_string()),
+            ("config".to_string(), "rates = 0.1".to_string()),
+        ];
+        let checkpoint = DtlCheckpoint::capture(&rng, 4, 10, stats, settings.clone());
+        let parsed = DtlCheckpoint::parse(&checkpoint.to_text()).unwrap();
+        assert_eq!(parsed, checkpoint);
+        assert!(parsed.changed_settings(&settings).is_empty());
+        let other = vec![
+            settings[0].clone(),
+            ("mode".to_string(), "Scan".to_string()),
+        ];
+        assert_eq!(parsed.changed_settings(&other), vec!["config", "mode"]);
+
+        let mut restored = parsed.restore_rng();
+        let expected: Vec<u64> = (0..10).map(|_| rng.gen()).collect();
+        let actual: Vec<u64> = (0..10).map(|_| restored.gen()).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn malformed_checkpoints_are_rejected() {
+        assert!(DtlCheckpoint::parse("hello").is_err());
+        assert!(DtlCheckpoint::parse(&format!("{HEADER}\ncompleted=1\n")).is_err());
+
+        let rng = ChaCha12Rng::seed_from_u64(5);
+        let text =
+            DtlCheckpoint::capture(&rng, 0, 3, ConditioningStats::default(), Vec::new()).to_text();
+        let seed = text
+            .lines()
+            .find_map(|l| l.strip_prefix("rng_seed="))
+            .unwrap()
+            .to_string();
+        // Multi-byte characters of the right byte length must not panic.
+        let non_ascii = format!("é{}", &seed[2..]);
+        assert_eq!(non_ascii.len(), 64);
+        assert!(DtlCheckpoint::parse(&text.replace(&seed, &non_ascii)).is_err());
+        let signed = format!("+f{}", &seed[2..]);
+        assert!(DtlCheckpoint::parse(&text.replace(&seed, &signed)).is_err());
+        let huge = text.replace("rng_stream=", &format!("rng_stream={}", u128::MAX / 10));
+        assert!(DtlCheckpoint::parse(&huge).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+// Conditioning of simulated gene families by rejection
+//
+// A `Conditioning` holds a list of predicates that a simulated family must
+// satisfy, together with a retry budget. Rejected families are redrawn, and
+// the number of attempts is recorded so the acceptance rate (the probability
+// of the conditioning event under the model) can be reported and used to
+// correct for conditioning bias.
+
+use crate::error::RustreeError;
+use crate::node::{Event, FlatTree, RecTree};
+use std::fmt;
+use std::sync::Arc;
+
+use super::event::DTLEvent;
+use super::utils::{count_extant_genes, is_extant_species};
+
+/// Default number of attempts per accepted family.
+pub const DEFAULT_MAX_ATTEMPTS: usize = 10_000;
+
+type FamilyPredicate = dyn Fn(&RecTree, &[DTLEvent]) -> bool + Send + Sync;
+
+/// A condition on a simulated family.
+#[derive(Clone)]
+pub enum SimulationCondition {
+    /// At least this many extant genes.
+    MinExtantGenes(usize),
+    /// Number of extant genes within `min..=max`.
+    ExtantGeneRange { min: usize, max: usize },
+    /// At least one extant gene in each of these species (node indices of
+    /// extant leaves; other species are rejected when the conditioning is set).
+    PresentInSpecies(Vec<usize>),
+    /// At least this many transfer events.
+    MinTransfers(usize),
+    /// Number of gene-tree nodes within `min..=max`.
+    GeneTreeSizeRange { min: usize, max: usize },
+    /// Arbitrary user predicate.
+    Custom(Arc<FamilyPredicate>),
+}
+
+impl fmt::Debug for SimulationCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MinExtantGenes(n) => f.debug_tuple("MinExtantGenes").field(n).finish(),
+            Self::ExtantGeneRange { min, max } => f
+                .debug_struct("ExtantGeneRange")
+                .field("min", min)
+                .field("max", max)
+                .finish(),
+            Self::PresentInSpecies(species) => {
+                f.debug_tuple("PresentInSpecies").field(species).finish()
+            }
+            Self::MinTransfers(n) => f.debug_tuple("MinTransfers").field(n).finish(),
+            Self::GeneTreeSizeRange { min, max } => f
+                .debug_struct("GeneTreeSizeRange")
+                .field("min", min)
+                .field("max", max)
+                .finish(),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+impl SimulationCondition {
+    fn validate(&self, species_tree: Option<&FlatTree>) -> Result<(), RustreeError> {
+        match self {
+            Self::ExtantGeneRange { min, max } | Self::GeneTreeSizeRange { min, max }
+                if min > max =>
+            {
+                Err(RustreeError::Validation(format!(
+                    "condition range is empty: min {min} > max {max}"
+                )))
+            }
+            Self::PresentInSpecies(species) => {
+                let Some(tree) = species_tree else {
+                    return Ok(());
+                };
+                let n = tree.nodes.len();
+                for &sp in species {
+                    let Some(node) = tree.nodes.get(sp) else {
+                        return Err(RustreeError::Index(format!(
+                            "PresentInSpecies species {sp} is out of bounds (species tree has {n} nodes)"
+                        )));
+                    };
+                    if !is_extant_species(node) {
+                        return Err(RustreeError::Validation(format!(
+                            "PresentInSpecies species {sp} ('{}') is not an extant leaf, so no family could satisfy it",
+                            node.name
+                        )));
+                    }
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn is_satisfied(&self, rec_tree: &RecTree, events: &[DTLEvent]) -> bool {
+        match self {
+            Self::MinExtantGenes(n) => count_extant_genes(rec_tree) >= *n,
+            Self::ExtantGeneRange { min, max } => {
+                (*min..=*max).contains(&count_extant_genes(rec_tree))
+            }
+            Self::PresentInSpecies(species) => species.iter().all(|&sp| {
+                is_extant_species(&rec_tree.species_tree.nodes[sp])
+                    && rec_tree
+                        .event_mapping
+                        .iter()
+                        .zip(&rec_tree.node_mapping)
+                        .any(|(event, mapping)| *event == Event::Leaf && *mapping == Some(sp))
+            }),
+            Self::MinTransfers(n) => {
+                events
+                    .iter()
+                    .filter(|e| matches!(e, DTLEvent::Transfer { .. }))
+                    .count()
+                    >= *n
+            }
+            Self::GeneTreeSizeRange { min, max } => {
+                (*min..=*max).contains(&rec_tree.gene_tree.nodes.len())
+            }
+            Self::Custom(predicate) => predicate(rec_tree, events),
+        }
+    }
+}
+
+/// Rejection conditioning for simulated families.
+///
+/// A family is accepted when every condition holds. Each accepted family may
+/// take at most `max_attempts` simulations.
+#[derive(Clone, Debug)]
+pub struct Conditioning {
+    pub conditions: Vec<SimulationCondition>,
+    pub max_attempts: usize,
+}
+
+impl Default for Conditioning {
+    fn default() -> Self {
+        Self {
+            conditions: Vec::new(),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+}
+
+impl Conditioning {
+    /// No conditions: every simulated family is accepted.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep only families with at least one extant gene.
+    pub fn require_extant() -> Self {
+        Self::new().with_condition(SimulationCondition::MinExtantGenes(1))
+    }
+
+    pub fn with_condition(mut self, condition: SimulationCondition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Add an arbitrary predicate on the gene tree and its event log.
+    pub fn with_predicate<F>(self, predicate: F) -> Self
+    where
+        F: Fn(&RecTree, &[DTLEvent]) -> bool + Send + Sync + 'static,
+    {
+        self.with_condition(SimulationCondition::Custom(Arc::new(predicate)))
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Result<Self, RustreeError> {
+        self.max_attempts = max_attempts;
+        self.validate()?;
+        Ok(self)
+    }
+
+    pub fn validate(&self) -> Result<(), RustreeError> {
+        self.validate_for_tree(None)
+    }
+
+    pub(crate) fn validate_for_tree(
+        &self,
+        species_tree: Option<&FlatTree>,
+    ) -> Result<(), RustreeError> {
+        if self.max_attempts == 0 {
+            return Err(RustreeError::Validation(
+                "conditioning max_attempts must be at least 1".to_string(),
+            ));
+        }
+        for condition in &self.conditions {
+            condition.validate(species_tree)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_unconditioned(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    /// Whether any condition is a user predicate, which cannot be described.
+    pub(crate) fn has_predicate(&self) -> bool {
+        self.conditions
+            .iter()
+            .any(|condition| matches!(condition, SimulationCondition::Custom(_)))
+    }
+
+    /// Whether a simulated family satisfies every condition.
+    pub fn accepts(&self, rec_tree: &RecTree, events: &[DTLEvent]) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.is_satisfied(rec_tree, events))
+    }
+}
+
+/// Attempt counts accumulated while conditioning.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConditioningStats {
+    pub attempts: usize,
+    pub accepted: usize,
+}
+
+impl ConditioningStats {
+    /// Fraction of simulated families that were accepted (1 before any attempt).
+    pub fn acceptance_rate(&self) -> f64 {
+        if self.attempts == 0 {
+            1.0
+        } else {
+            self.accepted as f64 / self.attempts as f64
+        }
+    }
+
+    pub fn rejected(&self) -> usize {
+        self.attempts - self.accepted
+    }
+}
@@ -0,0 +1,234 @@
+// Interval-based contemporaneity for the time subdivision of a species tree
+//
+// Each branch is alive on a contiguous run of time slices. Instead of listing
+// every alive branch in every slice (quadratic in tree size), branches are
+// stored once per node of their canonical decomposition in a segment tree
+// over the slices, O(n log S) in total. The branches alive in a slice are the
+// disjoint union of the lists on the leaf-to-root path of that slice, which
+// gives O(log S) counts and indexed draws and O(log S + k) iteration.
+
+use crate::error::RustreeError;
+use crate::node::FlatTree;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Contemporaneity {
+    n_slices: usize,
+    /// Number of segment-tree leaves, `n_slices` rounded up to a power of two.
+    size: usize,
+    /// `branches[offsets[v]..offsets[v + 1]]` are the branches stored on node `v`.
+    offsets: Vec<usize>,
+    branches: Vec<usize>,
+    /// Number of alive branches per slice.
+    counts: Vec<usize>,
+    /// Inclusive slice range of each branch; `None` for branches alive in no slice.
+    spans: Vec<Option<(usize, usize)>>,
+}
+
+impl Contemporaneity {
+    /// Alive branches over the subdivision `depths` of `tree`.
+    ///
+    /// A branch is alive on the slice ending at `depths[j]` under the same
+    /// rule as [`FlatTree::find_contemporaneity`].
+    ///
+    /// Fails if depths have not been assigned (via [`FlatTree::assign_depths`]).
+    pub fn new(tree: &FlatTree, depths: &[f64]) -> Result<Self, RustreeError> {
+        let spans = tree
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(idx, node)| {
+                let node_depth = node.depth.ok_or_else(|| {
+                    RustreeError::missing_depth("contemporaneity", idx, &node.name)
+                })?;
+                let start_index = tree.find_closest_index(depths, node_depth - node.length);
+                let end_index = tree.find_closest_index(depths, node_depth);
+                // Not alive on the interval that ends at the start index.
+                Ok((start_index + 1, end_index))
+            })
+            .collect::<Result<Vec<_>, RustreeError>>()?;
+        Ok(Self::from_spans(depths.len(), spans))
+    }
+
+    /// Builds the structure from the inclusive slice span of each branch,
+    /// indexed by branch. Empty spans (`first > last`) are allowed.
+    pub fn from_spans<I>(n_slices: usize, spans: I) -> Self
+    where
+        I: IntoIterator<Item = (usize, usize)>,
+    {
+        let size = n_slices.max(1).next_power_of_two();
+        let spans: Vec<Option<(usize, usize)>> = spans
+            .into_iter()
+            .map(|(first, last)| (first <= last && last < n_slices).then_some((first, last)))
+            .collect();
+
+        // Counting pass, then fill, so the branch lists share one allocation.
+        let mut offsets = vec![0usize; 2 * size + 1];
+        for &(first, last) in spans.iter().flatten() {
+            for_each_canonical_node(size, first, last, |v| offsets[v + 1] += 1);
+        }
+        for v in 0..2 * size {
+            offsets[v + 1] += offsets[v];
+        }
+        let mut next = offsets.clone();
+        let mut branches = vec![0usize; offsets[2 * size]];
+        for (branch, &(first, last)) in spans
+            .iter()
+            .enumerate()
+            .filter_map(|(i, span)| span.as_ref().map(|s| (i, s)))
+        {
+            for_each_canonical_node(size, first, last, |v| {
+                branches[next[v]] = branch;
+                next[v] += 1;
+            });
+        }
+
+        let mut delta = vec![0isize; n_slices + 1];
+        for &(first, last) in spans.iter().flatten() {
+            delta[first] += 1;
+            delta[last + 1] -= 1;
+        }
+        let mut alive = 0isize;
+        let counts = delta[..n_slices]
+            .iter()
+            .map(|d| {
+                alive += d;
+                alive as usize
+            })
+            .collect();
+
+        Self {
+            n_slices,
+            size,
+            offsets,
+            branches,
+            counts,
+            spans,
+        }
+    }
+
+    /// Number of branches alive in `slice`.
+    #[inline]
+    pub fn count(&self, slice: usize) -> usize {
+        self.counts[slice]
+    }
+
+    /// Whether `branch` is alive in `slice`.
+    #[inline]
+    pub fn contains(&self, slice: usize, branch: usize) -> bool {
+        self.spans
+            .get(branch)
+            .copied()
+            .flatten()
+            .is_some_and(|(first, last)| first <= slice && slice <= last)
+    }
+
+    /// Branches alive in `slice`, in a fixed order that [`nth`](Self::nth) follows.
+    pub fn alive(&self, slice: usize) -> impl Iterator<Item = usize> + '_ {
+        self.path(slice)
+            .flat_map(move |v| self.node_branches(v).iter().copied())
+    }
+
+    /// The `k`-th branch alive in `slice`, in the order of [`alive`](Self::alive).
+    pub fn nth(&self, slice: usize, mut k: usize) -> Option<usize> {
+        for v in self.path(slice) {
+            let branches = self.node_branches(v);
+            if k < branches.len() {
+                return Some(branches[k]);
+            }
+            k -= branches.len();
+        }
+        None
+    }
+
+    /// Sum of `weight` over the alive branches of every slice.
+    ///
+    /// Each branch is weighted once per canonical node, so this is
+    /// O(n log S + S log S) rather than proportional to the total number of
+    /// (slice, branch) pairs.
+    pub fn slice_totals<F: FnMut(usize) -> f64>(&self, mut weight: F) -> Vec<f64> {
+        let node_totals: Vec<f64> = (0..2 * self.size)
+            .map(|v| self.node_branches(v).iter().map(|&b| weight(b)).sum())
+            .collect();
+        (0..self.n_slices)
+            .map(|slice| self.path(slice).map(|v| node_totals[v]).sum())
+            .collect()
+    }
+
+    fn node_branches(&self, v: usize) -> &[usize] {
+        &self.branches[self.offsets[v]..self.offsets[v + 1]]
+    }
+
+    /// Segment-tree nodes from the root down to the leaf of `slice`.
+    fn path(&self, slice: usize) -> impl Iterator<Item = usize> {
+        let leaf = self.size + slice;
+        let height = self.size.trailing_zeros();
+        (0..=height).rev().map(move |level| leaf >> level)
+    }
+}
+
+/// Calls `visit` on the canonical segment-tree nodes covering `first..=last`.
+fn for_each_canonical_node<F: FnMut(usize)>(size: usize, first: usize, last: usize, mut visit: F) {
+    let mut l = first + size;
+    let mut r = last + size + 1;
+    while l < r {
+        if l & 1 == 1 {
+            visit(l);
+            l += 1;
+        }
+        if r & 1 == 1 {
+            r -= 1;
+            visit(r);
+        }
+        l >>= 1;
+        r >>= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_find_contemporaneity() {
+        let mut nodes = crate::parse_newick(
+            "(((A:1,B:2)AB:1,C:2.5)ABC:0.5,((D:1,E:0.3)DE:2,F:0.7)DEF:1)root:0.2;",
+        )
+        .unwrap();
+        let mut tree = nodes.pop().unwrap().to_flat_tree();
+        tree.assign_depths();
+        let depths = tree.make_subdivision();
+        let dense = tree.find_contemporaneity(&depths);
+        let compact = Contemporaneity::new(&tree, &depths).unwrap();
+
+        for (slice, expected) in dense.iter().enumerate() {
+            let mut alive: Vec<usize> = compact.alive(slice).collect();
+            assert_eq!(alive.len(), compact.count(slice));
+            let by_index: Vec<usize> = (0..alive.len())
+                .map(|k| compact.nth(slice, k).unwrap())
+                .collect();
+            assert_eq!(by_index, alive);
+            assert_eq!(compact.nth(slice, alive.len()), None);
+            alive.sort_unstable();
+            assert_eq!(&alive, expected, "slice {slice}");
+            for sp in 0..tree.nodes.len() {
+                assert_eq!(compact.contains(slice, sp), expected.contains(&sp));
+            }
+        }
+    }
+
+    #[test]
+    fn missing_depths_are_an_error() {
+        let mut nodes = crate::parse_newick("(A:1,B:2)root:0;").unwrap();
+        let tree = nodes.pop().unwrap().to_flat_tree();
+        let err = Contemporaneity::new(&tree, &[0.0, 1.0, 2.0]).unwrap_err();
+        assert!(matches!(err, RustreeError::MissingDepth { .. }));
+    }
+
+    #[test]
+    fn slice_totals_sum_alive_weights() {
+        // Slices: [], [0, 1, 2], [1, 2], [2]
+        let contemp = Contemporaneity::from_spans(4, [(1, 1), (1, 2), (1, 3)]);
+        let totals = contemp.slice_totals(|sp| [1.0, 10.0, 100.0][sp]);
+        assert_eq!(totals, vec![0.0, 111.0, 110.0, 100.0]);
+    }
+}
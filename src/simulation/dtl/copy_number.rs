@@ -0,0 +1,307 @@
+// Copy-number-dependent DTL rates
+//
+// By default every gene copy experiences the same rates whatever its genomic
+// context. These options make the simulation state-dependent:
+// - dosage balance: each copy gets an extra loss rate growing with the number
+//   of paralogs in its species, and its duplication rate is scaled down by a
+//   factor per paralog,
+// - duplicate retention: copies descending from a duplication get an extra
+//   loss rate that decays exponentially with the age of that duplication,
+// - homolog-dependent transfer acceptance: a transfer is kept with a
+//   probability that depends on whether the recipient already has a copy.
+//
+// The extra losses form an additional Gillespie channel, like transfer
+// highways. The age-dependent part and the scaled duplication rate never
+// exceed their base rates, so both are simulated exactly by thinning.
+
+use crate::error::RustreeError;
+use rand::Rng;
+
+use super::state::SimulationState;
+
+/// Duplicates older than this many retention times are treated as established.
+/// Their remaining extra loss rate (below e^-30 of the initial one) is dropped.
+const RETENTION_CUTOFF: f64 = 30.0;
+
+/// State-dependent modifiers of duplication, transfer and loss.
+///
+/// All extra loss rates are per gene copy, for both the per-gene and the
+/// per-species model. The neutral default changes nothing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CopyNumberDependence {
+    /// Extra loss rate per copy and per additional paralog: a copy in a species
+    /// holding `k` copies is lost at an extra rate `dosage_loss × (k − 1)`.
+    pub dosage_loss: f64,
+    /// Duplication rate factor per additional paralog, in `[0, 1]`: a copy in a
+    /// species holding `k` copies duplicates at `λ_D × dosage_duplication^(k − 1)`.
+    pub dosage_duplication: f64,
+    /// Extra loss rate of a freshly duplicated copy.
+    pub young_duplicate_loss: f64,
+    /// Time scale over which the extra loss of duplicates decays: a copy whose
+    /// lineage was duplicated `a` time units ago is lost at an extra rate
+    /// `young_duplicate_loss × exp(−a / duplicate_retention_time)`.
+    pub duplicate_retention_time: f64,
+    /// Probability that a transfer is kept when the recipient already has a copy.
+    pub transfer_acceptance_with_homolog: f64,
+    /// Probability that a transfer is kept when the recipient has no copy.
+    pub transfer_acceptance_without_homolog: f64,
+}
+
+impl Default for CopyNumberDependence {
+    fn default() -> Self {
+        Self {
+            dosage_loss: 0.0,
+            dosage_duplication: 1.0,
+            young_duplicate_loss: 0.0,
+            duplicate_retention_time: 1.0,
+            transfer_acceptance_with_homolog: 1.0,
+            transfer_acceptance_without_homolog: 1.0,
+        }
+    }
+}
+
+impl CopyNumberDependence {
+    /// Neutral settings; combine with the `with_*` methods.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the extra loss rate per copy and per additional paralog.
+    pub fn with_dosage_loss(mut self, dosage_loss: f64) -> Self {
+        self.dosage_loss = dosage_loss;
+        self
+    }
+
+    /// Set the duplication rate factor per additional paralog.
+    pub fn with_dosage_duplication(mut self, factor: f64) -> Self {
+        self.dosage_duplication = factor;
+        self
+    }
+
+    /// Set the extra loss of young duplicates and its decay time scale.
+    pub fn with_duplicate_retention(
+        mut self,
+        young_duplicate_loss: f64,
+        retention_time: f64,
+    ) -> Self {
+        self.young_duplicate_loss = young_duplicate_loss;
+        self.duplicate_retention_time = retention_time;
+        self
+    }
+
+    /// Set transfer acceptance probabilities with and without a resident homolog.
+    pub fn with_transfer_acceptance(mut self, with_homolog: f64, without_homolog: f64) -> Self {
+        self.transfer_acceptance_with_homolog = with_homolog;
+        self.transfer_acceptance_without_homolog = without_homolog;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), RustreeError> {
+        for (name, rate) in [
+            ("dosage_loss", self.dosage_loss),
+            ("young_duplicate_loss", self.young_duplicate_loss),
+        ] {
+            if !(rate.is_finite() && rate >= 0.0) {
+                return Err(RustreeError::Validation(format!(
+                    "{name} must be non-negative and finite, got {rate}"
+                )));
+            }
+        }
+        if self.duplicate_retention_time.is_nan() || self.duplicate_retention_time <= 0.0 {
+            return Err(RustreeError::Validation(format!(
+                "duplicate_retention_time must be positive, got {}",
+                self.duplicate_retention_time
+            )));
+        }
+        for (name, p) in [
+            ("dosage_duplication", self.dosage_duplication),
+            (
+                "transfer_acceptance_with_homolog",
+                self.transfer_acceptance_with_homolog,
+            ),
+            (
+                "transfer_acceptance_without_homolog",
+                self.transfer_acceptance_without_homolog,
+            ),
+        ] {
+            if !(0.0..=1.0).contains(&p) {
+                return Err(RustreeError::Validation(format!(
+                    "{name} must be in [0, 1], got {p}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the dosage loss channel draws copies from the paralog tree.
+    pub(crate) fn tracks_paralog_pairs(&self) -> bool {
+        self.dosage_loss > 0.0
+    }
+
+    /// Whether duplicate ages must be tracked during the simulation.
+    pub(crate) fn tracks_duplicate_ages(&self) -> bool {
+        self.young_duplicate_loss > 0.0
+    }
+
+    fn dosage_rate(&self, state: &SimulationState<'_>) -> f64 {
+        if self.dosage_loss <= 0.0 {
+            return 0.0;
+        }
+        self.dosage_loss * state.paralog_pairs() as f64
+    }
+
+    /// Upper bound on the age-dependent loss rate, used for thinning.
+    fn young_bound(&self, state: &SimulationState<'_>) -> f64 {
+        state
+            .young_duplicates
+            .as_ref()
+            .map_or(0.0, |young| self.young_duplicate_loss * young.len() as f64)
+    }
+
+    /// Total rate of the extra loss channel.
+    pub(crate) fn extra_loss_rate(&self, state: &SimulationState<'_>) -> f64 {
+        self.dosage_rate(state) + self.young_bound(state)
+    }
+
+    /// Realize one event of the extra loss channel at `event_time`.
+    ///
+    /// Age-dependent proposals may be rejected, in which case nothing happens.
+    pub(crate) fn apply_extra_loss<R: Rng>(
+        &self,
+        state: &mut SimulationState<'_>,
+        event_time: f64,
+        rng: &mut R,
+    ) {
+        let dosage = self.dosage_rate(state);
+        let total = dosage + self.young_bound(state);
+        if total <= 0.0 {
+            return;
+        }
+        let threshold = rng.gen::<f64>() * total;
+        if threshold < dosage {
+            let target = ((threshold / self.dosage_loss) as usize).min(state.paralog_pairs() - 1);
+            if let Some((species, gene)) = state.random_paralog_copy(target, rng) {
+                state.handle_loss(gene, species, event_time);
+            }
+            return;
+        }
+
+        let Some(young) = state.young_duplicates.as_ref() else {
+            return;
+        };
+        let slot = rng.gen_range(0..young.len());
+        let gene = young[slot];
+        let age = state.duplication_time[gene].map(|time| event_time - time);
+        let age = match age {
+            Some(age)
+                if state.is_alive_copy(gene)
+                    && age / self.duplicate_retention_time <= RETENTION_CUTOFF =>
+            {
+                age
+            }
+            _ => {
+                // Zero rate: drop the stale entry and reject the proposal.
+                if let Some(young) = state.young_duplicates.as_mut() {
+                    young.swap_remove(slot);
+                }
+                return;
+            }
+        };
+        if rng.gen::<f64>() < (-age / self.duplicate_retention_time).exp() {
+            if let Some(species) = state.node_mapping[gene] {
+                state.handle_loss(gene, species, event_time);
+            }
+        }
+    }
+
+    /// Decide whether a duplication drawn at the base rate in `species` is kept.
+    ///
+    /// Draws a random number only when the species holds paralogs and the
+    /// factor is below one.
+    pub(crate) fn accepts_duplication<R: Rng>(
+        &self,
+        state: &SimulationState<'_>,
+        species: usize,
+        rng: &mut R,
+    ) -> bool {
+        let paralogs = state.copies_in_species(species).saturating_sub(1);
+        if paralogs == 0 || self.dosage_duplication >= 1.0 {
+            return true;
+        }
+        let p = self
+            .dosage_duplication
+            .powi(paralogs.min(i32::MAX as usize) as i32);
+        rng.gen::<f64>() < p
+    }
+
+    /// Decide whether a transfer into `recipient` is kept.
+    ///
+    /// Draws a random number only when the acceptance probability is below one.
+    pub(crate) fn accepts_transfer<R: Rng>(
+        &self,
+        state: &SimulationState<'_>,
+        recipient: usize,
+        rng: &mut R,
+    ) -> bool {
+        let p = if state.copies_in_species(recipient) > 0 {
+            self.transfer_acceptance_with_homolog
+        } else {
+            self.transfer_acceptance_without_homolog
+        };
+        p >= 1.0 || rng.gen::<f64>() < p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation() {
+        assert!(CopyNumberDependence::new().validate().is_ok());
+        assert!(CopyNumberDependence::new()
+            .with_dosage_loss(-1.0)
+            .validate()
+            .is_err());
+        assert!(CopyNumberDependence::new()
+            .with_duplicate_retention(1.0, 0.0)
+            .validate()
+            .is_err());
+        assert!(CopyNumberDependence::new()
+            .with_transfer_acceptance(1.5, 1.0)
+            .validate()
+            .is_err());
+        assert!(CopyNumberDependence::new()
+            .with_dosage_duplication(1.5)
+            .validate()
+            .is_err());
+        assert!(CopyNumberDependence::new()
+            .with_dosage_loss(0.5)
+            .with_dosage_duplication(0.5)
+            .with_duplicate_retention(2.0, 0.1)
+            .with_transfer_acceptance(0.1, 0.9)
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn dosage_rate_counts_paralog_pairs() {
+        let mut nodes = crate::newick::parse_newick("(A:1,B:1)root:0;").unwrap();
+        let tree = nodes.pop().unwrap().to_flat_tree();
+        let mut state = SimulationState::with_branch_total_rates(4, &tree, None);
+        let mut genes = Vec::new();
+        for species in [0, 0, 0, 1] {
+            let gene = state.create_gene_node(None, species, crate::node::Event::Speciation, 0.0);
+            state.add_gene_to_species(species, gene);
+            genes.push(gene);
+        }
+        let dependence = CopyNumberDependence::new().with_dosage_loss(0.5);
+        // Species 0 holds 3 copies (3 × 2 ordered pairs), species 1 holds one.
+        assert_eq!(dependence.extra_loss_rate(&state), 3.0);
+
+        state.handle_loss(genes[0], 0, 0.5);
+        assert_eq!(dependence.extra_loss_rate(&state), 1.0);
+        state.take_genes_for_species(0);
+        assert_eq!(dependence.extra_loss_rate(&state), 0.0);
+    }
+}
@@ -20,6 +20,8 @@ pub enum DTLEvent {
         species_id: usize,
         child1: usize,
         child2: usize,
+        /// Whether the duplication is part of a whole-genome duplication
+        wgd: bool,
     },
     /// Transfer: gene transfers from one species to another
     Transfer {
@@ -61,7 +63,7 @@ impl DTLEvent {
                 right_child,
             } => {
                 format!(
-                    "{},{},Speciation,{},,,{},{}",
+                    "{},{},Speciation,{},,,{},{},",
                     time,
                     csv_field(&gene_tree.nodes[*gene_id].name),
                     csv_field(&species_tree.nodes[*species_id].name),
@@ -75,14 +77,16 @@ impl DTLEvent {
                 species_id,
                 child1,
                 child2,
+                wgd,
             } => {
                 format!(
-                    "{},{},Duplication,{},,,{},{}",
+                    "{},{},Duplication,{},,,{},{},{}",
                     time,
                     csv_field(&gene_tree.nodes[*gene_id].name),
                     csv_field(&species_tree.nodes[*species_id].name),
                     csv_field(&gene_tree.nodes[*child1].name),
-                    csv_field(&gene_tree.nodes[*child2].name)
+                    csv_field(&gene_tree.nodes[*child2].name),
+                    wgd
                 )
             }
             DTLEvent::Transfer {
@@ -95,7 +99,7 @@ impl DTLEvent {
                 recipient_child,
             } => {
                 format!(
-                    "{},{},Transfer,{},{},{},{},{}",
+                    "{},{},Transfer,{},{},{},{},{},",
                     time,
                     csv_field(&gene_tree.nodes[*gene_id].name),
                     csv_field(&species_tree.nodes[*species_id].name),
@@ -111,7 +115,7 @@ impl DTLEvent {
                 species_id,
             } => {
                 format!(
-                    "{},{},Loss,{},,,,",
+                    "{},{},Loss,{},,,,,",
                     time,
                     csv_field(&gene_tree.nodes[*gene_id].name),
                     csv_field(&species_tree.nodes[*species_id].name)
@@ -123,7 +127,7 @@ impl DTLEvent {
                 species_id,
             } => {
                 format!(
-                    "{},{},Leaf,{},,,,",
+                    "{},{},Leaf,{},,,,,",
                     time,
                     csv_field(&gene_tree.nodes[*gene_id].name),
                     csv_field(&species_tree.nodes[*species_id].name)
@@ -141,12 +145,14 @@ impl DTLEvent {
     // recipient_species: for Transfer events, the **name** of the species to which the gene is transferred
     // child1_name: name of the first child gene node involved in the event (if applicable)
     // child2_name: name of the second child gene node involved in the event (if applicable)
+    // wgd: for Duplication events, whether the duplication is part of a whole-genome duplication
     pub fn csv_header() -> &'static str {
-        "time,gene_node_name,event_type,species_node,donor_species,recipient_species,child1_name,child2_name"
+        "time,gene_node_name,event_type,species_node,donor_species,recipient_species,child1_name,child2_name,wgd"
     }
 }
 
-fn csv_field(value: &str) -> String {
+/// Quote a CSV field if it holds a separator, quote or newline.
+pub(super) fn csv_field(value: &str) -> String {
     if value.contains([',', '"', '\n', '\r']) {
         format!("\"{}\"", value.replace('"', "\"\""))
     } else {
@@ -0,0 +1,333 @@
+// Forest-level DTL simulation with gene family origination over time
+//
+// Instead of starting a single family at a fixed origin, new families arise as
+// a homogeneous Poisson process along every branch of the species tree (plus
+// an optional set of ancestral families present at the root). Each family then
+// evolves independently under the usual Gillespie DTL loop, yielding a
+// `GeneForest` with a realistic mix of core and accessory genes.
+
+use crate::error::RustreeError;
+use crate::node::{Event, FlatTree, GeneForest, RecTree};
+use crate::simulation::utils::draw_waiting_time;
+use rand::Rng;
+
+use super::event::DTLEvent;
+use super::gillespie::{simulate_dtl_gillespie_from, DTLMode};
+use super::observer::NoObserver;
+use super::utils::{count_extant_genes, is_extant_species, prepare_simulation};
+use super::DTLConfig;
+
+/// Gene family origination process.
+///
+/// `initial_families` families are present at the start of the root stem;
+/// further families originate at `rate` per unit of branch length, uniformly
+/// over all branches alive at that time (including lineages that later go
+/// extinct).
+#[derive(Clone, Debug, PartialEq)]
+pub struct FamilyOrigination {
+    pub rate: f64,
+    pub initial_families: usize,
+}
+
+impl FamilyOrigination {
+    pub fn new(rate: f64, initial_families: usize) -> Result<Self, RustreeError> {
+        let origination = Self {
+            rate,
+            initial_families,
+        };
+        origination.validate()?;
+        Ok(origination)
+    }
+
+    pub fn validate(&self) -> Result<(), RustreeError> {
+        if !self.rate.is_finite() || self.rate < 0.0 {
+            return Err(RustreeError::Validation(format!(
+                "origination rate must be finite and non-negative, got {}",
+                self.rate
+            )));
+        }
+        Ok(())
+    }
+
+    /// Expected number of originated families (before discarding extinct ones).
+    pub fn expected_families(&self, species_tree: &FlatTree) -> f64 {
+        self.initial_families as f64 + self.rate * total_branch_length(species_tree)
+    }
+
+    /// Draw origination points, sorted by time.
+    fn sample_origins<R: Rng>(
+        &self,
+        species_tree: &FlatTree,
+        rng: &mut R,
+    ) -> Result<Vec<FamilyOrigin>, RustreeError> {
+        let starts = species_tree
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(idx, node)| {
+                node.depth.map(|depth| depth - node.length).ok_or_else(|| {
+                    RustreeError::missing_depth("simulate_dtl_forest", idx, &node.name)
+                })
+            })
+            .collect::<Result<Vec<f64>, _>>()?;
+
+        let root = species_tree.root;
+        let mut origins = vec![
+            FamilyOrigin {
+                species: root,
+                time: starts[root],
+            };
+            self.initial_families
+        ];
+
+        // Concatenate branches in index order and walk exponential gaps along them.
+        let mut offsets = Vec::with_capacity(species_tree.nodes.len());
+        let mut total = 0.0;
+        for node in &species_tree.nodes {
+            offsets.push(total);
+            total += node.length.max(0.0);
+        }
+        let mut position = draw_waiting_time(self.rate, rng);
+        while position < total {
+            let species = offsets.partition_point(|&offset| offset <= position) - 1;
+            origins.push(FamilyOrigin {
+                species,
+                time: starts[species] + (position - offsets[species]),
+            });
+            position += draw_waiting_time(self.rate, rng);
+        }
+
+        origins.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(origins)
+    }
+}
+
+/// Where and when a gene family originated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FamilyOrigin {
+    pub species: usize,
+    pub time: f64,
+}
+
+/// Result of a forest-level simulation.
+///
+/// `forest.gene_trees`, `events` and `origins` are aligned: entry `i` of each
+/// describes the same family.
+#[derive(Clone, Debug)]
+pub struct OriginationForest {
+    pub forest: GeneForest,
+    pub events: Vec<Vec<DTLEvent>>,
+    pub origins: Vec<FamilyOrigin>,
+}
+
+impl OriginationForest {
+    /// Number of families in the forest.
+    pub fn len(&self) -> usize {
+        self.forest.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.forest.is_empty()
+    }
+
+    /// Extant species-tree leaves, in node-index order.
+    ///
+    /// This is the column order used by [`phyletic_patterns`](Self::phyletic_patterns).
+    pub fn extant_species(&self) -> Vec<usize> {
+        self.forest
+            .species_tree
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| is_extant_species(node))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Presence/absence matrix: one row per family, one column per extant species.
+    pub fn phyletic_patterns(&self) -> Vec<Vec<bool>> {
+        let extant = self.extant_species();
+        let mut column = vec![None; self.forest.species_tree.nodes.len()];
+        for (col, &species) in extant.iter().enumerate() {
+            column[species] = Some(col);
+        }
+
+        self.forest
+            .gene_trees
+            .iter()
+            .map(|rec_tree| {
+                let mut row = vec![false; extant.len()];
+                for (gene, event) in rec_tree.event_mapping.iter().enumerate() {
+                    if *event != Event::Leaf {
+                        continue;
+                    }
+                    if let Some(col) = rec_tree.node_mapping[gene].and_then(|sp| column[sp]) {
+                        row[col] = true;
+                    }
+                }
+                row
+            })
+            .collect()
+    }
+
+    /// Gene frequency spectrum: entry `k` counts families present in exactly
+    /// `k` extant species (length = number of extant species + 1).
+    pub fn gene_frequency_spectrum(&self) -> Vec<usize> {
+        let patterns = self.phyletic_patterns();
+        let mut spectrum = vec![0; self.extant_species().len() + 1];
+        for row in &patterns {
+            spectrum[row.iter().filter(|&&present| present).count()] += 1;
+        }
+        spectrum
+    }
+
+    /// Indices of core families (present in every extant species).
+    pub fn core_families(&self) -> Vec<usize> {
+        self.phyletic_patterns()
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| !row.is_empty() && row.iter().all(|&present| present))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+}
+
+/// Simulates a gene forest under the per-gene-copy DTL model with families
+/// originating over time.
+///
+/// The origination process replaces `origin_species` and any
+/// `origination_probability` in `config`. When `require_extant` is true,
+/// families that leave no extant gene are dropped from the result, as they
+/// would be unobservable in a real pangenome.
+pub fn simulate_dtl_forest<R: Rng>(
+    species_tree: &FlatTree,
+    config: &DTLConfig,
+    origination: &FamilyOrigination,
+    require_extant: bool,
+    rng: &mut R,
+) -> Result<OriginationForest, RustreeError> {
+    simulate_forest(
+        DTLMode::PerGene,
+        species_tree,
+        config,
+        origination,
+        require_extant,
+        rng,
+    )
+}
+
+/// Simulates a gene forest under the per-species DTL model with families
+/// originating over time.
+///
+/// See [`simulate_dtl_forest`] for details.
+pub fn simulate_dtl_per_species_forest<R: Rng>(
+    species_tree: &FlatTree,
+    config: &DTLConfig,
+    origination: &FamilyOrigination,
+    require_extant: bool,
+    rng: &mut R,
+) -> Result<OriginationForest, RustreeError> {
+    simulate_forest(
+        DTLMode::PerSpecies,
+        species_tree,
+        config,
+        origination,
+        require_extant,
+        rng,
+    )
+}
+
+fn simulate_forest<R: Rng>(
+    mode: DTLMode,
+    species_tree: &FlatTree,
+    config: &DTLConfig,
+    origination: &FamilyOrigination,
+    require_extant: bool,
+    rng: &mut R,
+) -> Result<OriginationForest, RustreeError> {
+    origination.validate()?;
+    // Every branch start other than the root stem is already a node depth,
+    // so preparing from the root covers all possible origination times.
+    let prepared = prepare_simulation(species_tree, species_tree.root, config)?;
+    let origins = origination.sample_origins(species_tree, rng)?;
+
+    let mut gene_trees: Vec<RecTree> = Vec::with_capacity(origins.len());
+    let mut events = Vec::with_capacity(origins.len());
+    let mut kept_origins = Vec::with_capacity(origins.len());
+    for origin in origins {
+        let (rec_tree, family_events) = simulate_dtl_gillespie_from(
+            mode,
+            &prepared.species_tree,
+            &prepared.species_events,
+            &prepared.depths,
+            &prepared.contemporaneity,
+            prepared.lca_depths.as_ref(),
+            origin.species,
+            origin.time,
+            config,
+            prepared.branch_totals.as_ref(),
+            &mut NoObserver,
+            rng,
+        )?
+        .into_completed()?;
+        if require_extant && count_extant_genes(&rec_tree) == 0 {
+            continue;
+        }
+        gene_trees.push(rec_tree);
+        events.push(family_events);
+        kept_origins.push(origin);
+    }
+
+    Ok(OriginationForest {
+        forest: GeneForest::from_rec_trees(prepared.species_tree, gene_trees),
+        events,
+        origins: kept_origins,
+    })
+}
+
+fn total_branch_length(species_tree: &FlatTree) -> f64 {
+    species_tree
+        .nodes
+        .iter()
+        .map(|node| node.length.max(0.0))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newick::parse_newick;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn tree(newick: &str) -> FlatTree {
+        let mut nodes = parse_newick(newick).unwrap();
+        let mut tree = nodes.pop().unwrap().to_flat_tree();
+        tree.assign_depths();
+        tree
+    }
+
+    #[test]
+    fn origins_fall_within_their_branches() {
+        let tree = tree("((A:1,B:2)AB:1,C:3)root:0.5;");
+        let origination = FamilyOrigination::new(5.0, 2).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        let origins = origination.sample_origins(&tree, &mut rng).unwrap();
+
+        assert!(origins.len() > 2);
+        assert!(origins.windows(2).all(|w| w[0].time <= w[1].time));
+        for origin in &origins {
+            let node = &tree.nodes[origin.species];
+            let end = node.depth.unwrap();
+            assert!(origin.time >= end - node.length && origin.time <= end);
+        }
+        assert_eq!(origins[0].species, tree.root);
+        assert_eq!(origins[1].species, tree.root);
+    }
+
+    #[test]
+    fn rejects_negative_rate() {
+        assert!(FamilyOrigination::new(-1.0, 0).is_err());
+        assert!(FamilyOrigination::new(f64::NAN, 0).is_err());
+    }
+}
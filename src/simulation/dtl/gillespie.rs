@@ -10,17 +10,25 @@ use crate::node::{Event, FlatTree, RecTree};
 use rand::Rng;
 use std::sync::Arc;
 
+use super::contemporaneity::Contemporaneity;
+use super::copy_number::CopyNumberDependence;
 use super::event::DTLEvent;
+use super::highways::ActiveHighways;
+use super::observer::{
+    NoObserver, ObservedSimulation, ObserverAction, SimulationObserver, SimulationView,
+    SpeciesBoundary,
+};
 use super::state::SimulationState;
 use super::utils::{
-    finalize_simulation, find_time_index, select_transfer_recipient,
-    select_transfer_recipient_assortative,
+    apply_highway_transfer, apply_transfer, choose_transfer_recipient, finalize_simulation,
+    find_time_index, LcaDepths,
 };
+use super::wgd::WgdSchedule;
 use super::DTLConfig;
 use crate::simulation::utils::draw_waiting_time;
 
 /// Determines how DTL event rates scale with the simulation state.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub(crate) enum DTLMode {
     /// Rate proportional to number of gene copies (per-gene model)
     PerGene,
@@ -31,84 +39,77 @@ pub(crate) enum DTLMode {
 impl DTLMode {
     fn total_event_rate(
         self,
+        rates: EventRates<'_>,
         state: &SimulationState<'_>,
         depths: &[f64],
-        contemporaneity: &[Vec<usize>],
+        contemporaneity: &Contemporaneity,
         current_time: f64,
-        config: &DTLConfig,
     ) -> f64 {
-        match self {
-            DTLMode::PerGene => {
-                if config.uses_branch_rates() {
-                    state.total_gene_weighted_rate(|species| config.branch_total_rate(species))
-                } else {
-                    state.total_gene_copies() as f64 * config.branch_total_rate(0)
-                }
+        match (self, rates) {
+            (DTLMode::PerGene, EventRates::Uniform(rate)) => {
+                state.total_gene_copies() as f64 * rate
             }
-            DTLMode::PerSpecies => {
+            (DTLMode::PerGene, EventRates::PerBranch(totals)) => {
+                state.total_gene_weighted_rate(|species| totals.branch[species])
+            }
+            (DTLMode::PerSpecies, EventRates::Uniform(rate)) => {
                 let time_idx = find_time_index(depths, current_time);
-                if config.uses_branch_rates() {
-                    contemporaneity[time_idx]
-                        .iter()
-                        .map(|&species| config.branch_total_rate(species))
-                        .sum()
-                } else {
-                    contemporaneity[time_idx].len() as f64 * config.branch_total_rate(0)
-                }
+                contemporaneity.count(time_idx) as f64 * rate
+            }
+            (DTLMode::PerSpecies, EventRates::PerBranch(totals)) => {
+                totals.slice[find_time_index(depths, current_time)]
             }
         }
     }
 
     fn select_affected_gene<R: Rng>(
         self,
+        rates: EventRates<'_>,
         state: &SimulationState<'_>,
         depths: &[f64],
-        contemporaneity: &[Vec<usize>],
+        contemporaneity: &Contemporaneity,
         current_time: f64,
-        config: &DTLConfig,
         rng: &mut R,
     ) -> Option<(usize, usize)> {
         match self {
-            DTLMode::PerGene => {
-                if config.uses_branch_rates() {
-                    state
-                        .random_gene_copy_weighted(|species| config.branch_total_rate(species), rng)
-                } else {
-                    state.random_gene_copy(rng)
+            DTLMode::PerGene => match rates {
+                EventRates::Uniform(_) => state.random_gene_copy(rng),
+                EventRates::PerBranch(totals) => {
+                    state.random_gene_copy_weighted(|species| totals.branch[species], rng)
                 }
-            }
+            },
             DTLMode::PerSpecies => {
                 let time_idx = find_time_index(depths, current_time);
-                let alive_species = &contemporaneity[time_idx];
-                if alive_species.is_empty() {
+                let n_alive = contemporaneity.count(time_idx);
+                if n_alive == 0 {
                     return None;
                 }
 
-                let species = if config.uses_branch_rates() {
-                    let total_rate: f64 = alive_species
-                        .iter()
-                        .map(|&species| config.branch_total_rate(species))
-                        .sum();
-                    if total_rate <= 0.0 {
-                        return None;
+                let species = match rates {
+                    EventRates::Uniform(_) => {
+                        contemporaneity.nth(time_idx, rng.gen_range(0..n_alive))?
                     }
-
-                    let mut threshold = rng.gen::<f64>() * total_rate;
-                    let mut species = *alive_species.last()?;
-                    for &candidate in alive_species {
-                        let rate = config.branch_total_rate(candidate);
-                        if rate <= 0.0 {
-                            continue;
+                    EventRates::PerBranch(totals) => {
+                        let total_rate = totals.slice[time_idx];
+                        if total_rate <= 0.0 {
+                            return None;
                         }
-                        if threshold < rate {
-                            species = candidate;
-                            break;
+
+                        let mut threshold = rng.gen::<f64>() * total_rate;
+                        let mut species = contemporaneity.nth(time_idx, n_alive - 1)?;
+                        for candidate in contemporaneity.alive(time_idx) {
+                            let rate = totals.branch[candidate];
+                            if rate <= 0.0 {
+                                continue;
+                            }
+                            if threshold < rate {
+                                species = candidate;
+                                break;
+                            }
+                            threshold -= rate;
                         }
-                        threshold -= rate;
+                        species
                     }
-                    species
-                } else {
-                    alive_species[rng.gen_range(0..alive_species.len())]
                 };
 
                 match state.genes_per_species.get(&species) {
@@ -123,49 +124,362 @@ impl DTLMode {
     }
 }
 
+/// Per-branch total DTL rates and their sum over the species alive in each
+/// time slice, for configs with branch-specific rates.
+///
+/// Iterators build this once per species tree; families whose rates were
+/// rescaled build their own.
+pub(crate) struct BranchRateTotals {
+    branch: Vec<f64>,
+    slice: Vec<f64>,
+}
+
+impl BranchRateTotals {
+    /// `None` when every branch shares the config's base rates.
+    pub(crate) fn new(
+        config: &DTLConfig,
+        species_tree: &FlatTree,
+        contemporaneity: &Contemporaneity,
+    ) -> Option<Self> {
+        if !config.uses_branch_rates() {
+            return None;
+        }
+        let branch: Vec<f64> = (0..species_tree.nodes.len())
+            .map(|species| config.branch_total_rate(species))
+            .collect();
+        let slice = contemporaneity.slice_totals(|species| branch[species]);
+        Some(Self { branch, slice })
+    }
+}
+
+/// How the loop turns branch rates into a total event rate and an affected copy.
+#[derive(Clone, Copy)]
+enum EventRates<'r> {
+    /// Every branch has the same total rate.
+    Uniform(f64),
+    /// Branch-specific totals.
+    PerBranch(&'r BranchRateTotals),
+}
+
+/// Event channels outside the branch DTL rates: transfer highways, the
+/// copy-number extra loss and scheduled whole-genome duplications.
+pub(crate) struct ExtraChannels<'c> {
+    highways: Option<ActiveHighways>,
+    wgds: Option<WgdSchedule>,
+    copy_number: Option<&'c CopyNumberDependence>,
+}
+
+/// Rates of the [`ExtraChannels`] in the current state.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ChannelRates {
+    highway: f64,
+    extra_loss: f64,
+}
+
+impl ChannelRates {
+    pub(crate) fn total(self) -> f64 {
+        self.highway + self.extra_loss
+    }
+}
+
+/// What the Gillespie loop processes next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum NextStep {
+    SpeciesEvent,
+    WholeGenomeDuplication,
+    HighwayBreakpoint,
+    Dtl,
+}
+
+impl<'c> ExtraChannels<'c> {
+    pub(crate) fn new(
+        config: &'c DTLConfig,
+        species_tree: &FlatTree,
+    ) -> Result<Self, RustreeError> {
+        Ok(ExtraChannels {
+            highways: config
+                .transfer_highways
+                .as_deref()
+                .map(|highways| ActiveHighways::new(highways, species_tree))
+                .filter(|highways| !highways.is_empty()),
+            wgds: config
+                .whole_genome_duplications
+                .as_deref()
+                .map(|wgds| WgdSchedule::new(wgds, species_tree))
+                .transpose()?,
+            copy_number: config.copy_number_dependence.as_ref(),
+        })
+    }
+
+    /// Enable the state bookkeeping the channels rely on.
+    pub(crate) fn prepare_state(&self, state: &mut SimulationState<'_>) {
+        if self.copy_number.is_some_and(|c| c.tracks_paralog_pairs()) {
+            state.track_paralog_pairs();
+        }
+        if self.copy_number.is_some_and(|c| c.tracks_duplicate_ages()) {
+            state.track_duplicate_ages();
+        }
+    }
+
+    pub(crate) fn highways(&self) -> Option<&ActiveHighways> {
+        self.highways.as_ref()
+    }
+
+    pub(crate) fn rates(
+        &self,
+        mode: DTLMode,
+        state: &SimulationState<'_>,
+        time: f64,
+    ) -> ChannelRates {
+        ChannelRates {
+            highway: self
+                .highways
+                .as_ref()
+                .map_or(0.0, |h| h.total_rate(mode, state, time)),
+            extra_loss: self.copy_number.map_or(0.0, |c| c.extra_loss_rate(state)),
+        }
+    }
+
+    /// Order the next species event, scheduled WGD, highway window boundary
+    /// and drawn DTL event.
+    pub(crate) fn next_step(
+        &self,
+        current_time: f64,
+        next_species_event_time: f64,
+        next_dtl_time: f64,
+    ) -> NextStep {
+        let next_highway_breakpoint = self
+            .highways
+            .as_ref()
+            .map_or(f64::INFINITY, |h| h.next_breakpoint(current_time));
+        let next_wgd_time = self
+            .wgds
+            .as_ref()
+            .map_or(f64::INFINITY, WgdSchedule::next_time);
+
+        if next_species_event_time.is_finite()
+            && next_species_event_time
+                <= next_dtl_time
+                    .min(next_highway_breakpoint)
+                    .min(next_wgd_time)
+        {
+            NextStep::SpeciesEvent
+        } else if next_wgd_time <= next_dtl_time.min(next_highway_breakpoint) {
+            NextStep::WholeGenomeDuplication
+        } else if next_highway_breakpoint < next_dtl_time {
+            NextStep::HighwayBreakpoint
+        } else {
+            NextStep::Dtl
+        }
+    }
+
+    /// Time of the highway window boundary after `time`.
+    pub(crate) fn next_highway_breakpoint(&self, time: f64) -> f64 {
+        self.highways
+            .as_ref()
+            .map_or(f64::INFINITY, |h| h.next_breakpoint(time))
+    }
+
+    /// Apply the next scheduled WGD and return the new current time.
+    pub(crate) fn apply_next_wgd<R: Rng>(
+        &mut self,
+        state: &mut SimulationState<'_>,
+        current_time: f64,
+        rng: &mut R,
+    ) -> f64 {
+        match self.wgds.as_mut().and_then(WgdSchedule::pop) {
+            Some(wgd) => {
+                let (species, time, retention) = (wgd.species, wgd.time, wgd.retention);
+                state.handle_whole_genome_duplication(species, time, retention, rng);
+                // WGDs before the family's origin find no copies and leave the clock alone.
+                current_time.max(time)
+            }
+            None => current_time,
+        }
+    }
+
+    /// Assign an event drawn at total rate `dtl_total_rate + rates.total()`
+    /// to a channel; returns `true` when a channel consumed it.
+    pub(crate) fn try_apply<R: Rng>(
+        &self,
+        mode: DTLMode,
+        state: &mut SimulationState<'_>,
+        rates: ChannelRates,
+        dtl_total_rate: f64,
+        time: f64,
+        config: &DTLConfig,
+        rng: &mut R,
+    ) -> bool {
+        let channel_rate = rates.total();
+        if channel_rate <= 0.0 {
+            return false;
+        }
+        let channel_draw = rng.gen::<f64>() * (dtl_total_rate + channel_rate);
+        if channel_draw < rates.highway {
+            if let Some(highways) = self.highways.as_ref() {
+                apply_highway_transfer(mode, state, highways, rates.highway, time, config, rng);
+            }
+            true
+        } else if channel_draw < channel_rate {
+            if let Some(copy_number) = self.copy_number {
+                copy_number.apply_extra_loss(state, time, rng);
+            }
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Apply a species-tree event to every gene copy in the affected species.
+pub(crate) fn apply_species_event(
+    state: &mut SimulationState<'_>,
+    sp_event: &TreeEvent,
+    time: f64,
+) -> Result<(), RustreeError> {
+    match sp_event.event_type {
+        BDEvent::Speciation => {
+            let child1 = sp_event.child1.ok_or_else(|| {
+                RustreeError::Simulation(format!(
+                    "Speciation event for node {} has no child1",
+                    sp_event.node_id
+                ))
+            })?;
+            let child2 = sp_event.child2.ok_or_else(|| {
+                RustreeError::Simulation(format!(
+                    "Speciation event for node {} has no child2",
+                    sp_event.node_id
+                ))
+            })?;
+            if let Some(genes) = state.take_genes_for_species(sp_event.node_id) {
+                for gene_idx in genes {
+                    state.handle_speciation(gene_idx, sp_event.node_id, child1, child2, time);
+                }
+            }
+        }
+        BDEvent::Extinction => {
+            if let Some(genes) = state.take_genes_for_species(sp_event.node_id) {
+                for gene_idx in genes {
+                    state.handle_loss(gene_idx, sp_event.node_id, time);
+                }
+            }
+        }
+        BDEvent::Leaf => {
+            if let Some(genes) = state.take_genes_for_species(sp_event.node_id) {
+                for gene_idx in genes {
+                    state.handle_leaf(gene_idx, sp_event.node_id, time);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Draw the event type for the selected gene copy from its branch rates and apply it.
+pub(crate) fn apply_dtl_event<R: Rng>(
+    state: &mut SimulationState<'_>,
+    gene: usize,
+    species: usize,
+    depths: &[f64],
+    contemporaneity: &Contemporaneity,
+    lca_depths: Option<&LcaDepths>,
+    highways: Option<&ActiveHighways>,
+    time: f64,
+    config: &DTLConfig,
+    rng: &mut R,
+) {
+    let (lambda_d, lambda_t, lambda_l) = config.branch_event_rates(species);
+    let branch_total_rate = lambda_d + lambda_t + lambda_l;
+    if branch_total_rate <= 0.0 {
+        return;
+    }
+
+    let event_draw = rng.gen::<f64>() * branch_total_rate;
+    if event_draw < lambda_d {
+        let accepted = config
+            .copy_number_dependence
+            .as_ref()
+            .is_none_or(|dependence| dependence.accepts_duplication(state, species, rng));
+        if accepted {
+            state.handle_duplication(gene, species, time);
+        }
+    } else if event_draw < lambda_d + lambda_t {
+        let recipient = choose_transfer_recipient(
+            depths,
+            contemporaneity,
+            lca_depths,
+            config.transfer_alpha,
+            highways,
+            config.recipient_kernel.as_deref(),
+            time,
+            species,
+            rng,
+        );
+        if let Some(recipient) = recipient {
+            apply_transfer(state, gene, species, recipient, time, config, rng);
+        }
+    } else if lambda_l > 0.0 {
+        state.handle_loss(gene, species, time);
+    }
+}
+
 /// Shared Gillespie-style DTL simulation.
 ///
 /// Both per-gene and per-species models use this function. The `mode` parameter
 /// determines:
-/// - **PerGene**: total rate = n_copies × (λ_D + λ_T + λ_L), random copy is selected
-/// - **PerSpecies**: total rate = n_alive_species × (λ_D + λ_T + λ_L), random species
-///   is selected then random gene in that species (event fails if species has no genes)
+/// - **PerGene**: total rate = sum over copies of their branch's λ_D + λ_T + λ_L;
+///   a copy is selected with probability proportional to that rate
+/// - **PerSpecies**: total rate = sum over alive species of their branch's
+///   λ_D + λ_T + λ_L; a species is selected in proportion to its rate, then a
+///   random gene in it (the event fails if the species has no genes)
 ///
-/// For now, rates are the same for all species in the tree. We can change it later if we want.
-/// Doing so will involve changing the rates at which events are proposed,
-/// as well as the probability of a given event affecting each gene or species at time t.
-/// We can have auxiliary functions to flexibly handle the different generalizations without loss of performance.
+/// With a single set of rates every copy or species is equally likely.
+/// `branch_totals` are the precomputed totals for a config with branch rates;
+/// when `None` they are built from `config` for this run.
 pub(crate) fn simulate_dtl_gillespie<R: Rng>(
     mode: DTLMode,
     species_tree: &Arc<FlatTree>,
     species_events: &[TreeEvent],
     depths: &[f64],
-    contemporaneity: &[Vec<usize>],
-    lca_depths: Option<&[Vec<f64>]>,
+    contemporaneity: &Contemporaneity,
+    lca_depths: Option<&LcaDepths>,
     origin_species: usize,
     config: &DTLConfig,
+    branch_totals: Option<&BranchRateTotals>,
     rng: &mut R,
 ) -> Result<(RecTree, Vec<DTLEvent>), RustreeError> {
-    let transfer_alpha = config.transfer_alpha;
-    let replacement_transfer = config.replacement_transfer;
-    let origin_species = config.sample_origin(origin_species, rng);
-
-    // Preallocate as many gene nodes as species nodes (for default case where there are no transfers, no duplications, no losses)
-    let estimated_capacity = species_tree.nodes.len();
-    let branch_total_rates = if config.uses_branch_rates() {
-        Some(
-            (0..species_tree.nodes.len())
-                .map(|species| config.branch_total_rate(species))
-                .collect::<Vec<_>>(),
-        )
-    } else {
-        None
-    };
-    let mut state = SimulationState::with_branch_total_rates(
-        estimated_capacity,
+    simulate_dtl_gillespie_observed(
+        mode,
         species_tree,
-        branch_total_rates.as_deref(),
-    );
+        species_events,
+        depths,
+        contemporaneity,
+        lca_depths,
+        origin_species,
+        config,
+        branch_totals,
+        &mut NoObserver,
+        rng,
+    )?
+    .into_completed()
+}
+
+/// [`simulate_dtl_gillespie`] with an observer notified of events and
+/// species boundaries, which may abort the run.
+pub(crate) fn simulate_dtl_gillespie_observed<R: Rng>(
+    mode: DTLMode,
+    species_tree: &Arc<FlatTree>,
+    species_events: &[TreeEvent],
+    depths: &[f64],
+    contemporaneity: &Contemporaneity,
+    lca_depths: Option<&LcaDepths>,
+    origin_species: usize,
+    config: &DTLConfig,
+    branch_totals: Option<&BranchRateTotals>,
+    observer: &mut dyn SimulationObserver,
+    rng: &mut R,
+) -> Result<ObservedSimulation, RustreeError> {
+    let origin_species = config.sample_origin(origin_species, rng);
 
     // Find when origin species starts (beginning of its branch)
     let origin_node = species_tree.nodes.get(origin_species).ok_or_else(|| {
@@ -178,6 +492,66 @@ pub(crate) fn simulate_dtl_gillespie<R: Rng>(
         RustreeError::missing_depth("simulate_dtl_gillespie", origin_species, &origin_node.name)
     })? - origin_node.length;
 
+    simulate_dtl_gillespie_from(
+        mode,
+        species_tree,
+        species_events,
+        depths,
+        contemporaneity,
+        lca_depths,
+        origin_species,
+        origin_start_time,
+        config,
+        branch_totals,
+        observer,
+        rng,
+    )
+}
+
+/// Shared Gillespie-style DTL simulation started at an arbitrary time.
+///
+/// The family originates as a single gene in `origin_species` at
+/// `origin_start_time`, which must lie within that species' branch.
+pub(crate) fn simulate_dtl_gillespie_from<R: Rng>(
+    mode: DTLMode,
+    species_tree: &Arc<FlatTree>,
+    species_events: &[TreeEvent],
+    depths: &[f64],
+    contemporaneity: &Contemporaneity,
+    lca_depths: Option<&LcaDepths>,
+    origin_species: usize,
+    origin_start_time: f64,
+    config: &DTLConfig,
+    branch_totals: Option<&BranchRateTotals>,
+    observer: &mut dyn SimulationObserver,
+    rng: &mut R,
+) -> Result<ObservedSimulation, RustreeError> {
+    let mut channels = ExtraChannels::new(config, species_tree)?;
+
+    // Preallocate as many gene nodes as species nodes (for default case where there are no transfers, no duplications, no losses)
+    let estimated_capacity = species_tree.nodes.len();
+    let owned_totals;
+    let branch_totals = match branch_totals {
+        Some(totals) => Some(totals),
+        None => {
+            owned_totals = BranchRateTotals::new(config, species_tree, contemporaneity);
+            owned_totals.as_ref()
+        }
+    };
+    let rates = branch_totals.map_or(
+        EventRates::Uniform(config.branch_total_rate(0)),
+        EventRates::PerBranch,
+    );
+    let mut state = SimulationState::with_branch_total_rates(
+        estimated_capacity,
+        species_tree,
+        branch_totals.map(|totals| totals.branch.as_slice()),
+    );
+    channels.prepare_state(&mut state);
+    if matches!(mode, DTLMode::PerGene) {
+        state.track_copy_selection();
+    }
+
     let mut current_time = origin_start_time;
 
     // Skip species events before origin time
@@ -203,8 +577,20 @@ pub(crate) fn simulate_dtl_gillespie<R: Rng>(
     // (same half-open boundary issue as speciations).
     current_time = current_time.next_up();
 
+    // Events already reported to the observer
+    let mut observed_events = 0;
+
     // Main Gillespie loop
     loop {
+        if notify_events(observer, &state, &mut observed_events, current_time)
+            == ObserverAction::Abort
+        {
+            return Ok(ObservedSimulation::Aborted {
+                time: current_time,
+                events: state.events,
+            });
+        }
+
         // We can enter here and have species_event_idx be out of bounds. In this case, next event time will be infinity.
         // what does it mean to be out of bounds?
 
@@ -218,9 +604,11 @@ pub(crate) fn simulate_dtl_gillespie<R: Rng>(
         }
 
         let dtl_total_rate =
-            mode.total_event_rate(&state, depths, contemporaneity, current_time, config);
+            mode.total_event_rate(rates, &state, depths, contemporaneity, current_time);
 
-        let next_dtl_time = current_time + draw_waiting_time(dtl_total_rate, rng);
+        let channel_rates = channels.rates(mode, &state, current_time);
+        let next_dtl_time =
+            current_time + draw_waiting_time(dtl_total_rate + channel_rates.total(), rng);
 
         if next_species_event_time == f64::INFINITY && next_dtl_time == f64::INFINITY {
             break;
@@ -228,128 +616,85 @@ pub(crate) fn simulate_dtl_gillespie<R: Rng>(
         // next DTL event was supposed to occur AFTER
         // the next species event, so we actually don't do the
         // DTL event and instead process a species-level event which will affect genes.
-        if next_species_event_time <= next_dtl_time && species_event_idx < species_events.len() {
-            // === Process species event ===
-            let sp_event = species_events[species_event_idx].clone();
-            current_time = sp_event.time;
-
-            match sp_event.event_type {
-                BDEvent::Speciation => {
-                    let child1 = sp_event.child1.ok_or_else(|| {
-                        RustreeError::Simulation(format!(
-                            "Speciation event for node {} has no child1",
-                            sp_event.node_id
-                        ))
-                    })?;
-                    let child2 = sp_event.child2.ok_or_else(|| {
-                        RustreeError::Simulation(format!(
-                            "Speciation event for node {} has no child2",
-                            sp_event.node_id
-                        ))
-                    })?;
-                    if let Some(genes) = state.take_genes_for_species(sp_event.node_id) {
-                        for gene_idx in genes {
-                            state.handle_speciation(
-                                gene_idx,
-                                sp_event.node_id,
-                                child1,
-                                child2,
-                                current_time,
-                            );
-                        }
-                    }
-                }
-                BDEvent::Extinction => {
-                    if let Some(genes) = state.take_genes_for_species(sp_event.node_id) {
-                        for gene_idx in genes {
-                            state.handle_loss(gene_idx, sp_event.node_id, current_time);
-                        }
-                    }
-                }
-                BDEvent::Leaf => {
-                    if let Some(genes) = state.take_genes_for_species(sp_event.node_id) {
-                        for gene_idx in genes {
-                            state.handle_leaf(gene_idx, sp_event.node_id, current_time);
-                        }
-                    }
+        match channels.next_step(current_time, next_species_event_time, next_dtl_time) {
+            NextStep::SpeciesEvent => {
+                // === Process species event ===
+                let sp_event = &species_events[species_event_idx];
+                current_time = sp_event.time;
+
+                let boundary = SpeciesBoundary {
+                    species: sp_event.node_id,
+                    time: current_time,
+                    kind: sp_event.event_type,
+                };
+                if observer
+                    .on_species_boundary(&boundary, &SimulationView::new(&state, current_time))
+                    == ObserverAction::Abort
+                {
+                    return Ok(ObservedSimulation::Aborted {
+                        time: current_time,
+                        events: state.events,
+                    });
                 }
-            }
 
-            species_event_idx += 1;
+                apply_species_event(&mut state, sp_event, current_time)?;
+                species_event_idx += 1;
 
-            // Nudge time past the event boundary so the next rate computation
-            // uses post-event contemporaneity (children instead of parent after
-            // speciations, excludes extinct/leaf species after those events).
-            current_time = current_time.next_up();
-        } else {
-            // === Process DTL event ===
-            current_time = next_dtl_time;
-
-            let selection = mode.select_affected_gene(
-                &state,
-                depths,
-                contemporaneity,
-                current_time,
-                config,
-                rng,
-            );
-
-            let (affected_species, affected_gene) = match selection {
-                Some(s) => s,
-                None => continue, // Event failed, time still advances. This can happen in PerSpecies mode if we select a species with no genes, or if there are no alive species at this time.
-            };
-
-            // Determine event type
-            let (lambda_d, lambda_t, lambda_l) = config.branch_event_rates(affected_species);
-            let branch_total_rate = lambda_d + lambda_t + lambda_l;
-            if branch_total_rate <= 0.0 {
-                continue;
+                // Nudge time past the event boundary so the next rate computation
+                // uses post-event contemporaneity (children instead of parent after
+                // speciations, excludes extinct/leaf species after those events).
+                current_time = current_time.next_up();
             }
+            NextStep::WholeGenomeDuplication => {
+                current_time = channels.apply_next_wgd(&mut state, current_time, rng);
+            }
+            NextStep::HighwayBreakpoint => {
+                // Highway rates change at window boundaries; waiting times are
+                // memoryless, so redrawing from the boundary is exact.
+                current_time = channels.next_highway_breakpoint(current_time);
+            }
+            NextStep::Dtl => {
+                // === Process DTL event ===
+                current_time = next_dtl_time;
+
+                if channels.try_apply(
+                    mode,
+                    &mut state,
+                    channel_rates,
+                    dtl_total_rate,
+                    current_time,
+                    config,
+                    rng,
+                ) {
+                    continue;
+                }
+
+                let selection = mode.select_affected_gene(
+                    rates,
+                    &state,
+                    depths,
+                    contemporaneity,
+                    current_time,
+                    rng,
+                );
 
-            let event_draw = rng.gen::<f64>() * branch_total_rate;
-            if event_draw < lambda_d {
-                // Duplication
-                state.handle_duplication(affected_gene, affected_species, current_time);
-            } else if event_draw < lambda_d + lambda_t {
-                // Transfer
-                let recipient = match (transfer_alpha, lca_depths) {
-                    (Some(alpha), Some(lca)) => select_transfer_recipient_assortative(
-                        depths,
-                        contemporaneity,
-                        lca,
-                        current_time,
-                        affected_species,
-                        alpha,
-                        rng,
-                    ),
-                    _ => select_transfer_recipient(
-                        depths,
-                        contemporaneity,
-                        current_time,
-                        affected_species,
-                        rng,
-                    ),
+                let (affected_species, affected_gene) = match selection {
+                    Some(s) => s,
+                    None => continue, // Event failed, time still advances. This can happen in PerSpecies mode if we select a species with no genes, or if there are no alive species at this time.
                 };
 
-                if let Some(recipient_species) = recipient {
-                    // Replacement transfer: find and remove victim BEFORE adding transfer
-                    let is_replacement =
-                        replacement_transfer.is_some_and(|p| p > 0.0 && rng.gen::<f64>() < p);
-                    if is_replacement {
-                        if let Some(victim) = state.random_gene_in_species(recipient_species, rng) {
-                            state.handle_loss(victim, recipient_species, current_time);
-                        }
-                    }
-                    state.handle_transfer(
-                        affected_gene,
-                        affected_species,
-                        recipient_species,
-                        current_time,
-                    );
-                }
-            } else if lambda_l > 0.0 {
-                // Loss
-                state.handle_loss(affected_gene, affected_species, current_time);
+                apply_dtl_event(
+                    &mut state,
+                    affected_gene,
+                    affected_species,
+                    depths,
+                    contemporaneity,
+                    lca_depths,
+                    channels.highways(),
+                    current_time,
+                    config,
+                    rng,
+                );
             }
         }
 
@@ -359,8 +704,16 @@ pub(crate) fn simulate_dtl_gillespie<R: Rng>(
         }
     }
 
+    if notify_events(observer, &state, &mut observed_events, current_time) == ObserverAction::Abort
+    {
+        return Ok(ObservedSimulation::Aborted {
+            time: current_time,
+            events: state.events,
+        });
+    }
+
     // Finalize tree
-    let rec_tree = finalize_simulation(
+    let mut rec_tree = finalize_simulation(
         species_tree.clone(),
         state.gene_nodes,
         state.node_mapping,
@@ -368,5 +721,28 @@ pub(crate) fn simulate_dtl_gillespie<R: Rng>(
         origin_species,
         origin_start_time,
     )?;
-    Ok((rec_tree, state.events))
+    rec_tree.set_wgd_nodes(state.wgd_nodes);
+    Ok(ObservedSimulation::Completed {
+        rec_tree,
+        events: state.events,
+    })
+}
+
+/// Report events recorded since the last call, all with the current state;
+/// stops at the first abort.
+fn notify_events(
+    observer: &mut dyn SimulationObserver,
+    state: &SimulationState<'_>,
+    observed_events: &mut usize,
+    current_time: f64,
+) -> ObserverAction {
+    let view = SimulationView::new(state, current_time);
+    while *observed_events < state.events.len() {
+        let event = &state.events[*observed_events];
+        *observed_events += 1;
+        if observer.on_event(event, &view) == ObserverAction::Abort {
+            return ObserverAction::Abort;
+        }
+    }
+    ObserverAction::Continue
 }
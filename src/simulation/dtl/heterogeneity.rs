@@ -0,0 +1,260 @@
+// Per-family DTL rate heterogeneity
+//
+// Real genomes show strongly heterogeneous duplication/transfer/loss rates
+// across gene families. This module draws one set of rate multipliers per
+// family around a base DTLConfig and records the realized rates so that
+// inferred rates can be scored against the truth.
+
+use crate::error::RustreeError;
+use crate::simulation::utils::{sample_gamma, sample_lognormal, sample_weighted_index};
+use rand::Rng;
+
+use super::DTLConfig;
+
+/// Distribution of a per-family rate multiplier.
+///
+/// The continuous distributions are parameterized to have mean 1, so the base
+/// rate in [`DTLConfig`] remains the expected rate across families.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RateMultiplier {
+    /// Every family uses the base rate (multiplier 1).
+    Fixed,
+    /// Gamma-distributed multiplier with mean 1 and variance `1 / shape`.
+    Gamma { shape: f64 },
+    /// Lognormal multiplier with mean 1 and log-scale standard deviation `sigma`.
+    LogNormal { sigma: f64 },
+    /// Discrete rate categories: `multipliers[i]` is drawn with probability
+    /// proportional to `weights[i]`.
+    Discrete {
+        multipliers: Vec<f64>,
+        weights: Vec<f64>,
+    },
+}
+
+impl RateMultiplier {
+    /// Validate distribution parameters.
+    pub fn validate(&self, name: &str) -> Result<(), RustreeError> {
+        match self {
+            RateMultiplier::Fixed => Ok(()),
+            RateMultiplier::Gamma { shape } => {
+                if !(shape.is_finite() && *shape > 0.0) {
+                    return Err(RustreeError::Validation(format!(
+                        "{name} gamma shape must be positive and finite, got {shape}"
+                    )));
+                }
+                Ok(())
+            }
+            RateMultiplier::LogNormal { sigma } => {
+                if !(sigma.is_finite() && *sigma >= 0.0) {
+                    return Err(RustreeError::Validation(format!(
+                        "{name} lognormal sigma must be non-negative and finite, got {sigma}"
+                    )));
+                }
+                Ok(())
+            }
+            RateMultiplier::Discrete {
+                multipliers,
+                weights,
+            } => {
+                if multipliers.is_empty() {
+                    return Err(RustreeError::Validation(format!(
+                        "{name} discrete rate categories cannot be empty"
+                    )));
+                }
+                if multipliers.len() != weights.len() {
+                    return Err(RustreeError::Validation(format!(
+                        "{name} discrete multipliers ({}) and weights ({}) must have the same length",
+                        multipliers.len(),
+                        weights.len()
+                    )));
+                }
+                for (idx, &m) in multipliers.iter().enumerate() {
+                    if !(m.is_finite() && m >= 0.0) {
+                        return Err(RustreeError::Validation(format!(
+                            "{name} discrete multiplier at category {idx} must be non-negative and finite, got {m}"
+                        )));
+                    }
+                }
+                for (idx, &w) in weights.iter().enumerate() {
+                    if !(w.is_finite() && w >= 0.0) {
+                        return Err(RustreeError::Validation(format!(
+                            "{name} discrete weight at category {idx} must be non-negative and finite, got {w}"
+                        )));
+                    }
+                }
+                if weights.iter().sum::<f64>() <= 0.0 {
+                    return Err(RustreeError::Validation(format!(
+                        "{name} discrete weights must have a positive sum"
+                    )));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Draw one multiplier. Parameters must have been validated.
+    pub(crate) fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        match self {
+            RateMultiplier::Fixed => 1.0,
+            RateMultiplier::Gamma { shape } => sample_gamma(*shape, 1.0 / shape, rng),
+            RateMultiplier::LogNormal { sigma } => {
+                if *sigma == 0.0 {
+                    1.0
+                } else {
+                    sample_lognormal(-0.5 * sigma * sigma, *sigma, rng)
+                }
+            }
+            RateMultiplier::Discrete {
+                multipliers,
+                weights,
+            } => sample_weighted_index(weights, rng).map_or(1.0, |idx| multipliers[idx]),
+        }
+    }
+}
+
+/// Per-family rate heterogeneity model for duplication, transfer and loss.
+///
+/// Each family draws one independent multiplier per event type. The
+/// multiplier scales the scalar rate in the base [`DTLConfig`], or every
+/// branch of its [`BranchDTLRates`](super::BranchDTLRates) table when
+/// branch-rate mode is active.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FamilyRateHeterogeneity {
+    pub duplication: RateMultiplier,
+    pub transfer: RateMultiplier,
+    pub loss: RateMultiplier,
+}
+
+impl FamilyRateHeterogeneity {
+    /// Create a validated heterogeneity model.
+    pub fn new(
+        duplication: RateMultiplier,
+        transfer: RateMultiplier,
+        loss: RateMultiplier,
+    ) -> Result<Self, RustreeError> {
+        let model = Self {
+            duplication,
+            transfer,
+            loss,
+        };
+        model.validate()?;
+        Ok(model)
+    }
+
+    /// Validate all three multiplier distributions.
+    pub fn validate(&self) -> Result<(), RustreeError> {
+        self.duplication.validate("Duplication")?;
+        self.transfer.validate("Transfer")?;
+        self.loss.validate("Loss")
+    }
+
+    /// Draw one family's rates and return the config to simulate it with.
+    pub(crate) fn draw_family<R: Rng>(
+        &self,
+        base: &DTLConfig,
+        rng: &mut R,
+    ) -> Result<(DTLConfig, FamilyRates), RustreeError> {
+        let duplication_multiplier = self.duplication.sample(rng);
+        let transfer_multiplier = self.transfer.sample(rng);
+        let loss_multiplier = self.loss.sample(rng);
+
+        let mut config = base.clone();
+        config.lambda_d *= duplication_multiplier;
+        config.lambda_t *= transfer_multiplier;
+        config.lambda_l *= loss_multiplier;
+        if let Some(branch_rates) = &base.branch_rates {
+            config.branch_rates = Some(branch_rates.scaled(
+                duplication_multiplier,
+                transfer_multiplier,
+                loss_multiplier,
+            )?);
+        }
+
+        let rates = FamilyRates {
+            duplication_multiplier,
+            transfer_multiplier,
+            loss_multiplier,
+            lambda_d: config.lambda_d,
+            lambda_t: config.lambda_t,
+            lambda_l: config.lambda_l,
+        };
+        Ok((config, rates))
+    }
+}
+
+/// Realized per-family rates recorded on a simulated [`RecTree`](crate::RecTree).
+///
+/// In branch-rate mode the scalar `lambda_*` fields stay at the base config's
+/// values (zero) and the multipliers apply to every branch of the table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FamilyRates {
+    pub duplication_multiplier: f64,
+    pub transfer_multiplier: f64,
+    pub loss_multiplier: f64,
+    /// Realized duplication rate (base rate times multiplier)
+    pub lambda_d: f64,
+    /// Realized transfer rate (base rate times multiplier)
+    pub lambda_t: f64,
+    /// Realized loss rate (base rate times multiplier)
+    pub lambda_l: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn validation_rejects_bad_parameters() {
+        assert!(RateMultiplier::Gamma { shape: 0.0 }.validate("d").is_err());
+        assert!(RateMultiplier::LogNormal { sigma: -1.0 }
+            .validate("d")
+            .is_err());
+        assert!(RateMultiplier::Discrete {
+            multipliers: vec![0.5, 2.0],
+            weights: vec![1.0],
+        }
+        .validate("d")
+        .is_err());
+        assert!(RateMultiplier::Discrete {
+            multipliers: vec![0.5, 2.0],
+            weights: vec![0.0, 0.0],
+        }
+        .validate("d")
+        .is_err());
+    }
+
+    #[test]
+    fn discrete_categories_only_yield_listed_multipliers() {
+        let dist = RateMultiplier::Discrete {
+            multipliers: vec![0.25, 4.0],
+            weights: vec![3.0, 1.0],
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let draws: Vec<f64> = (0..2_000).map(|_| dist.sample(&mut rng)).collect();
+        assert!(draws.iter().all(|&m| m == 0.25 || m == 4.0));
+        let high = draws.iter().filter(|&&m| m == 4.0).count() as f64 / 2_000.0;
+        assert!((high - 0.25).abs() < 0.05);
+    }
+
+    #[test]
+    fn draw_family_scales_base_rates() {
+        let base = DTLConfig::new(0.5, 0.2, 0.3, None, None).unwrap();
+        let model = FamilyRateHeterogeneity::new(
+            RateMultiplier::Discrete {
+                multipliers: vec![2.0],
+                weights: vec![1.0],
+            },
+            RateMultiplier::Fixed,
+            RateMultiplier::Gamma { shape: 2.0 },
+        )
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(5);
+        let (config, rates) = model.draw_family(&base, &mut rng).unwrap();
+        assert_eq!(config.lambda_d, 1.0);
+        assert_eq!(config.lambda_t, 0.2);
+        assert_eq!(rates.lambda_l, 0.3 * rates.loss_multiplier);
+        assert_eq!(config.lambda_l, rates.lambda_l);
+    }
+}
@@ -0,0 +1,277 @@
+// Transfer highways: donor/recipient branch pairs with elevated transfer activity
+//
+// A highway adds an extra transfer rate from a donor species branch that always
+// targets a given recipient branch, and/or boosts the recipient's weight when
+// ordinary transfers from the donor pick a recipient. Highways can be limited
+// to a time window and are clipped to the period where both branches exist.
+
+use crate::error::RustreeError;
+use crate::node::FlatTree;
+
+use super::gillespie::DTLMode;
+use super::state::SimulationState;
+
+/// A donor -> recipient species-branch pair with elevated transfer activity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferHighway {
+    /// Donor species-tree node index (the branch ending at this node)
+    pub donor: usize,
+    /// Recipient species-tree node index
+    pub recipient: usize,
+    /// Extra transfer rate along the highway.
+    ///
+    /// Per gene copy in the donor for the per-gene model, per donor species for
+    /// the per-species model. Highway transfers always land in `recipient`.
+    pub rate: f64,
+    /// Multiplier on the recipient's weight when an ordinary transfer from the
+    /// donor picks its recipient (1.0 = no preference).
+    pub recipient_weight: f64,
+    /// Start of the active window (inclusive)
+    pub start_time: f64,
+    /// End of the active window (exclusive)
+    pub end_time: f64,
+}
+
+impl TransferHighway {
+    /// Create a highway active for the whole time both branches coexist.
+    pub fn new(donor: usize, recipient: usize, rate: f64, recipient_weight: f64) -> Self {
+        Self {
+            donor,
+            recipient,
+            rate,
+            recipient_weight,
+            start_time: f64::NEG_INFINITY,
+            end_time: f64::INFINITY,
+        }
+    }
+
+    /// Restrict the highway to the window `[start_time, end_time)`.
+    pub fn with_window(mut self, start_time: f64, end_time: f64) -> Self {
+        self.start_time = start_time;
+        self.end_time = end_time;
+        self
+    }
+
+    fn validate(&self, idx: usize) -> Result<(), RustreeError> {
+        if self.donor == self.recipient {
+            return Err(RustreeError::Validation(format!(
+                "transfer highway {idx} has identical donor and recipient ({})",
+                self.donor
+            )));
+        }
+        if !(self.rate.is_finite() && self.rate >= 0.0) {
+            return Err(RustreeError::Validation(format!(
+                "transfer highway {idx} rate must be non-negative and finite, got {}",
+                self.rate
+            )));
+        }
+        if !(self.recipient_weight.is_finite() && self.recipient_weight >= 0.0) {
+            return Err(RustreeError::Validation(format!(
+                "transfer highway {idx} recipient_weight must be non-negative and finite, got {}",
+                self.recipient_weight
+            )));
+        }
+        if self.start_time.is_nan() || self.end_time.is_nan() || self.start_time >= self.end_time {
+            return Err(RustreeError::Validation(format!(
+                "transfer highway {idx} window must satisfy start_time < end_time, got [{}, {})",
+                self.start_time, self.end_time
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Validate a list of highways, optionally against a species tree size.
+pub(crate) fn validate_highways(
+    highways: &[TransferHighway],
+    node_count: Option<usize>,
+) -> Result<(), RustreeError> {
+    for (idx, highway) in highways.iter().enumerate() {
+        highway.validate(idx)?;
+        if let Some(n) = node_count {
+            if highway.donor >= n || highway.recipient >= n {
+                return Err(RustreeError::Index(format!(
+                    "transfer highway {idx} ({} -> {}) is out of bounds for species tree with {n} nodes",
+                    highway.donor, highway.recipient
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Highways clipped to the lifetime of both branches, ready for the Gillespie loop.
+pub(crate) struct ActiveHighways {
+    highways: Vec<TransferHighway>,
+    /// Sorted window boundaries where the highway rate can change.
+    breakpoints: Vec<f64>,
+}
+
+impl ActiveHighways {
+    /// Clip each highway window to the period where donor and recipient coexist.
+    ///
+    /// Highways whose clipped window is empty are dropped.
+    pub fn new(highways: &[TransferHighway], species_tree: &FlatTree) -> Self {
+        let lifetime = |idx: usize| {
+            let node = &species_tree.nodes[idx];
+            let end = node.depth.unwrap_or(f64::INFINITY);
+            (end - node.length, end)
+        };
+
+        let mut clipped = Vec::with_capacity(highways.len());
+        let mut breakpoints = Vec::with_capacity(2 * highways.len());
+        for highway in highways {
+            let (donor_start, donor_end) = lifetime(highway.donor);
+            let (recipient_start, recipient_end) = lifetime(highway.recipient);
+            let start = highway.start_time.max(donor_start).max(recipient_start);
+            let end = highway.end_time.min(donor_end).min(recipient_end);
+            if start.is_nan() || end.is_nan() || start >= end {
+                continue;
+            }
+            breakpoints.push(start);
+            breakpoints.push(end);
+            clipped.push(TransferHighway {
+                start_time: start,
+                end_time: end,
+                ..highway.clone()
+            });
+        }
+        breakpoints.sort_by(|a, b| a.total_cmp(b));
+        breakpoints.dedup();
+
+        Self {
+            highways: clipped,
+            breakpoints,
+        }
+    }
+
+    #[inline]
+    fn is_active(highway: &TransferHighway, time: f64) -> bool {
+        highway.start_time <= time && time < highway.end_time
+    }
+
+    /// First window boundary strictly after `time`, or infinity.
+    pub fn next_breakpoint(&self, time: f64) -> f64 {
+        let idx = self.breakpoints.partition_point(|&b| b <= time);
+        self.breakpoints.get(idx).copied().unwrap_or(f64::INFINITY)
+    }
+
+    fn highway_weight(
+        mode: DTLMode,
+        highway: &TransferHighway,
+        state: &SimulationState<'_>,
+    ) -> f64 {
+        match mode {
+            DTLMode::PerGene => {
+                let copies = state
+                    .genes_per_species
+                    .get(&highway.donor)
+                    .map_or(0, |genes| genes.len());
+                highway.rate * copies as f64
+            }
+            DTLMode::PerSpecies => highway.rate,
+        }
+    }
+
+    /// Total extra transfer rate contributed by highways active at `time`.
+    pub fn total_rate(&self, mode: DTLMode, state: &SimulationState<'_>, time: f64) -> f64 {
+        self.highways
+            .iter()
+            .filter(|h| h.rate > 0.0 && Self::is_active(h, time))
+            .map(|h| Self::highway_weight(mode, h, state))
+            .sum()
+    }
+
+    /// Pick the highway carrying the next highway transfer.
+    ///
+    /// Returns `(donor, recipient)`; the caller picks the gene copy in the donor.
+    pub fn select<R: rand::Rng>(
+        &self,
+        mode: DTLMode,
+        state: &SimulationState<'_>,
+        time: f64,
+        total_rate: f64,
+        rng: &mut R,
+    ) -> Option<(usize, usize)> {
+        let mut threshold = rng.gen::<f64>() * total_rate;
+        let mut last = None;
+        for highway in &self.highways {
+            if highway.rate <= 0.0 || !Self::is_active(highway, time) {
+                continue;
+            }
+            let weight = Self::highway_weight(mode, highway, state);
+            if weight <= 0.0 {
+                continue;
+            }
+            if threshold < weight {
+                return Some((highway.donor, highway.recipient));
+            }
+            threshold -= weight;
+            last = Some((highway.donor, highway.recipient));
+        }
+        last
+    }
+
+    /// Recipient weight multiplier for ordinary transfers from `donor` at `time`.
+    ///
+    /// Returns `None` when no highway from `donor` modifies recipient weights,
+    /// so callers can keep the unweighted fast path.
+    pub fn recipient_boosts(&self, donor: usize, time: f64) -> Option<Vec<(usize, f64)>> {
+        let boosts: Vec<(usize, f64)> = self
+            .highways
+            .iter()
+            .filter(|h| h.donor == donor && h.recipient_weight != 1.0 && Self::is_active(h, time))
+            .map(|h| (h.recipient, h.recipient_weight))
+            .collect();
+        if boosts.is_empty() {
+            None
+        } else {
+            Some(boosts)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.highways.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation_rejects_self_loops_and_bad_windows() {
+        assert!(validate_highways(&[TransferHighway::new(1, 1, 1.0, 1.0)], None).is_err());
+        assert!(validate_highways(
+            &[TransferHighway::new(0, 1, 1.0, 1.0).with_window(2.0, 1.0)],
+            None
+        )
+        .is_err());
+        assert!(validate_highways(&[TransferHighway::new(0, 5, 1.0, 1.0)], Some(3)).is_err());
+        assert!(validate_highways(&[TransferHighway::new(0, 1, -1.0, 1.0)], None).is_err());
+        assert!(validate_highways(&[TransferHighway::new(0, 1, 0.5, 3.0)], Some(3)).is_ok());
+    }
+
+    #[test]
+    fn windows_are_clipped_to_branch_overlap() {
+        let mut nodes = crate::newick::parse_newick("((A:1,B:1)AB:1,C:2)root:0;").unwrap();
+        let mut tree = nodes.pop().unwrap().to_flat_tree();
+        tree.assign_depths();
+        let idx = |name: &str| tree.nodes.iter().position(|n| n.name == name).unwrap();
+
+        let active = ActiveHighways::new(
+            &[
+                TransferHighway::new(idx("A"), idx("C"), 1.0, 1.0),
+                // AB and A never coexist
+                TransferHighway::new(idx("AB"), idx("A"), 1.0, 1.0),
+            ],
+            &tree,
+        );
+        assert_eq!(active.highways.len(), 1);
+        assert_eq!(active.highways[0].start_time, 1.0);
+        assert_eq!(active.highways[0].end_time, 2.0);
+        assert_eq!(active.next_breakpoint(0.5), 1.0);
+        assert_eq!(active.next_breakpoint(1.0), 2.0);
+        assert_eq!(active.next_breakpoint(2.0), f64::INFINITY);
+    }
+}
@@ -3,32 +3,76 @@
 // This module simulates gene tree evolution within a species tree using the DTL model.
 // Events: Speciation (S), Duplication (D), Transfer (T), Loss (L)
 
+mod abc;
+mod checkpoint;
+mod conditioning;
+pub(crate) mod contemporaneity;
+mod copy_number;
 mod event;
+mod forest;
 pub(crate) mod gillespie;
+mod heterogeneity;
+mod highways;
+mod kernel;
+mod observer;
+mod parallel;
 mod per_gene;
 mod per_species;
-mod state;
+pub(crate) mod state;
 pub(crate) mod stream;
-mod utils;
+mod sum_tree;
+mod summary;
+pub(crate) mod utils;
+mod wgd;
 
 // Re-export main types and functions
 use crate::error::RustreeError;
+use std::sync::Arc;
 
+pub use abc::{
+    abc_rejection, abc_smc, AbcParameters, AbcPriors, AbcResult, AbcSample, ForestStatistics,
+    Prior, SmcAbcOptions, N_FOREST_STATISTICS,
+};
+pub use checkpoint::{CheckpointRng, DtlCheckpoint, CHECKPOINT_FILE};
+pub use conditioning::{
+    Conditioning, ConditioningStats, SimulationCondition, DEFAULT_MAX_ATTEMPTS,
+};
+pub use copy_number::CopyNumberDependence;
 pub use event::DTLEvent;
+pub use forest::{
+    simulate_dtl_forest, simulate_dtl_per_species_forest, FamilyOrigin, FamilyOrigination,
+    OriginationForest,
+};
+pub use heterogeneity::{FamilyRateHeterogeneity, FamilyRates, RateMultiplier};
+pub use highways::TransferHighway;
+pub use kernel::{RecipientKernel, RecipientWeightMatrix};
+pub use observer::{
+    simulate_dtl_per_species_with_observer, simulate_dtl_with_observer, ObservedSimulation,
+    ObserverAction, SimulationObserver, SimulationView, SpeciesBoundary,
+};
+pub use parallel::{
+    family_seed, simulate_dtl_parallel, simulate_dtl_per_species_parallel, ConditionedFamily,
+    DtlParallelBatch,
+};
 pub use per_gene::{
-    simulate_dtl, simulate_dtl_batch, simulate_dtl_batch_with_branch_rates, simulate_dtl_iter,
+    simulate_dtl, simulate_dtl_batch, simulate_dtl_batch_with_branch_rates,
+    simulate_dtl_batch_with_rate_heterogeneity, simulate_dtl_iter,
     simulate_dtl_iter_with_branch_rates, simulate_dtl_iter_with_config,
-    simulate_dtl_with_branch_rates,
+    simulate_dtl_iter_with_rate_heterogeneity, simulate_dtl_with_branch_rates,
 };
 pub use per_species::{
     simulate_dtl_per_species, simulate_dtl_per_species_batch,
-    simulate_dtl_per_species_batch_with_branch_rates, simulate_dtl_per_species_iter,
+    simulate_dtl_per_species_batch_with_branch_rates,
+    simulate_dtl_per_species_batch_with_rate_heterogeneity, simulate_dtl_per_species_iter,
     simulate_dtl_per_species_iter_with_branch_rates, simulate_dtl_per_species_iter_with_config,
+    simulate_dtl_per_species_iter_with_rate_heterogeneity,
     simulate_dtl_per_species_with_branch_rates,
 };
-pub use stream::DtlSimIter;
+pub use stream::{ConditionedFamilies, DtlSimIter};
+pub use summary::{BranchEventCounts, ForestSummary};
 pub(crate) use utils::prepare_simulation;
 pub use utils::{count_events, count_extant_genes};
+pub use wgd::WholeGenomeDuplication;
 
 const ORIGINATION_SUM_TOLERANCE: f64 = 1e-8;
 
@@ -214,6 +258,19 @@ impl BranchDTLRates {
         Ok(())
     }
 
+    /// Return a copy with every branch's D/T/L rates multiplied by the given factors.
+    ///
+    /// Origination probabilities are unchanged.
+    pub fn scaled(&self, duplication: f64, transfer: f64, loss: f64) -> Result<Self, RustreeError> {
+        let scale = |rates: &[f64], factor: f64| rates.iter().map(|r| r * factor).collect();
+        Self::new(
+            scale(&self.lambda_d, duplication),
+            scale(&self.lambda_t, transfer),
+            scale(&self.lambda_l, loss),
+            self.origination_probability.clone(),
+        )
+    }
+
     #[inline]
     pub(crate) fn total_rate(&self, species_idx: usize) -> f64 {
         self.lambda_total[species_idx]
@@ -260,6 +317,14 @@ pub struct DTLConfig {
     pub replacement_transfer: Option<f64>,
     /// Optional full per-branch DTL rates and origination probabilities
     pub branch_rates: Option<BranchDTLRates>,
+    /// Optional transfer highways with elevated donor -> recipient activity
+    pub transfer_highways: Option<Vec<TransferHighway>>,
+    /// Optional user-supplied recipient weights consulted at transfer time
+    pub recipient_kernel: Option<Arc<dyn RecipientKernel>>,
+    /// Optional copy-number- and age-dependent loss and transfer acceptance
+    pub copy_number_dependence: Option<CopyNumberDependence>,
+    /// Optional whole-genome duplications applied to every simulated family
+    pub whole_genome_duplications: Option<Vec<WholeGenomeDuplication>>,
 }
 
 impl DTLConfig {
@@ -278,6 +343,10 @@ impl DTLConfig {
             transfer_alpha,
             replacement_transfer,
             branch_rates: None,
+            transfer_highways: None,
+            recipient_kernel: None,
+            copy_number_dependence: None,
+            whole_genome_duplications: None,
         };
         config.validate()?;
         Ok(config)
@@ -296,6 +365,10 @@ impl DTLConfig {
             transfer_alpha,
             replacement_transfer,
             branch_rates: Some(branch_rates),
+            transfer_highways: None,
+            recipient_kernel: None,
+            copy_number_dependence: None,
+            whole_genome_duplications: None,
         };
         config.validate()?;
         Ok(config)
@@ -327,15 +400,83 @@ impl DTLConfig {
             branch_rates.validate()?;
         }
 
+        if let Some(highways) = &self.transfer_highways {
+            highways::validate_highways(highways, None)?;
+        }
+
+        if let Some(dependence) = &self.copy_number_dependence {
+            dependence.validate()?;
+        }
+
+        if let Some(wgds) = &self.whole_genome_duplications {
+            wgd::validate_wgds(wgds, None)?;
+        }
+
         Ok(())
     }
 
+    /// Add transfer highways to this configuration.
+    ///
+    /// Highway transfers are recorded as ordinary [`DTLEvent::Transfer`] events.
+    pub fn with_transfer_highways(
+        mut self,
+        highways: Vec<TransferHighway>,
+    ) -> Result<Self, RustreeError> {
+        highways::validate_highways(&highways, None)?;
+        self.transfer_highways = Some(highways);
+        Ok(self)
+    }
+
+    /// Weight transfer recipients with a user-supplied kernel.
+    ///
+    /// Kernel weights multiply the assortative (`transfer_alpha`) weights and
+    /// highway recipient boosts when those are also configured.
+    pub fn with_recipient_kernel(mut self, kernel: Arc<dyn RecipientKernel>) -> Self {
+        self.recipient_kernel = Some(kernel);
+        self
+    }
+
+    /// Make loss and transfer acceptance depend on the gene content of species.
+    ///
+    /// Extra losses are recorded as ordinary [`DTLEvent::Loss`] events; rejected
+    /// transfers leave no trace.
+    pub fn with_copy_number_dependence(
+        mut self,
+        dependence: CopyNumberDependence,
+    ) -> Result<Self, RustreeError> {
+        dependence.validate()?;
+        self.copy_number_dependence = Some(dependence);
+        Ok(self)
+    }
+
+    /// Place whole-genome duplications on species branches.
+    ///
+    /// Every copy present in the species at the WGD time duplicates; the
+    /// duplications are recorded as [`DTLEvent::Duplication`] with `wgd` set.
+    pub fn with_whole_genome_duplications(
+        mut self,
+        wgds: Vec<WholeGenomeDuplication>,
+    ) -> Result<Self, RustreeError> {
+        wgd::validate_wgds(&wgds, None)?;
+        self.whole_genome_duplications = Some(wgds);
+        Ok(self)
+    }
+
     /// Validate branch-table shape against a concrete species tree.
     pub fn validate_for_tree(&self, node_count: usize) -> Result<(), RustreeError> {
         self.validate()?;
         if let Some(branch_rates) = &self.branch_rates {
             branch_rates.validate_for_tree(node_count)?;
         }
+        if let Some(highways) = &self.transfer_highways {
+            highways::validate_highways(highways, Some(node_count))?;
+        }
+        if let Some(kernel) = &self.recipient_kernel {
+            kernel.validate_for_tree(node_count)?;
+        }
+        if let Some(wgds) = &self.whole_genome_duplications {
+            wgd::validate_wgds(wgds, Some(node_count))?;
+        }
         Ok(())
     }
 
@@ -0,0 +1,195 @@
+// Observer hooks for the Gillespie DTL loop
+//
+// An observer is notified of every recorded gene-level event and of every
+// species-tree boundary (speciation, extinction, sampled leaf) as the
+// simulation runs, with a read-only view of the current copy numbers. It can
+// collect custom statistics without keeping trees around, and can stop a
+// simulation early.
+
+use crate::bd::BDEvent;
+use crate::error::RustreeError;
+use crate::node::{FlatTree, RecTree};
+use rand::Rng;
+
+use super::event::DTLEvent;
+use super::gillespie::{simulate_dtl_gillespie_observed, DTLMode};
+use super::state::SimulationState;
+use super::utils::prepare_simulation;
+use super::DTLConfig;
+
+/// What the simulation should do after an observer callback.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ObserverAction {
+    #[default]
+    Continue,
+    /// Stop the simulation; it returns [`ObservedSimulation::Aborted`].
+    Abort,
+}
+
+/// A species-tree node reached by the simulation clock.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpeciesBoundary {
+    pub species: usize,
+    pub time: f64,
+    pub kind: BDEvent,
+}
+
+/// Read-only view of the simulation passed to observers.
+pub struct SimulationView<'s, 'a> {
+    state: &'s SimulationState<'a>,
+    time: f64,
+}
+
+impl<'s, 'a> SimulationView<'s, 'a> {
+    pub(crate) fn new(state: &'s SimulationState<'a>, time: f64) -> Self {
+        Self { state, time }
+    }
+
+    /// Current simulation time.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn species_tree(&self) -> &FlatTree {
+        self.state.species_tree
+    }
+
+    /// Total number of alive gene copies.
+    pub fn total_copies(&self) -> usize {
+        self.state.total_gene_copies()
+    }
+
+    /// Number of alive gene copies in `species`.
+    pub fn copies_in_species(&self, species: usize) -> usize {
+        self.state.copies_in_species(species)
+    }
+
+    /// `(species, copies)` for every species currently holding at least one copy.
+    pub fn copy_counts(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.state
+            .genes_per_species
+            .iter()
+            .filter(|(_, genes)| !genes.is_empty())
+            .map(|(&species, genes)| (species, genes.len()))
+    }
+}
+
+/// Callbacks invoked by the DTL simulation loop.
+///
+/// Both methods default to doing nothing, so implementors override only what
+/// they need.
+pub trait SimulationObserver {
+    /// Called once for every event appended to the event log, in order.
+    ///
+    /// Events are reported after the simulation step that recorded them, and
+    /// `view` shows the state after the whole step. A step that records
+    /// several events (a species boundary speciating every copy, a
+    /// whole-genome duplication) reports each of them with that same view.
+    fn on_event(&mut self, _event: &DTLEvent, _view: &SimulationView<'_, '_>) -> ObserverAction {
+        ObserverAction::Continue
+    }
+
+    /// Called when the clock reaches the end of a species branch, before its
+    /// gene copies are speciated, lost or sampled, so
+    /// `view.copies_in_species(boundary.species)` is the copy number at the
+    /// end of the branch.
+    fn on_species_boundary(
+        &mut self,
+        _boundary: &SpeciesBoundary,
+        _view: &SimulationView<'_, '_>,
+    ) -> ObserverAction {
+        ObserverAction::Continue
+    }
+}
+
+/// Observer that ignores everything.
+pub(crate) struct NoObserver;
+
+impl SimulationObserver for NoObserver {}
+
+/// Outcome of an observed simulation.
+#[derive(Clone, Debug)]
+pub enum ObservedSimulation {
+    Completed {
+        rec_tree: RecTree,
+        events: Vec<DTLEvent>,
+    },
+    /// An observer requested an abort at `time`; `events` holds the log so far.
+    Aborted { time: f64, events: Vec<DTLEvent> },
+}
+
+impl ObservedSimulation {
+    pub fn is_aborted(&self) -> bool {
+        matches!(self, ObservedSimulation::Aborted { .. })
+    }
+
+    /// The reconciled tree and events, or an error if the run was aborted.
+    pub fn into_completed(self) -> Result<(RecTree, Vec<DTLEvent>), RustreeError> {
+        match self {
+            ObservedSimulation::Completed { rec_tree, events } => Ok((rec_tree, events)),
+            ObservedSimulation::Aborted { time, .. } => Err(RustreeError::Simulation(format!(
+                "simulation was aborted by an observer at time {time}"
+            ))),
+        }
+    }
+}
+
+/// Runs one per-gene-copy DTL simulation, reporting progress to `observer`.
+pub fn simulate_dtl_with_observer<R: Rng>(
+    species_tree: &FlatTree,
+    origin_species: usize,
+    config: &DTLConfig,
+    observer: &mut dyn SimulationObserver,
+    rng: &mut R,
+) -> Result<ObservedSimulation, RustreeError> {
+    simulate_observed(
+        DTLMode::PerGene,
+        species_tree,
+        origin_species,
+        config,
+        observer,
+        rng,
+    )
+}
+
+/// Runs one per-species DTL simulation, reporting progress to `observer`.
+pub fn simulate_dtl_per_species_with_observer<R: Rng>(
+    species_tree: &FlatTree,
+    origin_species: usize,
+    config: &DTLConfig,
+    observer: &mut dyn SimulationObserver,
+    rng: &mut R,
+) -> Result<ObservedSimulation, RustreeError> {
+    simulate_observed(
+        DTLMode::PerSpecies,
+        species_tree,
+        origin_species,
+        config,
+        observer,
+        rng,
+    )
+}
+
+fn simulate_observed<R: Rng>(
+    mode: DTLMode,
+    species_tree: &FlatTree,
+    origin_species: usize,
+    config: &DTLConfig,
+    observer: &mut dyn SimulationObserver,
+    rng: &mut R,
+) -> Result<ObservedSimulation, RustreeError> {
+    let prepared = prepare_simulation(species_tree, origin_species, config)?;
+    simulate_dtl_gillespie_observed(
+        mode,
+        &prepared.species_tree,
+        &prepared.species_events,
+        &prepared.depths,
+        &prepared.contemporaneity,
+        prepared.lca_depths.as_ref(),
+        origin_species,
+        config,
+        prepared.branch_totals.as_ref(),
+        observer,
+        rng,
+    )
+}
@@ -10,8 +10,8 @@ use rand::Rng;
 
 use super::event::DTLEvent;
 use super::gillespie::DTLMode;
-use super::stream::DtlSimIter;
-use super::{prepare_simulation, BranchDTLRates, DTLConfig};
+use super::stream::{DtlSimIter, FamilySimulator};
+use super::{prepare_simulation, BranchDTLRates, DTLConfig, FamilyRateHeterogeneity};
 
 /// Returns a lazy iterator that generates gene trees one at a time.
 ///
@@ -80,20 +80,19 @@ pub fn simulate_dtl_iter_with_config<'a, R: Rng>(
 ) -> Result<DtlSimIter<'a, R>, RustreeError> {
     let prepared = prepare_simulation(species_tree, origin_species, &config)?;
 
-    Ok(DtlSimIter::new(
+    let simulator = FamilySimulator::new(
         DTLMode::PerGene,
         prepared.species_tree,
         prepared.species_events,
         prepared.depths,
         prepared.contemporaneity,
         prepared.lca_depths,
-        prepared.runtime,
+        prepared.branch_totals,
         origin_species,
         config,
-        n_simulations,
         require_extant,
-        rng,
-    ))
+    );
+    Ok(DtlSimIter::new(simulator, n_simulations, rng))
 }
 
 /// Returns a lazy iterator using full per-branch DTL rates and origination probabilities.
@@ -218,3 +217,49 @@ pub fn simulate_dtl_batch_with_branch_rates<R: Rng>(
     )?
     .collect_all()
 }
+
+/// Returns a lazy per-gene iterator where each family draws its own D/T/L rates.
+///
+/// Rates are drawn from `heterogeneity` around `config` and recorded in
+/// `RecTree::family_rates`.
+pub fn simulate_dtl_iter_with_rate_heterogeneity<'a, R: Rng>(
+    species_tree: &FlatTree,
+    origin_species: usize,
+    config: DTLConfig,
+    heterogeneity: FamilyRateHeterogeneity,
+    n_simulations: usize,
+    require_extant: bool,
+    rng: &'a mut R,
+) -> Result<DtlSimIter<'a, R>, RustreeError> {
+    simulate_dtl_iter_with_config(
+        species_tree,
+        origin_species,
+        config,
+        n_simulations,
+        require_extant,
+        rng,
+    )?
+    .with_rate_heterogeneity(heterogeneity)
+}
+
+/// Simulates multiple gene trees (per-gene model) with per-family rate heterogeneity.
+pub fn simulate_dtl_batch_with_rate_heterogeneity<R: Rng>(
+    species_tree: &FlatTree,
+    origin_species: usize,
+    config: DTLConfig,
+    heterogeneity: FamilyRateHeterogeneity,
+    n_simulations: usize,
+    require_extant: bool,
+    rng: &mut R,
+) -> Result<(Vec<RecTree>, Vec<Vec<DTLEvent>>), RustreeError> {
+    simulate_dtl_iter_with_rate_heterogeneity(
+        species_tree,
+        origin_species,
+        config,
+        heterogeneity,
+        n_simulations,
+        require_extant,
+        rng,
+    )?
+    .collect_all()
+}
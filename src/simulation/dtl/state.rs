@@ -1,6 +1,7 @@
 // Unified simulation state for both per-gene and per-species DTL models
 
 use super::event::DTLEvent;
+use super::sum_tree::SumTree;
 use crate::node::{Event, FlatNode, FlatTree};
 use rand::Rng;
 use std::collections::BTreeMap;
@@ -22,12 +23,37 @@ pub(crate) struct SimulationState<'a> {
     /// Incrementally maintained total count of alive gene copies across all species.
     /// Avoids O(n_species) summation on every query.
     total_gene_count: usize,
+    /// Incrementally maintained sum of `k × (k − 1)` over species holding `k`
+    /// alive copies, the weight of the dosage-balance loss channel.
+    paralog_pairs: usize,
+    /// `k × (k − 1)` per species, for O(log n) selection of a dosage-balance
+    /// loss. Built only by [`track_paralog_pairs`](Self::track_paralog_pairs).
+    paralog_weights: Option<SumTree<usize>>,
+    /// Alive copies per species, indexed by species node, for O(log n) copy
+    /// selection. Built only by [`track_copy_selection`](Self::track_copy_selection),
+    /// so states that never draw a copy at random skip the O(n_species) allocation.
+    copy_counts: Option<SumTree<usize>>,
+    /// Branch total rate × alive copies per species. Present only when copy
+    /// selection is tracked in branch-rate mode.
+    copy_weights: Option<SumTree<f64>>,
+    /// Position of each alive gene copy in its species' list in `genes_per_species`,
+    /// so removals are O(1).
+    gene_slot: Vec<usize>,
     /// Optional branch-specific total DTL rates, indexed by species node.
     /// Present only when branch-rate mode is active.
     branch_total_rates: Option<&'a [f64]>,
     /// Incrementally maintained sum of branch total rates over alive gene copies.
     /// Used only when branch_total_rates is present.
     total_gene_weighted_rate: f64,
+    /// Time of the duplication that created each gene lineage, inherited through
+    /// speciations and transfers. `None` for lineages with no duplication ancestry.
+    pub duplication_time: Vec<Option<f64>>,
+    /// Gene copies with a duplication ancestry, tracked only when
+    /// duplicate-age-dependent rates are active. May contain dead copies,
+    /// which callers discard lazily.
+    pub young_duplicates: Option<Vec<usize>>,
+    /// Gene nodes that duplicated as part of a whole-genome duplication.
+    pub wgd_nodes: Vec<usize>,
 }
 
 impl<'a> SimulationState<'a> {
@@ -44,18 +70,110 @@ impl<'a> SimulationState<'a> {
             events: Vec::with_capacity(capacity),
             genes_per_species: BTreeMap::new(),
             total_gene_count: 0,
+            paralog_pairs: 0,
+            paralog_weights: None,
+            copy_counts: None,
+            copy_weights: None,
+            gene_slot: Vec::with_capacity(capacity),
             branch_total_rates,
             total_gene_weighted_rate: 0.0,
+            duplication_time: Vec::with_capacity(capacity),
+            young_duplicates: None,
+            wgd_nodes: Vec::new(),
         }
     }
 
+    /// Build the per-species selection trees used by
+    /// [`random_gene_copy`](Self::random_gene_copy) and
+    /// [`random_gene_copy_weighted`](Self::random_gene_copy_weighted).
+    pub fn track_copy_selection(&mut self) {
+        if self.copy_counts.is_some() {
+            return;
+        }
+        let n_species = self.species_tree.nodes.len();
+        self.copy_counts = Some(SumTree::new(n_species));
+        self.copy_weights = self.branch_total_rates.map(|_| SumTree::new(n_species));
+        let species: Vec<usize> = self.genes_per_species.keys().copied().collect();
+        for species_idx in species {
+            self.refresh_species_weight(species_idx);
+        }
+    }
+
+    /// Build the per-species tree of paralog pairs used by
+    /// [`random_paralog_copy`](Self::random_paralog_copy).
+    pub fn track_paralog_pairs(&mut self) {
+        if self.paralog_weights.is_some() {
+            return;
+        }
+        let mut weights = SumTree::new(self.species_tree.nodes.len());
+        for (&species, genes) in &self.genes_per_species {
+            weights.set(species, ordered_pairs(genes.len()));
+        }
+        self.paralog_weights = Some(weights);
+    }
+
+    /// Start tracking duplicate-born copies in `young_duplicates`.
+    pub fn track_duplicate_ages(&mut self) {
+        self.young_duplicates.get_or_insert_with(Vec::new);
+    }
+
+    /// Copy `parent`'s duplication time to `child`.
+    fn inherit_duplication_time(&mut self, parent: usize, child: usize) {
+        if let Some(time) = self.duplication_time[parent] {
+            self.mark_duplicate(child, time);
+        }
+    }
+
+    fn mark_duplicate(&mut self, gene_idx: usize, time: f64) {
+        self.duplication_time[gene_idx] = Some(time);
+        if let Some(young) = self.young_duplicates.as_mut() {
+            young.push(gene_idx);
+        }
+    }
+
+    /// Whether `gene_idx` is a currently alive copy (no children, not lost, not a leaf).
+    pub fn is_alive_copy(&self, gene_idx: usize) -> bool {
+        self.gene_nodes[gene_idx].left_child.is_none()
+            && !matches!(self.event_mapping[gene_idx], Event::Loss | Event::Leaf)
+    }
+
+    /// Number of alive gene copies in a species.
+    pub fn copies_in_species(&self, species_idx: usize) -> usize {
+        self.genes_per_species
+            .get(&species_idx)
+            .map_or(0, |genes| genes.len())
+    }
+
     fn cached_branch_rate(&self, species_idx: usize) -> Option<f64> {
         self.branch_total_rates
             .as_ref()
             .map(|rates| rates.get(species_idx).copied().unwrap_or(0.0))
     }
 
+    /// Sync the selection trees with the current copy count of `species_idx`.
+    fn refresh_species_weight(&mut self, species_idx: usize) {
+        if self.copy_counts.is_none() && self.paralog_weights.is_none() {
+            return;
+        }
+        let copies = self.copies_in_species(species_idx);
+        if let Some(pairs) = self.paralog_weights.as_mut() {
+            pairs.set(species_idx, ordered_pairs(copies));
+        }
+        let Some(counts) = self.copy_counts.as_mut() else {
+            return;
+        };
+        counts.set(species_idx, copies);
+        if let Some(rate) = self.cached_branch_rate(species_idx) {
+            if let Some(weights) = self.copy_weights.as_mut() {
+                weights.set(species_idx, rate * copies as f64);
+            }
+        }
+    }
+
     fn increment_gene_tracking(&mut self, species_idx: usize, count: usize) {
+        self.refresh_species_weight(species_idx);
+        let copies = self.copies_in_species(species_idx);
+        self.paralog_pairs += ordered_pairs(copies) - ordered_pairs(copies - count);
         self.total_gene_count += count;
         if let Some(rate) = self.cached_branch_rate(species_idx) {
             self.total_gene_weighted_rate += rate * count as f64;
@@ -63,6 +181,9 @@ impl<'a> SimulationState<'a> {
     }
 
     fn decrement_gene_tracking(&mut self, species_idx: usize, count: usize) {
+        self.refresh_species_weight(species_idx);
+        let copies = self.copies_in_species(species_idx);
+        self.paralog_pairs -= ordered_pairs(copies + count) - ordered_pairs(copies);
         self.total_gene_count -= count;
         if let Some(rate) = self.cached_branch_rate(species_idx) {
             if self.total_gene_count == 0 {
@@ -93,6 +214,8 @@ impl<'a> SimulationState<'a> {
         });
         self.node_mapping.push(Some(species_idx));
         self.event_mapping.push(event);
+        self.duplication_time.push(None);
+        self.gene_slot.push(0);
         idx
     }
 
@@ -107,18 +230,26 @@ impl<'a> SimulationState<'a> {
 
     /// Add gene to a species (per-species mode only, no-op otherwise)
     pub fn add_gene_to_species(&mut self, species_idx: usize, gene_idx: usize) {
-        self.genes_per_species
-            .entry(species_idx)
-            .or_default()
-            .push(gene_idx);
+        let genes = self.genes_per_species.entry(species_idx).or_default();
+        genes.push(gene_idx);
+        self.gene_slot[gene_idx] = genes.len() - 1;
         self.increment_gene_tracking(species_idx, 1);
     }
 
     /// Remove gene from a species (per-species mode only, no-op otherwise)
     fn remove_gene_from_species(&mut self, species_idx: usize, gene_idx: usize) {
         let removed = if let Some(genes) = self.genes_per_species.get_mut(&species_idx) {
-            if let Some(pos) = genes.iter().position(|&g| g == gene_idx) {
+            let slot = self.gene_slot[gene_idx];
+            let pos = if genes.get(slot) == Some(&gene_idx) {
+                Some(slot)
+            } else {
+                genes.iter().position(|&g| g == gene_idx)
+            };
+            if let Some(pos) = pos {
                 genes.swap_remove(pos);
+                if let Some(&moved) = genes.get(pos) {
+                    self.gene_slot[moved] = pos;
+                }
                 true
             } else {
                 false
@@ -149,6 +280,39 @@ impl<'a> SimulationState<'a> {
         parent_idx: usize,
         species_idx: usize,
         event_time: f64,
+    ) -> (usize, usize) {
+        self.duplicate(parent_idx, species_idx, event_time, false)
+    }
+
+    /// Handles a whole-genome duplication: every copy in the species duplicates.
+    ///
+    /// Each duplicate is kept with probability `retention`; otherwise its second
+    /// child is lost at the same time, leaving a duplication-loss pair.
+    pub fn handle_whole_genome_duplication<R: Rng>(
+        &mut self,
+        species_idx: usize,
+        event_time: f64,
+        retention: f64,
+        rng: &mut R,
+    ) {
+        let Some(genes) = self.genes_per_species.get(&species_idx).cloned() else {
+            return;
+        };
+        for gene_idx in genes {
+            self.wgd_nodes.push(gene_idx);
+            let (_, child2_idx) = self.duplicate(gene_idx, species_idx, event_time, true);
+            if retention < 1.0 && rng.gen::<f64>() >= retention {
+                self.handle_loss(child2_idx, species_idx, event_time);
+            }
+        }
+    }
+
+    fn duplicate(
+        &mut self,
+        parent_idx: usize,
+        species_idx: usize,
+        event_time: f64,
+        wgd: bool,
     ) -> (usize, usize) {
         self.update_gene_to_time(parent_idx, event_time);
         self.remove_gene_from_species(species_idx, parent_idx);
@@ -169,6 +333,8 @@ impl<'a> SimulationState<'a> {
 
         self.gene_nodes[parent_idx].left_child = Some(child1_idx);
         self.gene_nodes[parent_idx].right_child = Some(child2_idx);
+        self.mark_duplicate(child1_idx, event_time);
+        self.mark_duplicate(child2_idx, event_time);
 
         self.add_gene_to_species(species_idx, child1_idx);
         self.add_gene_to_species(species_idx, child2_idx);
@@ -179,6 +345,7 @@ impl<'a> SimulationState<'a> {
             species_id: species_idx,
             child1: child1_idx,
             child2: child2_idx,
+            wgd,
         });
 
         (child1_idx, child2_idx)
@@ -207,6 +374,8 @@ impl<'a> SimulationState<'a> {
 
         self.gene_nodes[parent_idx].left_child = Some(donor_child_idx);
         self.gene_nodes[parent_idx].right_child = Some(recipient_child_idx);
+        self.inherit_duplication_time(parent_idx, donor_child_idx);
+        self.inherit_duplication_time(parent_idx, recipient_child_idx);
 
         self.add_gene_to_species(donor_species, donor_child_idx);
         self.add_gene_to_species(recipient_species, recipient_child_idx);
@@ -248,16 +417,57 @@ impl<'a> SimulationState<'a> {
         None
     }
 
+    /// Sum of `k × (k − 1)` over species holding `k` alive copies.
+    pub fn paralog_pairs(&self) -> usize {
+        self.paralog_pairs
+    }
+
+    /// Picks a species with probability proportional to its `k × (k − 1)`
+    /// paralog pairs, then a uniform copy in it. `target` is uniform in
+    /// `0..paralog_pairs()`.
+    ///
+    /// Uses the paralog tree when tracked, otherwise scans species in order;
+    /// both select the same species.
+    pub fn random_paralog_copy<R: Rng>(
+        &self,
+        mut target: usize,
+        rng: &mut R,
+    ) -> Option<(usize, usize)> {
+        let species = match self.paralog_weights.as_ref() {
+            Some(weights) => weights.find(target)?.0,
+            None => self
+                .genes_per_species
+                .iter()
+                .find_map(|(&species, genes)| {
+                    let weight = ordered_pairs(genes.len());
+                    if target < weight {
+                        Some(species)
+                    } else {
+                        target -= weight;
+                        None
+                    }
+                })?,
+        };
+        let genes = self.genes_per_species.get(&species)?;
+        Some((species, genes[rng.gen_range(0..genes.len())]))
+    }
+
     /// Picks a random gene copy from all alive copies across all species.
     /// Used by per-gene Gillespie to select which copy experiences the next event.
-    /// Uses the cached total_gene_count to avoid a counting pass, then does a
-    /// single iteration to find the selected copy.
+    /// With copy selection tracked, descends the per-species count tree in
+    /// O(log n); otherwise scans `genes_per_species` in species order. Both
+    /// select the same copy.
     pub fn random_gene_copy<R: Rng>(&self, rng: &mut R) -> Option<(usize, usize)> {
         if self.total_gene_count == 0 {
             return None;
         }
         let mut target = rng.gen_range(0..self.total_gene_count);
-        for (&species, genes) in self.genes_per_species.iter() {
+        if let Some(counts) = self.copy_counts.as_ref() {
+            let (species, offset) = counts.find(target)?;
+            let genes = self.genes_per_species.get(&species)?;
+            return Some((species, genes[offset]));
+        }
+        for (&species, genes) in &self.genes_per_species {
             if target < genes.len() {
                 return Some((species, genes[target]));
             }
@@ -295,7 +505,12 @@ impl<'a> SimulationState<'a> {
             return None;
         }
 
-        let mut threshold = rng.gen::<f64>() * total_rate;
+        let threshold = rng.gen::<f64>() * total_rate;
+        if let Some(weights) = self.copy_weights.as_ref() {
+            return self.weighted_copy_from_tree(weights, threshold);
+        }
+
+        let mut threshold = threshold;
         let mut last_eligible = None;
 
         for (&species, genes) in self.genes_per_species.iter() {
@@ -317,6 +532,26 @@ impl<'a> SimulationState<'a> {
         last_eligible
     }
 
+    /// O(log n) weighted selection over the cached per-species weights.
+    ///
+    /// Falls back to the last copy of the last eligible species when rounding
+    /// puts `threshold` past the tree total, as the linear scan does.
+    fn weighted_copy_from_tree(
+        &self,
+        weights: &SumTree<f64>,
+        threshold: f64,
+    ) -> Option<(usize, usize)> {
+        if let Some((species, rest)) = weights.find(threshold) {
+            let genes = self.genes_per_species.get(&species)?;
+            let rate = self.cached_branch_rate(species)?;
+            let idx = ((rest / rate).floor() as usize).min(genes.len() - 1);
+            return Some((species, genes[idx]));
+        }
+        let species = weights.last_nonzero()?;
+        let gene = *self.genes_per_species.get(&species)?.last()?;
+        Some((species, gene))
+    }
+
     /// Returns the total number of alive gene copies across all species.
     /// O(1) via the incrementally maintained counter.
     pub fn total_gene_copies(&self) -> usize {
@@ -362,6 +597,8 @@ impl<'a> SimulationState<'a> {
 
         self.gene_nodes[parent_idx].left_child = Some(left_gene_idx);
         self.gene_nodes[parent_idx].right_child = Some(right_gene_idx);
+        self.inherit_duplication_time(parent_idx, left_gene_idx);
+        self.inherit_duplication_time(parent_idx, right_gene_idx);
 
         self.add_gene_to_species(left_species, left_gene_idx);
         self.add_gene_to_species(right_species, right_gene_idx);
@@ -377,3 +614,52 @@ impl<'a> SimulationState<'a> {
         (left_gene_idx, right_gene_idx)
     }
 }
+
+/// Ordered paralog pairs among `copies` copies of one species.
+fn ordered_pairs(copies: usize) -> usize {
+    copies * copies.saturating_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn copy_selection_trees_match_the_scan() {
+        let mut nodes = crate::newick::parse_newick("((A:1,B:1)AB:1,C:2)root:0;").unwrap();
+        let tree = nodes.pop().unwrap().to_flat_tree();
+        let mut scan = SimulationState::with_branch_total_rates(8, &tree, None);
+        for species in [0, 2, 2, 4, 1, 2] {
+            let gene = scan.create_gene_node(None, species, Event::Speciation, 0.0);
+            scan.add_gene_to_species(species, gene);
+        }
+        scan.handle_loss(1, 2, 0.5);
+
+        // Tracking may start after copies were added.
+        let mut tracked = SimulationState::with_branch_total_rates(8, &tree, None);
+        for species in [0, 2, 2, 4, 1, 2] {
+            let gene = tracked.create_gene_node(None, species, Event::Speciation, 0.0);
+            tracked.add_gene_to_species(species, gene);
+        }
+        tracked.track_copy_selection();
+        tracked.track_paralog_pairs();
+        tracked.handle_loss(1, 2, 0.5);
+
+        let (mut rng_scan, mut rng_tracked) = (StdRng::seed_from_u64(3), StdRng::seed_from_u64(3));
+        for _ in 0..50 {
+            assert_eq!(
+                scan.random_gene_copy(&mut rng_scan),
+                tracked.random_gene_copy(&mut rng_tracked)
+            );
+        }
+        assert_eq!(scan.paralog_pairs(), tracked.paralog_pairs());
+        for target in 0..scan.paralog_pairs() {
+            assert_eq!(
+                scan.random_paralog_copy(target, &mut rng_scan),
+                tracked.random_paralog_copy(target, &mut rng_tracked)
+            );
+        }
+    }
+}
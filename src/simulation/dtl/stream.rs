@@ -3,21 +3,27 @@
 //
 // Provides DtlSimIter, a lazy iterator that generates one gene tree at a time
 // without accumulating all trees in memory. Supports chainable convenience
-// methods like .save_xml(), .save_newick(), and .collect_all().
+// methods like .save_xml(), .save_newick(), .summarize() and .collect_all().
 
 use crate::bd::TreeEvent;
 use crate::error::RustreeError;
 use crate::node::{FlatTree, RecTree};
 use rand::Rng;
+use std::path::Path;
 use std::sync::Arc;
 
+use super::checkpoint::{CheckpointRng, DtlCheckpoint, CHECKPOINT_FILE};
+use super::conditioning::{Conditioning, ConditioningStats};
+use super::contemporaneity::Contemporaneity;
 use super::event::DTLEvent;
-use super::gillespie::{simulate_dtl_gillespie, DTLMode};
-use super::utils::count_extant_genes;
-pub(crate) use super::utils::{simulate_dtl_gillespie_prepared, PreparedDtlRuntime};
+use super::gillespie::{simulate_dtl_gillespie, BranchRateTotals, DTLMode};
+use super::heterogeneity::FamilyRateHeterogeneity;
+use super::summary::ForestSummary;
+pub(crate) use super::utils::LcaDepths;
 use super::DTLConfig;
 
-pub(crate) const MAX_EXTANT_ATTEMPTS: usize = 10_000;
+/// Trees, event logs and conditioning statistics from [`DtlSimIter::collect_conditioned`].
+pub type ConditionedFamilies = (Vec<RecTree>, Vec<Vec<DTLEvent>>, ConditioningStats);
 
 /// Lazy iterator that generates DTL-simulated gene trees one at a time.
 ///
@@ -43,57 +49,243 @@ pub(crate) const MAX_EXTANT_ATTEMPTS: usize = 10_000;
 ///     .unwrap();
 /// ```
 pub struct DtlSimIter<'a, R: Rng> {
+    simulator: FamilySimulator,
+    n_simulations: usize,
+    // Mutable state
+    rng: &'a mut R,
+    completed: usize,
+    stats: ConditioningStats,
+}
+
+/// Pre-computed state and parameters shared by every family of a batch.
+///
+/// Holds no RNG, so the sequential iterator and the parallel batch drive it
+/// with their own streams.
+pub(crate) struct FamilySimulator {
     // Pre-computed state (owned)
     species_arc: Arc<FlatTree>,
     species_events: Vec<TreeEvent>,
     depths: Vec<f64>,
-    contemporaneity: Vec<Vec<usize>>,
-    lca_depths: Option<Vec<Vec<f64>>>,
-    runtime: PreparedDtlRuntime,
+    contemporaneity: Contemporaneity,
+    lca_depths: Option<LcaDepths>,
+    branch_totals: Option<BranchRateTotals>,
     // Simulation parameters
     origin_species: usize,
     config: DTLConfig,
-    n_simulations: usize,
-    require_extant: bool,
+    conditioning: Conditioning,
     mode: DTLMode,
-    // Mutable state
-    rng: &'a mut R,
-    completed: usize,
+    rate_heterogeneity: Option<FamilyRateHeterogeneity>,
 }
 
-impl<'a, R: Rng> DtlSimIter<'a, R> {
-    /// Creates a new DtlSimIter with pre-computed state.
+impl FamilySimulator {
     pub(crate) fn new(
         mode: DTLMode,
         species_arc: Arc<FlatTree>,
         species_events: Vec<TreeEvent>,
         depths: Vec<f64>,
-        contemporaneity: Vec<Vec<usize>>,
-        lca_depths: Option<Vec<Vec<f64>>>,
-        runtime: PreparedDtlRuntime,
+        contemporaneity: Contemporaneity,
+        lca_depths: Option<LcaDepths>,
+        branch_totals: Option<BranchRateTotals>,
         origin_species: usize,
         config: DTLConfig,
-        n_simulations: usize,
         require_extant: bool,
-        rng: &'a mut R,
     ) -> Self {
-        DtlSimIter {
+        FamilySimulator {
             species_arc,
             species_events,
             depths,
             contemporaneity,
             lca_depths,
-            runtime,
+            branch_totals,
             origin_species,
             config,
-            n_simulations,
-            require_extant,
+            conditioning: if require_extant {
+                Conditioning::require_extant()
+            } else {
+                Conditioning::new()
+            },
             mode,
+            rate_heterogeneity: None,
+        }
+    }
+
+    #[cfg(feature = "python")]
+    pub(crate) fn species_tree(&self) -> &Arc<FlatTree> {
+        &self.species_arc
+    }
+
+    #[cfg(feature = "python")]
+    pub(crate) fn mode(&self) -> DTLMode {
+        self.mode
+    }
+
+    #[cfg(feature = "python")]
+    pub(crate) fn config(&self) -> &DTLConfig {
+        &self.config
+    }
+
+    pub(crate) fn set_conditioning(
+        &mut self,
+        conditioning: Conditioning,
+    ) -> Result<(), RustreeError> {
+        conditioning.validate_for_tree(Some(&self.species_arc))?;
+        self.conditioning = conditioning;
+        Ok(())
+    }
+
+    pub(crate) fn set_rate_heterogeneity(
+        &mut self,
+        heterogeneity: FamilyRateHeterogeneity,
+    ) -> Result<(), RustreeError> {
+        heterogeneity.validate()?;
+        self.rate_heterogeneity = Some(heterogeneity);
+        Ok(())
+    }
+
+    /// Everything that determines the simulated families, as labelled settings
+    /// stored in checkpoints.
+    pub(crate) fn describe(&self) -> Result<Vec<(String, String)>, RustreeError> {
+        if let Some(kernel) = &self.config.recipient_kernel {
+            if kernel.fingerprint().is_none() {
+                return Err(RustreeError::Validation(
+                    "simulations with a recipient kernel that has no fingerprint cannot be checkpointed"
+                        .to_string(),
+                ));
+            }
+        }
+        if self.conditioning.has_predicate() {
+            return Err(RustreeError::Validation(
+                "simulations conditioned on a custom predicate cannot be checkpointed".to_string(),
+            ));
+        }
+        let settings = [
+            ("mode", format!("{:?}", self.mode)),
+            ("species_tree", self.species_arc.to_newick()?),
+            ("origin_species", self.origin_species.to_string()),
+            ("config", format!("{:?}", self.config)),
+            ("conditioning", format!("{:?}", self.conditioning)),
+            (
+                "rate_heterogeneity",
+                format!("{:?}", self.rate_heterogeneity),
+            ),
+        ];
+        // Checkpoints hold one setting per line.
+        Ok(settings
+            .into_iter()
+            .map(|(key, value)| {
+                let value = value.replace(['\n', '\r'], " ");
+                (key.to_string(), value.trim().to_string())
+            })
+            .collect())
+    }
+
+    /// Simulate until one family satisfies the conditioning, recording attempts in `stats`.
+    pub(crate) fn simulate_family<R: Rng>(
+        &self,
+        rng: &mut R,
+        stats: &mut ConditioningStats,
+    ) -> Result<(RecTree, Vec<DTLEvent>), RustreeError> {
+        let mut attempts = 0;
+
+        loop {
+            let family = match &self.rate_heterogeneity {
+                Some(heterogeneity) => Some(heterogeneity.draw_family(&self.config, rng)?),
+                None => None,
+            };
+            let config = family.as_ref().map_or(&self.config, |(config, _)| config);
+
+            // The prepared totals hold the base branch rates; families with
+            // rescaled rates rebuild them.
+            let branch_totals = match family {
+                Some(_) => None,
+                None => self.branch_totals.as_ref(),
+            };
+            let (mut rec_tree, events) = simulate_dtl_gillespie(
+                self.mode,
+                &self.species_arc,
+                &self.species_events,
+                &self.depths,
+                &self.contemporaneity,
+                self.lca_depths.as_ref(),
+                self.origin_species,
+                config,
+                branch_totals,
+                rng,
+            )?;
+
+            stats.attempts += 1;
+            if self.conditioning.accepts(&rec_tree, &events) {
+                rec_tree.family_rates = family.map(|(_, rates)| rates);
+                stats.accepted += 1;
+                return Ok((rec_tree, events));
+            }
+            // Retry: tree failed a condition
+            attempts += 1;
+            if attempts >= self.conditioning.max_attempts {
+                let rate_hint = if self.config.uses_branch_rates() {
+                    "The branch-specific DTL rates may make the conditions extremely unlikely."
+                        .to_string()
+                } else {
+                    format!(
+                        "The DTL rates (d={}, t={}, l={}) may make the conditions extremely unlikely.",
+                        self.config.lambda_d, self.config.lambda_t, self.config.lambda_l
+                    )
+                };
+                return Err(RustreeError::ConditioningExhausted {
+                    attempts: self.conditioning.max_attempts,
+                    message: format!(
+                        "No gene tree satisfied {:?}. {}",
+                        self.conditioning.conditions, rate_hint
+                    ),
+                });
+            }
+        }
+    }
+}
+
+impl<'a, R: Rng> DtlSimIter<'a, R> {
+    /// Creates a new DtlSimIter with pre-computed state.
+    pub(crate) fn new(simulator: FamilySimulator, n_simulations: usize, rng: &'a mut R) -> Self {
+        DtlSimIter {
+            simulator,
+            n_simulations,
             rng,
             completed: 0,
+            stats: ConditioningStats::default(),
         }
     }
 
+    /// Replace the acceptance conditions (including any set by `require_extant`).
+    ///
+    /// Families failing a condition are re-simulated, up to
+    /// `conditioning.max_attempts` times per accepted family. The attempt
+    /// counts are available from [`conditioning_stats`](Self::conditioning_stats).
+    pub fn with_conditioning(mut self, conditioning: Conditioning) -> Result<Self, RustreeError> {
+        self.simulator.set_conditioning(conditioning)?;
+        Ok(self)
+    }
+
+    /// Attempts and acceptances so far.
+    ///
+    /// `acceptance_rate()` estimates the probability of the conditioning event
+    /// under the simulation model.
+    pub fn conditioning_stats(&self) -> ConditioningStats {
+        self.stats
+    }
+
+    /// Draw each family's D/T/L rates from `heterogeneity` around the base config.
+    ///
+    /// Rates are redrawn on every attempt, so with `require_extant` the
+    /// recorded [`FamilyRates`](super::FamilyRates) are those of the accepted
+    /// family. The realized rates are stored in `RecTree::family_rates`.
+    pub fn with_rate_heterogeneity(
+        mut self,
+        heterogeneity: FamilyRateHeterogeneity,
+    ) -> Result<Self, RustreeError> {
+        self.simulator.set_rate_heterogeneity(heterogeneity)?;
+        Ok(self)
+    }
+
     /// Save each tree as RecPhyloXML to the given directory.
     ///
     /// Files are named `gene_0000.xml`, `gene_0001.xml`, etc.
@@ -139,6 +331,30 @@ impl<'a, R: Rng> DtlSimIter<'a, R> {
             .ok_or_else(|| RustreeError::Validation("No simulations requested".to_string()))?
     }
 
+    /// Collect all trees and events, along with the conditioning statistics.
+    ///
+    /// Warning: this loads everything into memory.
+    pub fn collect_conditioned(mut self) -> Result<ConditionedFamilies, RustreeError> {
+        let mut trees = Vec::with_capacity(self.n_simulations);
+        let mut all_events = Vec::with_capacity(self.n_simulations);
+        for result in self.by_ref() {
+            let (rec_tree, events) = result?;
+            trees.push(rec_tree);
+            all_events.push(events);
+        }
+        Ok((trees, all_events, self.stats))
+    }
+
+    /// Accumulate forest-level summary statistics without keeping any tree.
+    pub fn summarize(self) -> Result<ForestSummary, RustreeError> {
+        let mut summary = ForestSummary::new(Arc::clone(&self.simulator.species_arc));
+        for result in self {
+            let (rec_tree, _events) = result?;
+            summary.add(&rec_tree)?;
+        }
+        Ok(summary)
+    }
+
     /// Collect all trees and events into vectors.
     ///
     /// Equivalent to the old `simulate_dtl_batch` behavior.
@@ -155,6 +371,94 @@ impl<'a, R: Rng> DtlSimIter<'a, R> {
     }
 }
 
+impl<'a> DtlSimIter<'a, CheckpointRng> {
+    /// Snapshot of the iterator's progress and RNG state.
+    ///
+    /// Fails if the configuration holds a recipient kernel without a
+    /// [`fingerprint`](super::RecipientKernel::fingerprint) or a custom
+    /// conditioning predicate, since a resumed run could not tell whether it
+    /// was given the same one.
+    pub fn checkpoint(&self) -> Result<DtlCheckpoint, RustreeError> {
+        Ok(DtlCheckpoint::capture(
+            self.rng,
+            self.completed,
+            self.n_simulations,
+            self.stats,
+            self.simulator.describe()?,
+        ))
+    }
+
+    /// Continue from `checkpoint`, restoring the RNG, progress and statistics.
+    ///
+    /// The iterator must have been built with the same species tree and
+    /// settings as the one that wrote the checkpoint; the RNG it was created
+    /// with is overwritten. A mismatch is reported with the names of the
+    /// settings that differ.
+    pub fn resume(mut self, checkpoint: &DtlCheckpoint) -> Result<Self, RustreeError> {
+        if checkpoint.n_simulations != self.n_simulations {
+            return Err(RustreeError::Validation(format!(
+                "checkpoint was written for {} simulations, not {}",
+                checkpoint.n_simulations, self.n_simulations
+            )));
+        }
+        let changed = checkpoint.changed_settings(&self.simulator.describe()?);
+        if !changed.is_empty() {
+            return Err(RustreeError::Validation(format!(
+                "checkpoint was written by a simulation with different settings: {}",
+                changed.join(", ")
+            )));
+        }
+        *self.rng = checkpoint.restore_rng();
+        self.completed = checkpoint.completed;
+        self.stats = checkpoint.stats;
+        Ok(self)
+    }
+
+    /// Save each tree as RecPhyloXML like [`save_xml`](Self::save_xml), writing
+    /// a checkpoint to `dir/checkpoint.txt` every `checkpoint_every` files.
+    ///
+    /// If `dir` already holds a checkpoint, the run resumes from it and writes
+    /// exactly the files an uninterrupted run would have written.
+    pub fn save_xml_resumable(
+        mut self,
+        dir: &str,
+        checkpoint_every: usize,
+    ) -> Result<(), RustreeError> {
+        if checkpoint_every == 0 {
+            return Err(RustreeError::Validation(
+                "checkpoint_every must be at least 1".to_string(),
+            ));
+        }
+        std::fs::create_dir_all(dir)?;
+        let checkpoint_path = Path::new(dir).join(CHECKPOINT_FILE);
+        if checkpoint_path.exists() {
+            self = self.resume(&DtlCheckpoint::read(&checkpoint_path)?)?;
+        }
+
+        let settings = self.simulator.describe()?;
+        let width = digit_width(self.n_simulations);
+        while let Some(result) = self.next() {
+            let (rec_tree, _events) = result?;
+            let i = self.completed - 1;
+            let path = format!("{}/gene_{:0>width$}.xml", dir, i, width = width);
+            std::fs::write(&path, rec_tree.to_xml())?;
+            if self.completed.is_multiple_of(checkpoint_every)
+                || self.completed == self.n_simulations
+            {
+                DtlCheckpoint::capture(
+                    self.rng,
+                    self.completed,
+                    self.n_simulations,
+                    self.stats,
+                    settings.clone(),
+                )
+                .write(&checkpoint_path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl<'a, R: Rng> Iterator for DtlSimIter<'a, R> {
     type Item = Result<(RecTree, Vec<DTLEvent>), RustreeError>;
 
@@ -163,64 +467,13 @@ impl<'a, R: Rng> Iterator for DtlSimIter<'a, R> {
             return None;
         }
 
-        let mut attempts = 0;
-
-        loop {
-            let lca_ref = self.lca_depths.as_deref();
-            let result = if self.config.uses_branch_rates() {
-                simulate_dtl_gillespie_prepared(
-                    self.mode,
-                    &self.species_arc,
-                    &self.species_events,
-                    &self.depths,
-                    &self.contemporaneity,
-                    lca_ref,
-                    self.origin_species,
-                    &self.config,
-                    &self.runtime,
-                    &mut *self.rng,
-                )
-            } else {
-                simulate_dtl_gillespie(
-                    self.mode,
-                    &self.species_arc,
-                    &self.species_events,
-                    &self.depths,
-                    &self.contemporaneity,
-                    lca_ref,
-                    self.origin_species,
-                    &self.config,
-                    &mut *self.rng,
-                )
-            };
-
-            match result {
-                Ok((rec_tree, events)) => {
-                    if !self.require_extant || count_extant_genes(&rec_tree) > 0 {
-                        self.completed += 1;
-                        return Some(Ok((rec_tree, events)));
-                    }
-                    // Retry: tree had no extant genes
-                    attempts += 1;
-                    if attempts >= MAX_EXTANT_ATTEMPTS {
-                        let rate_hint = if self.config.uses_branch_rates() {
-                            "The branch-specific DTL rates may make extant genes extremely unlikely.".to_string()
-                        } else {
-                            format!(
-                                "The DTL rates (d={}, t={}, l={}) may make extant genes extremely unlikely.",
-                                self.config.lambda_d, self.config.lambda_t, self.config.lambda_l
-                            )
-                        };
-                        return Some(Err(RustreeError::Simulation(format!(
-                            "Failed to generate a gene tree with extant genes after {} attempts. \
-                             {}",
-                            MAX_EXTANT_ATTEMPTS, rate_hint
-                        ))));
-                    }
-                }
-                Err(e) => return Some(Err(e)),
-            }
+        let result = self
+            .simulator
+            .simulate_family(&mut *self.rng, &mut self.stats);
+        if result.is_ok() {
+            self.completed += 1;
         }
+        Some(result)
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
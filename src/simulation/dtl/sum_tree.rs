@@ -0,0 +1,127 @@
+// Array-backed sum tree for logarithmic-time weighted selection
+//
+// Leaves hold non-negative weights indexed by species; each internal node
+// holds the sum of its two children. Updates recompute the path to the root
+// from the children (rather than adding deltas), so floating-point weights do
+// not drift and an emptied leaf contributes exactly zero.
+
+use std::ops::{Add, Sub};
+
+#[derive(Clone, Debug)]
+pub(crate) struct SumTree<T> {
+    /// Number of leaves, rounded up to a power of two.
+    capacity: usize,
+    /// `nodes[1]` is the root; leaf `i` is at `capacity + i`.
+    nodes: Vec<T>,
+}
+
+impl<T> SumTree<T>
+where
+    T: Copy + Default + PartialOrd + Add<Output = T> + Sub<Output = T>,
+{
+    /// A tree of `len` zero-weight leaves.
+    pub fn new(len: usize) -> Self {
+        let capacity = len.max(1).next_power_of_two();
+        Self {
+            capacity,
+            nodes: vec![T::default(); 2 * capacity],
+        }
+    }
+
+    /// Sum of all leaves.
+    pub fn total(&self) -> T {
+        self.nodes[1]
+    }
+
+    pub fn set(&mut self, index: usize, value: T) {
+        let mut node = self.capacity + index;
+        self.nodes[node] = value;
+        while node > 1 {
+            node /= 2;
+            self.nodes[node] = self.nodes[2 * node] + self.nodes[2 * node + 1];
+        }
+    }
+
+    /// First leaf whose cumulative weight exceeds `target`, with the part of
+    /// `target` left after subtracting the preceding leaves.
+    ///
+    /// Zero-weight leaves are never returned. `None` if `target >= total()`.
+    pub fn find(&self, mut target: T) -> Option<(usize, T)> {
+        if target >= self.total() {
+            return None;
+        }
+        let mut node = 1;
+        while node < self.capacity {
+            let left = self.nodes[2 * node];
+            if target < left {
+                node *= 2;
+            } else {
+                target = target - left;
+                node = 2 * node + 1;
+            }
+        }
+        Some((node - self.capacity, target))
+    }
+
+    /// Highest-index leaf with a positive weight.
+    pub fn last_nonzero(&self) -> Option<usize> {
+        let zero = T::default();
+        if self.total() <= zero {
+            return None;
+        }
+        let mut node = 1;
+        while node < self.capacity {
+            node = if self.nodes[2 * node + 1] > zero {
+                2 * node + 1
+            } else {
+                2 * node
+            };
+        }
+        Some(node - self.capacity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_matches_a_linear_scan() {
+        let weights = [3usize, 0, 0, 5, 1, 0, 2];
+        let mut tree = SumTree::new(weights.len());
+        for (i, &w) in weights.iter().enumerate() {
+            tree.set(i, w);
+        }
+        assert_eq!(tree.total(), 11);
+        for target in 0..11 {
+            let mut rest = target;
+            let expected = weights
+                .iter()
+                .position(|&w| {
+                    if rest < w {
+                        true
+                    } else {
+                        rest -= w;
+                        false
+                    }
+                })
+                .map(|i| (i, rest));
+            assert_eq!(tree.find(target), expected);
+        }
+        assert_eq!(tree.find(11), None);
+        assert_eq!(tree.last_nonzero(), Some(6));
+    }
+
+    #[test]
+    fn emptied_float_leaves_contribute_exactly_zero() {
+        let mut tree = SumTree::new(5);
+        tree.set(0, 0.1);
+        tree.set(3, 0.7);
+        tree.set(4, 0.2);
+        tree.set(3, 0.0);
+        tree.set(4, 0.0);
+        assert_eq!(tree.total(), 0.1);
+        assert_eq!(tree.find(0.099).map(|(i, _)| i), Some(0));
+        assert_eq!(tree.last_nonzero(), Some(0));
+    }
+}
@@ -0,0 +1,325 @@
+// Streaming forest-level summary statistics
+//
+// `ForestSummary` accumulates aggregates over simulated gene families one
+// tree at a time: event counts per species branch, transfer counts per
+// donor/recipient pair and copy-number histograms per extant species. Memory
+// depends on the species tree and the largest copy number seen, never on the
+// number of families, so it can sit at the end of a `DtlSimIter` for
+// parameter sweeps that don't need the trees themselves.
+
+use crate::error::RustreeError;
+use crate::node::{Event, FlatTree, RecTree, SubtreeIntervals};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::event::csv_field;
+use super::utils::is_extant_species;
+
+/// Event totals on one species branch, summed over families.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BranchEventCounts {
+    pub speciations: usize,
+    pub duplications: usize,
+    /// Transfers leaving this branch.
+    pub transfers_out: usize,
+    /// Transfers arriving on this branch.
+    pub transfers_in: usize,
+    pub losses: usize,
+    /// Extant gene copies (non-zero only for extant species).
+    pub leaves: usize,
+}
+
+/// Constant-memory accumulator of summary statistics over a gene forest.
+#[derive(Clone, Debug)]
+pub struct ForestSummary {
+    species_tree: Arc<FlatTree>,
+    families: usize,
+    branches: Vec<BranchEventCounts>,
+    transfers: BTreeMap<(usize, usize), usize>,
+    extant_species: Vec<usize>,
+    /// `copy_numbers[k][c]` = families with `c` copies in `extant_species[k]`.
+    copy_numbers: Vec<Vec<usize>>,
+    /// Position of each species node in `extant_species`.
+    extant_position: Vec<Option<usize>>,
+    species_subtrees: SubtreeIntervals,
+}
+
+impl ForestSummary {
+    /// An empty summary for families simulated on `species_tree`.
+    pub fn new(species_tree: Arc<FlatTree>) -> Self {
+        let n = species_tree.nodes.len();
+        let extant_species: Vec<usize> = (0..n)
+            .filter(|&i| is_extant_species(&species_tree.nodes[i]))
+            .collect();
+        let mut extant_position = vec![None; n];
+        for (k, &sp) in extant_species.iter().enumerate() {
+            extant_position[sp] = Some(k);
+        }
+        Self {
+            families: 0,
+            branches: vec![BranchEventCounts::default(); n],
+            transfers: BTreeMap::new(),
+            copy_numbers: vec![Vec::new(); extant_species.len()],
+            extant_species,
+            extant_position,
+            species_subtrees: SubtreeIntervals::new(&species_tree),
+            species_tree,
+        }
+    }
+
+    /// Summarizes every tree of `rec_trees`.
+    pub fn from_rec_trees<'r, I>(
+        species_tree: Arc<FlatTree>,
+        rec_trees: I,
+    ) -> Result<Self, RustreeError>
+    where
+        I: IntoIterator<Item = &'r RecTree>,
+    {
+        let mut summary = Self::new(species_tree);
+        for rec_tree in rec_trees {
+            summary.add(rec_tree)?;
+        }
+        Ok(summary)
+    }
+
+    /// Adds one family.
+    ///
+    /// Gene nodes with an unknown species mapping are ignored. The transfer
+    /// recipient is the child mapped outside the donor's subtree.
+    pub fn add(&mut self, rec_tree: &RecTree) -> Result<(), RustreeError> {
+        let n = self.species_tree.nodes.len();
+        if rec_tree.species_tree.nodes.len() != n {
+            return Err(RustreeError::Validation(format!(
+                "gene tree was reconciled with a species tree of {} nodes, summary has {n}",
+                rec_tree.species_tree.nodes.len()
+            )));
+        }
+
+        let mut copies = vec![0usize; self.extant_species.len()];
+        for (gene, (event, mapping)) in rec_tree
+            .event_mapping
+            .iter()
+            .zip(&rec_tree.node_mapping)
+            .enumerate()
+        {
+            let Some(species) = *mapping else { continue };
+            let counts = &mut self.branches[species];
+            match event {
+                Event::Speciation => counts.speciations += 1,
+                Event::Duplication => counts.duplications += 1,
+                Event::Loss => counts.losses += 1,
+                Event::Leaf => {
+                    counts.leaves += 1;
+                    if let Some(k) = self.extant_position[species] {
+                        copies[k] += 1;
+                    }
+                }
+                Event::Transfer => {
+                    if !rec_tree.is_transfer_event(gene) {
+                        continue;
+                    }
+                    counts.transfers_out += 1;
+                    let recipient = rec_tree
+                        .transfer_recipient_child(gene, &self.species_subtrees)
+                        .and_then(|child| rec_tree.node_mapping[child]);
+                    if let Some(recipient) = recipient {
+                        self.branches[recipient].transfers_in += 1;
+                        *self.transfers.entry((species, recipient)).or_default() += 1;
+                    }
+                }
+            }
+        }
+
+        for (histogram, &c) in self.copy_numbers.iter_mut().zip(&copies) {
+            if histogram.len() <= c {
+                histogram.resize(c + 1, 0);
+            }
+            histogram[c] += 1;
+        }
+        self.families += 1;
+        Ok(())
+    }
+
+    /// Adds the counts of another summary over the same species tree.
+    pub fn merge(&mut self, other: &ForestSummary) -> Result<(), RustreeError> {
+        if other.species_tree.nodes.len() != self.species_tree.nodes.len() {
+            return Err(RustreeError::Validation(
+                "cannot merge summaries over different species trees".to_string(),
+            ));
+        }
+        self.families += other.families;
+        for (mine, theirs) in self.branches.iter_mut().zip(&other.branches) {
+            mine.speciations += theirs.speciations;
+            mine.duplications += theirs.duplications;
+            mine.transfers_out += theirs.transfers_out;
+            mine.transfers_in += theirs.transfers_in;
+            mine.losses += theirs.losses;
+            mine.leaves += theirs.leaves;
+        }
+        for (&pair, &count) in &other.transfers {
+            *self.transfers.entry(pair).or_default() += count;
+        }
+        for (mine, theirs) in self.copy_numbers.iter_mut().zip(&other.copy_numbers) {
+            if mine.len() < theirs.len() {
+                mine.resize(theirs.len(), 0);
+            }
+            for (m, t) in mine.iter_mut().zip(theirs) {
+                *m += t;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn species_tree(&self) -> &FlatTree {
+        &self.species_tree
+    }
+
+    /// Number of families added.
+    pub fn n_families(&self) -> usize {
+        self.families
+    }
+
+    /// Event totals per species node index.
+    pub fn branch_counts(&self) -> &[BranchEventCounts] {
+        &self.branches
+    }
+
+    /// Transfer counts keyed by `(donor, recipient)` species index; absent pairs are zero.
+    pub fn transfer_counts(&self) -> &BTreeMap<(usize, usize), usize> {
+        &self.transfers
+    }
+
+    /// Dense donor × recipient transfer matrix indexed by species node.
+    pub fn transfer_matrix(&self) -> Vec<Vec<usize>> {
+        let n = self.species_tree.nodes.len();
+        let mut matrix = vec![vec![0; n]; n];
+        for (&(donor, recipient), &count) in &self.transfers {
+            matrix[donor][recipient] = count;
+        }
+        matrix
+    }
+
+    /// Species node indices of the extant species, in index order.
+    pub fn extant_species(&self) -> &[usize] {
+        &self.extant_species
+    }
+
+    /// Copy-number histogram of an extant species: element `c` is the number
+    /// of families with exactly `c` copies. `None` if `species` is not extant.
+    pub fn copy_number_histogram(&self, species: usize) -> Option<&[usize]> {
+        let k = (*self.extant_position.get(species)?)?;
+        Some(&self.copy_numbers[k])
+    }
+
+    /// CSV of per-branch event totals.
+    pub fn branch_events_csv(&self) -> String {
+        let mut csv = String::from(
+            "species_node,species_name,speciations,duplications,transfers_out,transfers_in,losses,leaves\n",
+        );
+        for (i, c) in self.branches.iter().enumerate() {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{}",
+                i,
+                csv_field(&self.species_tree.nodes[i].name),
+                c.speciations,
+                c.duplications,
+                c.transfers_out,
+                c.transfers_in,
+                c.losses,
+                c.leaves
+            );
+        }
+        csv
+    }
+
+    /// CSV of non-zero transfer counts, one row per donor/recipient pair.
+    pub fn transfers_csv(&self) -> String {
+        let mut csv = String::from("donor,donor_name,recipient,recipient_name,count\n");
+        for (&(donor, recipient), &count) in &self.transfers {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{}",
+                donor,
+                csv_field(&self.species_tree.nodes[donor].name),
+                recipient,
+                csv_field(&self.species_tree.nodes[recipient].name),
+                count
+            );
+        }
+        csv
+    }
+
+    /// CSV of copy-number histograms, one row per extant species and copy number.
+    pub fn copy_numbers_csv(&self) -> String {
+        let mut csv = String::from("species_node,species_name,copies,families\n");
+        for (k, &sp) in self.extant_species.iter().enumerate() {
+            for (c, &families) in self.copy_numbers[k].iter().enumerate() {
+                let _ = writeln!(
+                    csv,
+                    "{},{},{},{}",
+                    sp,
+                    csv_field(&self.species_tree.nodes[sp].name),
+                    c,
+                    families
+                );
+            }
+        }
+        csv
+    }
+
+    /// Writes `branch_events.csv`, `transfers.csv` and `copy_numbers.csv` to `dir`.
+    pub fn save_csv(&self, dir: &str) -> Result<(), RustreeError> {
+        std::fs::create_dir_all(dir)?;
+        let dir = Path::new(dir);
+        std::fs::write(dir.join("branch_events.csv"), self.branch_events_csv())?;
+        std::fs::write(dir.join("transfers.csv"), self.transfers_csv())?;
+        std::fs::write(dir.join("copy_numbers.csv"), self.copy_numbers_csv())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bd::simulate_bd_tree_bwd;
+    use crate::dtl::simulate_dtl_iter;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn merging_halves_equals_summarizing_everything() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (mut tree, _) = simulate_bd_tree_bwd(8, 1.0, 0.3, &mut rng).unwrap();
+        tree.assign_depths();
+        let (trees, _) = simulate_dtl_iter(
+            &tree, tree.root, 0.4, 0.4, 0.3, None, None, 30, false, &mut rng,
+        )
+        .unwrap()
+        .collect_all()
+        .unwrap();
+        let species = Arc::clone(&trees[0].species_tree);
+
+        let all = ForestSummary::from_rec_trees(Arc::clone(&species), &trees).unwrap();
+        let mut first = ForestSummary::from_rec_trees(Arc::clone(&species), &trees[..12]).unwrap();
+        let second = ForestSummary::from_rec_trees(species, &trees[12..]).unwrap();
+        first.merge(&second).unwrap();
+
+        assert_eq!(first.n_families(), 30);
+        assert_eq!(first.branch_counts(), all.branch_counts());
+        assert_eq!(first.transfer_counts(), all.transfer_counts());
+        assert_eq!(first.copy_numbers_csv(), all.copy_numbers_csv());
+    }
+
+    #[test]
+    fn species_names_are_quoted() {
+        let mut nodes = crate::parse_newick("('A,1':1,B:1)R:0;").unwrap();
+        let mut tree = nodes.pop().unwrap().to_flat_tree();
+        tree.assign_depths();
+        let summary = ForestSummary::new(Arc::new(tree));
+        let csv = summary.branch_events_csv();
+        assert!(csv.contains("\n1,\"A,1\",0,"), "{csv}");
+    }
+}
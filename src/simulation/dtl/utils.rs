@@ -2,13 +2,15 @@
 
 use crate::bd::{generate_events_from_tree, BDEvent, TreeEvent};
 use crate::error::RustreeError;
+use crate::metric_functions::LcaTable;
 use crate::node::{Event, FlatNode, FlatTree, RecTree};
-use crate::simulation::utils::draw_waiting_time;
 use rand::Rng;
 use std::sync::Arc;
 
-use super::event::DTLEvent;
-use super::gillespie::DTLMode;
+use super::contemporaneity::Contemporaneity;
+use super::gillespie::{BranchRateTotals, DTLMode};
+use super::highways::ActiveHighways;
+use super::kernel::RecipientKernel;
 use super::state::SimulationState;
 use super::DTLConfig;
 
@@ -17,55 +19,9 @@ pub(crate) struct PreparedDtlSimulation {
     pub species_tree: Arc<FlatTree>,
     pub species_events: Vec<TreeEvent>,
     pub depths: Vec<f64>,
-    pub contemporaneity: Vec<Vec<usize>>,
-    pub lca_depths: Option<Vec<Vec<f64>>>,
-    pub runtime: PreparedDtlRuntime,
-}
-
-/// Precomputed data used by repeated Gillespie simulations.
-pub(crate) struct PreparedDtlRuntime {
-    default_origin_start_time: f64,
-    branch_start_times: Option<Vec<f64>>,
-    branch_total_rates: Option<Vec<f64>>,
-    per_species_interval_total_rates: Option<Vec<f64>>,
-}
-
-impl PreparedDtlRuntime {
-    fn from_parts(
-        default_origin_start_time: f64,
-        branch_start_times: Option<Vec<f64>>,
-        config: &DTLConfig,
-        contemporaneity: &[Vec<usize>],
-    ) -> Self {
-        let branch_total_rates = config.branch_rates.as_ref().map(|rates| {
-            (0..rates.lambda_d.len())
-                .map(|species| rates.total_rate(species))
-                .collect::<Vec<_>>()
-        });
-
-        let per_species_interval_total_rates = branch_total_rates.as_ref().map(|rates| {
-            contemporaneity
-                .iter()
-                .map(|species| species.iter().map(|&sp| rates[sp]).sum())
-                .collect()
-        });
-
-        Self {
-            default_origin_start_time,
-            branch_start_times,
-            branch_total_rates,
-            per_species_interval_total_rates,
-        }
-    }
-
-    #[inline]
-    fn origin_start_time(&self, origin_species: usize) -> f64 {
-        self.branch_start_times
-            .as_ref()
-            .map_or(self.default_origin_start_time, |starts| {
-                starts[origin_species]
-            })
-    }
+    pub contemporaneity: Contemporaneity,
+    pub lca_depths: Option<LcaDepths>,
+    pub branch_totals: Option<BranchRateTotals>,
 }
 
 /// Prepare shared, reusable DTL simulation state.
@@ -98,14 +54,9 @@ pub(crate) fn prepare_simulation(
     depths.sort_by(|a, b| a.total_cmp(b));
     depths.dedup();
 
-    let contemporaneity = species_tree.find_contemporaneity(&depths);
+    let contemporaneity = Contemporaneity::new(species_tree, &depths)?;
     let lca_depths = precompute_lca(species_tree, config.transfer_alpha)?;
-    let runtime = PreparedDtlRuntime::from_parts(
-        origin_start_time,
-        branch_start_times,
-        config,
-        &contemporaneity,
-    );
+    let branch_totals = BranchRateTotals::new(config, species_tree, &contemporaneity);
 
     Ok(PreparedDtlSimulation {
         species_tree: Arc::new(species_tree.clone()),
@@ -113,7 +64,7 @@ pub(crate) fn prepare_simulation(
         depths,
         contemporaneity,
         lca_depths,
-        runtime,
+        branch_totals,
     })
 }
 
@@ -171,313 +122,50 @@ fn precompute_branch_start_times(
         .collect()
 }
 
-/// Precompute LCA depths if transfer_alpha is provided
-#[inline]
-pub(crate) fn precompute_lca(
-    species_tree: &FlatTree,
-    transfer_alpha: Option<f64>,
-) -> Result<Option<Vec<Vec<f64>>>, RustreeError> {
-    transfer_alpha
-        .map(|_| {
-            species_tree
-                .precompute_lca_depths()
-                .map_err(RustreeError::Simulation)
-        })
-        .transpose()
+/// Depth of the LCA of any two species, for assortative transfer weights.
+///
+/// Backed by an Euler-tour [`LcaTable`], so memory is O(n log n) and each
+/// lookup is O(1), instead of the dense n×n matrix built by
+/// [`FlatTree::precompute_lca_depths`].
+pub(crate) struct LcaDepths {
+    table: LcaTable,
+    node_depths: Vec<f64>,
 }
 
-fn total_event_rate(
-    mode: DTLMode,
-    state: &SimulationState<'_>,
-    depths: &[f64],
-    contemporaneity: &[Vec<usize>],
-    current_time: f64,
-    config: &DTLConfig,
-    runtime: &PreparedDtlRuntime,
-) -> f64 {
-    match mode {
-        DTLMode::PerGene => {
-            if let Some(branch_total_rates) = runtime.branch_total_rates.as_ref() {
-                state.total_gene_weighted_rate(|species| branch_total_rates[species])
-            } else {
-                state.total_gene_copies() as f64 * config.branch_total_rate(0)
-            }
-        }
-        DTLMode::PerSpecies => {
-            let time_idx = find_time_index(depths, current_time);
-            if let Some(interval_rates) = runtime.per_species_interval_total_rates.as_ref() {
-                interval_rates[time_idx]
-            } else {
-                contemporaneity[time_idx].len() as f64 * config.branch_total_rate(0)
-            }
-        }
+impl LcaDepths {
+    pub fn new(species_tree: &FlatTree) -> Result<Self, RustreeError> {
+        let node_depths = species_tree
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(idx, node)| {
+                node.depth.ok_or_else(|| {
+                    RustreeError::missing_depth("precompute_lca_depths", idx, &node.name)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            table: LcaTable::new(species_tree),
+            node_depths,
+        })
     }
-}
-
-fn select_affected_gene<R: Rng>(
-    mode: DTLMode,
-    state: &SimulationState<'_>,
-    depths: &[f64],
-    contemporaneity: &[Vec<usize>],
-    current_time: f64,
-    runtime: &PreparedDtlRuntime,
-    rng: &mut R,
-) -> Option<(usize, usize)> {
-    match mode {
-        DTLMode::PerGene => {
-            if let Some(branch_total_rates) = runtime.branch_total_rates.as_ref() {
-                state.random_gene_copy_weighted(|species| branch_total_rates[species], rng)
-            } else {
-                state.random_gene_copy(rng)
-            }
-        }
-        DTLMode::PerSpecies => {
-            let time_idx = find_time_index(depths, current_time);
-            let alive_species = &contemporaneity[time_idx];
-            if alive_species.is_empty() {
-                return None;
-            }
-
-            let species = if let Some(branch_total_rates) = runtime.branch_total_rates.as_ref() {
-                let total_rate = runtime
-                    .per_species_interval_total_rates
-                    .as_ref()
-                    .map_or_else(
-                        || {
-                            alive_species
-                                .iter()
-                                .map(|&sp| branch_total_rates[sp])
-                                .sum::<f64>()
-                        },
-                        |rates| rates[time_idx],
-                    );
-                if total_rate <= 0.0 {
-                    return None;
-                }
-
-                let mut threshold = rng.gen::<f64>() * total_rate;
-                let mut species = *alive_species.last()?;
-                for &candidate in alive_species {
-                    let rate = branch_total_rates[candidate];
-                    if rate <= 0.0 {
-                        continue;
-                    }
-                    if threshold < rate {
-                        species = candidate;
-                        break;
-                    }
-                    threshold -= rate;
-                }
-                species
-            } else {
-                alive_species[rng.gen_range(0..alive_species.len())]
-            };
 
-            match state.genes_per_species.get(&species) {
-                Some(genes) if !genes.is_empty() => {
-                    let gene = genes[rng.gen_range(0..genes.len())];
-                    Some((species, gene))
-                }
-                _ => None,
-            }
-        }
+    /// Depth of the LCA of species `a` and `b`.
+    #[inline]
+    pub fn get(&self, a: usize, b: usize) -> f64 {
+        self.node_depths[self.table.lca(a, b)]
     }
 }
 
-/// Shared Gillespie-style DTL simulation using prepared runtime data.
-#[allow(clippy::too_many_arguments)]
-pub(crate) fn simulate_dtl_gillespie_prepared<R: Rng>(
-    mode: DTLMode,
-    species_tree: &Arc<FlatTree>,
-    species_events: &[TreeEvent],
-    depths: &[f64],
-    contemporaneity: &[Vec<usize>],
-    lca_depths: Option<&[Vec<f64>]>,
-    origin_species: usize,
-    config: &DTLConfig,
-    runtime: &PreparedDtlRuntime,
-    rng: &mut R,
-) -> Result<(RecTree, Vec<DTLEvent>), RustreeError> {
-    let transfer_alpha = config.transfer_alpha;
-    let replacement_transfer = config.replacement_transfer;
-    let origin_species = config.sample_origin(origin_species, rng);
-
-    let estimated_capacity = species_tree.nodes.len();
-    let mut state = SimulationState::with_branch_total_rates(
-        estimated_capacity,
-        species_tree,
-        runtime.branch_total_rates.as_deref(),
-    );
-
-    let origin_start_time = runtime.origin_start_time(origin_species);
-    let mut current_time = origin_start_time;
-
-    let mut species_event_idx = 0;
-    while species_event_idx < species_events.len()
-        && species_events[species_event_idx].time < origin_start_time
-    {
-        species_event_idx += 1;
-    }
-
-    let initial_gene_idx =
-        state.create_gene_node(None, origin_species, Event::Speciation, origin_start_time);
-    state.add_gene_to_species(origin_species, initial_gene_idx);
-
-    current_time = current_time.next_up();
-
-    loop {
-        let next_species_event_time = species_events
-            .get(species_event_idx)
-            .map_or(f64::INFINITY, |e| e.time);
-
-        if state.total_gene_copies() == 0 {
-            break;
-        }
-
-        let dtl_total_rate = total_event_rate(
-            mode,
-            &state,
-            depths,
-            contemporaneity,
-            current_time,
-            config,
-            runtime,
-        );
-
-        let next_dtl_time = current_time + draw_waiting_time(dtl_total_rate, rng);
-
-        if next_species_event_time == f64::INFINITY && next_dtl_time == f64::INFINITY {
-            break;
-        }
-        if next_species_event_time <= next_dtl_time && species_event_idx < species_events.len() {
-            let sp_event = species_events[species_event_idx].clone();
-            current_time = sp_event.time;
-
-            match sp_event.event_type {
-                BDEvent::Speciation => {
-                    let child1 = sp_event.child1.ok_or_else(|| {
-                        RustreeError::Simulation(format!(
-                            "Speciation event for node {} has no child1",
-                            sp_event.node_id
-                        ))
-                    })?;
-                    let child2 = sp_event.child2.ok_or_else(|| {
-                        RustreeError::Simulation(format!(
-                            "Speciation event for node {} has no child2",
-                            sp_event.node_id
-                        ))
-                    })?;
-                    if let Some(genes) = state.take_genes_for_species(sp_event.node_id) {
-                        for gene_idx in genes {
-                            state.handle_speciation(
-                                gene_idx,
-                                sp_event.node_id,
-                                child1,
-                                child2,
-                                current_time,
-                            );
-                        }
-                    }
-                }
-                BDEvent::Extinction => {
-                    if let Some(genes) = state.take_genes_for_species(sp_event.node_id) {
-                        for gene_idx in genes {
-                            state.handle_loss(gene_idx, sp_event.node_id, current_time);
-                        }
-                    }
-                }
-                BDEvent::Leaf => {
-                    if let Some(genes) = state.take_genes_for_species(sp_event.node_id) {
-                        for gene_idx in genes {
-                            state.handle_leaf(gene_idx, sp_event.node_id, current_time);
-                        }
-                    }
-                }
-            }
-
-            species_event_idx += 1;
-            current_time = current_time.next_up();
-        } else {
-            current_time = next_dtl_time;
-
-            let selection = select_affected_gene(
-                mode,
-                &state,
-                depths,
-                contemporaneity,
-                current_time,
-                runtime,
-                rng,
-            );
-
-            let (affected_species, affected_gene) = match selection {
-                Some(s) => s,
-                None => continue,
-            };
-
-            let (lambda_d, lambda_t, lambda_l) = config.branch_event_rates(affected_species);
-            let branch_total_rate = lambda_d + lambda_t + lambda_l;
-            if branch_total_rate <= 0.0 {
-                continue;
-            }
-
-            let event_draw = rng.gen::<f64>() * branch_total_rate;
-            if event_draw < lambda_d {
-                state.handle_duplication(affected_gene, affected_species, current_time);
-            } else if event_draw < lambda_d + lambda_t {
-                let recipient = match (transfer_alpha, lca_depths) {
-                    (Some(alpha), Some(lca)) => select_transfer_recipient_assortative(
-                        depths,
-                        contemporaneity,
-                        lca,
-                        current_time,
-                        affected_species,
-                        alpha,
-                        rng,
-                    ),
-                    _ => select_transfer_recipient(
-                        depths,
-                        contemporaneity,
-                        current_time,
-                        affected_species,
-                        rng,
-                    ),
-                };
-
-                if let Some(recipient_species) = recipient {
-                    let is_replacement =
-                        replacement_transfer.is_some_and(|p| p > 0.0 && rng.gen::<f64>() < p);
-                    if is_replacement {
-                        if let Some(victim) = state.random_gene_in_species(recipient_species, rng) {
-                            state.handle_loss(victim, recipient_species, current_time);
-                        }
-                    }
-                    state.handle_transfer(
-                        affected_gene,
-                        affected_species,
-                        recipient_species,
-                        current_time,
-                    );
-                }
-            } else if lambda_l > 0.0 {
-                state.handle_loss(affected_gene, affected_species, current_time);
-            }
-        }
-
-        if species_event_idx >= species_events.len() && state.total_gene_copies() == 0 {
-            break;
-        }
-    }
-
-    let rec_tree = finalize_simulation(
-        species_tree.clone(),
-        state.gene_nodes,
-        state.node_mapping,
-        state.event_mapping,
-        origin_species,
-        origin_start_time,
-    )?;
-    Ok((rec_tree, state.events))
+/// Build LCA depth lookups if transfer_alpha is provided
+#[inline]
+pub(crate) fn precompute_lca(
+    species_tree: &FlatTree,
+    transfer_alpha: Option<f64>,
+) -> Result<Option<LcaDepths>, RustreeError> {
+    transfer_alpha
+        .map(|_| LcaDepths::new(species_tree))
+        .transpose()
 }
 
 // ============================================================================
@@ -492,7 +180,7 @@ pub(crate) fn simulate_dtl_gillespie_prepared<R: Rng>(
 ///
 /// 1. Calling [`find_time_index`] to map `event_time` to the correct time
 ///    subdivision index `j`.
-/// 2. Looking up `contemporaneity[j]` to get all species alive during that interval.
+/// 2. Listing the species alive during that interval from `contemporaneity`.
 /// 3. Filtering out the `donor` species (a lineage cannot transfer to itself).
 ///
 /// The result is used by [`select_transfer_recipient`] (uniform random selection)
@@ -501,7 +189,7 @@ pub(crate) fn simulate_dtl_gillespie_prepared<R: Rng>(
 /// # Arguments
 ///
 /// * `depths` - Sorted time subdivision boundaries from [`FlatTree::make_subdivision`](crate::node::FlatTree::make_subdivision).
-/// * `contemporaneity` - Per-interval species lists from [`FlatTree::find_contemporaneity`](crate::node::FlatTree::find_contemporaneity).
+/// * `contemporaneity` - Alive branches per interval, built by [`Contemporaneity::new`].
 /// * `event_time` - The time at which the transfer event occurs.
 /// * `donor` - The index of the donor species to exclude from recipients.
 ///
@@ -512,67 +200,68 @@ pub(crate) fn simulate_dtl_gillespie_prepared<R: Rng>(
 #[cfg(test)]
 fn get_contemporaneous_recipients(
     depths: &[f64],
-    contemporaneity: &[Vec<usize>],
+    contemporaneity: &Contemporaneity,
     event_time: f64,
     donor: usize,
 ) -> Vec<usize> {
     let time_idx = find_time_index(depths, event_time);
-    let contemporaries = &contemporaneity[time_idx];
-    contemporaries
-        .iter()
-        .filter(|&&sp| sp != donor)
-        .copied()
+    contemporaneity
+        .alive(time_idx)
+        .filter(|&sp| sp != donor)
         .collect()
 }
 
 /// Transfer recipient selection (uniform random).
 ///
-/// Selects directly from the contemporaneity slice without allocating.
+/// Draws an index into the alive branches in O(log S) without listing them;
+/// the donor's position is mapped to the last alive branch.
 pub(crate) fn select_transfer_recipient<R: Rng>(
     depths: &[f64],
-    contemporaneity: &[Vec<usize>],
+    contemporaneity: &Contemporaneity,
     event_time: f64,
     donor: usize,
     rng: &mut R,
 ) -> Option<usize> {
     let time_idx = find_time_index(depths, event_time);
-    let contemporaries = &contemporaneity[time_idx];
+    let alive = contemporaneity.count(time_idx);
 
     // Count eligible recipients (all contemporaries except donor)
-    let count = contemporaries.iter().filter(|&&sp| sp != donor).count();
+    let count = if contemporaneity.contains(time_idx, donor) {
+        alive - 1
+    } else {
+        alive
+    };
     if count == 0 {
         return None;
     }
 
-    // Pick the k-th eligible recipient (0-indexed)
+    // Pick the k-th alive branch, swapping the donor with the last one
     let k = rng.gen_range(0..count);
-    contemporaries
-        .iter()
-        .filter(|&&sp| sp != donor)
-        .nth(k)
-        .copied()
+    match contemporaneity.nth(time_idx, k)? {
+        sp if sp == donor => contemporaneity.nth(time_idx, alive - 1),
+        sp => Some(sp),
+    }
 }
 
 /// Transfer recipient selection with assortative preference (distance-weighted).
 ///
-/// Selects directly from the contemporaneity slice without allocating a recipient Vec.
+/// Iterates the alive branches directly without allocating a recipient Vec.
 pub(crate) fn select_transfer_recipient_assortative<R: Rng>(
     depths: &[f64],
-    contemporaneity: &[Vec<usize>],
-    lca_depths: &[Vec<f64>],
+    contemporaneity: &Contemporaneity,
+    lca_depths: &LcaDepths,
     event_time: f64,
     donor: usize,
     alpha: f64,
     rng: &mut R,
 ) -> Option<usize> {
     let time_idx = find_time_index(depths, event_time);
-    let contemporaries = &contemporaneity[time_idx];
 
     // Compute total weight over eligible recipients
     let mut total_weight = 0.0;
-    for &sp in contemporaries {
+    for sp in contemporaneity.alive(time_idx) {
         if sp != donor {
-            let lca_depth = lca_depths[donor][sp];
+            let lca_depth = lca_depths.get(donor, sp);
             let distance = 2.0 * (event_time - lca_depth);
             total_weight += (-alpha * distance).exp();
         }
@@ -587,9 +276,9 @@ pub(crate) fn select_transfer_recipient_assortative<R: Rng>(
     let mut cumulative = 0.0;
     let mut last_eligible = None;
 
-    for &sp in contemporaries {
+    for sp in contemporaneity.alive(time_idx) {
         if sp != donor {
-            let lca_depth = lca_depths[donor][sp];
+            let lca_depth = lca_depths.get(donor, sp);
             let distance = 2.0 * (event_time - lca_depth);
             cumulative += (-alpha * distance).exp();
             last_eligible = Some(sp);
@@ -603,6 +292,157 @@ pub(crate) fn select_transfer_recipient_assortative<R: Rng>(
     last_eligible
 }
 
+/// Transfer recipient selection with an arbitrary per-recipient weight.
+///
+/// Recipients with non-positive weight are never selected.
+pub(crate) fn select_transfer_recipient_weighted<R: Rng, W: FnMut(usize) -> f64>(
+    depths: &[f64],
+    contemporaneity: &Contemporaneity,
+    event_time: f64,
+    donor: usize,
+    mut weight: W,
+    rng: &mut R,
+) -> Option<usize> {
+    let time_idx = find_time_index(depths, event_time);
+
+    let mut weights = Vec::with_capacity(contemporaneity.count(time_idx));
+    let mut total_weight = 0.0;
+    for sp in contemporaneity.alive(time_idx) {
+        let w = if sp == donor {
+            0.0
+        } else {
+            weight(sp).max(0.0)
+        };
+        total_weight += w;
+        weights.push(w);
+    }
+    if total_weight <= 0.0 {
+        return None;
+    }
+
+    let threshold: f64 = rng.gen::<f64>() * total_weight;
+    let mut cumulative = 0.0;
+    let mut last_eligible = None;
+    for (sp, &w) in contemporaneity.alive(time_idx).zip(&weights) {
+        if w <= 0.0 {
+            continue;
+        }
+        cumulative += w;
+        last_eligible = Some(sp);
+        if cumulative >= threshold {
+            return Some(sp);
+        }
+    }
+    last_eligible
+}
+
+/// Picks the recipient of an ordinary transfer from `donor`.
+///
+/// Uses uniform or assortative selection, switching to weighted selection
+/// when a recipient kernel is configured or an active transfer highway
+/// boosts some recipients of this donor.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn choose_transfer_recipient<R: Rng>(
+    depths: &[f64],
+    contemporaneity: &Contemporaneity,
+    lca_depths: Option<&LcaDepths>,
+    transfer_alpha: Option<f64>,
+    highways: Option<&ActiveHighways>,
+    kernel: Option<&dyn RecipientKernel>,
+    event_time: f64,
+    donor: usize,
+    rng: &mut R,
+) -> Option<usize> {
+    let boosts = highways.and_then(|h| h.recipient_boosts(donor, event_time));
+    let assortative = transfer_alpha.zip(lca_depths);
+    if boosts.is_none() && kernel.is_none() {
+        return match assortative {
+            Some((alpha, lca)) => select_transfer_recipient_assortative(
+                depths,
+                contemporaneity,
+                lca,
+                event_time,
+                donor,
+                alpha,
+                rng,
+            ),
+            None => select_transfer_recipient(depths, contemporaneity, event_time, donor, rng),
+        };
+    }
+
+    select_transfer_recipient_weighted(
+        depths,
+        contemporaneity,
+        event_time,
+        donor,
+        |sp| {
+            let mut weight = kernel.map_or(1.0, |k| k.weight(donor, sp, event_time));
+            if let Some((alpha, lca)) = assortative {
+                weight *= (-alpha * 2.0 * (event_time - lca.get(donor, sp))).exp();
+            }
+            if let Some(boosts) = &boosts {
+                for &(recipient, boost) in boosts {
+                    if recipient == sp {
+                        weight *= boost;
+                    }
+                }
+            }
+            weight
+        },
+        rng,
+    )
+}
+
+/// Moves `gene` from `donor` to `recipient`, removing a random resident copy
+/// first when the transfer is drawn to be a replacement.
+///
+/// With copy-number dependence the transfer may be rejected, depending on
+/// whether the recipient already carries a copy.
+pub(crate) fn apply_transfer<R: Rng>(
+    state: &mut SimulationState<'_>,
+    gene: usize,
+    donor: usize,
+    recipient: usize,
+    event_time: f64,
+    config: &DTLConfig,
+    rng: &mut R,
+) {
+    if let Some(dependence) = &config.copy_number_dependence {
+        if !dependence.accepts_transfer(state, recipient, rng) {
+            return;
+        }
+    }
+    // Replacement transfer: find and remove victim BEFORE adding transfer
+    let is_replacement = config
+        .replacement_transfer
+        .is_some_and(|p| p > 0.0 && rng.gen::<f64>() < p);
+    if is_replacement {
+        if let Some(victim) = state.random_gene_in_species(recipient, rng) {
+            state.handle_loss(victim, recipient, event_time);
+        }
+    }
+    state.handle_transfer(gene, donor, recipient, event_time);
+}
+
+/// Performs one transfer along a highway active at `event_time`.
+pub(crate) fn apply_highway_transfer<R: Rng>(
+    mode: DTLMode,
+    state: &mut SimulationState<'_>,
+    highways: &ActiveHighways,
+    highway_rate: f64,
+    event_time: f64,
+    config: &DTLConfig,
+    rng: &mut R,
+) {
+    let Some((donor, recipient)) = highways.select(mode, state, event_time, highway_rate, rng)
+    else {
+        return;
+    };
+    if let Some(gene) = state.random_gene_in_species(donor, rng) {
+        apply_transfer(state, gene, donor, recipient, event_time, config, rng);
+    }
+}
+
 /// Maps a time value to the index of the time subdivision interval containing it.
 ///
 /// Given a sorted `depths` array representing time subdivision boundaries, this function
@@ -614,11 +454,11 @@ pub(crate) fn select_transfer_recipient_assortative<R: Rng>(
 ///
 /// The returned index `j` satisfies `depths[j-1] < time <= depths[j]`, which is consistent
 /// with how [`FlatTree::find_contemporaneity`](crate::node::FlatTree::find_contemporaneity)
-/// indexes its data: `contemporaneity[j]` contains the species alive during the interval
-/// `(depths[j-1], depths[j]]`. Therefore:
+/// and [`Contemporaneity`] index slices: slice `j` contains the species alive during the
+/// interval `(depths[j-1], depths[j]]`. Therefore:
 ///
 /// ```text
-/// contemporaneity[find_time_index(depths, t)]
+/// contemporaneity.alive(find_time_index(depths, t))
 /// ```
 ///
 /// gives the set of species alive at time `t`.
@@ -730,23 +570,23 @@ pub fn count_extant_genes(rec_tree: &RecTree) -> usize {
                 Some(idx) => idx,
                 None => return false, // unmapped node cannot be extant
             };
-            let species_node = &rec_tree.species_tree.nodes[species_idx];
-
-            // If bd_event is set, check for Leaf (not Extinction)
-            // If bd_event is None (tree from Newick), fall back to checking no children
-            match species_node.bd_event {
-                Some(BDEvent::Leaf) => true,
-                Some(BDEvent::Extinction) => false,
-                Some(BDEvent::Speciation) => false, // Internal nodes aren't leaves
-                None => {
-                    // Fallback for trees without bd_event: check if node has no children
-                    species_node.left_child.is_none() && species_node.right_child.is_none()
-                }
-            }
+            is_extant_species(&rec_tree.species_tree.nodes[species_idx])
         })
         .count()
 }
 
+/// Whether a species-tree node is an extant leaf.
+///
+/// Uses `bd_event` when set; trees without it (e.g. parsed from Newick) fall
+/// back to checking that the node has no children.
+pub(crate) fn is_extant_species(species_node: &FlatNode) -> bool {
+    match species_node.bd_event {
+        Some(BDEvent::Leaf) => true,
+        Some(BDEvent::Extinction) | Some(BDEvent::Speciation) => false,
+        None => species_node.left_child.is_none() && species_node.right_child.is_none(),
+    }
+}
+
 /// Counts events by type in a RecTree
 pub fn count_events(rec_tree: &RecTree) -> (usize, usize, usize, usize, usize) {
     let mut speciations = 0;
@@ -911,12 +751,8 @@ mod tests {
     #[test]
     fn get_contemporaneous_recipients_excludes_donor() {
         let depths = vec![0.0, 1.0, 2.0];
-        // contemporaneity[1] has species 0, 1, 2 alive in interval (0.0, 1.0]
-        let contemporaneity = vec![
-            vec![],        // interval ending at 0.0
-            vec![0, 1, 2], // interval (0.0, 1.0]
-            vec![1, 2],    // interval (1.0, 2.0]
-        ];
+        // Species 0, 1, 2 alive in interval (0.0, 1.0]; only 1 and 2 in (1.0, 2.0]
+        let contemporaneity = Contemporaneity::from_spans(3, [(1, 1), (1, 2), (1, 2)]);
         let recipients = get_contemporaneous_recipients(&depths, &contemporaneity, 0.5, 1);
         assert_eq!(recipients, vec![0, 2]);
     }
@@ -924,11 +760,38 @@ mod tests {
     #[test]
     fn get_contemporaneous_recipients_empty_when_donor_is_only_species() {
         let depths = vec![0.0, 1.0];
-        let contemporaneity = vec![
-            vec![],
-            vec![3], // only species 3 alive
-        ];
+        // Only species 3 alive; species 0-2 have empty spans
+        let contemporaneity = Contemporaneity::from_spans(2, [(1, 0), (1, 0), (1, 0), (1, 1)]);
         let recipients = get_contemporaneous_recipients(&depths, &contemporaneity, 0.5, 3);
         assert!(recipients.is_empty());
     }
+
+    // ---------------------------------------------------------------
+    // LcaDepths: parity with the dense matrix
+    // ---------------------------------------------------------------
+
+    #[test]
+    fn lca_depths_match_dense_matrix() {
+        let mut nodes =
+            crate::parse_newick("(((A:1,B:2)AB:1,C:2.5)ABC:0.5,(D:1,E:0.3)DE:2)root:0;").unwrap();
+        let mut tree = nodes.pop().unwrap().to_flat_tree();
+        tree.assign_depths();
+        let dense = tree.precompute_lca_depths().unwrap();
+        let lookup = LcaDepths::new(&tree).unwrap();
+        for (i, row) in dense.iter().enumerate() {
+            for (j, &depth) in row.iter().enumerate() {
+                assert_eq!(lookup.get(i, j), depth, "LCA depth of ({i}, {j})");
+            }
+        }
+    }
+
+    #[test]
+    fn lca_depths_require_assigned_depths() {
+        let mut nodes = crate::parse_newick("(A:1,B:1)root:0;").unwrap();
+        let tree = nodes.pop().unwrap().to_flat_tree();
+        assert!(matches!(
+            LcaDepths::new(&tree),
+            Err(RustreeError::MissingDepth { .. })
+        ));
+    }
 }
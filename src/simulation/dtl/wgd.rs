@@ -0,0 +1,168 @@
+// Whole-genome duplications placed on species branches
+//
+// A whole-genome duplication (WGD) duplicates every gene copy present in a
+// species at a given time. Each resulting duplicate is retained with a
+// per-copy probability; unretained duplicates are lost immediately, so the
+// gene tree still records the duplication followed by a loss.
+
+use crate::error::RustreeError;
+use crate::node::FlatTree;
+
+/// A whole-genome duplication on a species branch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WholeGenomeDuplication {
+    /// Species-tree node index (the branch ending at this node)
+    pub species: usize,
+    /// Time of the duplication, within the branch `[start, end)`
+    pub time: f64,
+    /// Probability that each duplicated copy is retained
+    pub retention: f64,
+}
+
+impl WholeGenomeDuplication {
+    /// A WGD at `time` on the branch ending at `species`, retaining all duplicates.
+    pub fn new(species: usize, time: f64) -> Self {
+        Self {
+            species,
+            time,
+            retention: 1.0,
+        }
+    }
+
+    /// Set the per-copy retention probability.
+    pub fn with_retention(mut self, retention: f64) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    fn validate(&self, idx: usize) -> Result<(), RustreeError> {
+        if !self.time.is_finite() {
+            return Err(RustreeError::Validation(format!(
+                "whole-genome duplication {idx} time must be finite, got {}",
+                self.time
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.retention) {
+            return Err(RustreeError::Validation(format!(
+                "whole-genome duplication {idx} retention must be in [0, 1], got {}",
+                self.retention
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Validate a list of WGDs, optionally against a species tree size.
+pub(crate) fn validate_wgds(
+    wgds: &[WholeGenomeDuplication],
+    node_count: Option<usize>,
+) -> Result<(), RustreeError> {
+    for (idx, wgd) in wgds.iter().enumerate() {
+        wgd.validate(idx)?;
+        if let Some(n) = node_count {
+            if wgd.species >= n {
+                return Err(RustreeError::Index(format!(
+                    "whole-genome duplication {idx} species {} is out of bounds for species tree with {n} nodes",
+                    wgd.species
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// WGDs sorted by time, consumed in order by the Gillespie loop.
+pub(crate) struct WgdSchedule {
+    wgds: Vec<WholeGenomeDuplication>,
+    next: usize,
+}
+
+impl WgdSchedule {
+    /// Sort WGDs by time, checking that each falls within its species branch.
+    pub fn new(
+        wgds: &[WholeGenomeDuplication],
+        species_tree: &FlatTree,
+    ) -> Result<Self, RustreeError> {
+        for (idx, wgd) in wgds.iter().enumerate() {
+            let node = species_tree.nodes.get(wgd.species).ok_or_else(|| {
+                RustreeError::Index(format!(
+                    "whole-genome duplication {idx} species {} is out of bounds for species tree with {} nodes",
+                    wgd.species,
+                    species_tree.nodes.len()
+                ))
+            })?;
+            let end = node.depth.ok_or_else(|| {
+                RustreeError::missing_depth("WgdSchedule::new", wgd.species, &node.name)
+            })?;
+            let start = end - node.length;
+            if !(start <= wgd.time && wgd.time < end) {
+                return Err(RustreeError::Validation(format!(
+                    "whole-genome duplication {idx} at time {} lies outside branch {} [{start}, {end})",
+                    wgd.time, node.name
+                )));
+            }
+        }
+        let mut sorted = wgds.to_vec();
+        sorted.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(Self {
+            wgds: sorted,
+            next: 0,
+        })
+    }
+
+    /// Time of the next pending WGD, or infinity.
+    pub fn next_time(&self) -> f64 {
+        self.wgds.get(self.next).map_or(f64::INFINITY, |w| w.time)
+    }
+
+    /// Take the next pending WGD.
+    pub fn pop(&mut self) -> Option<&WholeGenomeDuplication> {
+        let wgd = self.wgds.get(self.next)?;
+        self.next += 1;
+        Some(wgd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_sorts_and_checks_branches() {
+        let mut nodes = crate::newick::parse_newick("((A:1,B:1)AB:1,C:2)root:0;").unwrap();
+        let mut tree = nodes.pop().unwrap().to_flat_tree();
+        tree.assign_depths();
+        let idx = |name: &str| tree.nodes.iter().position(|n| n.name == name).unwrap();
+
+        let mut schedule = WgdSchedule::new(
+            &[
+                WholeGenomeDuplication::new(idx("C"), 1.5),
+                WholeGenomeDuplication::new(idx("AB"), 0.5),
+            ],
+            &tree,
+        )
+        .unwrap();
+        assert_eq!(schedule.next_time(), 0.5);
+        assert_eq!(schedule.pop().unwrap().species, idx("AB"));
+        assert_eq!(schedule.pop().unwrap().species, idx("C"));
+        assert_eq!(schedule.next_time(), f64::INFINITY);
+
+        assert!(WgdSchedule::new(&[WholeGenomeDuplication::new(idx("A"), 0.5)], &tree).is_err());
+    }
+
+    #[test]
+    fn validation() {
+        assert!(validate_wgds(&[WholeGenomeDuplication::new(0, f64::NAN)], None).is_err());
+        assert!(validate_wgds(
+            &[WholeGenomeDuplication::new(0, 1.0).with_retention(1.5)],
+            None
+        )
+        .is_err());
+        assert!(validate_wgds(&[WholeGenomeDuplication::new(4, 1.0)], Some(3)).is_err());
+        assert!(validate_wgds(
+            &[WholeGenomeDuplication::new(1, 1.0).with_retention(0.3)],
+            Some(3)
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,403 @@
+// Genome-structured DTL simulation
+//
+// Evolves ordered gene contents along the species tree instead of one family
+// at a time. Each species carries a linear genome, a list of gene copies from
+// many families. Duplications, transfers and losses act on contiguous
+// segments, so neighbouring genes (e.g. operons) are duplicated, transferred
+// and lost together. Every family still gets its own reconciled gene tree, and
+// the final gene order is reported for each extant species.
+
+use crate::bd::{generate_events_from_tree, BDEvent};
+use crate::dtl::DTLEvent;
+use crate::error::RustreeError;
+use crate::node::{Event, FlatTree, RecTree};
+use crate::simulation::dtl::contemporaneity::Contemporaneity;
+use crate::simulation::dtl::state::SimulationState;
+use crate::simulation::dtl::utils::{finalize_simulation, select_transfer_recipient};
+use crate::simulation::utils::draw_waiting_time;
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::Arc;
+
+/// Configuration of the genome-structured simulation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenomeConfig {
+    /// Number of genes (one family each) in the ancestral genome
+    pub initial_genes: usize,
+    /// Segmental duplication rate, per gene position
+    pub lambda_d: f64,
+    /// Segmental transfer rate, per gene position
+    pub lambda_t: f64,
+    /// Segmental loss rate, per gene position
+    pub lambda_l: f64,
+    /// Mean number of genes affected by one event (geometric segment lengths)
+    pub mean_segment_length: f64,
+}
+
+impl GenomeConfig {
+    /// Create a validated genome simulation configuration.
+    pub fn new(
+        initial_genes: usize,
+        lambda_d: f64,
+        lambda_t: f64,
+        lambda_l: f64,
+        mean_segment_length: f64,
+    ) -> Result<Self, RustreeError> {
+        let config = Self {
+            initial_genes,
+            lambda_d,
+            lambda_t,
+            lambda_l,
+            mean_segment_length,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), RustreeError> {
+        for (name, rate) in [
+            ("Duplication", self.lambda_d),
+            ("Transfer", self.lambda_t),
+            ("Loss", self.lambda_l),
+        ] {
+            if !(rate.is_finite() && rate >= 0.0) {
+                return Err(RustreeError::Validation(format!(
+                    "{name} rate must be non-negative and finite, got {rate}"
+                )));
+            }
+        }
+        if !(self.mean_segment_length.is_finite() && self.mean_segment_length >= 1.0) {
+            return Err(RustreeError::Validation(format!(
+                "mean_segment_length must be finite and at least 1, got {}",
+                self.mean_segment_length
+            )));
+        }
+        Ok(())
+    }
+
+    fn total_rate(&self) -> f64 {
+        self.lambda_d + self.lambda_t + self.lambda_l
+    }
+
+    /// Draw a segment length from a geometric distribution on {1, 2, ...}.
+    fn sample_segment_length<R: Rng>(&self, rng: &mut R) -> usize {
+        let p = 1.0 / self.mean_segment_length;
+        if p >= 1.0 {
+            return 1;
+        }
+        let u: f64 = rng.gen::<f64>().max(f64::MIN_POSITIVE);
+        1 + (u.ln() / (1.0 - p).ln()).floor() as usize
+    }
+}
+
+/// One gene copy in a genome: its family and node in that family's gene tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GenomeGene {
+    pub family: usize,
+    pub gene_node: usize,
+}
+
+/// Ordered gene content of an extant species.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenomeOrder {
+    /// Species-tree node index
+    pub species: usize,
+    /// Genes from the start to the end of the chromosome
+    pub genes: Vec<GenomeGene>,
+}
+
+/// Result of a genome-structured simulation.
+#[derive(Clone, Debug)]
+pub struct GenomeSimulation {
+    /// One reconciled gene tree per family, indexed by family
+    pub families: Vec<RecTree>,
+    /// DTL events of each family
+    pub events: Vec<Vec<DTLEvent>>,
+    /// Final gene order of every extant species, in species-index order
+    pub genomes: Vec<GenomeOrder>,
+}
+
+impl GenomeSimulation {
+    /// CSV header of the genome order table.
+    pub fn genome_csv_header() -> &'static str {
+        "species,position,family,gene_node_name"
+    }
+
+    /// Genome order table: one row per gene position in each extant species.
+    #[must_use]
+    pub fn genome_table_csv(&self) -> String {
+        let mut csv = String::from(Self::genome_csv_header());
+        csv.push('\n');
+        for genome in &self.genomes {
+            for (position, gene) in genome.genes.iter().enumerate() {
+                let family = &self.families[gene.family];
+                csv.push_str(&format!(
+                    "{},{},{},{}\n",
+                    family.species_tree.nodes[genome.species].name,
+                    position,
+                    gene.family,
+                    family.gene_tree.nodes[gene.gene_node].name
+                ));
+            }
+        }
+        csv
+    }
+
+    /// Write the genome order table to a CSV file.
+    pub fn save_genome_table_csv(&self, filepath: &str) -> Result<(), RustreeError> {
+        let mut writer = BufWriter::new(File::create(filepath)?);
+        writer.write_all(self.genome_table_csv().as_bytes())?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Simulate genome evolution along `species_tree`.
+///
+/// The ancestral genome holds `config.initial_genes` single-gene families at
+/// the start of the root branch. Events occur at rate `λ × genome size` in
+/// each living species; each picks a uniformly random start position and a
+/// geometric-length segment extending towards the end of the chromosome:
+/// - **duplication** inserts a copy of the segment right after it,
+/// - **transfer** copies the segment into a random position of a uniformly
+///   chosen contemporaneous species,
+/// - **loss** removes the segment.
+///
+/// Species must have depths assigned.
+pub fn simulate_genomes<R: Rng>(
+    species_tree: &FlatTree,
+    config: &GenomeConfig,
+    rng: &mut R,
+) -> Result<GenomeSimulation, RustreeError> {
+    config.validate()?;
+    let species_events = generate_events_from_tree(species_tree)?;
+    let depths = species_tree.make_subdivision();
+    let contemporaneity = Contemporaneity::new(species_tree, &depths)?;
+
+    let root = species_tree.root;
+    let root_node = species_tree.nodes.get(root).ok_or_else(|| {
+        RustreeError::Index(format!(
+            "species tree root {root} is out of bounds ({} nodes)",
+            species_tree.nodes.len()
+        ))
+    })?;
+    let origin_time = root_node
+        .depth
+        .ok_or_else(|| RustreeError::missing_depth("simulate_genomes", root, &root_node.name))?
+        - root_node.length;
+
+    let mut states: Vec<SimulationState<'_>> = (0..config.initial_genes)
+        .map(|_| SimulationState::with_branch_total_rates(1, species_tree, None))
+        .collect();
+    let mut genomes: BTreeMap<usize, Vec<GenomeGene>> = BTreeMap::new();
+    let mut extant: Vec<GenomeOrder> = Vec::new();
+
+    let ancestral = states
+        .iter_mut()
+        .enumerate()
+        .map(|(family, state)| {
+            let gene_node = state.create_gene_node(None, root, Event::Speciation, origin_time);
+            state.add_gene_to_species(root, gene_node);
+            GenomeGene { family, gene_node }
+        })
+        .collect();
+    genomes.insert(root, ancestral);
+
+    let mut species_event_idx = species_events
+        .iter()
+        .take_while(|e| e.time < origin_time)
+        .count();
+    let mut current_time = origin_time.next_up();
+
+    loop {
+        let next_species_event_time = species_events
+            .get(species_event_idx)
+            .map_or(f64::INFINITY, |e| e.time);
+        let genome_size: usize = genomes.values().map(Vec::len).sum();
+        let next_event_time =
+            current_time + draw_waiting_time(genome_size as f64 * config.total_rate(), rng);
+
+        if next_species_event_time == f64::INFINITY && next_event_time == f64::INFINITY {
+            break;
+        }
+
+        if next_species_event_time <= next_event_time {
+            let sp_event = &species_events[species_event_idx];
+            current_time = sp_event.time;
+            let species = sp_event.node_id;
+            let genome = genomes.remove(&species).unwrap_or_default();
+            for gene in &genome {
+                states[gene.family].take_genes_for_species(species);
+            }
+
+            match sp_event.event_type {
+                BDEvent::Speciation => {
+                    let (left, right) = sp_event.child1.zip(sp_event.child2).ok_or_else(|| {
+                        RustreeError::Simulation(format!(
+                            "Speciation event for node {species} is missing a child"
+                        ))
+                    })?;
+                    let mut left_genome = Vec::with_capacity(genome.len());
+                    let mut right_genome = Vec::with_capacity(genome.len());
+                    for gene in genome {
+                        let (l, r) = states[gene.family].handle_speciation(
+                            gene.gene_node,
+                            species,
+                            left,
+                            right,
+                            current_time,
+                        );
+                        left_genome.push(GenomeGene {
+                            family: gene.family,
+                            gene_node: l,
+                        });
+                        right_genome.push(GenomeGene {
+                            family: gene.family,
+                            gene_node: r,
+                        });
+                    }
+                    genomes.insert(left, left_genome);
+                    genomes.insert(right, right_genome);
+                }
+                BDEvent::Extinction => {
+                    for gene in genome {
+                        states[gene.family].handle_loss(gene.gene_node, species, current_time);
+                    }
+                }
+                BDEvent::Leaf => {
+                    for gene in &genome {
+                        states[gene.family].handle_leaf(gene.gene_node, species, current_time);
+                    }
+                    extant.push(GenomeOrder {
+                        species,
+                        genes: genome,
+                    });
+                }
+            }
+
+            species_event_idx += 1;
+            current_time = current_time.next_up();
+            continue;
+        }
+
+        current_time = next_event_time;
+        let mut threshold = rng.gen_range(0..genome_size);
+        let Some((&species, genome)) = genomes.iter().find(|(_, genome)| {
+            if threshold < genome.len() {
+                true
+            } else {
+                threshold -= genome.len();
+                false
+            }
+        }) else {
+            continue;
+        };
+        let start = threshold;
+        let end = (start + config.sample_segment_length(rng)).min(genome.len());
+        let segment: Vec<GenomeGene> = genome[start..end].to_vec();
+
+        let event_draw = rng.gen::<f64>() * config.total_rate();
+        if event_draw < config.lambda_d {
+            let mut originals = Vec::with_capacity(segment.len());
+            let mut copies = Vec::with_capacity(segment.len());
+            for gene in segment {
+                let (c1, c2) =
+                    states[gene.family].handle_duplication(gene.gene_node, species, current_time);
+                originals.push(GenomeGene {
+                    family: gene.family,
+                    gene_node: c1,
+                });
+                copies.push(GenomeGene {
+                    family: gene.family,
+                    gene_node: c2,
+                });
+            }
+            let genome = genomes.entry(species).or_default();
+            originals.extend(copies);
+            genome.splice(start..end, originals);
+        } else if event_draw < config.lambda_d + config.lambda_t {
+            let Some(recipient) =
+                select_transfer_recipient(&depths, &contemporaneity, current_time, species, rng)
+            else {
+                continue;
+            };
+            let mut kept = Vec::with_capacity(segment.len());
+            let mut transferred = Vec::with_capacity(segment.len());
+            for gene in segment {
+                let (donor_child, recipient_child) = states[gene.family].handle_transfer(
+                    gene.gene_node,
+                    species,
+                    recipient,
+                    current_time,
+                );
+                kept.push(GenomeGene {
+                    family: gene.family,
+                    gene_node: donor_child,
+                });
+                transferred.push(GenomeGene {
+                    family: gene.family,
+                    gene_node: recipient_child,
+                });
+            }
+            genomes.entry(species).or_default().splice(start..end, kept);
+            let recipient_genome = genomes.entry(recipient).or_default();
+            let insert_at = rng.gen_range(0..=recipient_genome.len());
+            recipient_genome.splice(insert_at..insert_at, transferred);
+        } else {
+            for gene in &segment {
+                states[gene.family].handle_loss(gene.gene_node, species, current_time);
+            }
+            genomes.entry(species).or_default().drain(start..end);
+        }
+    }
+
+    extant.sort_by_key(|genome| genome.species);
+    let species_arc = Arc::new(species_tree.clone());
+    let mut families = Vec::with_capacity(states.len());
+    let mut events = Vec::with_capacity(states.len());
+    for state in states {
+        let rec_tree = finalize_simulation(
+            Arc::clone(&species_arc),
+            state.gene_nodes,
+            state.node_mapping,
+            state.event_mapping,
+            root,
+            origin_time,
+        )?;
+        families.push(rec_tree);
+        events.push(state.events);
+    }
+
+    Ok(GenomeSimulation {
+        families,
+        events,
+        genomes: extant,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn config_validation() {
+        assert!(GenomeConfig::new(10, 0.1, 0.1, 0.1, 2.0).is_ok());
+        assert!(GenomeConfig::new(10, -0.1, 0.1, 0.1, 2.0).is_err());
+        assert!(GenomeConfig::new(10, 0.1, 0.1, 0.1, 0.5).is_err());
+    }
+
+    #[test]
+    fn segment_lengths_have_requested_mean() {
+        let config = GenomeConfig::new(1, 0.0, 0.0, 0.0, 3.0).unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        let n = 20_000;
+        let mean = (0..n)
+            .map(|_| config.sample_segment_length(&mut rng))
+            .sum::<usize>() as f64
+            / n as f64;
+        assert!((mean - 3.0).abs() < 0.1, "mean = {mean}");
+    }
+}
@@ -1,5 +1,9 @@
-// Simulation modules: birth-death and DTL (Duplication-Transfer-Loss)
+// Simulation modules: birth-death, DTL (Duplication-Transfer-Loss), genome-structured
+// DTL, molecular clocks and sequence evolution
 
 pub mod bd;
+pub mod clock;
 pub mod dtl;
+pub mod genome;
+pub mod sequence;
 pub(crate) mod utils;
@@ -0,0 +1,88 @@
+// Multiple sequence alignments and their FASTA / PHYLIP serialization
+
+use crate::error::RustreeError;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Named, equal-length sequences.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Alignment {
+    pub names: Vec<String>,
+    pub sequences: Vec<String>,
+}
+
+impl Alignment {
+    /// Number of sequences.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Number of sites (zero for an empty alignment).
+    pub fn n_sites(&self) -> usize {
+        self.sequences.first().map_or(0, String::len)
+    }
+
+    /// FASTA text, one unwrapped sequence line per record.
+    #[must_use]
+    pub fn to_fasta(&self) -> String {
+        let mut out = String::with_capacity(self.len() * (self.n_sites() + 32));
+        for (name, seq) in self.names.iter().zip(&self.sequences) {
+            out.push('>');
+            out.push_str(name);
+            out.push('\n');
+            out.push_str(seq);
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Relaxed sequential PHYLIP text (names separated from sequences by a space).
+    #[must_use]
+    pub fn to_phylip(&self) -> String {
+        let mut out = String::with_capacity(self.len() * (self.n_sites() + 32) + 32);
+        out.push_str(&format!("{} {}\n", self.len(), self.n_sites()));
+        for (name, seq) in self.names.iter().zip(&self.sequences) {
+            out.push_str(name);
+            out.push(' ');
+            out.push_str(seq);
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Write the alignment as FASTA.
+    pub fn save_fasta(&self, filepath: &str) -> Result<(), RustreeError> {
+        write_text(filepath, &self.to_fasta())
+    }
+
+    /// Write the alignment as relaxed PHYLIP.
+    pub fn save_phylip(&self, filepath: &str) -> Result<(), RustreeError> {
+        write_text(filepath, &self.to_phylip())
+    }
+}
+
+fn write_text(filepath: &str, text: &str) -> Result<(), RustreeError> {
+    let mut writer = BufWriter::new(File::create(filepath)?);
+    writer.write_all(text.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fasta_and_phylip_layout() {
+        let alignment = Alignment {
+            names: vec!["a".into(), "b".into()],
+            sequences: vec!["ACGT".into(), "AC-T".into()],
+        };
+        assert_eq!(alignment.to_fasta(), ">a\nACGT\n>b\nAC-T\n");
+        assert_eq!(alignment.to_phylip(), "2 4\na ACGT\nb AC-T\n");
+    }
+}
@@ -0,0 +1,423 @@
+// Sequence evolution along gene trees
+//
+// Simulates nucleotide or amino-acid alignments along the branches of a
+// `FlatTree` under a time-reversible substitution model, with optional
+// gamma-distributed rate heterogeneity and invariant sites. Each site evolves
+// by the jump chain of its continuous-time Markov process, so no matrix
+// exponentials are needed and the result is exact for any branch length.
+//
+// Branch lengths are read as expected substitutions per site; the rate matrix
+// is normalized so that a site with rate 1 undergoes one substitution per unit
+// of branch length on average.
+
+mod alignment;
+mod matrices;
+
+pub use alignment::Alignment;
+
+use crate::error::RustreeError;
+use crate::node::{Event, FlatTree, RecTree};
+use crate::simulation::utils::{draw_waiting_time, sample_gamma, sample_weighted_index};
+use rand::Rng;
+
+/// Time-reversible substitution model.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SubstitutionModel {
+    /// Jukes-Cantor: equal rates and frequencies
+    JC69,
+    /// Kimura two-parameter: transition/transversion ratio `kappa`
+    K80 { kappa: f64 },
+    /// Hasegawa-Kishino-Yano: `kappa` with unequal base frequencies (A, C, G, T)
+    HKY { kappa: f64, frequencies: [f64; 4] },
+    /// General time-reversible: exchangeabilities (AC, AG, AT, CG, CT, GT) and
+    /// base frequencies (A, C, G, T)
+    GTR {
+        rates: [f64; 6],
+        frequencies: [f64; 4],
+    },
+    /// LG empirical amino-acid model
+    LG,
+    /// WAG empirical amino-acid model
+    WAG,
+}
+
+const NUCLEOTIDES: &[u8; 4] = b"ACGT";
+
+impl SubstitutionModel {
+    /// Symbols emitted by this model.
+    pub fn alphabet(&self) -> &'static [u8] {
+        match self {
+            SubstitutionModel::LG | SubstitutionModel::WAG => matrices::AMINO_ACIDS,
+            _ => NUCLEOTIDES,
+        }
+    }
+
+    fn nucleotide_parameters(&self) -> Option<([f64; 6], [f64; 4])> {
+        let equal = [0.25; 4];
+        // Exchangeability order: AC, AG, AT, CG, CT, GT (transitions are AG and CT).
+        let kappa_rates = |kappa: f64| [1.0, kappa, 1.0, 1.0, kappa, 1.0];
+        match self {
+            SubstitutionModel::JC69 => Some(([1.0; 6], equal)),
+            SubstitutionModel::K80 { kappa } => Some((kappa_rates(*kappa), equal)),
+            SubstitutionModel::HKY { kappa, frequencies } => {
+                Some((kappa_rates(*kappa), *frequencies))
+            }
+            SubstitutionModel::GTR { rates, frequencies } => Some((*rates, *frequencies)),
+            SubstitutionModel::LG | SubstitutionModel::WAG => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), RustreeError> {
+        let Some((rates, frequencies)) = self.nucleotide_parameters() else {
+            return Ok(());
+        };
+        if let Some(&r) = rates.iter().find(|r| !(r.is_finite() && **r > 0.0)) {
+            return Err(RustreeError::Validation(format!(
+                "substitution model rates must be positive and finite, got {r}"
+            )));
+        }
+        validate_frequencies(&frequencies)
+    }
+
+    /// Exchangeabilities (full symmetric matrix) and stationary frequencies.
+    fn exchangeabilities(&self) -> (Vec<f64>, Vec<f64>) {
+        match self {
+            SubstitutionModel::LG => (
+                matrices::symmetric_from_lower(&matrices::LG_EXCHANGEABILITIES),
+                normalized(&matrices::LG_FREQUENCIES),
+            ),
+            SubstitutionModel::WAG => (
+                matrices::symmetric_from_lower(&matrices::WAG_EXCHANGEABILITIES),
+                normalized(&matrices::WAG_FREQUENCIES),
+            ),
+            _ => {
+                let (rates, frequencies) = self
+                    .nucleotide_parameters()
+                    .unwrap_or(([1.0; 6], [0.25; 4]));
+                let mut full = vec![0.0; 16];
+                let pairs = [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)];
+                for (&(i, j), &rate) in pairs.iter().zip(&rates) {
+                    full[i * 4 + j] = rate;
+                    full[j * 4 + i] = rate;
+                }
+                (full, normalized(&frequencies))
+            }
+        }
+    }
+}
+
+fn validate_frequencies(frequencies: &[f64]) -> Result<(), RustreeError> {
+    if frequencies.iter().any(|f| !(f.is_finite() && *f > 0.0)) {
+        return Err(RustreeError::Validation(format!(
+            "state frequencies must be positive and finite, got {frequencies:?}"
+        )));
+    }
+    let sum: f64 = frequencies.iter().sum();
+    if (sum - 1.0).abs() > 1e-6 {
+        return Err(RustreeError::Validation(format!(
+            "state frequencies must sum to 1, got {sum}"
+        )));
+    }
+    Ok(())
+}
+
+fn normalized(values: &[f64]) -> Vec<f64> {
+    let sum: f64 = values.iter().sum();
+    values.iter().map(|v| v / sum).collect()
+}
+
+/// Across-site rate heterogeneity.
+///
+/// Invariant sites never change. Variable sites draw a rate from a mean-one
+/// gamma distribution when `gamma_shape` is set; rates are rescaled so that
+/// the mean rate over all sites stays one.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct SiteRates {
+    /// Shape of the continuous gamma distribution of site rates (None = equal rates)
+    pub gamma_shape: Option<f64>,
+    /// Proportion of invariant sites
+    pub invariant_proportion: f64,
+}
+
+impl SiteRates {
+    /// All sites evolve at rate one.
+    pub fn uniform() -> Self {
+        Self::default()
+    }
+
+    /// Draw variable-site rates from a mean-one gamma distribution.
+    pub fn with_gamma(mut self, shape: f64) -> Self {
+        self.gamma_shape = Some(shape);
+        self
+    }
+
+    /// Make a proportion of sites invariant.
+    pub fn with_invariant_sites(mut self, proportion: f64) -> Self {
+        self.invariant_proportion = proportion;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), RustreeError> {
+        if let Some(shape) = self.gamma_shape {
+            if !(shape.is_finite() && shape > 0.0) {
+                return Err(RustreeError::Validation(format!(
+                    "gamma_shape must be positive and finite, got {shape}"
+                )));
+            }
+        }
+        if !(0.0..1.0).contains(&self.invariant_proportion) {
+            return Err(RustreeError::Validation(format!(
+                "invariant_proportion must be in [0, 1), got {}",
+                self.invariant_proportion
+            )));
+        }
+        Ok(())
+    }
+
+    fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        let p_inv = self.invariant_proportion;
+        if p_inv > 0.0 && rng.gen::<f64>() < p_inv {
+            return 0.0;
+        }
+        let rate = self
+            .gamma_shape
+            .map_or(1.0, |shape| sample_gamma(shape, 1.0 / shape, rng));
+        rate / (1.0 - p_inv)
+    }
+}
+
+/// Substitution model plus across-site rate heterogeneity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SequenceModel {
+    pub substitution: SubstitutionModel,
+    pub site_rates: SiteRates,
+}
+
+impl SequenceModel {
+    /// Create a validated sequence model.
+    pub fn new(
+        substitution: SubstitutionModel,
+        site_rates: SiteRates,
+    ) -> Result<Self, RustreeError> {
+        substitution.validate()?;
+        site_rates.validate()?;
+        Ok(Self {
+            substitution,
+            site_rates,
+        })
+    }
+}
+
+/// Normalized rate matrix in jump-chain form.
+struct JumpChain {
+    frequencies: Vec<f64>,
+    /// Total rate of leaving each state.
+    exit_rates: Vec<f64>,
+    /// Row-major jump probabilities (zero on the diagonal).
+    jumps: Vec<f64>,
+    n: usize,
+}
+
+impl JumpChain {
+    fn new(model: &SubstitutionModel) -> Self {
+        let (exchangeabilities, frequencies) = model.exchangeabilities();
+        let n = frequencies.len();
+        let mut q = vec![0.0; n * n];
+        for i in 0..n {
+            for j in 0..n {
+                if i != j {
+                    q[i * n + j] = exchangeabilities[i * n + j] * frequencies[j];
+                }
+            }
+        }
+        let exit: Vec<f64> = (0..n).map(|i| q[i * n..(i + 1) * n].iter().sum()).collect();
+        let mean_rate: f64 = exit.iter().zip(&frequencies).map(|(e, f)| e * f).sum();
+        let exit_rates = exit.iter().map(|e| e / mean_rate).collect();
+        let jumps = (0..n * n).map(|k| q[k] / exit[k / n]).collect();
+        Self {
+            frequencies,
+            exit_rates,
+            jumps,
+            n,
+        }
+    }
+
+    fn evolve<R: Rng>(&self, mut state: usize, duration: f64, rng: &mut R) -> usize {
+        let mut remaining = duration;
+        loop {
+            remaining -= draw_waiting_time(self.exit_rates[state], rng);
+            if remaining < 0.0 {
+                return state;
+            }
+            let row = &self.jumps[state * self.n..(state + 1) * self.n];
+            state = sample_weighted_index(row, rng).unwrap_or(state);
+        }
+    }
+}
+
+/// Simulate an alignment of `length` sites along `tree`.
+///
+/// The root sequence is drawn from the stationary frequencies and evolved
+/// down every branch; one sequence is returned per leaf, named after the leaf
+/// and in node-index order.
+pub fn simulate_sequences<R: Rng>(
+    tree: &FlatTree,
+    model: &SequenceModel,
+    length: usize,
+    rng: &mut R,
+) -> Result<Alignment, RustreeError> {
+    let leaves: Vec<usize> = (0..tree.nodes.len())
+        .filter(|&i| tree.nodes[i].left_child.is_none() && tree.nodes[i].right_child.is_none())
+        .collect();
+    simulate_for_nodes(tree, model, length, &leaves, rng)
+}
+
+/// Simulate an alignment along the gene tree of a reconciled tree.
+///
+/// Only extant gene copies (leaves with a [`Event::Leaf`] event) are
+/// returned; lost lineages still evolve but are dropped from the alignment.
+pub fn simulate_rectree_sequences<R: Rng>(
+    rec_tree: &RecTree,
+    model: &SequenceModel,
+    length: usize,
+    rng: &mut R,
+) -> Result<Alignment, RustreeError> {
+    let tree = &rec_tree.gene_tree;
+    let extant: Vec<usize> = (0..tree.nodes.len())
+        .filter(|&i| rec_tree.event_mapping[i] == Event::Leaf && tree.nodes[i].left_child.is_none())
+        .collect();
+    simulate_for_nodes(tree, model, length, &extant, rng)
+}
+
+fn simulate_for_nodes<R: Rng>(
+    tree: &FlatTree,
+    model: &SequenceModel,
+    length: usize,
+    output: &[usize],
+    rng: &mut R,
+) -> Result<Alignment, RustreeError> {
+    model.substitution.validate()?;
+    model.site_rates.validate()?;
+    if tree.root >= tree.nodes.len() {
+        return Err(RustreeError::Tree(format!(
+            "tree root {} is out of bounds for tree with {} nodes",
+            tree.root,
+            tree.nodes.len()
+        )));
+    }
+    if let Some(node) = tree
+        .nodes
+        .iter()
+        .find(|n| !(n.length.is_finite() && n.length >= 0.0))
+    {
+        return Err(RustreeError::Validation(format!(
+            "branch length of node '{}' must be non-negative and finite, got {}",
+            node.name, node.length
+        )));
+    }
+
+    let chain = JumpChain::new(&model.substitution);
+    let site_rates: Vec<f64> = (0..length).map(|_| model.site_rates.sample(rng)).collect();
+
+    let mut sequences: Vec<Option<Vec<u8>>> = vec![None; tree.nodes.len()];
+    let root: Vec<u8> = (0..length)
+        .map(|_| sample_weighted_index(&chain.frequencies, rng).unwrap_or(0) as u8)
+        .collect();
+    sequences[tree.root] = Some(root);
+
+    // Preorder traversal: each child evolves from its parent's sequence.
+    let mut stack = vec![tree.root];
+    while let Some(parent) = stack.pop() {
+        let node = &tree.nodes[parent];
+        for child in [node.left_child, node.right_child].into_iter().flatten() {
+            let length = tree.nodes[child].length;
+            let evolved = sequences[parent]
+                .as_ref()
+                .map(|seq| {
+                    seq.iter()
+                        .zip(&site_rates)
+                        .map(|(&state, &rate)| {
+                            chain.evolve(state as usize, rate * length, rng) as u8
+                        })
+                        .collect()
+                })
+                .ok_or_else(|| {
+                    RustreeError::Tree(format!("node {parent} visited before its sequence"))
+                })?;
+            sequences[child] = Some(evolved);
+            stack.push(child);
+        }
+    }
+
+    let alphabet = model.substitution.alphabet();
+    let mut alignment = Alignment::default();
+    for &idx in output {
+        let seq = sequences[idx].as_ref().ok_or_else(|| {
+            RustreeError::Tree(format!(
+                "node '{}' is not reachable from the root",
+                tree.nodes[idx].name
+            ))
+        })?;
+        alignment.names.push(tree.nodes[idx].name.clone());
+        alignment
+            .sequences
+            .push(seq.iter().map(|&s| alphabet[s as usize] as char).collect());
+    }
+    Ok(alignment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empirical_frequencies_sum_to_one() {
+        for freqs in [matrices::LG_FREQUENCIES, matrices::WAG_FREQUENCIES] {
+            let sum: f64 = freqs.iter().sum();
+            assert!((sum - 1.0).abs() < 1e-3, "sum = {sum}");
+        }
+    }
+
+    #[test]
+    fn jump_chain_is_normalized() {
+        for model in [
+            SubstitutionModel::JC69,
+            SubstitutionModel::HKY {
+                kappa: 4.0,
+                frequencies: [0.1, 0.2, 0.3, 0.4],
+            },
+            SubstitutionModel::LG,
+            SubstitutionModel::WAG,
+        ] {
+            let chain = JumpChain::new(&model);
+            let mean: f64 = chain
+                .exit_rates
+                .iter()
+                .zip(&chain.frequencies)
+                .map(|(e, f)| e * f)
+                .sum();
+            assert!((mean - 1.0).abs() < 1e-12);
+            for i in 0..chain.n {
+                let row: f64 = chain.jumps[i * chain.n..(i + 1) * chain.n].iter().sum();
+                assert!((row - 1.0).abs() < 1e-12);
+                assert_eq!(chain.jumps[i * chain.n + i], 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn model_validation() {
+        assert!(SubstitutionModel::K80 { kappa: -1.0 }.validate().is_err());
+        assert!(SubstitutionModel::HKY {
+            kappa: 2.0,
+            frequencies: [0.5, 0.5, 0.5, 0.5]
+        }
+        .validate()
+        .is_err());
+        assert!(SiteRates::uniform()
+            .with_invariant_sites(1.0)
+            .validate()
+            .is_err());
+        assert!(SiteRates::uniform().with_gamma(0.0).validate().is_err());
+    }
+}
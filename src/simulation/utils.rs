@@ -16,3 +16,115 @@ pub(crate) fn draw_waiting_time<R: Rng>(total_rate: f64, rng: &mut R) -> f64 {
         f64::INFINITY
     }
 }
+
+/// Draws a standard normal variate using the Box-Muller transform.
+#[inline]
+pub(crate) fn sample_standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    let u1: f64 = rng.gen::<f64>().max(f64::MIN_POSITIVE);
+    let u2: f64 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+/// Draws a Gamma(shape, scale) variate (Marsaglia-Tsang).
+///
+/// Shapes below 1 use the boosting identity
+/// `Gamma(a) = Gamma(a + 1) * U^(1/a)`. Callers are responsible for passing
+/// a positive, finite shape and scale.
+pub(crate) fn sample_gamma<R: Rng>(shape: f64, scale: f64, rng: &mut R) -> f64 {
+    if shape < 1.0 {
+        let u: f64 = rng.gen::<f64>().max(f64::MIN_POSITIVE);
+        return sample_gamma(shape + 1.0, scale, rng) * u.powf(1.0 / shape);
+    }
+
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        let x = sample_standard_normal(rng);
+        let v = 1.0 + c * x;
+        if v <= 0.0 {
+            continue;
+        }
+        let v = v * v * v;
+        let u: f64 = rng.gen::<f64>().max(f64::MIN_POSITIVE);
+        if u < 1.0 - 0.0331 * x.powi(4) || u.ln() < 0.5 * x * x + d * (1.0 - v + v.ln()) {
+            return d * v * scale;
+        }
+    }
+}
+
+/// Draws a lognormal variate with the given log-scale mean and standard deviation.
+#[inline]
+pub(crate) fn sample_lognormal<R: Rng>(mu: f64, sigma: f64, rng: &mut R) -> f64 {
+    (mu + sigma * sample_standard_normal(rng)).exp()
+}
+
+/// Draws an index with probability proportional to `weights`.
+///
+/// Returns `None` when no weight is positive.
+pub(crate) fn sample_weighted_index<R: Rng>(weights: &[f64], rng: &mut R) -> Option<usize> {
+    let total: f64 = weights.iter().filter(|w| **w > 0.0).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let mut threshold = rng.gen::<f64>() * total;
+    let mut last_positive = None;
+    for (idx, &w) in weights.iter().enumerate() {
+        if w <= 0.0 {
+            continue;
+        }
+        if threshold < w {
+            return Some(idx);
+        }
+        threshold -= w;
+        last_positive = Some(idx);
+    }
+    last_positive
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn mean_and_variance(values: &[f64]) -> (f64, f64) {
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        (mean, var)
+    }
+
+    #[test]
+    fn gamma_moments_match_shape_and_scale() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for &(shape, scale) in &[(0.5, 2.0), (1.0, 1.0), (4.0, 0.25)] {
+            let draws: Vec<f64> = (0..40_000)
+                .map(|_| sample_gamma(shape, scale, &mut rng))
+                .collect();
+            let (mean, var) = mean_and_variance(&draws);
+            assert!((mean - shape * scale).abs() < 0.05 * shape * scale + 0.02);
+            assert!((var - shape * scale * scale).abs() < 0.1 * shape * scale * scale + 0.02);
+        }
+    }
+
+    #[test]
+    fn lognormal_mean_matches_closed_form() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let sigma: f64 = 0.5;
+        let draws: Vec<f64> = (0..40_000)
+            .map(|_| sample_lognormal(-0.5 * sigma * sigma, sigma, &mut rng))
+            .collect();
+        let (mean, _) = mean_and_variance(&draws);
+        assert!((mean - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn weighted_index_skips_zero_weights() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..1_000 {
+            let idx = sample_weighted_index(&[0.0, 1.0, 0.0, 3.0], &mut rng).unwrap();
+            assert!(idx == 1 || idx == 3);
+        }
+        assert_eq!(sample_weighted_index(&[0.0, 0.0], &mut rng), None);
+    }
+}
@@ -331,3 +331,67 @@ pub fn spr_topology(
 
     Ok(())
 }
+
+/// NNI move for ROOTED trees around the branch above `node_idx` (`U`).
+///
+/// Swaps one child of `U` (left if `swap_left`, otherwise right) with `U`'s
+/// sibling `S`. Branch lengths travel with the swapped subtrees.
+/// Rules & Limitations:
+/// - `U` must be an internal, non-root node with two children.
+/// - `U`'s parent must have two children (binary tree).
+pub fn nni_topology(
+    flat_tree: &mut FlatTree,
+    node_idx: usize,
+    swap_left: bool,
+) -> Result<(), String> {
+    let node = flat_tree
+        .nodes
+        .get(node_idx)
+        .ok_or_else(|| format!("NNI node index {} out of bounds.", node_idx))?;
+    let parent_idx = node
+        .parent
+        .ok_or_else(|| format!("Invalid NNI: node {} is the root.", node_idx))?;
+    let (Some(left_idx), Some(right_idx)) = (node.left_child, node.right_child) else {
+        return Err(format!(
+            "Invalid NNI: node {} is not an internal node with two children.",
+            node_idx
+        ));
+    };
+    let child_idx = if swap_left { left_idx } else { right_idx };
+
+    let (sibling_idx, node_was_left) = {
+        let p_node = &flat_tree[parent_idx];
+        match (p_node.left_child, p_node.right_child) {
+            (Some(l), Some(r)) if l == node_idx => (r, true),
+            (Some(l), Some(r)) if r == node_idx => (l, false),
+            _ => {
+                return Err(format!(
+                    "NNI consistency error: parent {} of node {} does not have it as one of two children. L:{:?}, R:{:?}",
+                    parent_idx, node_idx, p_node.left_child, p_node.right_child
+                ))
+            }
+        }
+    };
+
+    // Sibling takes the child's slot under U; the child takes U's sibling slot.
+    {
+        let u_node = &mut flat_tree.nodes[node_idx];
+        if swap_left {
+            u_node.left_child = Some(sibling_idx);
+        } else {
+            u_node.right_child = Some(sibling_idx);
+        }
+    }
+    {
+        let p_node = &mut flat_tree.nodes[parent_idx];
+        if node_was_left {
+            p_node.right_child = Some(child_idx);
+        } else {
+            p_node.left_child = Some(child_idx);
+        }
+    }
+    flat_tree.nodes[sibling_idx].parent = Some(node_idx);
+    flat_tree.nodes[child_idx].parent = Some(parent_idx);
+
+    Ok(())
+}
@@ -19,6 +19,10 @@
 //   [x] DTL rate validation    — both via validate_dtl_rates
 //   [x] Distance type parsing  — both via parse_distance_type
 //   [x] Replacement transfer   — both via validate_replacement_transfer
+//   [x] pairwise_distances_condensed / save_pairwise_distances_npy
+//                              — both via FlatTree::pairwise_distances_condensed
+//                                and FlatTree::write_pairwise_distances_npy
+//   [x] summarize              — both via ForestSummary (same columns as its CSVs)
 
 use rustree::bindings_common::{
     digit_width, extract_extant_gene_tree, init_rng, is_leaf, parse_distance_type,
@@ -319,3 +323,83 @@ fn test_species_tree_simulation_parity() {
     );
     assert!(!events.is_empty());
 }
+
+#[test]
+fn test_condensed_distances_parity() {
+    // Both Python and R delegate to tree.pairwise_distances_condensed() and
+    // tree.write_pairwise_distances_npy()
+    use rustree::parse_newick;
+
+    let nodes = parse_newick("((A:1.0,B:2.0):0.5,(C:1.5,D:2.5):0.3):0.0;").unwrap();
+    let tree = nodes[0].to_flat_tree();
+
+    let condensed = tree.pairwise_distances_condensed(DistanceType::Metric, true);
+    assert_eq!(condensed.nodes.len(), 4);
+    assert_eq!(condensed.values.len(), 6);
+    let pairs = tree.pairwise_distances(DistanceType::Metric, true).unwrap();
+    for d in &pairs {
+        let i = condensed
+            .nodes
+            .iter()
+            .position(|&n| tree.nodes[n].name == d.node1)
+            .unwrap();
+        let j = condensed
+            .nodes
+            .iter()
+            .position(|&n| tree.nodes[n].name == d.node2)
+            .unwrap();
+        assert!((condensed.get(i, j) - d.distance).abs() < 1e-12);
+    }
+
+    let mut npy = Vec::new();
+    tree.write_pairwise_distances_npy(&mut npy, DistanceType::Metric, true)
+        .unwrap();
+    assert!(npy.starts_with(b"\x93NUMPY"));
+    let data: Vec<f64> = npy[npy.len() - 6 * 8..]
+        .chunks(8)
+        .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+    assert_eq!(data, condensed.values);
+}
+
+#[test]
+fn test_forest_summary_parity() {
+    // Both Python and R build a ForestSummary and export its three tables
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use rustree::dtl::{simulate_dtl_batch, ForestSummary};
+    use rustree::parse_newick;
+    use std::sync::Arc;
+
+    let mut species = parse_newick("((A:1,B:1)AB:1,C:2)R:0;").unwrap()[0].to_flat_tree();
+    species.assign_depths();
+    let mut rng = StdRng::seed_from_u64(42);
+    let (trees, _) = simulate_dtl_batch(
+        &species,
+        species.root,
+        0.5,
+        0.3,
+        0.3,
+        None,
+        None,
+        20,
+        false,
+        &mut rng,
+    )
+    .unwrap();
+    let summary = ForestSummary::from_rec_trees(Arc::new(species), &trees).unwrap();
+
+    let csv = summary.branch_events_csv();
+    assert_eq!(csv.lines().count(), summary.branch_counts().len() + 1);
+    assert_eq!(
+        csv.lines().next().unwrap(),
+        "species_node,species_name,speciations,duplications,transfers_out,transfers_in,losses,leaves"
+    );
+    assert_eq!(
+        summary.transfers_csv().lines().count(),
+        summary.transfer_counts().len() + 1
+    );
+    assert!(summary
+        .copy_numbers_csv()
+        .starts_with("species_node,species_name,copies,families"));
+}
@@ -0,0 +1,47 @@
+// Fixtures shared by the integration tests.
+//
+// Each test binary compiles this module separately and uses only part of it.
+#![allow(dead_code)]
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::bd::simulate_bd_tree_bwd;
+use rustree::dtl::DTLEvent;
+use rustree::{parse_newick, FlatTree};
+
+/// Birth-death species tree with `n` extant species and assigned depths.
+pub fn species_tree(n: usize, seed: u64) -> FlatTree {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (mut tree, _) = simulate_bd_tree_bwd(n, 1.0, 0.3, &mut rng).unwrap();
+    tree.assign_depths();
+    tree
+}
+
+/// Parse a single Newick tree and assign its depths.
+pub fn tree(newick: &str) -> FlatTree {
+    let mut tree = parse_newick(newick).unwrap().pop().unwrap().to_flat_tree();
+    tree.assign_depths();
+    tree
+}
+
+/// Ultrametric tree `((A,B)AB,(C,D)CD)root` with unit branch lengths.
+pub fn four_taxon_tree() -> FlatTree {
+    tree("((A:1,B:1)AB:1,(C:1,D:1)CD:1)root:0;")
+}
+
+/// `(time, donor, recipient)` of every transfer, in event order.
+pub fn transfers(events: &[Vec<DTLEvent>]) -> Vec<(f64, usize, usize)> {
+    events
+        .iter()
+        .flatten()
+        .filter_map(|e| match e {
+            DTLEvent::Transfer {
+                time,
+                from_species,
+                to_species,
+                ..
+            } => Some((*time, *from_species, *to_species)),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,212 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::dtl::{
+    abc_rejection, abc_smc, simulate_dtl_batch, AbcPriors, ForestStatistics, Prior, SmcAbcOptions,
+};
+use rustree::{FlatTree, GeneForest};
+use std::sync::Arc;
+
+mod common;
+use common::tree;
+
+fn species() -> FlatTree {
+    tree("(((A:1,B:1)AB:1,(C:1.5,D:1.5)CD:0.5)ABCD:1,(E:2,F:2)EF:1)R:0.5;")
+}
+
+fn observed(species: &FlatTree, rates: (f64, f64, f64), seed: u64) -> ForestStatistics {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (trees, _) = simulate_dtl_batch(
+        species,
+        species.root,
+        rates.0,
+        rates.1,
+        rates.2,
+        None,
+        None,
+        60,
+        true,
+        &mut rng,
+    )
+    .unwrap();
+    ForestStatistics::from_forest(&GeneForest::from_rec_trees(
+        Arc::new(species.clone()),
+        trees,
+    ))
+    .unwrap()
+}
+
+fn priors() -> AbcPriors {
+    AbcPriors {
+        lambda_d: Prior::Uniform {
+            low: 0.0,
+            high: 1.0,
+        },
+        lambda_t: Prior::Fixed(0.1),
+        lambda_l: Prior::LogUniform {
+            low: 0.01,
+            high: 1.0,
+        },
+        transfer_alpha: None,
+    }
+}
+
+#[test]
+fn statistics_of_congruent_single_copy_families() {
+    let species = tree("((A:1,B:1)AB:1,(C:1,D:1)CD:1)R:0;");
+    let stats = observed(&species, (0.0, 0.0, 0.0), 1);
+    assert_eq!(stats.families, 60);
+    let expected = [1.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.5];
+    for (name, (value, expected)) in ForestStatistics::NAMES
+        .iter()
+        .zip(stats.values.iter().zip(expected))
+    {
+        assert!((value - expected).abs() < 1e-12, "{name} = {value}");
+    }
+}
+
+#[test]
+fn rejection_keeps_the_closest_prior_draws() {
+    let species = species();
+    let truth = (0.6, 0.1, 0.1);
+    let observed = observed(&species, truth, 2);
+    let mut rng = StdRng::seed_from_u64(3);
+    let result = abc_rejection(&species, &observed, &priors(), 300, 30, &mut rng).unwrap();
+
+    assert_eq!(result.samples.len(), 30);
+    assert_eq!(result.simulations, 300);
+    let tolerance = result.tolerances[0];
+    for sample in &result.samples {
+        assert!(sample.distance <= tolerance);
+        assert_eq!(sample.parameters.lambda_t, 0.1);
+        assert!((0.01..=1.0).contains(&sample.parameters.lambda_l));
+        assert!((sample.weight - 1.0 / 30.0).abs() < 1e-12);
+    }
+    // The prior mean of lambda_d is 0.5; the posterior moves towards 0.6 and
+    // concentrates well within the prior range.
+    let mean = result.posterior_mean();
+    assert!((mean.lambda_d - truth.0).abs() < 0.2, "{mean:?}");
+    assert!(mean.lambda_l < 0.3, "{mean:?}");
+    assert!(mean.transfer_alpha.is_none());
+
+    // Same seed, same samples, whatever the thread scheduling.
+    let mut rng = StdRng::seed_from_u64(3);
+    let again = abc_rejection(&species, &observed, &priors(), 300, 30, &mut rng).unwrap();
+    assert_eq!(again.samples, result.samples);
+}
+
+#[test]
+fn smc_tolerances_decrease_over_generations() {
+    let species = species();
+    let observed = observed(&species, (0.6, 0.1, 0.1), 4);
+    let priors = AbcPriors {
+        transfer_alpha: Some(Prior::Uniform {
+            low: 0.0,
+            high: 2.0,
+        }),
+        ..priors()
+    };
+    let options = SmcAbcOptions {
+        particles: 40,
+        generations: 3,
+        quantile: 0.5,
+        max_simulations: 5_000,
+    };
+    let mut rng = StdRng::seed_from_u64(5);
+    let result = abc_smc(&species, &observed, &priors, &options, &mut rng).unwrap();
+
+    assert_eq!(result.tolerances.len(), 4);
+    assert!(result.tolerances.windows(2).all(|w| w[1] <= w[0]));
+    assert_eq!(result.samples.len(), 40);
+    let total: f64 = result.samples.iter().map(|s| s.weight).sum();
+    assert!((total - 1.0).abs() < 1e-9);
+    for sample in &result.samples {
+        assert!(sample.distance <= *result.tolerances.last().unwrap());
+        let alpha = sample.parameters.transfer_alpha.unwrap();
+        assert!((0.0..=2.0).contains(&alpha));
+        assert_eq!(sample.parameters.lambda_t, 0.1);
+    }
+    assert!(result.simulations >= 4 * 40);
+
+    // A tight budget stops after the prior population.
+    let options = SmcAbcOptions {
+        max_simulations: 50,
+        ..options
+    };
+    let result = abc_smc(&species, &observed, &priors, &options, &mut rng).unwrap();
+    assert_eq!(result.tolerances.len(), 1);
+    assert_eq!(result.samples.len(), 40);
+    assert!(result.simulations <= 50);
+}
+
+#[test]
+fn invalid_priors_are_rejected() {
+    let species = species();
+    let observed = observed(&species, (0.2, 0.1, 0.1), 6);
+    let mut rng = StdRng::seed_from_u64(7);
+    let negative = AbcPriors {
+        lambda_d: Prior::Uniform {
+            low: -1.0,
+            high: 1.0,
+        },
+        ..priors()
+    };
+    assert!(abc_rejection(&species, &observed, &negative, 10, 5, &mut rng).is_err());
+    let log_zero = AbcPriors {
+        lambda_l: Prior::LogUniform {
+            low: 0.0,
+            high: 1.0,
+        },
+        ..priors()
+    };
+    assert!(abc_rejection(&species, &observed, &log_zero, 10, 5, &mut rng).is_err());
+    assert!(abc_rejection(&species, &observed, &priors(), 10, 11, &mut rng).is_err());
+    let options = SmcAbcOptions {
+        quantile: 1.0,
+        ..SmcAbcOptions::default()
+    };
+    assert!(abc_smc(&species, &observed, &priors(), &options, &mut rng).is_err());
+}
+
+#[test]
+fn draws_that_cannot_keep_extant_genes_are_rejected() {
+    let species = species();
+    let observed = observed(&species, (0.0, 0.0, 0.1), 8);
+    // Loss rates above ~5 leave no extant gene within the attempt budget.
+    let priors = AbcPriors {
+        lambda_d: Prior::Fixed(0.0),
+        lambda_t: Prior::Fixed(0.0),
+        lambda_l: Prior::Uniform {
+            low: 0.0,
+            high: 12.0,
+        },
+        transfer_alpha: None,
+    };
+    let mut rng = StdRng::seed_from_u64(9);
+    let result = abc_rejection(&species, &observed, &priors, 30, 5, &mut rng).unwrap();
+    assert_eq!(result.simulations, 30);
+    assert!(result.tolerances[0].is_finite());
+    for sample in &result.samples {
+        assert!(sample.distance.is_finite());
+        assert!(sample.parameters.lambda_l < 5.0, "{sample:?}");
+    }
+
+    let options = SmcAbcOptions {
+        particles: 10,
+        generations: 1,
+        quantile: 0.5,
+        max_simulations: 200,
+    };
+    let result = abc_smc(&species, &observed, &priors, &options, &mut rng).unwrap();
+    assert_eq!(result.samples.len(), 10);
+    assert!(result.simulations > 20);
+    assert!(result.samples.iter().all(|s| s.distance.is_finite()));
+
+    let hopeless = AbcPriors {
+        lambda_l: Prior::Uniform {
+            low: 30.0,
+            high: 40.0,
+        },
+        ..priors
+    };
+    assert!(abc_rejection(&species, &observed, &hopeless, 4, 1, &mut rng).is_err());
+}
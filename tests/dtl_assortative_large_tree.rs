@@ -0,0 +1,29 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::bd::simulate_bd_tree_bwd;
+use rustree::dtl::{simulate_dtl, DTLEvent};
+
+/// Assortative transfers on a tree large enough that the LCA-depth lookup
+/// must not be a dense n×n matrix.
+#[test]
+fn assortative_transfers_on_large_species_tree() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let (mut tree, _) = simulate_bd_tree_bwd(5_000, 1.0, 0.3, &mut rng).unwrap();
+    tree.assign_depths();
+
+    let (_, events) = simulate_dtl(
+        &tree,
+        tree.root,
+        0.0,
+        0.2,
+        0.1,
+        Some(1.0),
+        None,
+        false,
+        &mut rng,
+    )
+    .unwrap();
+    assert!(events
+        .iter()
+        .any(|e| matches!(e, DTLEvent::Transfer { .. })));
+}
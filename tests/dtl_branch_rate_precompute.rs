@@ -121,8 +121,9 @@ fn dtl_event_signatures(events: &[DTLEvent]) -> Vec<String> {
                 species_id,
                 child1,
                 child2,
+                wgd,
             } => format!(
-                "D:{}:{gene_id}:{species_id}:{child1}:{child2}",
+                "D:{}:{gene_id}:{species_id}:{child1}:{child2}:{wgd}",
                 time.to_bits()
             ),
             DTLEvent::Transfer {
@@ -0,0 +1,189 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::dtl::{
+    simulate_dtl_iter, simulate_dtl_iter_with_config, CheckpointRng, Conditioning, DTLConfig,
+    DtlCheckpoint, RecipientWeightMatrix, CHECKPOINT_FILE,
+};
+use std::path::Path;
+use std::sync::Arc;
+
+mod common;
+use common::species_tree;
+
+fn read(dir: &Path, i: usize) -> Option<String> {
+    std::fs::read_to_string(dir.join(format!("gene_{i:0>2}.xml"))).ok()
+}
+
+#[test]
+fn resumed_run_writes_the_same_remaining_files() {
+    let tree = species_tree(10, 1);
+    let full = tempfile::tempdir().unwrap();
+    let resumed = tempfile::tempdir().unwrap();
+    let plain = tempfile::tempdir().unwrap();
+
+    let mut rng = CheckpointRng::seed_from_u64(42);
+    simulate_dtl_iter(
+        &tree, tree.root, 0.3, 0.2, 0.4, None, None, 20, true, &mut rng,
+    )
+    .unwrap()
+    .save_xml_resumable(full.path().to_str().unwrap(), 3)
+    .unwrap();
+    let finished = DtlCheckpoint::read(&full.path().join(CHECKPOINT_FILE)).unwrap();
+    assert!(finished.is_finished());
+    assert_eq!(finished.stats.accepted, 20);
+
+    // Same seed through StdRng and the plain writer gives the same files.
+    let mut std_rng = StdRng::seed_from_u64(42);
+    simulate_dtl_iter(
+        &tree,
+        tree.root,
+        0.3,
+        0.2,
+        0.4,
+        None,
+        None,
+        20,
+        true,
+        &mut std_rng,
+    )
+    .unwrap()
+    .save_xml(plain.path().to_str().unwrap())
+    .unwrap();
+
+    // Simulate a job killed after 7 families.
+    let mut rng = CheckpointRng::seed_from_u64(42);
+    let mut iter = simulate_dtl_iter(
+        &tree, tree.root, 0.3, 0.2, 0.4, None, None, 20, true, &mut rng,
+    )
+    .unwrap();
+    for _ in 0..7 {
+        iter.next().unwrap().unwrap();
+    }
+    iter.checkpoint()
+        .unwrap()
+        .write(&resumed.path().join(CHECKPOINT_FILE))
+        .unwrap();
+
+    // The restarted job uses a different seed; the checkpoint overrides it.
+    let mut rng = CheckpointRng::seed_from_u64(0);
+    simulate_dtl_iter(
+        &tree, tree.root, 0.3, 0.2, 0.4, None, None, 20, true, &mut rng,
+    )
+    .unwrap()
+    .save_xml_resumable(resumed.path().to_str().unwrap(), 5)
+    .unwrap();
+
+    for i in 0..20 {
+        let expected = read(full.path(), i).unwrap();
+        assert_eq!(read(plain.path(), i).unwrap(), expected);
+        if i < 7 {
+            assert!(read(resumed.path(), i).is_none());
+        } else {
+            assert_eq!(read(resumed.path(), i).unwrap(), expected);
+        }
+    }
+    let resumed_checkpoint = DtlCheckpoint::read(&resumed.path().join(CHECKPOINT_FILE)).unwrap();
+    assert_eq!(resumed_checkpoint, finished);
+}
+
+#[test]
+fn checkpoint_from_other_settings_is_rejected() {
+    let tree = species_tree(8, 2);
+    let mut rng = CheckpointRng::seed_from_u64(1);
+    let checkpoint = simulate_dtl_iter(
+        &tree, tree.root, 0.3, 0.2, 0.4, None, None, 10, false, &mut rng,
+    )
+    .unwrap()
+    .checkpoint()
+    .unwrap();
+
+    let mut rng = CheckpointRng::seed_from_u64(1);
+    let other_rates = simulate_dtl_iter(
+        &tree, tree.root, 0.3, 0.2, 0.5, None, None, 10, false, &mut rng,
+    )
+    .unwrap()
+    .resume(&checkpoint);
+    assert!(other_rates.is_err());
+
+    let mut rng = CheckpointRng::seed_from_u64(1);
+    let other_count = simulate_dtl_iter(
+        &tree, tree.root, 0.3, 0.2, 0.4, None, None, 11, false, &mut rng,
+    )
+    .unwrap()
+    .resume(&checkpoint);
+    assert!(other_count.is_err());
+}
+
+#[test]
+fn kernels_without_fingerprint_cannot_be_checkpointed() {
+    let tree = species_tree(6, 3);
+    let n = tree.nodes.len();
+    let closure = DTLConfig::new(0.3, 0.2, 0.4, None, None)
+        .unwrap()
+        .with_recipient_kernel(Arc::new(|_: usize, _: usize, _: f64| 1.0));
+    let mut rng = CheckpointRng::seed_from_u64(1);
+    let iter =
+        simulate_dtl_iter_with_config(&tree, tree.root, closure, 5, false, &mut rng).unwrap();
+    assert!(iter.checkpoint().is_err());
+
+    let matrix = |w: f64| {
+        let weights = vec![vec![w; n]; n];
+        DTLConfig::new(0.3, 0.2, 0.4, None, None)
+            .unwrap()
+            .with_recipient_kernel(Arc::new(RecipientWeightMatrix::new(weights).unwrap()))
+    };
+    let mut rng = CheckpointRng::seed_from_u64(1);
+    let checkpoint =
+        simulate_dtl_iter_with_config(&tree, tree.root, matrix(1.0), 5, false, &mut rng)
+            .unwrap()
+            .checkpoint()
+            .unwrap();
+    let mut rng = CheckpointRng::seed_from_u64(1);
+    let other_weights =
+        simulate_dtl_iter_with_config(&tree, tree.root, matrix(2.0), 5, false, &mut rng)
+            .unwrap()
+            .resume(&checkpoint);
+    assert!(other_weights.is_err());
+}
+
+#[test]
+fn mismatch_names_the_changed_settings() {
+    let tree = species_tree(8, 4);
+    let mut rng = CheckpointRng::seed_from_u64(1);
+    let checkpoint = simulate_dtl_iter(
+        &tree, tree.root, 0.3, 0.2, 0.4, None, None, 10, false, &mut rng,
+    )
+    .unwrap()
+    .checkpoint()
+    .unwrap();
+    assert!(checkpoint
+        .settings
+        .iter()
+        .any(|(key, value)| key == "species_tree" && *value == tree.to_newick().unwrap()));
+
+    let mut rng = CheckpointRng::seed_from_u64(1);
+    let err = simulate_dtl_iter(
+        &tree, tree.root, 0.3, 0.2, 0.5, None, None, 10, false, &mut rng,
+    )
+    .unwrap()
+    .resume(&checkpoint)
+    .err()
+    .unwrap();
+    assert!(err.to_string().contains("config"), "{err}");
+    assert!(!err.to_string().contains("species_tree"), "{err}");
+}
+
+#[test]
+fn custom_predicates_cannot_be_checkpointed() {
+    let tree = species_tree(6, 5);
+    let mut rng = CheckpointRng::seed_from_u64(1);
+    let iter = simulate_dtl_iter(
+        &tree, tree.root, 0.3, 0.2, 0.4, None, None, 5, false, &mut rng,
+    )
+    .unwrap()
+    .with_conditioning(
+        Conditioning::new().with_predicate(|rec_tree, _| rec_tree.gene_tree.nodes.len() > 1),
+    )
+    .unwrap();
+    assert!(iter.checkpoint().is_err());
+}
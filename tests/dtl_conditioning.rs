@@ -0,0 +1,134 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::dtl::{
+    count_extant_genes, simulate_dtl_iter, Conditioning, DTLEvent, SimulationCondition,
+};
+use rustree::{Event, RustreeError};
+
+mod common;
+use common::species_tree;
+
+#[test]
+fn require_extant_flag_matches_explicit_conditioning() {
+    let tree = species_tree(10, 1);
+    let mut rng1 = StdRng::seed_from_u64(2);
+    let mut rng2 = StdRng::seed_from_u64(2);
+    let (a, _) = simulate_dtl_iter(
+        &tree, tree.root, 0.2, 0.2, 0.8, None, None, 20, true, &mut rng1,
+    )
+    .unwrap()
+    .collect_all()
+    .unwrap();
+    let (b, _, stats) = simulate_dtl_iter(
+        &tree, tree.root, 0.2, 0.2, 0.8, None, None, 20, false, &mut rng2,
+    )
+    .unwrap()
+    .with_conditioning(Conditioning::require_extant())
+    .unwrap()
+    .collect_conditioned()
+    .unwrap();
+
+    let names = |trees: &[rustree::RecTree]| -> Vec<String> {
+        trees
+            .iter()
+            .map(|t| t.gene_tree.to_newick().unwrap())
+            .collect()
+    };
+    assert_eq!(names(&a), names(&b));
+    assert_eq!(stats.accepted, 20);
+    assert!(stats.attempts >= 20);
+    assert!(stats.acceptance_rate() <= 1.0);
+}
+
+#[test]
+fn combined_conditions_hold_for_every_accepted_family() {
+    let tree = species_tree(12, 3);
+    let leaf = tree
+        .nodes
+        .iter()
+        .position(|n| n.left_child.is_none() && n.bd_event == Some(rustree::bd::BDEvent::Leaf))
+        .unwrap();
+    let conditioning = Conditioning::new()
+        .with_condition(SimulationCondition::ExtantGeneRange { min: 3, max: 40 })
+        .with_condition(SimulationCondition::PresentInSpecies(vec![leaf]))
+        .with_condition(SimulationCondition::MinTransfers(1))
+        .with_predicate(|rec_tree, _| rec_tree.gene_tree.nodes.len() % 2 == 1);
+
+    let mut rng = StdRng::seed_from_u64(4);
+    let mut iter = simulate_dtl_iter(
+        &tree, tree.root, 0.3, 0.5, 0.4, None, None, 15, false, &mut rng,
+    )
+    .unwrap()
+    .with_conditioning(conditioning)
+    .unwrap();
+    for result in iter.by_ref() {
+        let (rec_tree, events) = result.unwrap();
+        let extant = count_extant_genes(&rec_tree);
+        assert!((3..=40).contains(&extant));
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, DTLEvent::Transfer { .. })));
+        assert!(rec_tree
+            .event_mapping
+            .iter()
+            .zip(&rec_tree.node_mapping)
+            .any(|(e, m)| *e == Event::Leaf && *m == Some(leaf)));
+        assert_eq!(rec_tree.gene_tree.nodes.len() % 2, 1);
+    }
+    let stats = iter.conditioning_stats();
+    assert_eq!(stats.accepted, 15);
+    assert!(stats.rejected() > 0);
+    assert!(stats.acceptance_rate() < 1.0);
+}
+
+#[test]
+fn exhausted_budget_reports_an_error() {
+    let tree = species_tree(6, 5);
+    let conditioning = Conditioning::new()
+        .with_condition(SimulationCondition::MinExtantGenes(10_000))
+        .with_max_attempts(25)
+        .unwrap();
+    let mut rng = StdRng::seed_from_u64(6);
+    let mut iter = simulate_dtl_iter(
+        &tree, tree.root, 0.1, 0.1, 0.1, None, None, 1, false, &mut rng,
+    )
+    .unwrap()
+    .with_conditioning(conditioning)
+    .unwrap();
+    let err = iter.next().unwrap().unwrap_err();
+    assert!(matches!(
+        err,
+        RustreeError::ConditioningExhausted { attempts: 25, .. }
+    ));
+    assert!(err.to_string().contains("25 attempts"));
+    assert_eq!(iter.conditioning_stats().attempts, 25);
+}
+
+#[test]
+fn invalid_conditions_are_rejected() {
+    let tree = species_tree(6, 7);
+    assert!(Conditioning::new().with_max_attempts(0).is_err());
+    let mut rng = StdRng::seed_from_u64(8);
+    let result = simulate_dtl_iter(
+        &tree, tree.root, 0.1, 0.1, 0.1, None, None, 1, false, &mut rng,
+    )
+    .unwrap()
+    .with_conditioning(
+        Conditioning::new().with_condition(SimulationCondition::PresentInSpecies(vec![999])),
+    );
+    assert!(result.is_err());
+
+    // An internal node never holds extant genes, so the condition is unsatisfiable.
+    let mut rng = StdRng::seed_from_u64(8);
+    let result = simulate_dtl_iter(
+        &tree, tree.root, 0.1, 0.1, 0.1, None, None, 1, false, &mut rng,
+    )
+    .unwrap()
+    .with_conditioning(
+        Conditioning::new().with_condition(SimulationCondition::PresentInSpecies(vec![tree.root])),
+    );
+    assert!(matches!(result, Err(RustreeError::Validation(_))));
+    let empty_range = Conditioning::new()
+        .with_condition(SimulationCondition::GeneTreeSizeRange { min: 5, max: 2 });
+    assert!(empty_range.validate().is_err());
+}
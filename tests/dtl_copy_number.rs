@@ -0,0 +1,137 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::dtl::{
+    count_events, count_extant_genes, simulate_dtl_iter_with_config,
+    simulate_dtl_per_species_iter_with_config, CopyNumberDependence, DTLConfig,
+};
+use rustree::node::{Event, RecTree};
+use rustree::FlatTree;
+use std::collections::BTreeMap;
+
+mod common;
+use common::species_tree;
+
+fn simulate(tree: &FlatTree, config: DTLConfig, n: usize, seed: u64) -> Vec<RecTree> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    simulate_dtl_iter_with_config(tree, tree.root, config, n, false, &mut rng)
+        .unwrap()
+        .collect_all()
+        .unwrap()
+        .0
+}
+
+fn mean_extant(trees: &[RecTree]) -> f64 {
+    trees.iter().map(count_extant_genes).sum::<usize>() as f64 / trees.len() as f64
+}
+
+fn max_copies_per_leaf_species(rec_tree: &RecTree) -> usize {
+    let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+    for (event, species) in rec_tree.event_mapping.iter().zip(&rec_tree.node_mapping) {
+        if let (Event::Leaf, Some(species)) = (event, species) {
+            *counts.entry(*species).or_default() += 1;
+        }
+    }
+    counts.values().copied().max().unwrap_or(0)
+}
+
+#[test]
+fn neutral_dependence_matches_plain_config() {
+    let tree = species_tree(10, 1);
+    let base = DTLConfig::new(0.5, 0.3, 0.4, None, None).unwrap();
+    let neutral = base
+        .clone()
+        .with_copy_number_dependence(CopyNumberDependence::new())
+        .unwrap();
+
+    let plain = simulate(&tree, base, 20, 2);
+    let with_neutral = simulate(&tree, neutral, 20, 2);
+    for (a, b) in plain.iter().zip(&with_neutral) {
+        assert_eq!(a.gene_tree.nodes.len(), b.gene_tree.nodes.len());
+        assert_eq!(count_events(a), count_events(b));
+    }
+}
+
+#[test]
+fn dosage_loss_limits_copy_number() {
+    let tree = species_tree(10, 3);
+    let base = DTLConfig::new(1.0, 0.0, 0.1, None, None).unwrap();
+    let dosage = base
+        .clone()
+        .with_copy_number_dependence(CopyNumberDependence::new().with_dosage_loss(2.0))
+        .unwrap();
+
+    let neutral_mean = mean_extant(&simulate(&tree, base, 50, 4));
+    let dosage_mean = mean_extant(&simulate(&tree, dosage, 50, 4));
+    assert!(
+        dosage_mean < 0.5 * neutral_mean,
+        "dosage {dosage_mean} vs neutral {neutral_mean}"
+    );
+}
+
+#[test]
+fn dosage_duplication_forbids_second_paralogs() {
+    let tree = species_tree(10, 9);
+    let base = DTLConfig::new(1.0, 0.0, 0.1, None, None).unwrap();
+    let dosage = base
+        .clone()
+        .with_copy_number_dependence(CopyNumberDependence::new().with_dosage_duplication(0.0))
+        .unwrap();
+
+    let neutral = simulate(&tree, base, 50, 10);
+    let damped = simulate(&tree, dosage, 50, 10);
+    assert!(neutral.iter().any(|t| max_copies_per_leaf_species(t) > 2));
+    // A species with two copies cannot duplicate again.
+    for rec_tree in &damped {
+        assert!(max_copies_per_leaf_species(rec_tree) <= 2);
+    }
+}
+
+#[test]
+fn young_duplicates_are_lost_without_base_loss() {
+    let tree = species_tree(10, 5);
+    let config = DTLConfig::new(0.5, 0.0, 0.0, None, None)
+        .unwrap()
+        .with_copy_number_dependence(
+            CopyNumberDependence::new().with_duplicate_retention(20.0, 100.0),
+        )
+        .unwrap();
+
+    let trees = simulate(&tree, config, 30, 6);
+    let duplications: usize = trees.iter().map(|t| count_events(t).1).sum();
+    let losses: usize = trees.iter().map(|t| count_events(t).3).sum();
+    assert!(duplications > 0);
+    assert!(losses > 0, "only duplicate-age losses are possible here");
+}
+
+#[test]
+fn homolog_rejection_keeps_single_copies() {
+    let tree = species_tree(12, 7);
+    let config = DTLConfig::new(0.0, 1.0, 0.5, None, None)
+        .unwrap()
+        .with_copy_number_dependence(CopyNumberDependence::new().with_transfer_acceptance(0.0, 1.0))
+        .unwrap();
+
+    let mut rng = StdRng::seed_from_u64(8);
+    let (trees, _) =
+        simulate_dtl_per_species_iter_with_config(&tree, tree.root, config, 50, false, &mut rng)
+            .unwrap()
+            .collect_all()
+            .unwrap();
+
+    let mut transfers = 0;
+    for rec_tree in &trees {
+        transfers += count_events(rec_tree).2;
+        assert!(max_copies_per_leaf_species(rec_tree) <= 1);
+    }
+    assert!(transfers > 0);
+}
+
+#[test]
+fn invalid_dependence_is_rejected() {
+    let config = DTLConfig::new(0.1, 0.1, 0.1, None, None).unwrap();
+    assert!(config
+        .with_copy_number_dependence(
+            CopyNumberDependence::new().with_duplicate_retention(1.0, -1.0)
+        )
+        .is_err());
+}
@@ -0,0 +1,116 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::dtl::{simulate_dtl_iter, DTLEvent};
+use rustree::FlatTree;
+
+mod common;
+use common::tree;
+
+/// Observed and expected per-species counts of copy-level events, plus the
+/// number of duplications, transfers and losses.
+struct Tally {
+    observed: Vec<f64>,
+    expected: Vec<f64>,
+    by_type: [f64; 3],
+}
+
+/// Replays each family's events, tracking alive copies per species, and adds
+/// `copies[s] / total` to the expected count of species `s` at every
+/// duplication, transfer and loss, which is what uniform copy selection gives.
+fn tally(species: &FlatTree, families: &[Vec<DTLEvent>]) -> Tally {
+    let n = species.nodes.len();
+    let mut tally = Tally {
+        observed: vec![0.0; n],
+        expected: vec![0.0; n],
+        by_type: [0.0; 3],
+    };
+    for events in families {
+        let mut copies = vec![0usize; n];
+        copies[species.root] = 1;
+        for event in events {
+            let (kind, at) = match *event {
+                DTLEvent::Speciation { species_id, .. } => {
+                    let node = &species.nodes[species_id];
+                    copies[species_id] -= 1;
+                    for child in [node.left_child, node.right_child].into_iter().flatten() {
+                        copies[child] += 1;
+                    }
+                    continue;
+                }
+                DTLEvent::Leaf { .. } => continue,
+                DTLEvent::Duplication { species_id, .. } => (0, species_id),
+                DTLEvent::Transfer { species_id, .. } => (1, species_id),
+                DTLEvent::Loss { species_id, .. } => (2, species_id),
+            };
+            let total: usize = copies.iter().sum();
+            for (expected, &k) in tally.expected.iter_mut().zip(&copies) {
+                *expected += k as f64 / total as f64;
+            }
+            tally.observed[at] += 1.0;
+            tally.by_type[kind] += 1.0;
+            match *event {
+                DTLEvent::Duplication { species_id, .. } => copies[species_id] += 1,
+                DTLEvent::Transfer { to_species, .. } => copies[to_species] += 1,
+                DTLEvent::Loss { species_id, .. } => copies[species_id] -= 1,
+                _ => {}
+            }
+        }
+    }
+    tally
+}
+
+#[test]
+fn per_gene_selection_is_uniform_over_copies() {
+    // No extinct species, so every loss is a copy-level event.
+    let species = tree(
+        "(((A:1,B:1)AB:1,(C:0.5,D:0.5)CD:1.5)ABCD:1,((E:1.5,F:1.5)EF:0.5,(G:1,H:1)GH:1)EFGH:1)R:0;",
+    );
+    let (d, t, l) = (0.6, 0.3, 0.4);
+    let mut families = Vec::new();
+    for seed in 0..20 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (_, events) = simulate_dtl_iter(
+            &species,
+            species.root,
+            d,
+            t,
+            l,
+            None,
+            None,
+            25,
+            false,
+            &mut rng,
+        )
+        .unwrap()
+        .collect_all()
+        .unwrap();
+        families.extend(events);
+    }
+
+    let tally = tally(&species, &families);
+    let draws: f64 = tally.by_type.iter().sum();
+    assert!(draws > 2000.0, "only {draws} copy-level events");
+
+    // Species frequencies follow the alive copy counts.
+    let mut chi_square = 0.0;
+    let mut cells = 0;
+    for (&observed, &expected) in tally.observed.iter().zip(&tally.expected) {
+        if expected > 0.0 {
+            chi_square += (observed - expected).powi(2) / expected;
+            cells += 1;
+        }
+    }
+    assert!(
+        chi_square < 2.0 * cells as f64,
+        "chi-square {chi_square:.1} over {cells} species"
+    );
+
+    // Event types follow the rates.
+    for (count, rate) in tally.by_type.iter().zip([d, t, l]) {
+        let expected = draws * rate / (d + t + l);
+        assert!(
+            (count - expected).abs() < 4.0 * expected.sqrt(),
+            "{count} events, expected {expected:.0}"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::dtl::{
+    count_extant_genes, simulate_dtl_forest, simulate_dtl_per_species_forest, DTLConfig,
+    FamilyOrigination,
+};
+
+mod common;
+use common::species_tree;
+
+#[test]
+fn initial_families_without_events_are_core() {
+    let tree = species_tree(8, 1);
+    let config = DTLConfig::new(0.0, 0.0, 0.0, None, None).unwrap();
+    let origination = FamilyOrigination::new(0.0, 12).unwrap();
+    let mut rng = StdRng::seed_from_u64(2);
+    let sim = simulate_dtl_forest(&tree, &config, &origination, true, &mut rng).unwrap();
+
+    assert_eq!(sim.len(), 12);
+    assert_eq!(sim.core_families().len(), 12);
+    let spectrum = sim.gene_frequency_spectrum();
+    assert_eq!(spectrum.len(), 9);
+    assert_eq!(spectrum[8], 12);
+}
+
+#[test]
+fn late_origins_produce_accessory_genes() {
+    let tree = species_tree(12, 3);
+    let config = DTLConfig::new(0.1, 0.1, 0.3, None, None).unwrap();
+    let origination = FamilyOrigination::new(10.0, 20).unwrap();
+    let mut rng = StdRng::seed_from_u64(4);
+    let sim = simulate_dtl_forest(&tree, &config, &origination, true, &mut rng).unwrap();
+
+    assert_eq!(sim.len(), sim.events.len());
+    assert_eq!(sim.len(), sim.origins.len());
+    assert!(sim
+        .forest
+        .gene_trees
+        .iter()
+        .all(|t| count_extant_genes(t) > 0));
+
+    let spectrum = sim.gene_frequency_spectrum();
+    assert_eq!(spectrum[0], 0);
+    assert_eq!(spectrum.iter().sum::<usize>(), sim.len());
+    // Singletons and partially shared families coexist with widely shared ones.
+    assert!(spectrum[1] > 0);
+    assert!(spectrum[1..12].iter().sum::<usize>() > spectrum[12]);
+}
+
+#[test]
+fn keeping_extinct_families_reports_every_origin() {
+    let tree = species_tree(6, 5);
+    let config = DTLConfig::new(0.0, 0.0, 2.0, None, None).unwrap();
+    let origination = FamilyOrigination::new(3.0, 0).unwrap();
+
+    let mut rng = StdRng::seed_from_u64(6);
+    let all =
+        simulate_dtl_per_species_forest(&tree, &config, &origination, false, &mut rng).unwrap();
+    let mut rng = StdRng::seed_from_u64(6);
+    let observed =
+        simulate_dtl_per_species_forest(&tree, &config, &origination, true, &mut rng).unwrap();
+
+    assert!(observed.len() < all.len());
+    assert!(all.origins.windows(2).all(|w| w[0].time <= w[1].time));
+    assert!(all.gene_frequency_spectrum()[0] > 0);
+}
+
+#[test]
+fn forest_simulation_is_deterministic() {
+    let tree = species_tree(6, 7);
+    let config = DTLConfig::new(0.2, 0.2, 0.2, None, None).unwrap();
+    let origination = FamilyOrigination::new(2.0, 5).unwrap();
+    let mut rng1 = StdRng::seed_from_u64(8);
+    let mut rng2 = StdRng::seed_from_u64(8);
+    let a = simulate_dtl_forest(&tree, &config, &origination, true, &mut rng1).unwrap();
+    let b = simulate_dtl_forest(&tree, &config, &origination, true, &mut rng2).unwrap();
+    assert_eq!(a.origins, b.origins);
+    assert_eq!(a.phyletic_patterns(), b.phyletic_patterns());
+}
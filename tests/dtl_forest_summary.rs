@@ -0,0 +1,123 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::dtl::{count_events, simulate_dtl_iter, DTLEvent, ForestSummary};
+use rustree::Event;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+mod common;
+use common::species_tree;
+
+#[test]
+fn streamed_summary_matches_the_collected_forest() {
+    let tree = species_tree(12, 1);
+    let mut rng1 = StdRng::seed_from_u64(2);
+    let summary = simulate_dtl_iter(
+        &tree, tree.root, 0.5, 0.4, 0.4, None, None, 50, false, &mut rng1,
+    )
+    .unwrap()
+    .summarize()
+    .unwrap();
+
+    let mut rng2 = StdRng::seed_from_u64(2);
+    let (trees, events) = simulate_dtl_iter(
+        &tree, tree.root, 0.5, 0.4, 0.4, None, None, 50, false, &mut rng2,
+    )
+    .unwrap()
+    .collect_all()
+    .unwrap();
+
+    assert_eq!(summary.n_families(), 50);
+
+    let (mut s, mut d, mut t, mut l, mut leaves) = (0, 0, 0, 0, 0);
+    for rec_tree in &trees {
+        let counts = count_events(rec_tree);
+        s += counts.0;
+        d += counts.1;
+        l += counts.3;
+        leaves += counts.4;
+    }
+    let mut expected_pairs: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+    for family in &events {
+        for event in family {
+            if let DTLEvent::Transfer {
+                from_species,
+                to_species,
+                ..
+            } = event
+            {
+                t += 1;
+                *expected_pairs
+                    .entry((*from_species, *to_species))
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    let branches = summary.branch_counts();
+    assert_eq!(branches.iter().map(|c| c.speciations).sum::<usize>(), s);
+    assert_eq!(branches.iter().map(|c| c.duplications).sum::<usize>(), d);
+    assert_eq!(branches.iter().map(|c| c.losses).sum::<usize>(), l);
+    assert_eq!(branches.iter().map(|c| c.leaves).sum::<usize>(), leaves);
+    assert_eq!(branches.iter().map(|c| c.transfers_out).sum::<usize>(), t);
+    assert_eq!(branches.iter().map(|c| c.transfers_in).sum::<usize>(), t);
+    assert!(t > 0);
+    assert_eq!(summary.transfer_counts(), &expected_pairs);
+
+    let matrix = summary.transfer_matrix();
+    for (&(donor, recipient), &count) in &expected_pairs {
+        assert_eq!(matrix[donor][recipient], count);
+    }
+}
+
+#[test]
+fn copy_number_histograms_count_every_family() {
+    let tree = species_tree(10, 3);
+    let mut rng = StdRng::seed_from_u64(4);
+    let (trees, _) = simulate_dtl_iter(
+        &tree, tree.root, 0.6, 0.2, 0.3, None, None, 40, true, &mut rng,
+    )
+    .unwrap()
+    .collect_all()
+    .unwrap();
+    let summary =
+        ForestSummary::from_rec_trees(Arc::clone(&trees[0].species_tree), &trees).unwrap();
+
+    assert!(!summary.extant_species().is_empty());
+    for &sp in summary.extant_species() {
+        let histogram = summary.copy_number_histogram(sp).unwrap();
+        assert_eq!(histogram.iter().sum::<usize>(), 40);
+        let copies: usize = histogram.iter().enumerate().map(|(c, n)| c * n).sum();
+        let expected = trees
+            .iter()
+            .flat_map(|t| t.event_mapping.iter().zip(&t.node_mapping))
+            .filter(|(e, m)| **e == Event::Leaf && **m == Some(sp))
+            .count();
+        assert_eq!(copies, expected);
+    }
+    assert!(summary.copy_number_histogram(tree.root).is_none());
+}
+
+#[test]
+fn csv_exports_have_one_row_per_entry() {
+    let tree = species_tree(6, 5);
+    let mut rng = StdRng::seed_from_u64(6);
+    let summary = simulate_dtl_iter(
+        &tree, tree.root, 0.3, 0.3, 0.3, None, None, 10, true, &mut rng,
+    )
+    .unwrap()
+    .summarize()
+    .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    summary.save_csv(dir.path().to_str().unwrap()).unwrap();
+    let branch_csv = std::fs::read_to_string(dir.path().join("branch_events.csv")).unwrap();
+    assert_eq!(branch_csv.lines().count(), tree.nodes.len() + 1);
+    let transfers_csv = std::fs::read_to_string(dir.path().join("transfers.csv")).unwrap();
+    assert_eq!(
+        transfers_csv.lines().count(),
+        summary.transfer_counts().len() + 1
+    );
+    let copies_csv = std::fs::read_to_string(dir.path().join("copy_numbers.csv")).unwrap();
+    assert!(copies_csv.starts_with("species_node,species_name,copies,families"));
+}
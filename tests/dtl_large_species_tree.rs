@@ -0,0 +1,17 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::bd::simulate_bd_tree_bwd;
+use rustree::dtl::simulate_dtl_per_species;
+
+/// Per-species events draw from the alive branches at 100k leaves.
+#[test]
+fn per_species_simulation_on_100k_leaf_tree() {
+    let mut rng = StdRng::seed_from_u64(11);
+    let (mut tree, _) = simulate_bd_tree_bwd(100_000, 1.0, 0.3, &mut rng).unwrap();
+    tree.assign_depths();
+    let (_, events) = simulate_dtl_per_species(
+        &tree, tree.root, 0.01, 0.01, 0.01, None, None, false, &mut rng,
+    )
+    .unwrap();
+    assert!(!events.is_empty());
+}
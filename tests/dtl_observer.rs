@@ -0,0 +1,125 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::bd::BDEvent;
+use rustree::dtl::{
+    simulate_dtl, simulate_dtl_per_species_with_observer, simulate_dtl_with_observer, DTLConfig,
+    DTLEvent, ObservedSimulation, ObserverAction, SimulationObserver, SimulationView,
+    SpeciesBoundary,
+};
+use rustree::Event;
+use std::collections::BTreeMap;
+
+mod common;
+use common::species_tree;
+
+#[derive(Default)]
+struct Recorder {
+    events: Vec<DTLEvent>,
+    last_time: f64,
+    copies_at_leaves: BTreeMap<usize, usize>,
+    boundaries: usize,
+}
+
+impl SimulationObserver for Recorder {
+    fn on_event(&mut self, event: &DTLEvent, view: &SimulationView<'_, '_>) -> ObserverAction {
+        assert!(view.time() >= self.last_time);
+        self.last_time = view.time();
+        assert_eq!(
+            view.total_copies(),
+            view.copy_counts().map(|(_, n)| n).sum::<usize>()
+        );
+        self.events.push(event.clone());
+        ObserverAction::Continue
+    }
+
+    fn on_species_boundary(
+        &mut self,
+        boundary: &SpeciesBoundary,
+        view: &SimulationView<'_, '_>,
+    ) -> ObserverAction {
+        self.boundaries += 1;
+        if boundary.kind == BDEvent::Leaf {
+            self.copies_at_leaves
+                .insert(boundary.species, view.copies_in_species(boundary.species));
+        }
+        ObserverAction::Continue
+    }
+}
+
+#[test]
+fn observer_sees_every_event_without_changing_the_simulation() {
+    let tree = species_tree(10, 1);
+    let mut rng1 = StdRng::seed_from_u64(2);
+    let (expected_tree, expected_events) = simulate_dtl(
+        &tree, tree.root, 0.3, 0.3, 0.3, None, None, false, &mut rng1,
+    )
+    .unwrap();
+
+    let config = DTLConfig::new(0.3, 0.3, 0.3, None, None).unwrap();
+    let mut recorder = Recorder::default();
+    let mut rng2 = StdRng::seed_from_u64(2);
+    let (rec_tree, events) =
+        simulate_dtl_with_observer(&tree, tree.root, &config, &mut recorder, &mut rng2)
+            .unwrap()
+            .into_completed()
+            .unwrap();
+
+    assert_eq!(format!("{events:?}"), format!("{expected_events:?}"));
+    assert_eq!(format!("{:?}", recorder.events), format!("{events:?}"));
+    assert_eq!(
+        rec_tree.gene_tree.to_newick().unwrap(),
+        expected_tree.gene_tree.to_newick().unwrap()
+    );
+    assert_eq!(recorder.boundaries, tree.nodes.len());
+}
+
+#[test]
+fn leaf_boundaries_report_extant_copy_numbers() {
+    let tree = species_tree(12, 3);
+    let config = DTLConfig::new(0.5, 0.2, 0.3, None, None).unwrap();
+    let mut recorder = Recorder::default();
+    let mut rng = StdRng::seed_from_u64(4);
+    let (rec_tree, _) =
+        simulate_dtl_per_species_with_observer(&tree, tree.root, &config, &mut recorder, &mut rng)
+            .unwrap()
+            .into_completed()
+            .unwrap();
+
+    let mut extant: BTreeMap<usize, usize> = BTreeMap::new();
+    for (event, mapping) in rec_tree.event_mapping.iter().zip(&rec_tree.node_mapping) {
+        if *event == Event::Leaf {
+            *extant.entry(mapping.unwrap()).or_default() += 1;
+        }
+    }
+    for (&species, &copies) in &recorder.copies_at_leaves {
+        assert_eq!(copies, extant.get(&species).copied().unwrap_or(0));
+    }
+    assert!(recorder.copies_at_leaves.values().any(|&n| n > 0));
+}
+
+struct CapCopies(usize);
+
+impl SimulationObserver for CapCopies {
+    fn on_event(&mut self, _event: &DTLEvent, view: &SimulationView<'_, '_>) -> ObserverAction {
+        if view.total_copies() > self.0 {
+            ObserverAction::Abort
+        } else {
+            ObserverAction::Continue
+        }
+    }
+}
+
+#[test]
+fn observer_can_abort_runaway_families() {
+    let tree = species_tree(10, 5);
+    let config = DTLConfig::new(3.0, 0.0, 0.1, None, None).unwrap();
+    let mut rng = StdRng::seed_from_u64(6);
+    let outcome =
+        simulate_dtl_with_observer(&tree, tree.root, &config, &mut CapCopies(20), &mut rng)
+            .unwrap();
+    match &outcome {
+        ObservedSimulation::Aborted { events, .. } => assert!(!events.is_empty()),
+        ObservedSimulation::Completed { .. } => panic!("expected the observer to abort"),
+    }
+    assert!(outcome.into_completed().is_err());
+}
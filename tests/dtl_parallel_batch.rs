@@ -0,0 +1,97 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use rustree::dtl::{
+    family_seed, simulate_dtl, simulate_dtl_parallel, simulate_dtl_per_species_parallel,
+    Conditioning, DTLConfig, DTLEvent, SimulationCondition,
+};
+use rustree::RecTree;
+
+mod common;
+use common::species_tree;
+
+fn fingerprint(trees: &[RecTree], events: &[Vec<DTLEvent>]) -> Vec<String> {
+    trees
+        .iter()
+        .zip(events)
+        .map(|(t, e)| format!("{} {:?}", t.gene_tree.to_newick().unwrap(), e))
+        .collect()
+}
+
+fn with_threads<T: Send>(n: usize, f: impl FnOnce() -> T + Send) -> T {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(n)
+        .build()
+        .unwrap()
+        .install(f)
+}
+
+#[test]
+fn output_does_not_depend_on_thread_count() {
+    let tree = species_tree(15, 1);
+    let config = DTLConfig::new(0.4, 0.3, 0.5, None, None).unwrap();
+    let batch = simulate_dtl_parallel(&tree, tree.root, config, 40, true, 99).unwrap();
+
+    let (t1, e1) = with_threads(1, || batch.collect_all().unwrap());
+    let (t4, e4) = with_threads(4, || batch.collect_all().unwrap());
+    let sequential: Vec<_> = batch.iter().map(|r| r.unwrap()).collect();
+    let (ts, es): (Vec<_>, Vec<_>) = sequential.into_iter().unzip();
+
+    assert_eq!(t1.len(), 40);
+    assert_eq!(fingerprint(&t1, &e1), fingerprint(&t4, &e4));
+    assert_eq!(fingerprint(&t1, &e1), fingerprint(&ts, &es));
+}
+
+#[test]
+fn single_family_can_be_regenerated_alone() {
+    let tree = species_tree(12, 2);
+    let config = DTLConfig::new(0.3, 0.2, 0.3, None, None).unwrap();
+    let batch = simulate_dtl_per_species_parallel(&tree, tree.root, config, 25, true, 5).unwrap();
+    let from_par: Vec<_> = batch.par_iter().map(|r| r.unwrap()).collect();
+
+    for i in [0, 7, 24] {
+        let (rec_tree, events) = batch.family(i).unwrap();
+        assert_eq!(
+            rec_tree.gene_tree.to_newick().unwrap(),
+            from_par[i].0.gene_tree.to_newick().unwrap()
+        );
+        assert_eq!(format!("{events:?}"), format!("{:?}", from_par[i].1));
+    }
+    assert!(batch.family(25).is_err());
+}
+
+#[test]
+fn family_matches_a_direct_simulation_with_its_seed() {
+    let tree = species_tree(10, 3);
+    let config = DTLConfig::new(0.2, 0.2, 0.2, None, None).unwrap();
+    let batch = simulate_dtl_parallel(&tree, tree.root, config, 5, false, 11).unwrap();
+    let (rec_tree, _) = batch.family(3).unwrap();
+
+    let mut rng = StdRng::seed_from_u64(family_seed(11, 3));
+    let (expected, _) =
+        simulate_dtl(&tree, tree.root, 0.2, 0.2, 0.2, None, None, false, &mut rng).unwrap();
+    assert_eq!(
+        rec_tree.gene_tree.to_newick().unwrap(),
+        expected.gene_tree.to_newick().unwrap()
+    );
+}
+
+#[test]
+fn conditioning_stats_are_summed_over_families() {
+    let tree = species_tree(10, 4);
+    let config = DTLConfig::new(0.3, 0.3, 0.6, None, None).unwrap();
+    let batch = simulate_dtl_parallel(&tree, tree.root, config, 20, false, 8)
+        .unwrap()
+        .with_conditioning(
+            Conditioning::new().with_condition(SimulationCondition::MinExtantGenes(3)),
+        )
+        .unwrap();
+    let (trees, _, stats) = with_threads(3, || batch.collect_conditioned().unwrap());
+    let per_family: usize = (0..20)
+        .map(|i| batch.family_with_stats(i).unwrap().2.attempts)
+        .sum();
+
+    assert_eq!(trees.len(), 20);
+    assert_eq!(stats.accepted, 20);
+    assert_eq!(stats.attempts, per_family);
+}
@@ -0,0 +1,138 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::dtl::{
+    count_events, simulate_dtl_batch, simulate_dtl_batch_with_rate_heterogeneity,
+    simulate_dtl_iter_with_config, simulate_dtl_per_species_batch_with_rate_heterogeneity,
+    BranchDTLRates, DTLConfig, FamilyRateHeterogeneity, RateMultiplier,
+};
+
+mod common;
+use common::species_tree;
+
+fn two_category_duplication() -> FamilyRateHeterogeneity {
+    FamilyRateHeterogeneity::new(
+        RateMultiplier::Discrete {
+            multipliers: vec![0.0, 2.0],
+            weights: vec![1.0, 1.0],
+        },
+        RateMultiplier::Fixed,
+        RateMultiplier::Fixed,
+    )
+    .unwrap()
+}
+
+#[test]
+fn heterogeneous_batch_records_family_rates() {
+    let tree = species_tree(20, 1);
+    let config = DTLConfig::new(0.5, 0.2, 0.3, None, None).unwrap();
+    let heterogeneity = FamilyRateHeterogeneity::new(
+        RateMultiplier::Gamma { shape: 2.0 },
+        RateMultiplier::LogNormal { sigma: 0.5 },
+        RateMultiplier::Fixed,
+    )
+    .unwrap();
+    let mut rng = StdRng::seed_from_u64(42);
+    let (trees, _events) = simulate_dtl_batch_with_rate_heterogeneity(
+        &tree,
+        tree.root,
+        config,
+        heterogeneity,
+        50,
+        true,
+        &mut rng,
+    )
+    .unwrap();
+
+    let rates: Vec<_> = trees
+        .iter()
+        .map(|rt| rt.family_rates.clone().expect("family rates recorded"))
+        .collect();
+    assert!(rates.iter().all(|r| r.loss_multiplier == 1.0));
+    assert!(rates
+        .iter()
+        .all(|r| (r.lambda_d - 0.5 * r.duplication_multiplier).abs() < 1e-12));
+    let distinct: std::collections::BTreeSet<u64> =
+        rates.iter().map(|r| r.lambda_d.to_bits()).collect();
+    assert!(distinct.len() > 40, "gamma draws should differ per family");
+}
+
+#[test]
+fn zero_multiplier_category_suppresses_duplications() {
+    let tree = species_tree(15, 2);
+    let config = DTLConfig::new(0.5, 0.0, 0.0, None, None).unwrap();
+    let mut rng = StdRng::seed_from_u64(7);
+    let (trees, _) = simulate_dtl_per_species_batch_with_rate_heterogeneity(
+        &tree,
+        tree.root,
+        config,
+        two_category_duplication(),
+        40,
+        false,
+        &mut rng,
+    )
+    .unwrap();
+
+    let mut saw_high = false;
+    for rec_tree in &trees {
+        let rates = rec_tree.family_rates.as_ref().unwrap();
+        let (_, d, _, _, _) = count_events(rec_tree);
+        if rates.duplication_multiplier == 0.0 {
+            assert_eq!(d, 0);
+        } else {
+            saw_high = true;
+        }
+    }
+    assert!(saw_high);
+}
+
+#[test]
+fn homogeneous_iterator_matches_plain_batch() {
+    let tree = species_tree(10, 3);
+    let mut rng1 = StdRng::seed_from_u64(9);
+    let mut rng2 = StdRng::seed_from_u64(9);
+    let (plain, _) = simulate_dtl_batch(
+        &tree, tree.root, 0.5, 0.2, 0.3, None, None, 5, false, &mut rng1,
+    )
+    .unwrap();
+    let config = DTLConfig::new(0.5, 0.2, 0.3, None, None).unwrap();
+    let (iterated, _) =
+        simulate_dtl_iter_with_config(&tree, tree.root, config, 5, false, &mut rng2)
+            .unwrap()
+            .collect_all()
+            .unwrap();
+
+    for (a, b) in plain.iter().zip(&iterated) {
+        assert_eq!(a.gene_tree.nodes.len(), b.gene_tree.nodes.len());
+        assert!(a.family_rates.is_none() && b.family_rates.is_none());
+    }
+}
+
+#[test]
+fn heterogeneity_scales_branch_rate_tables() {
+    let tree = species_tree(10, 4);
+    let n = tree.nodes.len();
+    let mut origination = vec![0.0; n];
+    origination[tree.root] = 1.0;
+    let branch_rates =
+        BranchDTLRates::new(vec![0.5; n], vec![0.0; n], vec![0.0; n], origination).unwrap();
+    let config = DTLConfig::with_branch_rates(branch_rates, None, None).unwrap();
+
+    let mut rng = StdRng::seed_from_u64(11);
+    let (trees, _) = simulate_dtl_batch_with_rate_heterogeneity(
+        &tree,
+        tree.root,
+        config,
+        two_category_duplication(),
+        30,
+        false,
+        &mut rng,
+    )
+    .unwrap();
+
+    for rec_tree in &trees {
+        let rates = rec_tree.family_rates.as_ref().unwrap();
+        if rates.duplication_multiplier == 0.0 {
+            assert_eq!(count_events(rec_tree).1, 0);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::dtl::{
+    simulate_dtl_iter_with_config, simulate_dtl_per_species_iter_with_config, DTLConfig, DTLEvent,
+    RecipientWeightMatrix,
+};
+use std::sync::Arc;
+
+mod common;
+use common::{four_taxon_tree, transfers};
+
+#[test]
+fn weight_matrix_restricts_recipients() {
+    let tree = four_taxon_tree();
+    let n = tree.nodes.len();
+    let (a, b, d) = (
+        tree.find_node_index("A").unwrap(),
+        tree.find_node_index("B").unwrap(),
+        tree.find_node_index("D").unwrap(),
+    );
+    // Transfers out of A may only go to D; every other donor may only send to A.
+    let mut weights = vec![vec![0.0; n]; n];
+    for (donor, row) in weights.iter_mut().enumerate() {
+        if donor == a {
+            row[d] = 1.0;
+        } else {
+            row[a] = 1.0;
+        }
+    }
+    let matrix = RecipientWeightMatrix::new(weights).unwrap();
+    let config = DTLConfig::new(0.0, 1.0, 0.0, None, None)
+        .unwrap()
+        .with_recipient_kernel(Arc::new(matrix));
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let (_, events) =
+        simulate_dtl_per_species_iter_with_config(&tree, tree.root, config, 100, false, &mut rng)
+            .unwrap()
+            .collect_all()
+            .unwrap();
+
+    let transfers = transfers(&events);
+    assert!(transfers.iter().any(|&(_, from, _)| from == a));
+    assert!(transfers.iter().any(|&(_, from, _)| from == b));
+    for (_, from, to) in transfers {
+        if from == a {
+            assert_eq!(to, d);
+        } else {
+            assert_eq!(to, a);
+        }
+    }
+}
+
+#[test]
+fn closure_kernel_sees_event_time() {
+    let tree = four_taxon_tree();
+    let c = tree.find_node_index("C").unwrap();
+    // Before t = 1.5 transfers are impossible; afterwards they target C only.
+    let kernel = move |_donor: usize, recipient: usize, time: f64| {
+        if time >= 1.5 && recipient == c {
+            1.0
+        } else {
+            0.0
+        }
+    };
+    let config = DTLConfig::new(0.0, 1.0, 0.0, None, None)
+        .unwrap()
+        .with_recipient_kernel(Arc::new(kernel));
+
+    let mut rng = StdRng::seed_from_u64(2);
+    let (_, events) = simulate_dtl_iter_with_config(&tree, tree.root, config, 100, false, &mut rng)
+        .unwrap()
+        .collect_all()
+        .unwrap();
+
+    let mut n_transfers = 0;
+    for event in events.iter().flatten() {
+        if let DTLEvent::Transfer {
+            time, to_species, ..
+        } = event
+        {
+            n_transfers += 1;
+            assert!(*time >= 1.5);
+            assert_eq!(*to_species, c);
+        }
+    }
+    assert!(n_transfers > 0);
+}
+
+#[test]
+fn matrix_size_is_validated_against_tree() {
+    let tree = four_taxon_tree();
+    let matrix = RecipientWeightMatrix::new(vec![vec![0.0, 1.0], vec![1.0, 0.0]]).unwrap();
+    let config = DTLConfig::new(0.0, 1.0, 0.0, None, None)
+        .unwrap()
+        .with_recipient_kernel(Arc::new(matrix));
+    let mut rng = StdRng::seed_from_u64(3);
+    assert!(simulate_dtl_iter_with_config(&tree, tree.root, config, 1, false, &mut rng).is_err());
+}
@@ -0,0 +1,121 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::dtl::{
+    simulate_dtl_iter_with_config, simulate_dtl_per_species_iter_with_config, DTLConfig,
+    TransferHighway,
+};
+
+mod common;
+use common::{four_taxon_tree, transfers};
+
+#[test]
+fn highway_rate_only_transfers_along_highway() {
+    let tree = four_taxon_tree();
+    let (a, c) = (
+        tree.find_node_index("A").unwrap(),
+        tree.find_node_index("C").unwrap(),
+    );
+    let config = DTLConfig::new(0.0, 0.0, 0.0, None, None)
+        .unwrap()
+        .with_transfer_highways(vec![TransferHighway::new(a, c, 2.0, 1.0)])
+        .unwrap();
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let (_, events) = simulate_dtl_iter_with_config(&tree, tree.root, config, 50, false, &mut rng)
+        .unwrap()
+        .collect_all()
+        .unwrap();
+
+    let transfers = transfers(&events);
+    assert!(!transfers.is_empty());
+    for (time, from, to) in transfers {
+        assert_eq!((from, to), (a, c));
+        assert!((1.0..2.0).contains(&time));
+    }
+}
+
+#[test]
+fn highway_window_limits_transfer_times() {
+    let tree = four_taxon_tree();
+    let (a, d) = (
+        tree.find_node_index("A").unwrap(),
+        tree.find_node_index("D").unwrap(),
+    );
+    let config = DTLConfig::new(0.0, 0.0, 0.0, None, None)
+        .unwrap()
+        .with_transfer_highways(vec![
+            TransferHighway::new(a, d, 5.0, 1.0).with_window(1.5, 1.75)
+        ])
+        .unwrap();
+
+    let mut rng = StdRng::seed_from_u64(2);
+    let (_, events) =
+        simulate_dtl_per_species_iter_with_config(&tree, tree.root, config, 50, false, &mut rng)
+            .unwrap()
+            .collect_all()
+            .unwrap();
+
+    let transfers = transfers(&events);
+    assert!(!transfers.is_empty());
+    assert!(transfers
+        .iter()
+        .all(|&(time, from, to)| from == a && to == d && (1.5..1.75).contains(&time)));
+}
+
+#[test]
+fn recipient_weight_biases_ordinary_transfers() {
+    let tree = four_taxon_tree();
+    let (a, c) = (
+        tree.find_node_index("A").unwrap(),
+        tree.find_node_index("C").unwrap(),
+    );
+    let config = DTLConfig::new(0.0, 1.0, 0.0, None, None)
+        .unwrap()
+        .with_transfer_highways(vec![TransferHighway::new(a, c, 0.0, 50.0)])
+        .unwrap();
+
+    let mut rng = StdRng::seed_from_u64(3);
+    let (_, events) =
+        simulate_dtl_per_species_iter_with_config(&tree, tree.root, config, 200, false, &mut rng)
+            .unwrap()
+            .collect_all()
+            .unwrap();
+
+    let from_a: Vec<_> = transfers(&events)
+        .into_iter()
+        .filter(|&(_, from, _)| from == a)
+        .collect();
+    assert!(from_a.len() > 20);
+    let to_c = from_a.iter().filter(|&&(_, _, to)| to == c).count() as f64;
+    assert!(to_c / from_a.len() as f64 > 0.8);
+}
+
+#[test]
+fn empty_highways_do_not_change_simulation() {
+    let tree = four_taxon_tree();
+    let base = DTLConfig::new(0.3, 0.5, 0.2, None, None).unwrap();
+    let with_empty = base.clone().with_transfer_highways(Vec::new()).unwrap();
+
+    let mut rng1 = StdRng::seed_from_u64(4);
+    let mut rng2 = StdRng::seed_from_u64(4);
+    let (_, e1) = simulate_dtl_iter_with_config(&tree, tree.root, base, 20, false, &mut rng1)
+        .unwrap()
+        .collect_all()
+        .unwrap();
+    let (_, e2) = simulate_dtl_iter_with_config(&tree, tree.root, with_empty, 20, false, &mut rng2)
+        .unwrap()
+        .collect_all()
+        .unwrap();
+    assert_eq!(transfers(&e1), transfers(&e2));
+}
+
+#[test]
+fn highways_are_validated_against_tree() {
+    let tree = four_taxon_tree();
+    let config = DTLConfig::new(0.0, 0.0, 0.0, None, None)
+        .unwrap()
+        .with_transfer_highways(vec![TransferHighway::new(0, 99, 1.0, 1.0)])
+        .unwrap();
+    let mut rng = StdRng::seed_from_u64(5);
+    assert!(simulate_dtl_iter_with_config(&tree, tree.root, config, 1, false, &mut rng).is_err());
+}
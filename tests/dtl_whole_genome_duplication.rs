@@ -0,0 +1,164 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::dtl::{
+    count_extant_genes, simulate_dtl_iter_with_config, simulate_dtl_per_species_iter_with_config,
+    DTLConfig, DTLEvent, WholeGenomeDuplication,
+};
+use rustree::io::RecTreeColumns;
+use rustree::{FlatTree, RecTree};
+
+mod common;
+use common::tree;
+
+fn species_tree() -> FlatTree {
+    tree("((A:1,B:1)AB:1,C:2)root:0;")
+}
+
+fn simulate(config: DTLConfig, n: usize, seed: u64) -> (Vec<RecTree>, Vec<Vec<DTLEvent>>) {
+    let tree = species_tree();
+    let mut rng = StdRng::seed_from_u64(seed);
+    simulate_dtl_iter_with_config(&tree, tree.root, config, n, false, &mut rng)
+        .unwrap()
+        .collect_all()
+        .unwrap()
+}
+
+#[test]
+fn wgd_duplicates_every_copy_in_every_family() {
+    let tree = species_tree();
+    let config = DTLConfig::new(0.0, 0.0, 0.0, None, None)
+        .unwrap()
+        .with_whole_genome_duplications(vec![WholeGenomeDuplication::new(
+            tree.find_node_index("AB").unwrap(),
+            0.5,
+        )])
+        .unwrap();
+
+    let (trees, events) = simulate(config, 5, 1);
+    for (rec_tree, events) in trees.iter().zip(&events) {
+        // A and B inherit both copies, C keeps one.
+        assert_eq!(count_extant_genes(rec_tree), 5);
+        let wgd: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                DTLEvent::Duplication {
+                    time,
+                    gene_id,
+                    species_id,
+                    wgd: true,
+                    ..
+                } => Some((*time, *gene_id, *species_id)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(wgd.len(), 1);
+        assert_eq!(wgd[0].0, 0.5);
+        assert_eq!(wgd[0].2, tree.find_node_index("AB").unwrap());
+        assert_eq!(rec_tree.wgd_nodes, vec![wgd[0].1]);
+        assert!(rec_tree.to_xml().contains("wgd=\"true\""));
+    }
+}
+
+#[test]
+fn unretained_duplicates_are_lost_immediately() {
+    let tree = species_tree();
+    let wgd =
+        WholeGenomeDuplication::new(tree.find_node_index("C").unwrap(), 1.0).with_retention(0.0);
+    let config = DTLConfig::new(0.0, 0.0, 0.0, None, None)
+        .unwrap()
+        .with_whole_genome_duplications(vec![wgd])
+        .unwrap();
+
+    let mut rng = StdRng::seed_from_u64(2);
+    let (trees, events) =
+        simulate_dtl_per_species_iter_with_config(&tree, tree.root, config, 5, false, &mut rng)
+            .unwrap()
+            .collect_all()
+            .unwrap();
+    for (rec_tree, events) in trees.iter().zip(&events) {
+        assert_eq!(count_extant_genes(rec_tree), 3);
+        let losses: Vec<f64> = events
+            .iter()
+            .filter_map(|e| match e {
+                DTLEvent::Loss { time, .. } => Some(*time),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(losses, vec![1.0]);
+    }
+}
+
+#[test]
+fn wgd_is_flagged_in_event_csv() {
+    let tree = species_tree();
+    let config = DTLConfig::new(0.3, 0.0, 0.0, None, None)
+        .unwrap()
+        .with_whole_genome_duplications(vec![WholeGenomeDuplication::new(
+            tree.find_node_index("AB").unwrap(),
+            0.5,
+        )])
+        .unwrap();
+
+    let (trees, events) = simulate(config, 3, 3);
+    assert!(DTLEvent::csv_header().ends_with(",wgd"));
+    for (rec_tree, events) in trees.iter().zip(&events) {
+        let rows: Vec<String> = events
+            .iter()
+            .filter(|e| matches!(e, DTLEvent::Duplication { .. }))
+            .map(|e| e.to_csv_row(&tree, &rec_tree.gene_tree))
+            .collect();
+        assert!(rows.iter().any(|row| row.ends_with(",true")));
+        for row in rows {
+            assert_eq!(row.split(',').count(), 9);
+        }
+    }
+}
+
+#[test]
+fn wgd_flags_survive_xml_round_trip() {
+    let tree = species_tree();
+    let config = DTLConfig::new(0.3, 0.0, 0.2, None, None)
+        .unwrap()
+        .with_whole_genome_duplications(vec![WholeGenomeDuplication::new(
+            tree.find_node_index("AB").unwrap(),
+            0.5,
+        )])
+        .unwrap();
+
+    let (trees, _) = simulate(config, 4, 5);
+    for rec_tree in &trees {
+        assert!(!rec_tree.wgd_nodes.is_empty());
+        let parsed = RecTree::from_xml(&rec_tree.to_xml()).unwrap();
+        assert_eq!(parsed.gene_tree.nodes.len(), rec_tree.gene_tree.nodes.len());
+        let flagged = |t: &RecTree| -> Vec<String> {
+            t.wgd_nodes
+                .iter()
+                .map(|&i| t.gene_tree.nodes[i].name.clone())
+                .collect()
+        };
+        assert_eq!(flagged(&parsed), flagged(rec_tree));
+
+        let cols = parsed.to_columns();
+        assert!(RecTreeColumns::csv_header().ends_with(",event,wgd"));
+        assert!(cols.wgd.iter().any(|&wgd| wgd));
+        for (i, &wgd) in cols.wgd.iter().enumerate() {
+            assert_eq!(wgd, parsed.is_wgd(cols.node_id[i]));
+        }
+    }
+}
+
+#[test]
+fn wgd_outside_its_branch_is_rejected() {
+    let tree = species_tree();
+    let config = DTLConfig::new(0.0, 0.0, 0.0, None, None)
+        .unwrap()
+        .with_whole_genome_duplications(vec![WholeGenomeDuplication::new(
+            tree.find_node_index("A").unwrap(),
+            0.5,
+        )])
+        .unwrap();
+    let mut rng = StdRng::seed_from_u64(4);
+    let mut iter =
+        simulate_dtl_iter_with_config(&tree, tree.root, config, 1, false, &mut rng).unwrap();
+    assert!(iter.next().unwrap().is_err());
+}
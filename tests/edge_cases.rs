@@ -1,8 +1,11 @@
 // Edge case tests for various tree operations (#78)
 
-use rustree::node::{FlatNode, FlatTree, TraversalOrder};
-use rustree::parse_newick;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::genome::{simulate_genomes, GenomeConfig};
+use rustree::node::{FlatNode, FlatTree, TraversalOrder, TreeView};
 use rustree::sampling::{extract_induced_subtree, extract_induced_subtree_by_names};
+use rustree::{parse_newick, RustreeError};
 use std::collections::HashSet;
 
 // ============================================================================
@@ -215,3 +218,30 @@ fn test_assign_depths_twice_idempotent() {
         "assign_depths should be idempotent"
     );
 }
+
+#[test]
+fn test_empty_compact_tree_is_an_error() {
+    let tree = FlatTree {
+        nodes: Vec::new(),
+        root: 0,
+    };
+    let mut compact = tree.to_compact_tree().unwrap();
+    assert!(compact.is_empty());
+    assert!(compact.postorder().is_err());
+    assert!(compact.lca(0, 0).is_err());
+    assert!(compact.assign_depths().is_err());
+    assert!(compact.to_flat_tree().is_empty());
+}
+
+#[test]
+fn test_genome_simulation_without_depths_is_an_error() {
+    let tree = parse_newick("((A:1,B:1)AB:1,C:2)root:0;")
+        .unwrap()
+        .pop()
+        .unwrap()
+        .to_flat_tree();
+    let config = GenomeConfig::new(5, 0.1, 0.1, 0.1, 2.0).unwrap();
+    let mut rng = StdRng::seed_from_u64(1);
+    let err = simulate_genomes(&tree, &config, &mut rng).unwrap_err();
+    assert!(matches!(err, RustreeError::MissingDepth { .. }), "{err}");
+}
@@ -0,0 +1,88 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::dtl::DTLEvent;
+use rustree::genome::{simulate_genomes, GenomeConfig, GenomeSimulation};
+use rustree::{Event, FlatTree};
+use std::collections::BTreeMap;
+
+mod common;
+use common::species_tree;
+
+fn simulate(tree: &FlatTree, config: &GenomeConfig, seed: u64) -> GenomeSimulation {
+    let mut rng = StdRng::seed_from_u64(seed);
+    simulate_genomes(tree, config, &mut rng).unwrap()
+}
+
+#[test]
+fn without_events_every_species_keeps_the_ancestral_genome() {
+    let tree = species_tree(8, 1);
+    let config = GenomeConfig::new(5, 0.0, 0.0, 0.0, 1.0).unwrap();
+    let sim = simulate(&tree, &config, 2);
+
+    assert_eq!(sim.families.len(), 5);
+    assert_eq!(sim.genomes.len(), 8);
+    for genome in &sim.genomes {
+        let families: Vec<usize> = genome.genes.iter().map(|g| g.family).collect();
+        assert_eq!(families, vec![0, 1, 2, 3, 4]);
+    }
+}
+
+#[test]
+fn genome_tables_match_family_gene_trees() {
+    let tree = species_tree(10, 3);
+    let config = GenomeConfig::new(20, 0.3, 0.3, 0.3, 2.0).unwrap();
+    let sim = simulate(&tree, &config, 4);
+
+    let mut from_genomes: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+    for genome in &sim.genomes {
+        for gene in &genome.genes {
+            *from_genomes
+                .entry((gene.family, gene.gene_node))
+                .or_default() += 1;
+            assert_eq!(
+                sim.families[gene.family].node_mapping[gene.gene_node],
+                Some(genome.species)
+            );
+        }
+    }
+    let mut from_trees: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+    for (family, rec_tree) in sim.families.iter().enumerate() {
+        for (node, event) in rec_tree.event_mapping.iter().enumerate() {
+            if *event == Event::Leaf {
+                *from_trees.entry((family, node)).or_default() += 1;
+            }
+        }
+    }
+    assert_eq!(from_genomes, from_trees);
+
+    let total: usize = sim.genomes.iter().map(|g| g.genes.len()).sum();
+    assert_eq!(sim.genome_table_csv().lines().count(), total + 1);
+}
+
+#[test]
+fn long_segments_transfer_several_families_at_once() {
+    let tree = species_tree(10, 5);
+    let config = GenomeConfig::new(30, 0.0, 0.2, 0.0, 5.0).unwrap();
+    let sim = simulate(&tree, &config, 6);
+
+    let mut families_per_transfer: BTreeMap<u64, usize> = BTreeMap::new();
+    for events in &sim.events {
+        for event in events {
+            if let DTLEvent::Transfer { time, .. } = event {
+                *families_per_transfer.entry(time.to_bits()).or_default() += 1;
+            }
+        }
+    }
+    assert!(!families_per_transfer.is_empty());
+    assert!(families_per_transfer.values().any(|&n| n > 1));
+}
+
+#[test]
+fn simulation_is_deterministic() {
+    let tree = species_tree(6, 7);
+    let config = GenomeConfig::new(10, 0.2, 0.2, 0.2, 1.5).unwrap();
+    let a = simulate(&tree, &config, 8);
+    let b = simulate(&tree, &config, 8);
+    assert_eq!(a.genomes, b.genomes);
+    assert_eq!(a.genome_table_csv(), b.genome_table_csv());
+}
@@ -0,0 +1,153 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::clock::{apply_clock, apply_family_clocks, apply_gene_clock, ClockModel};
+use rustree::dtl::{simulate_dtl, RateMultiplier};
+use rustree::{Event, FlatTree, RecTree};
+
+mod common;
+use common::species_tree;
+
+fn gene_tree(species: &FlatTree, lambda_t: f64, seed: u64) -> RecTree {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (rec_tree, _) = simulate_dtl(
+        species,
+        species.root,
+        0.3,
+        lambda_t,
+        0.2,
+        None,
+        None,
+        true,
+        &mut rng,
+    )
+    .unwrap();
+    rec_tree
+}
+
+/// Sum of branch lengths from `idx` up to the start of the root stem.
+fn path_length(tree: &FlatTree, idx: usize) -> f64 {
+    let mut total = tree.nodes[idx].length;
+    let mut current = idx;
+    while let Some(parent) = tree.nodes[current].parent {
+        total += tree.nodes[parent].length;
+        current = parent;
+    }
+    total
+}
+
+#[test]
+fn uncorrelated_clocks_have_the_requested_mean() {
+    let tree = species_tree(400, 1);
+    let mut rng = StdRng::seed_from_u64(2);
+    for model in [
+        ClockModel::UncorrelatedLognormal {
+            mean_rate: 0.5,
+            sigma: 0.4,
+        },
+        ClockModel::UncorrelatedGamma {
+            mean_rate: 0.5,
+            shape: 4.0,
+        },
+    ] {
+        let clock = apply_clock(&tree, &model, &mut rng).unwrap();
+        let mean = clock.rates.iter().sum::<f64>() / clock.rates.len() as f64;
+        assert!((mean - 0.5).abs() < 0.03, "{model:?}: mean rate {mean}");
+        for ((scaled, original), rate) in clock
+            .tree
+            .nodes
+            .iter()
+            .zip(&clock.time_tree.nodes)
+            .zip(&clock.rates)
+        {
+            assert!((scaled.length - original.length * rate).abs() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn autocorrelated_clock_without_variance_is_strict() {
+    let tree = species_tree(30, 3);
+    let mut rng = StdRng::seed_from_u64(4);
+    let model = ClockModel::AutocorrelatedLognormal {
+        root_rate: 2.0,
+        sigma: 0.0,
+    };
+    let clock = apply_clock(&tree, &model, &mut rng).unwrap();
+    assert!(clock.rates.iter().all(|&r| (r - 2.0).abs() < 1e-12));
+
+    let model = ClockModel::AutocorrelatedLognormal {
+        root_rate: 2.0,
+        sigma: 0.5,
+    };
+    let mut rng1 = StdRng::seed_from_u64(5);
+    let mut rng2 = StdRng::seed_from_u64(5);
+    let a = apply_clock(&tree, &model, &mut rng1).unwrap();
+    let b = apply_clock(&tree, &model, &mut rng2).unwrap();
+    assert_eq!(a.rates, b.rates);
+    assert!(a.rates.iter().all(|&r| r > 0.0));
+}
+
+#[test]
+fn gene_leaves_accumulate_their_species_lineage_substitutions() {
+    let species = species_tree(12, 6);
+    let mut rng = StdRng::seed_from_u64(7);
+    let species_clock = apply_clock(
+        &species,
+        &ClockModel::UncorrelatedLognormal {
+            mean_rate: 1.0,
+            sigma: 0.8,
+        },
+        &mut rng,
+    )
+    .unwrap();
+    // Without transfers each gene lineage follows its species lineage exactly.
+    let rec_tree = gene_tree(&species, 0.0, 8);
+    let family = apply_gene_clock(&rec_tree, &species_clock.rates, 1.5).unwrap();
+
+    for (idx, event) in rec_tree.event_mapping.iter().enumerate() {
+        if *event != Event::Leaf {
+            continue;
+        }
+        let sp = rec_tree.node_mapping[idx].unwrap();
+        let expected = 1.5 * path_length(&species_clock.tree, sp);
+        let observed = path_length(&family.clock.tree, idx);
+        assert!(
+            (observed - expected).abs() < 1e-9,
+            "{observed} vs {expected}"
+        );
+    }
+}
+
+#[test]
+fn family_clocks_draw_one_multiplier_per_family() {
+    let species = species_tree(10, 9);
+    let rec_trees: Vec<RecTree> = (0..20).map(|i| gene_tree(&species, 0.3, 10 + i)).collect();
+    let mut rng = StdRng::seed_from_u64(11);
+    let species_clock = apply_clock(&species, &ClockModel::Strict { rate: 0.1 }, &mut rng).unwrap();
+    let families = apply_family_clocks(
+        &rec_trees,
+        &species_clock.rates,
+        &RateMultiplier::Gamma { shape: 2.0 },
+        &mut rng,
+    )
+    .unwrap();
+
+    assert_eq!(families.len(), rec_trees.len());
+    for (family, rec_tree) in families.iter().zip(&rec_trees) {
+        // Under a strict species clock every gene branch has the same rate.
+        assert!(family
+            .clock
+            .rates
+            .iter()
+            .all(|&r| (r - 0.1 * family.multiplier).abs() < 1e-9));
+        let rescaled = family.apply_to(rec_tree).unwrap();
+        assert_eq!(
+            rescaled.gene_tree.nodes.len(),
+            rec_tree.gene_tree.nodes.len()
+        );
+        assert_eq!(rescaled.node_mapping, rec_tree.node_mapping);
+    }
+    assert!(families
+        .windows(2)
+        .any(|w| w[0].multiplier != w[1].multiplier));
+}
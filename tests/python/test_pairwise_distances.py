@@ -51,9 +51,24 @@ try:
 except ValueError as e:
     print(f"✓ Correctly raised error: {e}")
 
+# Test 5: Condensed matrix and streamed NPY output
+print("\nTest 5: Condensed matrix and NPY streaming...")
+import numpy as np
+
+names, condensed = tree.pairwise_distances_condensed("metric", leaves_only=True)
+df = tree.pairwise_distances("metric", leaves_only=True)
+assert len(names) == tree.num_leaves()
+assert condensed.shape == (len(df),)
+assert np.allclose(condensed, df["distance"].to_numpy())
+print("✓ Condensed matrix matches pairwise_distances")
+
+tree.save_pairwise_distances_npy("test_metric_leaves.npy", "metric", leaves_only=True)
+assert np.allclose(np.load("test_metric_leaves.npy"), condensed)
+print("✓ NPY file matches condensed matrix")
+
 # Cleanup
 print("\nCleaning up test files...")
-for filename in ["test_topo_leaves.csv", "test_metric_all.csv", "test_topo_alt.csv", "test_metric_alt.csv"]:
+for filename in ["test_topo_leaves.csv", "test_metric_all.csv", "test_topo_alt.csv", "test_metric_alt.csv", "test_metric_leaves.npy"]:
     if os.path.exists(filename):
         os.remove(filename)
         print(f"Removed {filename}")
@@ -0,0 +1,146 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::bd::simulate_bd_tree_bwd;
+use rustree::bindings_common::extract_extant_gene_tree;
+use rustree::dtl::simulate_dtl;
+use rustree::reconciliation::{
+    map_leaves_by_name, reconcile_lca, reconcile_lca_by_name, reconcile_parsimony, DtlCosts,
+    ParsimonyOptions,
+};
+use rustree::{Event, RecTree, RustreeError};
+use std::sync::Arc;
+
+mod common;
+use common::tree;
+
+fn count(rec: &RecTree, event: Event) -> usize {
+    rec.event_mapping.iter().filter(|e| **e == event).count()
+}
+
+fn species_name(rec: &RecTree, gene_node: usize) -> &str {
+    &rec.species_tree.nodes[rec.node_mapping[gene_node].unwrap()].name
+}
+
+#[test]
+fn congruent_gene_tree_maps_node_to_node() {
+    let species = Arc::new(tree("((A:1,B:1)AB:1,C:2)R:0;"));
+    let rec = reconcile_lca_by_name(species, &tree("((A_1:1,B_1:1):1,C_1:2):0;")).unwrap();
+    assert_eq!(count(&rec, Event::Duplication), 0);
+    assert_eq!(count(&rec, Event::Loss), 0);
+    assert_eq!(count(&rec, Event::Speciation), 2);
+    assert_eq!(species_name(&rec, rec.gene_tree.root), "R");
+}
+
+#[test]
+fn duplication_with_loss_in_one_copy() {
+    let species = Arc::new(tree("((A:1,B:1)AB:1,C:2)R:0;"));
+    let rec = reconcile_lca_by_name(species, &tree("((A_1:1,B_1:1):1,A_2:1):0;")).unwrap();
+    let root = rec.gene_tree.root;
+    assert_eq!(rec.event_mapping[root], Event::Duplication);
+    assert_eq!(species_name(&rec, root), "AB");
+    assert_eq!(count(&rec, Event::Duplication), 1);
+
+    // The A_2 copy passes the AB speciation and is lost in B.
+    let losses: Vec<usize> = (0..rec.gene_tree.nodes.len())
+        .filter(|&i| rec.event_mapping[i] == Event::Loss)
+        .collect();
+    assert_eq!(losses.len(), 1);
+    assert_eq!(species_name(&rec, losses[0]), "B");
+    let parent = rec.gene_tree.nodes[losses[0]].parent.unwrap();
+    assert_eq!(rec.event_mapping[parent], Event::Speciation);
+    assert_eq!(species_name(&rec, parent), "AB");
+}
+
+#[test]
+fn skipped_branches_become_losses() {
+    let species = Arc::new(tree("(((A:1,B:1)AB:1,C:1)ABC:1,D:1)R:0;"));
+    let gene = tree("(A_1:1,D_1:1):0;");
+    let leaves = map_leaves_by_name(&gene, &species).unwrap();
+    let rec = reconcile_lca(species, &gene, &leaves).unwrap();
+    assert_eq!(count(&rec, Event::Duplication), 0);
+    assert_eq!(count(&rec, Event::Loss), 2);
+    let lost: Vec<&str> = (0..rec.gene_tree.nodes.len())
+        .filter(|&i| rec.event_mapping[i] == Event::Loss)
+        .map(|i| species_name(&rec, i))
+        .collect();
+    assert_eq!(lost, ["C", "B"]);
+    assert_eq!(extract_extant_gene_tree(&rec).unwrap().nodes.len(), 3);
+}
+
+#[test]
+fn matches_parsimony_on_transfer_free_families() {
+    let mut rng = StdRng::seed_from_u64(23);
+    let (mut species, _) = simulate_bd_tree_bwd(10, 1.0, 0.0, &mut rng).unwrap();
+    species.assign_depths();
+    let species = Arc::new(species);
+    let costs = DtlCosts {
+        duplication: 2.0,
+        transfer: 1e6,
+        loss: 1.0,
+    };
+
+    let mut checked = 0;
+    while checked < 5 {
+        let (sim, _) = simulate_dtl(
+            &species,
+            species.root,
+            0.5,
+            0.0,
+            0.5,
+            None,
+            None,
+            true,
+            &mut rng,
+        )
+        .unwrap();
+        let gene = extract_extant_gene_tree(&sim).unwrap();
+        if gene.nodes.len() < 3 {
+            continue;
+        }
+        checked += 1;
+
+        let rec = reconcile_lca_by_name(species.clone(), &gene).unwrap();
+        let lca_cost = costs.duplication * count(&rec, Event::Duplication) as f64
+            + costs.loss * count(&rec, Event::Loss) as f64;
+        assert_eq!(count(&rec, Event::Transfer), 0);
+        assert_eq!(
+            count(&rec, Event::Leaf),
+            gene.nodes.iter().filter(|n| n.left_child.is_none()).count()
+        );
+
+        let leaves = map_leaves_by_name(&gene, &species).unwrap();
+        let options = ParsimonyOptions {
+            costs,
+            time_consistent: false,
+        };
+        let parsimony = reconcile_parsimony(species.clone(), &gene, &leaves, options).unwrap();
+        assert_eq!(lca_cost, parsimony.cost());
+    }
+}
+
+#[test]
+fn unmatched_leaf_is_an_error() {
+    let species = Arc::new(tree("(A:1,B:1)R:0;"));
+    assert!(reconcile_lca_by_name(species, &tree("(A_1:1,X_1:1):0;")).is_err());
+}
+
+#[test]
+fn unary_species_node_is_an_error() {
+    let mut species = tree("((A:1,B:1)AB:1,C:2)R:0;");
+    let ab = species.nodes.iter().position(|n| n.name == "AB").unwrap();
+    species.nodes[ab].left_child = None;
+    let species = Arc::new(species);
+    let gene_tree = tree("((B_1:1,C_1:2):1,C_2:2):0;");
+
+    let err = reconcile_lca_by_name(species.clone(), &gene_tree).unwrap_err();
+    assert!(matches!(err, RustreeError::Tree(_)), "{err}");
+    let leaf_species = map_leaves_by_name(&gene_tree, &species).unwrap();
+    let err = reconcile_parsimony(
+        species,
+        &gene_tree,
+        &leaf_species,
+        ParsimonyOptions::default(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, RustreeError::Tree(_)), "{err}");
+}
@@ -0,0 +1,212 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::bd::simulate_bd_tree_bwd;
+use rustree::bindings_common::extract_extant_gene_tree;
+use rustree::dtl::simulate_dtl;
+use rustree::reconciliation::{
+    map_leaves_by_name, reconcile_parsimony, DtlCosts, ParsimonyOptions,
+};
+use rustree::{parse_newick, Event, RecTree};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+mod common;
+use common::tree;
+
+fn options(time_consistent: bool) -> ParsimonyOptions {
+    ParsimonyOptions {
+        costs: DtlCosts::default(),
+        time_consistent,
+    }
+}
+
+/// Recomputes the DTL cost from the events of a reconciled tree.
+fn scenario_cost(rec: &RecTree, costs: DtlCosts) -> f64 {
+    rec.event_mapping
+        .iter()
+        .map(|event| match event {
+            Event::Duplication => costs.duplication,
+            Event::Transfer => costs.transfer,
+            Event::Loss => costs.loss,
+            _ => 0.0,
+        })
+        .sum()
+}
+
+fn count_events(rec: &RecTree, event: Event) -> usize {
+    rec.event_mapping.iter().filter(|e| **e == event).count()
+}
+
+#[test]
+fn congruent_gene_tree_needs_no_events() {
+    let species = Arc::new(tree("((A:1,B:1)AB:1,(C:1,D:1)CD:1)R:0;"));
+    let gene = tree("((A_1:1,B_1:1):1,(C_1:1,D_1:1):1):0;");
+    let leaves = map_leaves_by_name(&gene, &species).unwrap();
+
+    for time_consistent in [false, true] {
+        let rec =
+            reconcile_parsimony(species.clone(), &gene, &leaves, options(time_consistent)).unwrap();
+        assert_eq!(rec.cost(), 0.0);
+        assert_eq!(rec.count_optimal(), 1.0);
+
+        let best = rec.best().unwrap();
+        assert_eq!(best.gene_tree.nodes.len(), gene.nodes.len());
+        let root = best.gene_tree.root;
+        assert_eq!(best.node_mapping[root], species.find_node_index("R"));
+        assert_eq!(count_events(&best, Event::Speciation), 3);
+        assert_eq!(count_events(&best, Event::Leaf), 4);
+    }
+}
+
+#[test]
+fn paralogs_are_explained_by_a_root_duplication() {
+    let species = Arc::new(tree("(A:1,B:1)R:0;"));
+    let gene = tree("((A_1:1,B_1:1):1,(A_2:1,B_2:1):1):0;");
+    let leaves = map_leaves_by_name(&gene, &species).unwrap();
+
+    for time_consistent in [false, true] {
+        let rec =
+            reconcile_parsimony(species.clone(), &gene, &leaves, options(time_consistent)).unwrap();
+        assert_eq!(rec.cost(), 2.0);
+        assert_eq!(rec.count_optimal(), 1.0);
+        let best = rec.best().unwrap();
+        let root = best.gene_tree.root;
+        assert_eq!(best.event_mapping[root], Event::Duplication);
+        assert_eq!(best.node_mapping[root], species.find_node_index("R"));
+    }
+}
+
+#[test]
+fn incongruent_pair_is_explained_by_a_transfer() {
+    let species = Arc::new(tree("((A:1,B:1)AB:1,(C:1,D:1)CD:1)R:0;"));
+    let gene = tree("((A_1:1,C_1:1):1,B_1:1):0;");
+    let leaves = map_leaves_by_name(&gene, &species).unwrap();
+
+    let rec = reconcile_parsimony(species.clone(), &gene, &leaves, options(false)).unwrap();
+    assert_eq!(rec.cost(), 3.0);
+    let best = rec.best().unwrap();
+    assert_eq!(count_events(&best, Event::Transfer), 1);
+    assert_eq!(count_events(&best, Event::Loss), 0);
+    assert_eq!(scenario_cost(&best, DtlCosts::default()), 3.0);
+}
+
+#[test]
+fn time_consistency_forbids_transfers_between_non_overlapping_branches() {
+    // A lives on (1, 2] and C on (4, 5]: no direct A <-> C transfer is possible.
+    let species = Arc::new(tree("((A:1,B:3)AB:1,(C:1,D:1)CD:3)R:0;"));
+    let gene = tree("((A_1:1,C_1:1):1,B_1:1):0;");
+    let leaves = map_leaves_by_name(&gene, &species).unwrap();
+
+    let undated = reconcile_parsimony(species.clone(), &gene, &leaves, options(false)).unwrap();
+    let dated = reconcile_parsimony(species.clone(), &gene, &leaves, options(true)).unwrap();
+    assert_eq!(undated.cost(), 3.0);
+    assert!(dated.cost() > undated.cost());
+    let best = dated.best().unwrap();
+    assert_eq!(scenario_cost(&best, DtlCosts::default()), dated.cost());
+}
+
+#[test]
+fn enumeration_and_sampling_cover_the_co_optimal_set() {
+    let species = Arc::new(tree("((A:1,B:1)AB:1,(C:1,D:1)CD:1)R:0;"));
+    let gene = tree("(((A_1:1,C_1:1):1,(B_1:1,D_1:1):1):1,(A_2:1,D_2:1):1):0;");
+    let leaves = map_leaves_by_name(&gene, &species).unwrap();
+    let costs = DtlCosts {
+        duplication: 1.0,
+        transfer: 1.0,
+        loss: 1.0,
+    };
+
+    for time_consistent in [false, true] {
+        let opts = ParsimonyOptions {
+            costs,
+            time_consistent,
+        };
+        let rec = reconcile_parsimony(species.clone(), &gene, &leaves, opts).unwrap();
+        assert!(rec.count_optimal() > 1.0);
+
+        let all = rec.enumerate(10_000).unwrap();
+        assert_eq!(all.len() as f64, rec.count_optimal());
+        let fingerprints: HashSet<String> = all
+            .iter()
+            .map(|r| format!("{:?}{:?}", r.node_mapping, r.event_mapping))
+            .collect();
+        assert_eq!(fingerprints.len(), all.len());
+        for r in &all {
+            assert_eq!(scenario_cost(r, costs), rec.cost());
+        }
+        assert_eq!(rec.enumerate(2).unwrap().len(), 2);
+
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..50 {
+            let r = rec.sample(&mut rng).unwrap();
+            assert_eq!(scenario_cost(&r, costs), rec.cost());
+        }
+    }
+}
+
+#[test]
+fn simulated_families_reconcile_at_consistent_costs() {
+    let mut rng = StdRng::seed_from_u64(17);
+    let (mut species, _) = simulate_bd_tree_bwd(12, 1.0, 0.3, &mut rng).unwrap();
+    species.assign_depths();
+    let species = Arc::new(species);
+
+    for _ in 0..5 {
+        let (sim, _) = simulate_dtl(
+            &species,
+            species.root,
+            0.3,
+            0.3,
+            0.3,
+            None,
+            None,
+            true,
+            &mut rng,
+        )
+        .unwrap();
+        let Ok(gene) = extract_extant_gene_tree(&sim) else {
+            continue;
+        };
+        if gene.nodes.len() < 3 {
+            continue;
+        }
+        let leaves = map_leaves_by_name(&gene, &species).unwrap();
+        let undated = reconcile_parsimony(species.clone(), &gene, &leaves, options(false)).unwrap();
+        let dated = reconcile_parsimony(species.clone(), &gene, &leaves, options(true)).unwrap();
+        assert!(dated.cost() >= undated.cost());
+
+        for rec in [&undated, &dated] {
+            let best = rec.sample(&mut rng).unwrap();
+            assert_eq!(scenario_cost(&best, DtlCosts::default()), rec.cost());
+            let extant = count_events(&best, Event::Leaf);
+            assert_eq!(extant, leaves.iter().flatten().count());
+        }
+    }
+}
+
+#[test]
+fn invalid_inputs_are_rejected() {
+    let species = Arc::new(tree("(A:1,B:1)R:0;"));
+    let gene = tree("(A_1:1,Z_1:1):0;");
+    assert!(map_leaves_by_name(&gene, &species).is_err());
+
+    let gene = tree("(A_1:1,B_1:1):0;");
+    let leaves = map_leaves_by_name(&gene, &species).unwrap();
+    let negative = ParsimonyOptions {
+        costs: DtlCosts {
+            loss: -1.0,
+            ..DtlCosts::default()
+        },
+        time_consistent: false,
+    };
+    assert!(reconcile_parsimony(species.clone(), &gene, &leaves, negative).is_err());
+
+    let undated_species = Arc::new(
+        parse_newick("(A:1,B:1)R:0;")
+            .unwrap()
+            .pop()
+            .unwrap()
+            .to_flat_tree(),
+    );
+    assert!(reconcile_parsimony(undated_species, &gene, &leaves, options(true)).is_err());
+}
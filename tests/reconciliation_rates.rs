@@ -0,0 +1,163 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::bd::simulate_bd_tree_bwd;
+use rustree::dtl::{simulate_dtl, simulate_dtl_iter_with_config, BranchDTLRates, DTLConfig};
+use rustree::reconciliation::{estimate_dtl_rates, reconcile_lca_by_name, Exposure};
+use rustree::{parse_newick, FlatTree, GeneForest};
+use std::sync::Arc;
+
+mod common;
+use common::tree;
+
+fn simulated_forest(
+    species: &Arc<FlatTree>,
+    rates: (f64, f64, f64),
+    families: usize,
+    seed: u64,
+) -> GeneForest {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let trees = (0..families)
+        .map(|_| {
+            simulate_dtl(
+                species,
+                species.root,
+                rates.0,
+                rates.1,
+                rates.2,
+                None,
+                None,
+                false,
+                &mut rng,
+            )
+            .unwrap()
+            .0
+        })
+        .collect();
+    GeneForest::from_rec_trees(species.clone(), trees)
+}
+
+#[test]
+fn event_free_families_are_exposed_along_the_whole_tree() {
+    let species = Arc::new(tree("((A:1,B:1)AB:1,C:2)R:0.5;"));
+    let forest = simulated_forest(&species, (0.0, 0.0, 0.0), 3, 1);
+    let total_length: f64 = species.nodes.iter().map(|n| n.length).sum();
+
+    for exposure in [Exposure::GeneDepths, Exposure::EvenlySpaced] {
+        let estimates = estimate_dtl_rates(&forest, exposure, 0.95).unwrap();
+        assert!((estimates.global.exposure() - 3.0 * total_length).abs() < 1e-9);
+        assert_eq!(estimates.global.duplication.events, 0);
+        assert_eq!(estimates.global.loss.rate, 0.0);
+        assert!(estimates.global.transfer.upper > 0.0);
+        for (node, branch) in species.nodes.iter().zip(&estimates.branches) {
+            assert!((branch.exposure() - 3.0 * node.length).abs() < 1e-9);
+        }
+    }
+
+    // Four speciations and three leaves per family.
+    let steps = estimate_dtl_rates(&forest, Exposure::SpeciationSteps, 0.95).unwrap();
+    assert_eq!(steps.global.exposure(), 15.0);
+}
+
+#[test]
+fn undated_events_are_spaced_along_their_branch() {
+    let species = Arc::new(tree("((A:1,B:1)AB:1,C:2)R:0;"));
+    // Duplication on AB; the A_2 copy is lost in B.
+    let rec = reconcile_lca_by_name(species.clone(), &tree("((A_1:1,B_1:1):1,A_2:1):0;")).unwrap();
+    let forest = GeneForest::from_rec_trees(species.clone(), vec![rec]);
+    let idx = |name: &str| species.find_node_index(name).unwrap();
+
+    let estimates = estimate_dtl_rates(&forest, Exposure::EvenlySpaced, 0.95).unwrap();
+    assert!((estimates.global.exposure() - 5.0).abs() < 1e-12);
+    let ab = &estimates.branches[idx("AB")];
+    assert!((ab.exposure() - 1.5).abs() < 1e-12);
+    assert_eq!(ab.duplication.events, 1);
+    assert!((ab.duplication.rate - 1.0 / 1.5).abs() < 1e-12);
+    let b = &estimates.branches[idx("B")];
+    assert!((b.exposure() - 1.5).abs() < 1e-12);
+    assert_eq!(b.loss.events, 1);
+    assert_eq!(estimates.branches[idx("A")].exposure(), 2.0);
+    assert_eq!(estimates.branches[idx("C")].exposure(), 0.0);
+
+    let steps = estimate_dtl_rates(&forest, Exposure::SpeciationSteps, 0.95).unwrap();
+    let point = steps.global.point_estimates();
+    assert_eq!(
+        (point.duplication, point.transfer, point.loss),
+        (0.2, 0.0, 0.2)
+    );
+}
+
+#[test]
+fn simulated_rates_fall_in_the_intervals() {
+    let mut rng = StdRng::seed_from_u64(31);
+    let (mut species, _) = simulate_bd_tree_bwd(12, 1.0, 0.3, &mut rng).unwrap();
+    species.assign_depths();
+    let species = Arc::new(species);
+    let rates = (0.4, 0.3, 0.5);
+    let forest = simulated_forest(&species, rates, 400, 8);
+
+    let estimates = estimate_dtl_rates(&forest, Exposure::GeneDepths, 0.99).unwrap();
+    assert!(estimates.global.duplication.events > 100);
+    assert!(estimates.global.transfer.events > 100);
+    assert!(estimates.global.contains(rates.0, rates.1, rates.2));
+
+    let config = DTLConfig::new(rates.0, rates.1, rates.2, None, None).unwrap();
+    let coverage = estimates.coverage(&config).unwrap();
+    assert_eq!(coverage.global, (true, true, true));
+    assert!((coverage.expected.0 - rates.0).abs() < 1e-12);
+    assert!(coverage.branches.0 > 0.8);
+    assert!(coverage.branches.1 > 0.8);
+    assert!(coverage.branches.2 > 0.8);
+
+    let wrong = DTLConfig::new(2.0, rates.1, rates.2, None, None).unwrap();
+    assert!(!estimates.coverage(&wrong).unwrap().global.0);
+}
+
+#[test]
+fn branch_rates_are_recovered_per_branch() {
+    let species = tree("((A:2,B:2)AB:2,C:4)R:1;");
+    let n = species.nodes.len();
+    let fast = species.find_node_index("A").unwrap();
+    let mut lambda_d = vec![0.1; n];
+    lambda_d[fast] = 1.0;
+    let mut origination = vec![0.0; n];
+    origination[species.root] = 1.0;
+    let rates = BranchDTLRates::new(lambda_d, vec![0.0; n], vec![0.3; n], origination).unwrap();
+    let config = DTLConfig::with_branch_rates(rates, None, None).unwrap();
+
+    let mut rng = StdRng::seed_from_u64(12);
+    let trees =
+        simulate_dtl_iter_with_config(&species, species.root, config.clone(), 300, false, &mut rng)
+            .unwrap()
+            .map(|family| family.unwrap().0)
+            .collect();
+    let forest = GeneForest::from_rec_trees(Arc::new(species), trees);
+
+    let estimates = estimate_dtl_rates(&forest, Exposure::GeneDepths, 0.99).unwrap();
+    assert!(estimates.branches[fast].duplication.contains(1.0));
+    assert!(estimates.branches[fast].duplication.lower > 0.5);
+    let coverage = estimates.coverage(&config).unwrap();
+    assert!(coverage.expected.0 > 0.1 && coverage.expected.0 < 1.0);
+    assert_eq!(coverage.global, (true, true, true));
+    assert_eq!(coverage.branches.0, 1.0);
+}
+
+#[test]
+fn invalid_requests_are_rejected() {
+    let species = Arc::new(tree("(A:1,B:1)R:0;"));
+    let forest = simulated_forest(&species, (0.1, 0.1, 0.1), 2, 3);
+    assert!(estimate_dtl_rates(&forest, Exposure::GeneDepths, 1.0).is_err());
+
+    let steps = estimate_dtl_rates(&forest, Exposure::SpeciationSteps, 0.9).unwrap();
+    let config = DTLConfig::new(0.1, 0.1, 0.1, None, None).unwrap();
+    assert!(steps.coverage(&config).is_err());
+
+    let undated = Arc::new(
+        parse_newick("(A:1,B:1)R:0;")
+            .unwrap()
+            .pop()
+            .unwrap()
+            .to_flat_tree(),
+    );
+    let forest = GeneForest::from_rec_trees(undated, forest.gene_trees);
+    assert!(estimate_dtl_rates(&forest, Exposure::EvenlySpaced, 0.9).is_err());
+}
@@ -0,0 +1,168 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::bindings_common::extract_extant_gene_tree;
+use rustree::dtl::simulate_dtl;
+use rustree::reconciliation::{
+    fit_undated_rates, map_leaves_by_name, UndatedDtlModel, UndatedRates,
+};
+use rustree::{Event, GeneForest};
+use std::sync::Arc;
+
+mod common;
+use common::tree;
+
+fn rates(duplication: f64, transfer: f64, loss: f64) -> UndatedRates {
+    UndatedRates {
+        duplication,
+        transfer,
+        loss,
+    }
+}
+
+fn log_likelihood(model: &UndatedDtlModel, gene: &str) -> f64 {
+    let gene = tree(gene);
+    let leaves = map_leaves_by_name(&gene, model.species_tree()).unwrap();
+    model.log_likelihood(&gene, &leaves).unwrap()
+}
+
+#[test]
+fn without_events_only_origination_matters() {
+    let species = Arc::new(tree("(A:1,B:1)R:0;"));
+    let model = UndatedDtlModel::new(species, rates(0.0, 0.0, 0.0)).unwrap();
+    // Three origination branches, each certain to leave the matching tree.
+    let ll = log_likelihood(&model, "(A_1:1,B_1:1):0;");
+    assert!((ll - (1.0f64 / 3.0).ln()).abs() < 1e-12);
+    assert!((log_likelihood(&model, "A_1:1;") - ll).abs() < 1e-12);
+    assert_eq!(
+        log_likelihood(&model, "(A_1:1,A_2:1):0;"),
+        f64::NEG_INFINITY
+    );
+}
+
+#[test]
+fn loss_only_probabilities_sum_to_one() {
+    let species = Arc::new(tree("(A:1,B:1)R:0;"));
+    let model = UndatedDtlModel::new(species, rates(0.0, 0.0, 0.7)).unwrap();
+    let total: f64 = ["(A_1:1,B_1:1):0;", "A_1:1;", "B_1:1;"]
+        .iter()
+        .map(|gene| log_likelihood(&model, gene).exp())
+        .sum();
+    assert!((total - 1.0).abs() < 1e-10, "total = {total}");
+}
+
+#[test]
+fn transfers_explain_incongruence() {
+    let species = Arc::new(tree("((A:1,B:1)AB:1,(C:1,D:1)CD:1)R:0;"));
+    let congruent = "((A_1:1,B_1:1):1,(C_1:1,D_1:1):1):0;";
+    let incongruent = "((A_1:1,C_1:1):1,(B_1:1,D_1:1):1):0;";
+
+    let rare = UndatedDtlModel::new(species.clone(), rates(0.01, 0.01, 0.01)).unwrap();
+    assert!(log_likelihood(&rare, congruent) > log_likelihood(&rare, incongruent));
+
+    let frequent = UndatedDtlModel::new(species, rates(0.01, 1.0, 0.01)).unwrap();
+    assert!(log_likelihood(&frequent, incongruent) > log_likelihood(&rare, incongruent));
+}
+
+#[test]
+fn sampled_reconciliations_embed_the_gene_tree() {
+    let species = Arc::new(tree("((A:1,B:1)AB:1,(C:1,D:1)CD:1)R:0;"));
+    let gene = tree("(((A_1:1,C_1:1):1,B_1:1):1,(D_1:1,D_2:1):1):0;");
+    let leaves = map_leaves_by_name(&gene, &species).unwrap();
+    let model = UndatedDtlModel::new(species, rates(0.2, 0.3, 0.4)).unwrap();
+    let rec = model.reconcile(&gene, &leaves).unwrap();
+    assert!(rec.log_likelihood().is_finite());
+
+    let mut rng = StdRng::seed_from_u64(5);
+    let mut transfers = 0;
+    for _ in 0..200 {
+        let sample = rec.sample(&mut rng).unwrap();
+        for (i, node) in sample.gene_tree.nodes.iter().enumerate() {
+            let is_leaf = node.left_child.is_none() && node.right_child.is_none();
+            let event = &sample.event_mapping[i];
+            assert_eq!(is_leaf, matches!(event, Event::Leaf | Event::Loss));
+            if *event == Event::Leaf {
+                let original = gene.find_node_index(&node.name).unwrap();
+                assert_eq!(sample.node_mapping[i], leaves[original]);
+            }
+        }
+        let extant = extract_extant_gene_tree(&sample).unwrap();
+        assert_eq!(extant.nodes.len(), gene.nodes.len());
+        transfers += sample
+            .event_mapping
+            .iter()
+            .filter(|e| **e == Event::Transfer)
+            .count();
+    }
+    assert!(transfers > 0);
+}
+
+#[test]
+fn rates_fit_to_a_congruent_forest_shrink() {
+    let species = tree("((A:1,B:1)AB:1,(C:1,D:1)CD:1)R:0;");
+    let mut rng = StdRng::seed_from_u64(9);
+    let species_arc = Arc::new(species.clone());
+    let families = (0..4)
+        .map(|_| {
+            simulate_dtl(
+                &species,
+                species.root,
+                0.0,
+                0.0,
+                0.0,
+                None,
+                None,
+                true,
+                &mut rng,
+            )
+            .unwrap()
+            .0
+        })
+        .collect();
+    let forest = GeneForest::from_rec_trees(species_arc.clone(), families);
+
+    let start = rates(0.5, 0.5, 0.5);
+    let start_ll = UndatedDtlModel::new(species_arc, start)
+        .unwrap()
+        .forest_log_likelihood(&forest)
+        .unwrap();
+    let fit = fit_undated_rates(&forest, start, 200).unwrap();
+    assert!(fit.log_likelihood > start_ll);
+    assert!(fit.rates.duplication < start.duplication);
+    assert!(fit.rates.transfer < start.transfer);
+    assert!(fit.rates.loss < start.loss);
+}
+
+#[test]
+fn invalid_rates_are_rejected() {
+    let species = Arc::new(tree("(A:1,B:1)R:0;"));
+    assert!(UndatedDtlModel::new(species.clone(), rates(-0.1, 0.0, 0.0)).is_err());
+    assert!(UndatedDtlModel::new(species, rates(0.1, f64::NAN, 0.0)).is_err());
+}
+
+#[test]
+fn forest_must_share_the_model_species_tree() {
+    let species = tree("((A:1,B:1)AB:1,C:2)R:0;");
+    let mut rng = StdRng::seed_from_u64(11);
+    let (family, _) = simulate_dtl(
+        &species,
+        species.root,
+        0.0,
+        0.0,
+        0.0,
+        None,
+        None,
+        true,
+        &mut rng,
+    )
+    .unwrap();
+    let forest = GeneForest::from_rec_trees(Arc::new(species.clone()), vec![family]);
+
+    // An equal copy behind another Arc is accepted.
+    let same = UndatedDtlModel::new(Arc::new(species), rates(0.1, 0.1, 0.1)).unwrap();
+    assert!(same.forest_log_likelihood(&forest).is_ok());
+
+    // Same shape and node count, but the leaves are named differently.
+    let renamed = Arc::new(tree("((A:1,C:1)AB:1,B:2)R:0;"));
+    let model = UndatedDtlModel::new(renamed, rates(0.1, 0.1, 0.1)).unwrap();
+    assert!(model.forest_log_likelihood(&forest).is_err());
+}
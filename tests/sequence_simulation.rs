@@ -0,0 +1,127 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::dtl::{count_extant_genes, simulate_dtl};
+use rustree::sequence::{
+    simulate_rectree_sequences, simulate_sequences, SequenceModel, SiteRates, SubstitutionModel,
+};
+
+mod common;
+use common::tree;
+
+fn p_distance(a: &str, b: &str) -> f64 {
+    let diff = a.bytes().zip(b.bytes()).filter(|(x, y)| x != y).count();
+    diff as f64 / a.len() as f64
+}
+
+#[test]
+fn jc69_divergence_matches_expectation() {
+    let tree = tree("(A:0.05,B:0.05)root:0;");
+    let model = SequenceModel::new(SubstitutionModel::JC69, SiteRates::uniform()).unwrap();
+    let mut rng = StdRng::seed_from_u64(1);
+    let alignment = simulate_sequences(&tree, &model, 20_000, &mut rng).unwrap();
+
+    assert_eq!(alignment.names, vec!["A", "B"]);
+    let expected = 0.75 * (1.0 - (-4.0 * 0.1 / 3.0_f64).exp());
+    let observed = p_distance(&alignment.sequences[0], &alignment.sequences[1]);
+    assert!(
+        (observed - expected).abs() < 0.01,
+        "{observed} vs {expected}"
+    );
+}
+
+#[test]
+fn zero_length_branches_copy_the_root() {
+    let tree = tree("((A:0,B:0)AB:0,C:0)root:0;");
+    let model = SequenceModel::new(
+        SubstitutionModel::GTR {
+            rates: [1.0, 2.0, 0.5, 0.7, 3.0, 1.0],
+            frequencies: [0.1, 0.4, 0.4, 0.1],
+        },
+        SiteRates::uniform().with_gamma(0.5),
+    )
+    .unwrap();
+    let mut rng = StdRng::seed_from_u64(2);
+    let alignment = simulate_sequences(&tree, &model, 500, &mut rng).unwrap();
+    assert_eq!(alignment.len(), 3);
+    assert!(alignment
+        .sequences
+        .iter()
+        .all(|s| s == &alignment.sequences[0]));
+}
+
+#[test]
+fn invariant_sites_stay_constant() {
+    let tree = tree("(A:5,B:5)root:0;");
+    let model = SequenceModel::new(
+        SubstitutionModel::K80 { kappa: 2.0 },
+        SiteRates::uniform().with_invariant_sites(0.5),
+    )
+    .unwrap();
+    let mut rng = StdRng::seed_from_u64(3);
+    let alignment = simulate_sequences(&tree, &model, 5_000, &mut rng).unwrap();
+    // Variable sites are saturated (identity 1/4), invariant ones always agree.
+    let identity = 1.0 - p_distance(&alignment.sequences[0], &alignment.sequences[1]);
+    assert!((identity - 0.625).abs() < 0.03, "identity = {identity}");
+}
+
+#[test]
+fn protein_models_emit_amino_acids_deterministically() {
+    let tree = tree("((A:0.3,B:0.2)AB:0.1,C:0.4)root:0;");
+    for substitution in [SubstitutionModel::LG, SubstitutionModel::WAG] {
+        let model = SequenceModel::new(substitution, SiteRates::uniform().with_gamma(1.0)).unwrap();
+        let mut rng1 = StdRng::seed_from_u64(4);
+        let mut rng2 = StdRng::seed_from_u64(4);
+        let a = simulate_sequences(&tree, &model, 300, &mut rng1).unwrap();
+        let b = simulate_sequences(&tree, &model, 300, &mut rng2).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.n_sites(), 300);
+        assert!(a
+            .sequences
+            .iter()
+            .all(|s| s.bytes().all(|c| b"ARNDCQEGHILKMFPSTWYV".contains(&c))));
+    }
+}
+
+#[test]
+fn rectree_alignment_keeps_extant_genes_and_writes_files() {
+    let species = tree("((A:1,B:1)AB:1,(C:1,D:1)CD:1)root:0;");
+    let mut rng = StdRng::seed_from_u64(5);
+    let (rec_tree, _) = loop {
+        let (rec_tree, events) = simulate_dtl(
+            &species,
+            species.root,
+            0.5,
+            0.3,
+            0.5,
+            None,
+            None,
+            false,
+            &mut rng,
+        )
+        .unwrap();
+        if count_extant_genes(&rec_tree) > 1 {
+            break (rec_tree, events);
+        }
+    };
+
+    let model = SequenceModel::new(
+        SubstitutionModel::HKY {
+            kappa: 3.0,
+            frequencies: [0.3, 0.2, 0.2, 0.3],
+        },
+        SiteRates::uniform(),
+    )
+    .unwrap();
+    let alignment = simulate_rectree_sequences(&rec_tree, &model, 100, &mut rng).unwrap();
+    assert_eq!(alignment.len(), count_extant_genes(&rec_tree));
+
+    let tmp = tempfile::tempdir().unwrap();
+    let fasta = tmp.path().join("family.fasta");
+    let phylip = tmp.path().join("family.phy");
+    alignment.save_fasta(fasta.to_str().unwrap()).unwrap();
+    alignment.save_phylip(phylip.to_str().unwrap()).unwrap();
+    let fasta = std::fs::read_to_string(fasta).unwrap();
+    assert_eq!(fasta.matches('>').count(), alignment.len());
+    let phylip = std::fs::read_to_string(phylip).unwrap();
+    assert!(phylip.starts_with(&format!("{} 100\n", alignment.len())));
+}
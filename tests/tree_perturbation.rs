@@ -0,0 +1,110 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustree::perturbation::{perturb_tree, RearrangementKind, TreePerturbation};
+use rustree::robinson_foulds::robinson_foulds;
+use rustree::surgery::nni_topology;
+use rustree::{parse_newick, FlatTree};
+use std::collections::BTreeSet;
+
+mod common;
+use common::species_tree;
+
+fn leaf_names(tree: &FlatTree) -> BTreeSet<String> {
+    tree.nodes
+        .iter()
+        .filter(|n| n.left_child.is_none() && n.right_child.is_none())
+        .map(|n| n.name.clone())
+        .collect()
+}
+
+#[test]
+fn nni_swaps_a_child_with_the_sibling() {
+    let mut nodes = parse_newick("((A:1,B:1)AB:1,C:2)root:0;").unwrap();
+    let mut tree = nodes.pop().unwrap().to_flat_tree();
+    let ab = tree.nodes.iter().position(|n| n.name == "AB").unwrap();
+    nni_topology(&mut tree, ab, true).unwrap();
+    assert_eq!(
+        tree.to_newick().unwrap(),
+        "((C:2.000000,B:1.000000)AB:1.000000,A:1.000000)root:0.000000"
+    );
+    let root = tree.root;
+    assert!(nni_topology(&mut tree, root, true).is_err());
+}
+
+#[test]
+fn k_moves_preserve_leaves_and_binary_structure() {
+    let tree = species_tree(30, 1);
+    for kind in [
+        RearrangementKind::Nni,
+        RearrangementKind::Spr,
+        RearrangementKind::Mixed { spr_fraction: 0.5 },
+    ] {
+        let perturbation = TreePerturbation::moves(kind.clone(), 5).unwrap();
+        let mut rng = StdRng::seed_from_u64(2);
+        let noisy = perturb_tree(&tree, &perturbation, &mut rng).unwrap();
+
+        assert_eq!(noisy.moves, 5);
+        assert_eq!(leaf_names(&noisy.tree), leaf_names(&tree));
+        assert!(noisy
+            .tree
+            .nodes
+            .iter()
+            .all(|n| { n.left_child.is_some() == n.right_child.is_some() }));
+        assert_eq!(
+            noisy.robinson_foulds,
+            robinson_foulds(&tree, &noisy.tree).unwrap()
+        );
+        assert_eq!(noisy.robinson_foulds, 2 * noisy.modified_clades.len());
+        assert!(
+            noisy.robinson_foulds > 0,
+            "{kind:?} left the tree unchanged"
+        );
+    }
+}
+
+#[test]
+fn rf_target_is_reached() {
+    let tree = species_tree(40, 3);
+    let perturbation = TreePerturbation::robinson_foulds(RearrangementKind::Spr, 12).unwrap();
+    let mut rng = StdRng::seed_from_u64(4);
+    let noisy = perturb_tree(&tree, &perturbation, &mut rng).unwrap();
+    assert!(noisy.robinson_foulds >= 12);
+    assert!(noisy.moves >= 1);
+}
+
+#[test]
+fn short_branch_bias_targets_short_branches() {
+    let tree = species_tree(60, 5);
+    let mean_modified_length = |scale: Option<f64>| {
+        let mut perturbation = TreePerturbation::moves(RearrangementKind::Nni, 1).unwrap();
+        if let Some(scale) = scale {
+            perturbation = perturbation.with_short_branch_bias(scale).unwrap();
+        }
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut total = 0.0;
+        for _ in 0..300 {
+            let noisy = perturb_tree(&tree, &perturbation, &mut rng).unwrap();
+            // The NNI branch is the parent of the swapped-in sibling; its
+            // length is unchanged, so read it from the original tree.
+            let idx = noisy.modified_clades[0];
+            total += tree.nodes[idx].length;
+        }
+        total / 300.0
+    };
+    assert!(mean_modified_length(Some(0.01)) < mean_modified_length(None));
+}
+
+#[test]
+fn perturbation_is_deterministic() {
+    let tree = species_tree(20, 7);
+    let perturbation = TreePerturbation::moves(RearrangementKind::Mixed { spr_fraction: 0.3 }, 4)
+        .unwrap()
+        .with_short_branch_bias(0.2)
+        .unwrap();
+    let mut rng1 = StdRng::seed_from_u64(8);
+    let mut rng2 = StdRng::seed_from_u64(8);
+    let a = perturb_tree(&tree, &perturbation, &mut rng1).unwrap();
+    let b = perturb_tree(&tree, &perturbation, &mut rng2).unwrap();
+    assert_eq!(a.tree.to_newick().unwrap(), b.tree.to_newick().unwrap());
+    assert_eq!(a.modified_clades, b.modified_clades);
+}